@@ -0,0 +1,66 @@
+use reqwest::{Method, RequestBuilder};
+use serde::de::DeserializeOwned;
+
+/// Where the server is and the session token to send with every request.
+#[derive(Default, Debug, PartialEq, Clone, Hash)]
+pub(crate) struct Api {
+    pub url: String,
+    pub token: Option<String>,
+}
+
+impl Api {
+    pub(crate) fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = reqwest::Client::new().request(method, format!("{}{path}", self.url));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    pub(crate) fn get(&self, path: &str) -> RequestBuilder {
+        self.request(Method::GET, path)
+    }
+
+    pub(crate) fn post(&self, path: &str) -> RequestBuilder {
+        self.request(Method::POST, path)
+    }
+
+    pub(crate) fn put(&self, path: &str) -> RequestBuilder {
+        self.request(Method::PUT, path)
+    }
+}
+
+/// Sends the request and decodes the JSON response, turning HTTP error statuses into errors.
+pub(crate) async fn send<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, String> {
+    request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json::<T>()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Like [`send`] for requests whose response has no body.
+pub(crate) async fn execute(request: RequestBuilder) -> Result<(), String> {
+    request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Like [`send`] for responses that are a file rather than JSON.
+pub(crate) async fn bytes(request: RequestBuilder) -> Result<Vec<u8>, String> {
+    request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?
+        .bytes()
+        .await
+        .map(|bytes| bytes.to_vec())
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use shared::{Item, Promotion, Receipt};
+
+const ITEMS: &str = "items.json";
+const PROMOTIONS: &str = "promotions.json";
+const QUEUE: &str = "queue.json";
+const REFUSED: &str = "refused.json";
+
+/// Where the files were kept before they moved to the config directory. Only read, to migrate.
+const LEGACY_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/asset");
+
+/// Generations of the queue and of the refused receipts last written to disk, see
+/// [`write_latest`].
+static QUEUE_GENERATION: Mutex<u64> = Mutex::new(0);
+static REFUSED_GENERATION: Mutex<u64> = Mutex::new(0);
+
+/// `name` in the platform config directory, e.g. `~/.config/sunminimart` on Linux, where the
+/// settings are kept too.
+pub(crate) fn path(name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("sunminimart").join(name))
+}
+
+pub(crate) fn read<T: DeserializeOwned + Default>(name: &str) -> T {
+    path(name)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .or_else(|| fs::read_to_string(Path::new(LEGACY_DIR).join(name)).ok())
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Writes to a temporary file first and renames it over the old one, so a crash half way through
+/// leaves the previous copy intact.
+pub(crate) fn write<T: Serialize + ?Sized>(name: &str, value: &T) -> Result<(), String> {
+    let path = path(name).ok_or_else(|| "no config directory".to_string())?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json_data = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, json_data).map_err(|e| e.to_string())?;
+    fs::rename(&temporary, path).map_err(|e| e.to_string())
+}
+
+/// Removes the file, and the copy left in the old asset directory so it is not read back.
+pub(crate) fn remove(name: &str) -> Result<(), String> {
+    for path in path(name)
+        .into_iter()
+        .chain([Path::new(LEGACY_DIR).join(name)])
+    {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.to_string()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The catalogue as it was last fetched from the server.
+pub(crate) fn read_items() -> Vec<Item> {
+    read(ITEMS)
+}
+
+pub(crate) fn save_items(items: &[Item]) -> Result<(), String> {
+    write(ITEMS, items)
+}
+
+/// The promotions as they were last fetched, so an offline till still gives them.
+pub(crate) fn read_promotions() -> Vec<Promotion> {
+    read(PROMOTIONS)
+}
+
+pub(crate) fn save_promotions(promotions: &[Promotion]) -> Result<(), String> {
+    write(PROMOTIONS, promotions)
+}
+
+/// Receipts that were sold but had not reached the server when the client last ran.
+pub(crate) fn read_queue() -> Vec<Receipt> {
+    read(QUEUE)
+}
+
+/// Saves run as tasks and may finish out of order, so a copy older than the one already on disk
+/// is dropped.
+fn write_latest<T: Serialize + ?Sized>(
+    written: &Mutex<u64>,
+    generation: u64,
+    name: &str,
+    value: &T,
+) -> Result<(), String> {
+    let mut written = written.lock().map_err(|e| e.to_string())?;
+    if generation < *written {
+        return Ok(());
+    }
+    write(name, value)?;
+    *written = generation;
+    Ok(())
+}
+
+/// Saves the receipts that have not reached the server yet.
+pub(crate) async fn save_queue(generation: u64, queue: Vec<Receipt>) -> Result<(), String> {
+    write_latest(&QUEUE_GENERATION, generation, QUEUE, &queue)
+}
+
+/// Receipts the server refused, with why, so the sales are not lost when they leave the queue.
+pub(crate) fn read_refused() -> Vec<(Receipt, String)> {
+    read(REFUSED)
+}
+
+pub(crate) async fn save_refused(
+    generation: u64,
+    refused: Vec<(Receipt, String)>,
+) -> Result<(), String> {
+    write_latest(&REFUSED_GENERATION, generation, REFUSED, &refused)
+}
@@ -0,0 +1,248 @@
+use std::time::Duration;
+
+use iced::{Subscription, Task};
+use reqwest::StatusCode;
+use shared::Receipt;
+
+use crate::api::Api;
+use crate::cache;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Whether the server can be reached and the receipts waiting to be sent to it.
+#[derive(Default, Debug, PartialEq)]
+pub(crate) struct State {
+    pub online: bool,
+    pub queue: Vec<Receipt>,
+    pub replaying: bool,
+    /// Number of the last save of the queue or of the refused receipts, newer saves count higher.
+    pub generation: u64,
+    pub error: String,
+    /// Receipts the server refused, with why, kept for the owner to see to.
+    pub refused: Vec<(Receipt, String)>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Check,
+    Checked(bool),
+    Replayed(Replay),
+    QueueSaved(Result<(), String>),
+}
+
+/// What happened to the receipts of one replay. Receipts the server refused would be refused
+/// again, so they leave the queue for [`State::refused`]. Those that failed on the server stay
+/// in the queue and are tried again on the next replay.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub sent: Vec<String>,
+    pub rejected: Vec<(String, String)>,
+    pub failed: Vec<(String, String)>,
+    pub offline: bool,
+}
+
+pub(crate) fn update(state: &mut crate::State, message: Message) -> Task<Message> {
+    let api = state.api();
+    let connection = &mut state.connection;
+    match message {
+        Message::Check => return Task::perform(check(api), Message::Checked),
+        Message::Checked(online) => {
+            connection.online = online;
+            if state.session.is_some() {
+                return replay(connection, api);
+            }
+        }
+        Message::Replayed(replay) => {
+            connection.replaying = false;
+            connection.online = !replay.offline;
+            for (key, error) in &replay.rejected {
+                if let Some(receipt) = connection.queue.iter().find(|receipt| &receipt.key == key) {
+                    connection.refused.push((receipt.clone(), error.clone()));
+                }
+            }
+            let mut tasks = Vec::new();
+            if !replay.rejected.is_empty() {
+                connection.generation += 1;
+                tasks.push(Task::perform(
+                    cache::save_refused(connection.generation, connection.refused.clone()),
+                    Message::QueueSaved,
+                ));
+            }
+            connection.queue.retain(|receipt| {
+                !replay.sent.contains(&receipt.key)
+                    && !replay.rejected.iter().any(|(key, _)| key == &receipt.key)
+            });
+            connection.error = replay
+                .rejected
+                .iter()
+                .chain(&replay.failed)
+                .map(|(key, error)| format!("{key}: {error}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            if !replay.sent.is_empty() || !replay.rejected.is_empty() {
+                tasks.push(save(connection));
+            }
+            return Task::batch(tasks);
+        }
+        Message::QueueSaved(Ok(())) => {}
+        Message::QueueSaved(Err(e)) => eprintln!("queue error: {e}"),
+    }
+    Task::none()
+}
+
+/// Queues a finished sale and saves the queue to disk before trying to send it.
+pub(crate) fn enqueue(state: &mut crate::State, receipt: Receipt) -> Task<Message> {
+    let api = state.api();
+    let connection = &mut state.connection;
+    connection.queue.push(receipt);
+    Task::batch([save(connection), replay(connection, api)])
+}
+
+fn save(connection: &mut State) -> Task<Message> {
+    connection.generation += 1;
+    Task::perform(
+        cache::save_queue(connection.generation, connection.queue.clone()),
+        Message::QueueSaved,
+    )
+}
+
+fn replay(connection: &mut State, api: Api) -> Task<Message> {
+    if !connection.online || connection.replaying || connection.queue.is_empty() {
+        return Task::none();
+    }
+    connection.replaying = true;
+    Task::perform(send(api, connection.queue.clone()), Message::Replayed)
+}
+
+/// Connection status for the screens that sell or show the state of the till.
+pub(crate) fn status(connection: &State) -> String {
+    let online = match connection.online {
+        true => "เชื่อมต่อแล้ว",
+        false => "ออฟไลน์",
+    };
+    let status = match connection.queue.len() {
+        0 => online.to_string(),
+        queued => format!("{online} (รอส่ง {queued} ใบเสร็จ)"),
+    };
+    match connection.refused.len() {
+        0 => status,
+        refused => format!("{status} (ถูกปฏิเสธ {refused} ใบเสร็จ)"),
+    }
+}
+
+async fn check(api: Api) -> bool {
+    api.get("/")
+        .timeout(Duration::from_secs(3))
+        .send()
+        .await
+        .is_ok_and(|response| response.status().is_success())
+}
+
+/// Sends the receipts oldest first and stops at the first one that could not reach the server.
+async fn send(api: Api, queue: Vec<Receipt>) -> Replay {
+    let mut replay = Replay::default();
+    for receipt in queue {
+        match api.post("/receipts").json(&receipt).send().await {
+            Err(e) => {
+                eprintln!("reqwest error: {e}");
+                replay.offline = true;
+                break;
+            }
+            Ok(response) if response.status().is_success() => replay.sent.push(receipt.key),
+            Ok(response) if response.status() == StatusCode::UNAUTHORIZED => break,
+            Ok(response) => {
+                let status = response.status();
+                let error = format!("{status} {}", response.text().await.unwrap_or_default());
+                match status.is_client_error() {
+                    true => replay.rejected.push((receipt.key, error)),
+                    false => replay.failed.push((receipt.key, error)),
+                }
+            }
+        }
+    }
+    replay
+}
+
+pub(crate) fn subscription() -> Subscription<crate::Message> {
+    iced::time::every(CHECK_INTERVAL).map(|_| crate::Message::Connection(Message::Check))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn receipt(key: &str) -> Receipt {
+        Receipt {
+            key: key.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn init_state() -> crate::State {
+        crate::State {
+            screen: crate::Screen::Home,
+            session: Some(shared::Session::default()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn checked() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Connection(Message::Checked(true)));
+        assert!(state.connection.online);
+        assert!(!state.connection.replaying);
+
+        state.connection.queue = vec![receipt("a")];
+        let _ = state.update(crate::Message::Connection(Message::Checked(true)));
+        assert!(state.connection.replaying);
+
+        let _ = state.update(crate::Message::Connection(Message::Checked(false)));
+        assert!(!state.connection.online);
+    }
+
+    #[test]
+    fn replayed() {
+        let mut state = init_state();
+        state.connection.online = true;
+        let _ = enqueue(&mut state, receipt("a"));
+        let _ = enqueue(&mut state, receipt("b"));
+        assert!(state.connection.replaying);
+
+        // Sold while the replay was in flight
+        let _ = enqueue(&mut state, receipt("c"));
+        let _ = state.update(crate::Message::Connection(Message::Replayed(Replay {
+            sent: vec!["a".to_string()],
+            offline: true,
+            ..Default::default()
+        })));
+        assert_eq!(state.connection.queue, vec![receipt("b"), receipt("c")]);
+        assert!(!state.connection.online);
+        assert!(!state.connection.replaying);
+        assert_eq!(state.connection.generation, 4);
+
+        let _ = state.update(crate::Message::Connection(Message::Replayed(Replay {
+            sent: vec!["c".to_string()],
+            failed: vec![("b".to_string(), "500".to_string())],
+            offline: false,
+            ..Default::default()
+        })));
+        assert_eq!(state.connection.queue, vec![receipt("b")]);
+        assert!(state.connection.online);
+        assert_eq!(state.connection.error, "b: 500");
+
+        // Refused receipts would be refused again, so they leave the queue
+        let _ = state.update(crate::Message::Connection(Message::Replayed(Replay {
+            rejected: vec![("b".to_string(), "400".to_string())],
+            ..Default::default()
+        })));
+        assert!(state.connection.queue.is_empty());
+        assert_eq!(
+            state.connection.refused,
+            vec![(receipt("b"), "400".to_string())]
+        );
+        assert_eq!(state.connection.error, "b: 400");
+        // The refused receipts and the queue are saved as two generations
+        assert_eq!(state.connection.generation, 7);
+    }
+}
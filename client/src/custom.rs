@@ -29,6 +29,7 @@ pub(crate) fn labeled_text_input<'a>(
     value: &str,
     id: Option<&'static str>,
     shaping: Option<text::Shaping>,
+    on_input: Option<impl Fn(String) -> Message + 'a>,
 ) -> Row<'a, Message> {
     let mut input_label: text::Text = text(label).width(Length::Fill);
     let mut text_input: text_input::TextInput<'_, Message> =
@@ -40,6 +41,9 @@ pub(crate) fn labeled_text_input<'a>(
     if let Some(id) = id {
         text_input = text_input.id(text_input::Id::new(id))
     }
+    if let Some(on_input) = on_input {
+        text_input = text_input.on_input(on_input)
+    }
 
     row![input_label, text_input]
 }
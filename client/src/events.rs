@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use iced::futures::channel::mpsc::Sender;
+use iced::futures::{SinkExt, Stream};
+use iced::{Subscription, Task};
+use shared::ItemEvent;
+
+use crate::api::Api;
+use crate::screen::{inventory, sale};
+
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Applies a catalogue change pushed by the server to the screen that shows items.
+pub(crate) fn update(state: &mut crate::State, event: ItemEvent) -> Task<crate::Message> {
+    let api = state.api();
+    match &mut state.screen {
+        crate::Screen::Inventory(inventory) => {
+            if !inventory::apply_event(inventory, &event) {
+                return Task::perform(inventory::fetch_items(api), |items| {
+                    crate::Message::Inventory(inventory::Message::ItemsFetched(items))
+                });
+            }
+        }
+        crate::Screen::Sale(sale) => {
+            if !sale::apply_event(sale, &event) {
+                return Task::perform(inventory::fetch_items(api), |items| {
+                    crate::Message::Sale(sale::Message::ItemsFetched(items))
+                });
+            }
+        }
+        _ => {}
+    }
+    Task::none()
+}
+
+pub(crate) fn subscription(api: Api) -> Subscription<crate::Message> {
+    Subscription::run_with(api, listen)
+}
+
+/// Follows `GET /events` for as long as the client is logged in, reconnecting when the stream
+/// breaks. Changes may have been missed while disconnected, so every reconnect asks for a reload.
+fn listen(api: &Api) -> impl Stream<Item = crate::Message> + use<> {
+    let api = api.clone();
+    iced::stream::channel(100, |mut output: Sender<crate::Message>| async move {
+        let mut reconnect = false;
+        loop {
+            if let Err(e) = read(&api, &mut output, reconnect).await {
+                eprintln!("events error: {e}");
+            }
+            reconnect = true;
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        }
+    })
+}
+
+async fn read(
+    api: &Api,
+    output: &mut Sender<crate::Message>,
+    reconnect: bool,
+) -> Result<(), String> {
+    let mut response = api
+        .get("/events")
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    if reconnect {
+        output
+            .send(crate::Message::Event(ItemEvent::Reload))
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let mut buffer = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.windows(2).position(|window| window == b"\n\n") {
+            let block: Vec<u8> = buffer.drain(..end + 2).collect();
+            if let Some(event) = parse(&String::from_utf8_lossy(&block)) {
+                output
+                    .send(crate::Message::Event(event))
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the data of one server-sent event. Keep-alive comments have no data.
+fn parse(block: &str) -> Option<ItemEvent> {
+    let data = block
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(str::trim_start)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if data.is_empty() {
+        return None;
+    }
+    match serde_json::from_str(&data) {
+        Ok(event) => Some(event),
+        Err(e) => {
+            eprintln!("json error: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_events() {
+        assert_eq!(parse(":\n\n"), None);
+        assert_eq!(
+            parse("data: {\"type\":\"reload\"}\n\n"),
+            Some(ItemEvent::Reload)
+        );
+        assert_eq!(
+            parse("data:{\"type\":\"deleted\",\"barcode\":\"0\"}\n\n"),
+            Some(ItemEvent::Deleted {
+                barcode: "0".to_string()
+            })
+        );
+        assert_eq!(parse("data: {\"type\":\"unknown\"}\n\n"), None);
+    }
+
+    #[test]
+    fn other_screens() {
+        let mut state = crate::State {
+            screen: crate::Screen::Home,
+            ..Default::default()
+        };
+        let _ = state.update(crate::Message::Event(ItemEvent::Reload));
+        assert_eq!(state.screen, crate::Screen::Home);
+    }
+}
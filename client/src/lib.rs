@@ -1,57 +1,146 @@
+pub(crate) mod api;
+pub(crate) mod cache;
+pub(crate) mod connection;
 pub(crate) mod custom;
+pub(crate) mod events;
+pub(crate) mod printer;
 pub(crate) mod screen;
 
 use iced::{Element, Subscription, Task};
 
-use screen::setting::State as Setting;
-use screen::{home, inventory, setting};
+use screen::setting::Setting;
+use screen::{
+    customer, home, inventory, labels, login, report, returns, sale, setting, stock_take,
+};
+use shared::{ItemEvent, Session};
 
 #[derive(Default, Debug)]
 pub struct State {
     pub(crate) screen: Screen,
     pub(crate) setting: Setting,
+    pub(crate) session: Option<Session>,
+    pub(crate) connection: connection::State,
 }
 
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq)]
 pub(crate) enum Screen {
-    #[default]
     Home,
+    Login(login::State),
     Inventory(Box<inventory::State>),
+    Sale(Box<sale::State>),
     Setting(setting::State),
+    StockTake(Box<stock_take::State>),
+    Customer(Box<customer::State>),
+    Returns(Box<returns::State>),
+    Labels(Box<labels::State>),
+    Report(Box<report::State>),
 }
 
 #[derive(Clone, Debug)]
 pub enum Message {
     Home(home::Message),
+    Login(login::Message),
     Inventory(inventory::Message),
+    Sale(sale::Message),
     Setting(setting::Message),
+    StockTake(stock_take::Message),
+    Customer(customer::Message),
+    Returns(returns::Message),
+    Labels(labels::Message),
+    Report(report::Message),
+    Connection(connection::Message),
+    Event(ItemEvent),
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Screen::Login(login::State::default())
+    }
 }
 
 impl State {
+    /// Starts with the saved settings and the receipts that were still queued, or refused by the
+    /// server, when the client was last closed.
+    pub fn new() -> Self {
+        State {
+            setting: setting::read(),
+            connection: connection::State {
+                queue: cache::read_queue(),
+                refused: cache::read_refused(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn theme(&self) -> iced::Theme {
+        self.setting.theme.into()
+    }
+
+    pub(crate) fn api(&self) -> api::Api {
+        api::Api {
+            url: self.setting.url.clone(),
+            token: self.session.as_ref().map(|session| session.token.clone()),
+        }
+    }
+
     pub fn update(&mut self, message: Message) -> Task<Message> {
+        if let Message::Connection(message) = message {
+            return connection::update(self, message).map(Message::Connection);
+        }
+        if let Message::Event(event) = message {
+            return events::update(self, event);
+        }
         match self.screen {
             Screen::Home => home::update(self, message),
+            Screen::Login(_) => login::update(self, message).map(Message::Login),
             Screen::Inventory(_) => inventory::update(self, message).map(Message::Inventory),
-            Screen::Setting(_) => {
-                setting::update(self, message);
-                Task::none()
-            }
+            Screen::Sale(_) => sale::update(self, message),
+            Screen::Setting(_) => setting::update(self, message).map(Message::Setting),
+            Screen::StockTake(_) => stock_take::update(self, message).map(Message::StockTake),
+            Screen::Customer(_) => customer::update(self, message).map(Message::Customer),
+            Screen::Returns(_) => returns::update(self, message).map(Message::Returns),
+            Screen::Labels(_) => labels::update(self, message).map(Message::Labels),
+            Screen::Report(_) => report::update(self, message).map(Message::Report),
         }
     }
 
     pub fn view(&self) -> Element<Message> {
+        // The rate of the server the till logged in to, which taxes the receipts
+        let vat_rate = self.session.as_ref().map(|session| session.vat_rate);
         match &self.screen {
-            Screen::Home => home::view(),
+            Screen::Home => home::view(&self.connection, self.setting.language),
+            Screen::Login(state) => login::view(state),
             Screen::Inventory(state) => inventory::view(state),
-            Screen::Setting(state) => setting::view(state),
+            Screen::Sale(state) => {
+                sale::view(state, &self.connection, vat_rate.unwrap_or_default())
+            }
+            Screen::Setting(state) => setting::view(state, vat_rate),
+            Screen::StockTake(state) => stock_take::view(state),
+            Screen::Customer(state) => customer::view(state),
+            Screen::Returns(state) => returns::view(state),
+            Screen::Labels(state) => labels::view(state),
+            Screen::Report(state) => report::view(state),
         }
     }
 }
 
 pub fn subscription(state: &State) -> Subscription<Message> {
-    match &state.screen {
+    let screen = match &state.screen {
         Screen::Home => Subscription::none(),
+        Screen::Login(_) => Subscription::none(),
         Screen::Setting(state) => setting::subscription(state),
         Screen::Inventory(state) => inventory::subscription(state),
-    }
+        Screen::Sale(state) => sale::subscription(state),
+        Screen::StockTake(state) => stock_take::subscription(state),
+        Screen::Customer(state) => customer::subscription(state),
+        Screen::Returns(state) => returns::subscription(state),
+        Screen::Labels(state) => labels::subscription(state),
+        Screen::Report(state) => report::subscription(state),
+    };
+    let events = match state.session {
+        Some(_) => events::subscription(state.api()),
+        None => Subscription::none(),
+    };
+    Subscription::batch([screen, connection::subscription(), events])
 }
@@ -2,11 +2,11 @@ use client::{State, subscription};
 use iced::Font;
 
 fn main() -> iced::Result {
-    iced::application(State::default, State::update, State::view)
+    iced::application(State::new, State::update, State::view)
         .title("Sunminimart")
-        .theme(|_| iced::Theme::Light)
+        .theme(State::theme)
         .centered()
-        .font(include_bytes!("../asset/Sarabun-Regular.ttf"))
+        .font(shared::FONT)
         .default_font(Font::with_name("Sarabun"))
         .subscription(subscription)
         .run()
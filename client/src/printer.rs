@@ -0,0 +1,47 @@
+use std::io::Write;
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+/// How long a network printer has to take the data, from connecting to the last byte.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sends plain text to the printer of the settings: a network printer given as `host:port`
+/// (usually port 9100), or the device file of one plugged into this computer.
+pub(crate) async fn print(printer: String, text: String) -> Result<(), String> {
+    // Feed the paper past the cutter
+    send(printer, format!("{text}\n\n\n\n").into_bytes()).await
+}
+
+/// Sends `data` to a printer as it is, like the commands of a label printer. See [`print`] for
+/// the printers it understands.
+pub(crate) async fn send(printer: String, data: Vec<u8>) -> Result<(), String> {
+    if printer.is_empty() {
+        return Err("ยังไม่ได้ตั้งค่าเครื่องพิมพ์".to_string());
+    }
+    match printer.rsplit_once(':') {
+        Some((_, port)) if !printer.starts_with('/') && port.parse::<u16>().is_ok() => {
+            let write = async {
+                let mut stream = TcpStream::connect(&printer).await?;
+                stream.write_all(&data).await?;
+                stream.flush().await
+            };
+            tokio::time::timeout(TIMEOUT, write)
+                .await
+                .map_err(|_| format!("เครื่องพิมพ์ {printer} ไม่ตอบสนอง"))?
+                .map_err(|e| e.to_string())
+        }
+        // Only a device that is there, a mistyped path would otherwise become a new file
+        _ => {
+            let mut device = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&printer)
+                .map_err(|e| e.to_string())?;
+            device
+                .write_all(&data)
+                .and_then(|()| device.flush())
+                .map_err(|e| e.to_string())
+        }
+    }
+}
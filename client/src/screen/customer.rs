@@ -0,0 +1,732 @@
+use chrono::{Datelike, Local, NaiveDate};
+use iced::alignment::{Horizontal, Vertical};
+use iced::keyboard::key;
+use iced::widget::text::LineHeight;
+use iced::widget::{
+    button, column, container, horizontal_space, pick_list, row, text, text_input, vertical_space,
+};
+use iced::{Element, Length, Pixels, Subscription, Task, keyboard};
+use rust_decimal::Decimal;
+
+use crate::api::{self, Api};
+use crate::{custom, printer};
+use shared::number;
+use shared::{Customer, CustomerPayment, LedgerEntry, PaymentMethod, Statement};
+
+/// Ways a customer can pay off their balance.
+const PAYMENT_METHODS: [PaymentMethod; 3] = [
+    PaymentMethod::Cash,
+    PaymentMethod::PromptPay,
+    PaymentMethod::BankTransfer,
+];
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct State {
+    pub search: String,
+    pub customers: Vec<Customer>,
+    /// The customer being looked at, with `id` 0 for one not saved yet.
+    pub customer: Option<Customer>,
+    pub credit_limit: String,
+    pub from: String,
+    pub to: String,
+    pub statement: Option<Statement>,
+    pub method: PaymentMethod,
+    pub amount: String,
+    pub note: String,
+    pub status: String,
+}
+
+impl Default for State {
+    /// Statements of the month so far.
+    fn default() -> Self {
+        let today = Local::now().date_naive();
+        State {
+            search: String::new(),
+            customers: Vec::new(),
+            customer: None,
+            credit_limit: String::new(),
+            from: today.with_day(1).unwrap_or(today).to_string(),
+            to: today.to_string(),
+            statement: None,
+            method: PaymentMethod::Cash,
+            amount: String::new(),
+            note: String::new(),
+            status: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Back,
+    OnSearchChange(String),
+    Search,
+    Found(Result<Vec<Customer>, String>),
+    Select(usize),
+    New,
+
+    OnNameChange(String),
+    OnPhoneChange(String),
+    OnAddressChange(String),
+    OnCreditLimitChange(String),
+    Save,
+    Saved(Result<Customer, String>),
+
+    OnFromChange(String),
+    OnToChange(String),
+    FetchStatement,
+    StatementFetched(Result<Statement, String>),
+    Print,
+    Printed(Result<(), String>),
+
+    SelectMethod(PaymentMethod),
+    OnAmountChange(String),
+    OnNoteChange(String),
+    Pay,
+    Paid(Result<Customer, String>),
+}
+
+pub fn update(state: &mut crate::State, message: crate::Message) -> Task<Message> {
+    let crate::Message::Customer(message) = message else {
+        return Task::none();
+    };
+
+    let api = state.api();
+    let device = state.setting.printer.clone();
+    let shop_name = state.setting.shop_name.clone();
+    let shop_address = state.setting.shop_address.clone();
+    let mut tasks = Vec::new();
+    match message {
+        Message::Back => {
+            state.screen = crate::Screen::Home;
+        }
+        Message::OnSearchChange(search) => {
+            modify(state, |state| {
+                state.search = search;
+            });
+        }
+        Message::Search => {
+            modify(state, |state| {
+                tasks.push(Task::perform(
+                    search(api, state.search.trim().to_string()),
+                    Message::Found,
+                ));
+            });
+        }
+        Message::Found(result) => {
+            modify(state, |state| match result {
+                Ok(customers) => {
+                    state.status = match customers.is_empty() {
+                        true => format!("ไม่พบลูกค้า {}", state.search),
+                        false => String::new(),
+                    };
+                    state.customers = customers;
+                }
+                Err(e) => state.status = e,
+            });
+        }
+        Message::Select(i) => {
+            modify(state, |state| {
+                if let Some(customer) = state.customers.get(i).cloned() {
+                    select(state, customer);
+                    tasks.push(fetch_statement(api, state));
+                }
+            });
+        }
+        Message::New => {
+            modify(state, |state| {
+                select(state, Customer::default());
+            });
+        }
+        Message::OnNameChange(name) => {
+            modify(state, |state| {
+                if let Some(customer) = &mut state.customer {
+                    customer.name = name;
+                }
+            });
+        }
+        Message::OnPhoneChange(phone) => {
+            modify(state, |state| {
+                if let Some(customer) = &mut state.customer {
+                    customer.phone = phone;
+                }
+            });
+        }
+        Message::OnAddressChange(address) => {
+            modify(state, |state| {
+                if let Some(customer) = &mut state.customer {
+                    customer.address = address;
+                }
+            });
+        }
+        Message::OnCreditLimitChange(credit_limit) => {
+            modify(state, |state| {
+                state.credit_limit = credit_limit;
+            });
+        }
+        Message::Save => {
+            modify(state, |state| {
+                let Some(customer) = &mut state.customer else {
+                    return;
+                };
+                match number::parse_decimal(&state.credit_limit) {
+                    Some(credit_limit) if credit_limit >= Decimal::ZERO => {
+                        customer.credit_limit = credit_limit;
+                        tasks.push(Task::perform(save(api, customer.clone()), Message::Saved));
+                    }
+                    _ => state.status = format!("วงเงินไม่ถูกต้อง: {}", state.credit_limit),
+                }
+            });
+        }
+        Message::Saved(result) => {
+            modify(state, |state| match result {
+                Ok(customer) => {
+                    state.status = "บันทึกแล้ว".to_string();
+                    match state.customers.iter_mut().find(|c| c.id == customer.id) {
+                        Some(listed) => *listed = customer.clone(),
+                        None => state.customers.push(customer.clone()),
+                    }
+                    select(state, customer);
+                }
+                Err(e) => state.status = e,
+            });
+        }
+        Message::OnFromChange(from) => {
+            modify(state, |state| {
+                state.from = from;
+            });
+        }
+        Message::OnToChange(to) => {
+            modify(state, |state| {
+                state.to = to;
+            });
+        }
+        Message::FetchStatement => {
+            modify(state, |state| {
+                tasks.push(fetch_statement(api, state));
+            });
+        }
+        Message::StatementFetched(result) => {
+            modify(state, |state| match result {
+                Ok(statement) => {
+                    state.statement = Some(statement);
+                    state.status = String::new();
+                }
+                Err(e) => state.status = e,
+            });
+        }
+        Message::Print => {
+            modify(state, |state| {
+                if let Some(statement) = &state.statement {
+                    tasks.push(Task::perform(
+                        printer::print(
+                            device,
+                            statement_text(&shop_name, &shop_address, statement),
+                        ),
+                        Message::Printed,
+                    ));
+                }
+            });
+        }
+        Message::Printed(result) => {
+            modify(state, |state| {
+                state.status = match result {
+                    Ok(()) => "พิมพ์แล้ว".to_string(),
+                    Err(e) => e,
+                };
+            });
+        }
+        Message::SelectMethod(method) => {
+            modify(state, |state| {
+                state.method = method;
+            });
+        }
+        Message::OnAmountChange(amount) => {
+            modify(state, |state| {
+                state.amount = amount;
+            });
+        }
+        Message::OnNoteChange(note) => {
+            modify(state, |state| {
+                state.note = note;
+            });
+        }
+        Message::Pay => {
+            modify(state, |state| {
+                let Some(customer) = state.customer.as_ref().filter(|c| c.id != 0) else {
+                    return;
+                };
+                match number::parse_decimal(&state.amount) {
+                    Some(amount) if amount > Decimal::ZERO => {
+                        let payment = CustomerPayment {
+                            method: state.method,
+                            amount: amount.round_dp(2),
+                            note: state.note.trim().to_string(),
+                        };
+                        tasks.push(Task::perform(pay(api, customer.id, payment), Message::Paid));
+                    }
+                    _ => state.status = format!("จำนวนเงินไม่ถูกต้อง: {}", state.amount),
+                }
+            });
+        }
+        Message::Paid(result) => {
+            modify(state, |state| match result {
+                Ok(customer) => {
+                    state.status = format!("รับชำระแล้ว ยอดค้าง {} บาท", customer.balance);
+                    state.amount = String::new();
+                    state.note = String::new();
+                    if let Some(listed) = state.customers.iter_mut().find(|c| c.id == customer.id) {
+                        *listed = customer.clone();
+                    }
+                    state.customer = Some(customer);
+                    tasks.push(fetch_statement(api, state));
+                }
+                Err(e) => state.status = e,
+            });
+        }
+    }
+
+    Task::batch(tasks)
+}
+
+fn modify<F>(state: &mut crate::State, f: F)
+where
+    F: FnOnce(&mut State),
+{
+    if let crate::Screen::Customer(ref mut state) = state.screen {
+        f(state);
+    } else {
+        panic!("Screen error in customer");
+    }
+}
+
+fn select(state: &mut State, customer: Customer) {
+    state.credit_limit = customer.credit_limit.to_string();
+    state.customer = Some(customer);
+    state.statement = None;
+    state.amount = String::new();
+    state.note = String::new();
+}
+
+fn fetch_statement(api: Api, state: &mut State) -> Task<Message> {
+    let Some(customer) = state.customer.as_ref().filter(|c| c.id != 0) else {
+        return Task::none();
+    };
+    match (
+        state.from.trim().parse::<NaiveDate>(),
+        state.to.trim().parse::<NaiveDate>(),
+    ) {
+        (Ok(from), Ok(to)) => Task::perform(
+            statement(api, customer.id, from, to),
+            Message::StatementFetched,
+        ),
+        _ => {
+            state.status = "วันที่ต้องเป็นแบบ 2026-01-31".to_string();
+            Task::none()
+        }
+    }
+}
+
+/// Customers whose name or phone number contains `keyword`.
+pub(crate) async fn search(api: Api, keyword: String) -> Result<Vec<Customer>, String> {
+    api::send(api.get("/customers").query(&[("keyword", keyword)])).await
+}
+
+/// Adds the customer, or changes them when they have an id.
+async fn save(api: Api, customer: Customer) -> Result<Customer, String> {
+    match customer.id {
+        0 => api::send(api.post("/customers").json(&customer)).await,
+        id => api::send(api.put(&format!("/customers/{id}")).json(&customer)).await,
+    }
+}
+
+async fn statement(api: Api, id: u32, from: NaiveDate, to: NaiveDate) -> Result<Statement, String> {
+    api::send(
+        api.get(&format!("/customers/{id}/statement"))
+            .query(&[("from", from), ("to", to)]),
+    )
+    .await
+}
+
+async fn pay(api: Api, id: u32, payment: CustomerPayment) -> Result<Customer, String> {
+    api::send(
+        api.post(&format!("/customers/{id}/payments"))
+            .json(&payment),
+    )
+    .await
+}
+
+fn describe(entry: &LedgerEntry) -> String {
+    let mut description = match (entry.receipt_id, entry.method) {
+        (Some(receipt_id), _) if entry.return_id.is_some() => {
+            format!("คืนสินค้า ใบเสร็จ #{receipt_id}")
+        }
+        (Some(receipt_id), _) => format!("ใบเสร็จ #{receipt_id}"),
+        (None, Some(method)) => format!("ชำระ {method}"),
+        (None, None) => String::new(),
+    };
+    if !entry.note.is_empty() {
+        description = format!("{description} {}", entry.note);
+    }
+    description
+}
+
+/// The statement as printed on the receipt printer, one line per entry, under the name and
+/// address of the shop.
+fn statement_text(shop_name: &str, shop_address: &str, statement: &Statement) -> String {
+    let mut lines = vec![shop_name.to_string()];
+    if !shop_address.is_empty() {
+        lines.push(shop_address.to_string());
+    }
+    lines.extend([
+        format!("ใบแจ้งยอด {}", statement.customer),
+        format!("{} ถึง {}", statement.from, statement.to),
+        format!("ยอดยกมา {:>12}", statement.opening_balance),
+    ]);
+    for entry in &statement.entries {
+        lines.push(format!(
+            "{} {} {:>10}",
+            entry.created_at.format("%d/%m/%y"),
+            describe(entry),
+            entry.amount
+        ));
+    }
+    lines.push(format!("ยอดค้างชำระ {:>12}", statement.closing_balance()));
+    lines.push(format!("แต้มสะสม {}", statement.customer.points));
+    lines.join("\n")
+}
+
+fn cell<'a>(value: String) -> Element<'a, crate::Message> {
+    text(value)
+        .line_height(LineHeight::Relative(2.0))
+        .shaping(text::Shaping::Advanced)
+        .width(Length::Fill)
+        .align_x(Horizontal::Center)
+        .into()
+}
+
+fn labeled<'a>(
+    label: &'a str,
+    input: impl Into<Element<'a, crate::Message>>,
+) -> Element<'a, crate::Message> {
+    row![
+        text(label)
+            .shaping(text::Shaping::Advanced)
+            .line_height(LineHeight::Relative(2.0))
+            .align_y(Vertical::Center)
+            .width(Length::Fill),
+        container(input).width(Length::FillPortion(3)),
+    ]
+    .into()
+}
+
+/// The details of the customer, and their statement and payments once they are saved.
+fn details(state: &State) -> Element<'_, crate::Message> {
+    let Some(customer) = &state.customer else {
+        return column![].into();
+    };
+    let mut details = column![
+        labeled(
+            "ชื่อ: ",
+            text_input("", &customer.name)
+                .on_input(|input| crate::Message::Customer(Message::OnNameChange(input))),
+        ),
+        labeled(
+            "โทรศัพท์: ",
+            text_input("", &customer.phone)
+                .on_input(|input| crate::Message::Customer(Message::OnPhoneChange(input))),
+        ),
+        labeled(
+            "ที่อยู่: ",
+            text_input("", &customer.address)
+                .on_input(|input| crate::Message::Customer(Message::OnAddressChange(input))),
+        ),
+        labeled(
+            "วงเงินเชื่อ: ",
+            text_input("0", &state.credit_limit)
+                .on_input(|input| crate::Message::Customer(Message::OnCreditLimitChange(input))),
+        ),
+        custom::button("บันทึก", crate::Message::Customer(Message::Save)),
+    ]
+    .spacing(Pixels(10.0));
+    if customer.id == 0 {
+        return details.into();
+    }
+
+    details = details.push(
+        text(format!(
+            "ยอดค้างชำระ: {} บาท (เชื่อได้อีก {} บาท) แต้มสะสม: {}",
+            customer.balance,
+            customer.available_credit(),
+            customer.points
+        ))
+        .shaping(text::Shaping::Advanced),
+    );
+    details = details.push(
+        row![
+            text_input("", &state.from)
+                .on_input(|input| crate::Message::Customer(Message::OnFromChange(input))),
+            text("ถึง").shaping(text::Shaping::Advanced),
+            text_input("", &state.to)
+                .on_input(|input| crate::Message::Customer(Message::OnToChange(input))),
+            button(text("ดูรายการ").shaping(text::Shaping::Advanced))
+                .on_press(crate::Message::Customer(Message::FetchStatement)),
+            button(text("พิมพ์").shaping(text::Shaping::Advanced))
+                .on_press(crate::Message::Customer(Message::Print)),
+        ]
+        .spacing(Pixels(10.0)),
+    );
+    if let Some(statement) = &state.statement {
+        details = details
+            .push(row![
+                cell("วันที่".to_string()),
+                cell("รายการ".to_string()),
+                cell("จำนวนเงิน".to_string()),
+                cell("แต้ม".to_string()),
+            ])
+            .push(row![
+                cell(statement.from.to_string()),
+                cell("ยอดยกมา".to_string()),
+                cell(statement.opening_balance.to_string()),
+                cell(String::new()),
+            ])
+            .push(
+                custom::list(statement.entries.clone(), |_, entry| {
+                    row![
+                        cell(entry.created_at.format("%Y-%m-%d %H:%M").to_string()),
+                        cell(describe(entry)),
+                        cell(entry.amount.to_string()),
+                        cell(entry.points.to_string()),
+                    ]
+                    .into()
+                })
+                .height(Length::Fill),
+            )
+            .push(
+                text(format!("ยอดค้างชำระ: {} บาท", statement.closing_balance()))
+                    .shaping(text::Shaping::Advanced),
+            );
+    }
+    details
+        .push(
+            row![
+                pick_list(PAYMENT_METHODS, Some(state.method), |method| {
+                    crate::Message::Customer(Message::SelectMethod(method))
+                })
+                .text_shaping(text::Shaping::Advanced),
+                text_input("จำนวนเงิน", &state.amount)
+                    .on_input(|input| crate::Message::Customer(Message::OnAmountChange(input)))
+                    .on_submit(crate::Message::Customer(Message::Pay)),
+                text_input("หมายเหตุ", &state.note)
+                    .on_input(|input| crate::Message::Customer(Message::OnNoteChange(input)))
+                    .on_submit(crate::Message::Customer(Message::Pay)),
+                button(text("รับชำระ").shaping(text::Shaping::Advanced))
+                    .on_press(crate::Message::Customer(Message::Pay)),
+            ]
+            .spacing(Pixels(10.0)),
+        )
+        .into()
+}
+
+pub fn view(state: &State) -> Element<crate::Message> {
+    column![
+        vertical_space(),
+        custom::title("ลูกค้า"),
+        row![
+            horizontal_space(),
+            column![
+                row![
+                    text_input("ชื่อหรือเบอร์โทร", &state.search)
+                        .id(text_input::Id::new("customer_search"))
+                        .on_input(|input| crate::Message::Customer(Message::OnSearchChange(input)))
+                        .on_submit(crate::Message::Customer(Message::Search)),
+                    button(text("ค้นหา").shaping(text::Shaping::Advanced))
+                        .on_press(crate::Message::Customer(Message::Search)),
+                    button(text("เพิ่ม").shaping(text::Shaping::Advanced))
+                        .on_press(crate::Message::Customer(Message::New)),
+                ]
+                .spacing(Pixels(10.0)),
+                custom::list(state.customers.clone(), |i, customer| {
+                    button(
+                        row![
+                            cell(customer.to_string()),
+                            cell(format!("ค้าง {} บาท", customer.balance)),
+                        ]
+                        .width(Length::Fill),
+                    )
+                    .on_press(crate::Message::Customer(Message::Select(i)))
+                    .width(Length::Fill)
+                    .into()
+                })
+                .height(Length::Fill),
+                text(state.status.clone()).shaping(text::Shaping::Advanced),
+            ]
+            .width(Length::FillPortion(5))
+            .spacing(Pixels(10.0)),
+            horizontal_space(),
+            details(state),
+            horizontal_space(),
+        ]
+        .height(Length::FillPortion(12)),
+        vertical_space()
+    ]
+    .height(Length::Fill)
+    .width(Length::Fill)
+    .into()
+}
+
+pub(crate) fn subscription(_state: &State) -> Subscription<crate::Message> {
+    keyboard::on_key_press(|keyboard, _| match keyboard {
+        keyboard::Key::Named(key::Named::Escape) => Some(crate::Message::Customer(Message::Back)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn init_state() -> crate::State {
+        crate::State {
+            screen: crate::Screen::Customer(Box::default()),
+            ..Default::default()
+        }
+    }
+
+    fn test<F>(state: &crate::State, f: F)
+    where
+        F: FnOnce(&State),
+    {
+        if let crate::Screen::Customer(state) = &state.screen {
+            f(state);
+        } else {
+            panic!("Screen error in customer");
+        }
+    }
+
+    fn somchai() -> Customer {
+        Customer {
+            id: 1,
+            name: "สมชาย".to_string(),
+            phone: "0812345678".to_string(),
+            credit_limit: Decimal::new(500, 0),
+            balance: Decimal::new(150, 0),
+            points: 6,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn back() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Customer(Message::Back));
+        assert_eq!(state.screen, crate::Screen::Home);
+    }
+
+    #[test]
+    fn select_and_edit() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Customer(Message::Found(Ok(
+            vec![somchai()],
+        ))));
+        let _ = state.update(crate::Message::Customer(Message::Select(0)));
+        test(&state, |state| {
+            assert_eq!(state.customer, Some(somchai()));
+            assert_eq!(state.credit_limit, "500");
+        });
+
+        let _ = state.update(crate::Message::Customer(Message::OnCreditLimitChange(
+            "-1".to_string(),
+        )));
+        let _ = state.update(crate::Message::Customer(Message::Save));
+        test(&state, |state| {
+            assert!(state.status.contains("-1"));
+        });
+
+        let saved = Customer {
+            credit_limit: Decimal::new(1000, 0),
+            ..somchai()
+        };
+        let _ = state.update(crate::Message::Customer(Message::Saved(Ok(saved.clone()))));
+        test(&state, |state| {
+            assert_eq!(state.customers, vec![saved.clone()]);
+            assert_eq!(state.credit_limit, "1000");
+        });
+
+        let _ = state.update(crate::Message::Customer(Message::New));
+        test(&state, |state| {
+            assert_eq!(state.customer, Some(Customer::default()));
+        });
+    }
+
+    #[test]
+    fn paid() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Customer(Message::Found(Ok(
+            vec![somchai()],
+        ))));
+        let _ = state.update(crate::Message::Customer(Message::Select(0)));
+        let _ = state.update(crate::Message::Customer(Message::OnAmountChange(
+            "abc".to_string(),
+        )));
+        let _ = state.update(crate::Message::Customer(Message::Pay));
+        test(&state, |state| {
+            assert!(state.status.contains("abc"));
+        });
+
+        let paid = Customer {
+            balance: Decimal::new(50, 0),
+            ..somchai()
+        };
+        let _ = state.update(crate::Message::Customer(Message::Paid(Ok(paid.clone()))));
+        test(&state, |state| {
+            assert_eq!(state.customer, Some(paid.clone()));
+            assert_eq!(state.customers, vec![paid.clone()]);
+            assert!(state.status.contains("50"));
+        });
+    }
+
+    #[test]
+    fn print_statement() {
+        let statement = Statement {
+            customer: somchai(),
+            from: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap(),
+            to: NaiveDate::from_ymd_opt(2026, 10, 31).unwrap(),
+            opening_balance: Decimal::new(100, 0),
+            entries: vec![
+                LedgerEntry {
+                    created_at: NaiveDate::from_ymd_opt(2026, 10, 5)
+                        .unwrap()
+                        .and_hms_opt(9, 0, 0)
+                        .unwrap(),
+                    receipt_id: Some(12),
+                    amount: Decimal::new(150, 0),
+                    points: 6,
+                    ..Default::default()
+                },
+                LedgerEntry {
+                    created_at: NaiveDate::from_ymd_opt(2026, 10, 30)
+                        .unwrap()
+                        .and_hms_opt(18, 0, 0)
+                        .unwrap(),
+                    method: Some(PaymentMethod::Cash),
+                    amount: Decimal::new(-100, 0),
+                    note: "งวดแรก".to_string(),
+                    ..Default::default()
+                },
+            ],
+        };
+        let text = statement_text("ซันมินิมาร์ท", "", &statement);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "ซันมินิมาร์ท");
+        assert_eq!(lines[1], "ใบแจ้งยอด สมชาย (0812345678)");
+        assert!(lines[4].starts_with("05/10/26 ใบเสร็จ #12"));
+        assert!(lines[5].contains("ชำระ เงินสด งวดแรก"));
+        assert!(lines[6].ends_with("150"));
+
+        let text = statement_text("ซันมินิมาร์ท", "1 ถนนสุขุมวิท", &statement);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[1], "1 ถนนสุขุมวิท");
+        assert_eq!(lines[2], "ใบแจ้งยอด สมชาย (0812345678)");
+    }
+}
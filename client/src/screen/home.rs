@@ -1,43 +1,125 @@
 use iced::widget::container::Style;
 use iced::widget::text::Shaping;
-use iced::widget::{button, column, container, horizontal_space, row, text};
+use iced::widget::{button, column, container, horizontal_space, row, text, text_input};
 use iced::{Alignment, Border, Element, Length, Pixels, Task, color};
 
 // use crate::screen::{inventory, setting};
-use super::{inventory, setting};
+use super::{inventory, labels, login, report, sale, setting, stock_take};
+use crate::{api, connection};
 
 #[derive(Clone, Debug)]
 pub enum Message {
+    GotoSale,
     GotoInventory,
+    GotoStockTake,
+    GotoCustomer,
+    GotoReturns,
+    GotoLabels,
+    GotoReport,
     GotoSetting,
+    Logout,
 }
 
 pub fn update(state: &mut crate::State, message: crate::Message) -> Task<crate::Message> {
     if let crate::Message::Home(message) = message {
         match message {
+            Message::GotoSale => {
+                state.screen = crate::Screen::Sale(Box::default());
+                Task::batch([
+                    Task::perform(inventory::fetch_items(state.api()), |items| {
+                        crate::Message::Sale(sale::Message::ItemsFetched(items))
+                    }),
+                    Task::perform(sale::fetch_promotions(state.api()), |promotions| {
+                        crate::Message::Sale(sale::Message::PromotionsFetched(promotions))
+                    }),
+                    text_input::focus(text_input::Id::new("sale_barcode")),
+                ])
+            }
             Message::GotoInventory => {
                 state.screen = crate::Screen::Inventory(Box::default());
-                Task::perform(inventory::fetch_items(state.setting.url.clone()), |items| {
-                    crate::Message::Inventory(inventory::Message::ItemsFetched(items))
-                })
+                Task::batch([
+                    Task::perform(inventory::fetch_items(state.api()), |items| {
+                        crate::Message::Inventory(inventory::Message::ItemsFetched(items))
+                    }),
+                    Task::perform(inventory::fetch_categories(state.api()), |categories| {
+                        crate::Message::Inventory(inventory::Message::CategoriesFetched(categories))
+                    }),
+                ])
+            }
+            Message::GotoStockTake => {
+                state.screen = crate::Screen::StockTake(Box::default());
+                Task::batch([
+                    Task::perform(stock_take::start(state.api()), |draft| {
+                        crate::Message::StockTake(stock_take::Message::Started(draft))
+                    }),
+                    Task::perform(inventory::fetch_items(state.api()), |items| {
+                        crate::Message::StockTake(stock_take::Message::ItemsFetched(items))
+                    }),
+                ])
+            }
+            Message::GotoCustomer => {
+                state.screen = crate::Screen::Customer(Box::default());
+                text_input::focus(text_input::Id::new("customer_search"))
+            }
+            Message::GotoReturns => {
+                state.screen = crate::Screen::Returns(Box::default());
+                text_input::focus(text_input::Id::new("return_receipt_id"))
+            }
+            Message::GotoLabels => {
+                state.screen = crate::Screen::Labels(Box::default());
+                Task::batch([
+                    Task::perform(labels::fetch_queue(state.api()), |queue| {
+                        crate::Message::Labels(labels::Message::QueueFetched(queue))
+                    }),
+                    Task::perform(inventory::fetch_items(state.api()), |items| {
+                        crate::Message::Labels(labels::Message::ItemsFetched(items))
+                    }),
+                    text_input::focus(text_input::Id::new("label_barcode")),
+                ])
+            }
+            Message::GotoReport => {
+                let mut report = report::State::default();
+                let fetch = report::fetch_sales(state.api(), &mut report);
+                state.screen = crate::Screen::Report(Box::new(report));
+                fetch.map(crate::Message::Report)
             }
             Message::GotoSetting => {
-                state.screen = crate::Screen::Setting(setting::State::default());
+                state.screen = crate::Screen::Setting(setting::State::new(&state.setting));
                 Task::none()
             }
+            Message::Logout => {
+                let logout = api::execute(state.api().post("/logout"));
+                state.session = None;
+                state.screen = crate::Screen::Login(login::State::default());
+                Task::future(logout).discard()
+            }
         }
     } else {
         Task::none()
     }
 }
 
-pub fn view<'a>() -> Element<'a, crate::Message> {
+pub fn view<'a>(
+    connection: &connection::State,
+    language: setting::Language,
+) -> Element<'a, crate::Message> {
     container(row![
         horizontal_space().width(Length::Fill),
         container(row![
             column![
+                text(connection::status(connection)).shaping(Shaping::Advanced),
+                text(connection.error.clone()).shaping(Shaping::Advanced),
+                button(
+                    text(language.pick("ขายสินค้า", "Sell"))
+                        .shaping(Shaping::Advanced)
+                        .size(Pixels(30.0))
+                        .width(Length::Fill)
+                        .align_x(Alignment::Center)
+                )
+                .padding(20)
+                .on_press(crate::Message::Home(Message::GotoSale)),
                 button(
-                    text("คลังสินค้า")
+                    text(language.pick("คลังสินค้า", "Inventory"))
                         .size(Pixels(30.0))
                         .width(Length::Fill)
                         .align_x(Alignment::Center)
@@ -45,7 +127,52 @@ pub fn view<'a>() -> Element<'a, crate::Message> {
                 .padding(20)
                 .on_press(crate::Message::Home(Message::GotoInventory)),
                 button(
-                    text("ตั้งค่า")
+                    text(language.pick("ตรวจนับสต็อก", "Stock take"))
+                        .shaping(Shaping::Advanced)
+                        .size(Pixels(30.0))
+                        .width(Length::Fill)
+                        .align_x(Alignment::Center)
+                )
+                .padding(20)
+                .on_press(crate::Message::Home(Message::GotoStockTake)),
+                button(
+                    text(language.pick("ลูกค้า", "Customers"))
+                        .shaping(Shaping::Advanced)
+                        .size(Pixels(30.0))
+                        .width(Length::Fill)
+                        .align_x(Alignment::Center)
+                )
+                .padding(20)
+                .on_press(crate::Message::Home(Message::GotoCustomer)),
+                button(
+                    text(language.pick("คืนสินค้า", "Returns"))
+                        .shaping(Shaping::Advanced)
+                        .size(Pixels(30.0))
+                        .width(Length::Fill)
+                        .align_x(Alignment::Center)
+                )
+                .padding(20)
+                .on_press(crate::Message::Home(Message::GotoReturns)),
+                button(
+                    text(language.pick("ป้ายราคา", "Shelf tags"))
+                        .shaping(Shaping::Advanced)
+                        .size(Pixels(30.0))
+                        .width(Length::Fill)
+                        .align_x(Alignment::Center)
+                )
+                .padding(20)
+                .on_press(crate::Message::Home(Message::GotoLabels)),
+                button(
+                    text(language.pick("ยอดขายตามหมวดหมู่", "Sales by category"))
+                        .shaping(Shaping::Advanced)
+                        .size(Pixels(30.0))
+                        .width(Length::Fill)
+                        .align_x(Alignment::Center)
+                )
+                .padding(20)
+                .on_press(crate::Message::Home(Message::GotoReport)),
+                button(
+                    text(language.pick("ตั้งค่า", "Settings"))
                         .shaping(Shaping::Advanced)
                         .size(Pixels(30.0))
                         .width(Length::Fill)
@@ -53,6 +180,15 @@ pub fn view<'a>() -> Element<'a, crate::Message> {
                 )
                 .padding(20)
                 .on_press(crate::Message::Home(Message::GotoSetting)),
+                button(
+                    text(language.pick("ออกจากระบบ", "Log out"))
+                        .shaping(Shaping::Advanced)
+                        .size(Pixels(30.0))
+                        .width(Length::Fill)
+                        .align_x(Alignment::Center)
+                )
+                .padding(20)
+                .on_press(crate::Message::Home(Message::Logout)),
             ]
             .spacing(Pixels(20.0)),
         ])
@@ -77,7 +213,18 @@ mod test {
     use super::*;
 
     fn init_state() -> crate::State {
-        crate::State::default()
+        crate::State {
+            screen: crate::Screen::Home,
+            session: Some(shared::Session::default()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn goto_sale() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Home(Message::GotoSale));
+        assert_eq!(state.screen, crate::Screen::Sale(Box::default()));
     }
 
     #[test]
@@ -87,13 +234,56 @@ mod test {
         assert_eq!(state.screen, crate::Screen::Inventory(Box::default()));
     }
 
+    #[test]
+    fn goto_stock_take() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Home(Message::GotoStockTake));
+        assert_eq!(state.screen, crate::Screen::StockTake(Box::default()));
+    }
+
+    #[test]
+    fn goto_customer() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Home(Message::GotoCustomer));
+        assert_eq!(state.screen, crate::Screen::Customer(Box::default()));
+    }
+
+    #[test]
+    fn goto_returns() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Home(Message::GotoReturns));
+        assert_eq!(state.screen, crate::Screen::Returns(Box::default()));
+    }
+
+    #[test]
+    fn goto_labels() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Home(Message::GotoLabels));
+        assert_eq!(state.screen, crate::Screen::Labels(Box::default()));
+    }
+
+    #[test]
+    fn goto_report() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Home(Message::GotoReport));
+        assert_eq!(state.screen, crate::Screen::Report(Box::default()));
+    }
+
+    #[test]
+    fn logout() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Home(Message::Logout));
+        assert_eq!(state.screen, crate::Screen::Login(login::State::default()));
+        assert_eq!(state.session, None);
+    }
+
     #[test]
     fn goto_setting() {
         let mut state = init_state();
         let _ = state.update(crate::Message::Home(Message::GotoSetting));
         assert_eq!(
             state.screen,
-            crate::Screen::Setting(setting::State::default())
+            crate::Screen::Setting(setting::State::new(&state.setting))
         );
     }
 }
@@ -3,16 +3,15 @@ use iced::alignment::{Horizontal, Vertical};
 use iced::keyboard::key;
 use iced::widget::text::LineHeight;
 use iced::widget::{
-    button, column, container, horizontal_space, row, text, text_input, vertical_space,
+    button, column, container, horizontal_space, pick_list, row, text, text_input, vertical_space,
 };
 use iced::{Color, Element, Length, Pixels, Subscription, Task, color, keyboard};
-use reqwest;
-use rust_decimal::Decimal;
-use rust_decimal::prelude::FromPrimitive;
-use serde_json;
 
-use crate::custom;
-use shared::Item;
+use crate::api::{self, Api};
+use crate::{cache, custom};
+use shared::barcode::{self, BarcodeError, BarcodeKind};
+use shared::number::{self, MoneyError};
+use shared::{AuditEntry, Category, Item, ItemEvent, TaxClass, Unit};
 
 #[derive(Default, Debug, PartialEq)]
 pub(crate) struct State {
@@ -22,6 +21,46 @@ pub(crate) struct State {
     pub position: usize,
     pub search: Search,
     pub mode: Mode,
+    pub categories: Vec<Category>,
+    pub category: Option<CategoryFilter>,
+    pub history: Vec<AuditEntry>,
+    pub draft: Draft,
+    pub status: String,
+}
+
+/// The numbers of a new item as they are typed, which may not parse yet halfway through.
+#[derive(Default, Debug, PartialEq)]
+pub(crate) struct Draft {
+    cost: String,
+    price: String,
+    quantity: String,
+}
+
+/// What the category picker narrows the items to: a category with everything nested under it,
+/// or the items left without one.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum CategoryFilter {
+    Category(Category),
+    NoCategory,
+}
+
+impl std::fmt::Display for CategoryFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CategoryFilter::Category(category) => write!(f, "{category}"),
+            CategoryFilter::NoCategory => write!(f, "ไม่มีหมวดหมู่"),
+        }
+    }
+}
+
+/// The choices of the category picker, the categories followed by no category.
+fn category_filters(categories: &[Category]) -> Vec<CategoryFilter> {
+    categories
+        .iter()
+        .cloned()
+        .map(CategoryFilter::Category)
+        .chain([CategoryFilter::NoCategory])
+        .collect()
 }
 
 #[derive(Default, Debug, PartialEq)]
@@ -35,6 +74,8 @@ pub(crate) enum Mode {
     #[default]
     Search,
     Edit,
+    /// Filling in an item that is not in the catalogue yet
+    Create,
 }
 
 #[derive(Debug, Clone)]
@@ -47,16 +88,27 @@ pub enum Message {
 
     FetchItems,
     ItemsFetched(Vec<Item>),
+    CategoriesFetched(Vec<Category>),
+    OnCategorySelect(CategoryFilter),
+    HistoryFetched(Vec<AuditEntry>),
 
     ChangePosition(key::Named),
     PositionChanged(key::Named, bool),
 
     EnterEditMode,
+    NewItem,
+    OnBarcodeChange(String),
+    AllocateBarcode(BarcodeKind),
+    BarcodeAllocated(Result<String, String>),
+    Save,
+    Saved(Result<Item, String>),
 
     OnNameChange(String),
     OnCostChange(String),
     OnPriceChange(String),
     OnQuantityChange(String),
+    OnUnitSelect(Unit),
+    OnTaxClassSelect(TaxClass),
 }
 
 pub fn update(state: &mut crate::State, message: crate::Message) -> Task<Message> {
@@ -70,22 +122,96 @@ pub fn update(state: &mut crate::State, message: crate::Message) -> Task<Message
             }
             Message::OnSearchChange(search) => {
                 modify(state, |state| {
-                    state.search.value = search.to_string();
-                    state.position = 0;
-                    state.filtered_items = state
-                        .all_items
-                        .clone()
-                        .into_iter()
-                        .filter(|item| {
-                            item.barcode.contains(&search) || item.name.contains(&search)
-                        })
-                        .collect();
+                    state.search.value = search;
+                    filter(state);
+                });
+            }
+            Message::OnCategorySelect(category) => {
+                modify(state, |state| {
+                    state.category = Some(category);
+                    filter(state);
                 });
             }
             Message::OnSearchSubmit => {
-                modify(state, |state| match state.filtered_items.is_empty() {
-                    true => state.current_item = Item::default(),
-                    false => state.current_item = state.filtered_items[state.position].clone(),
+                let mut barcode = String::new();
+                modify(state, |state| {
+                    match state.filtered_items.is_empty() {
+                        true => state.current_item = Item::default(),
+                        false => state.current_item = state.filtered_items[state.position].clone(),
+                    }
+                    state.history = Vec::new();
+                    barcode = state.current_item.barcode.clone();
+                });
+                if !barcode.is_empty() {
+                    tasks.push(Task::perform(
+                        fetch_history(state.api(), barcode),
+                        Message::HistoryFetched,
+                    ));
+                }
+            }
+            Message::NewItem => {
+                modify(state, |state| {
+                    state.mode = Mode::Create;
+                    state.current_item = Item::default();
+                    state.history = Vec::new();
+                    state.draft = Draft::default();
+                    state.status = String::new();
+                });
+                tasks.push(text_input::focus(text_input::Id::new("barcode")));
+            }
+            Message::OnBarcodeChange(barcode) => {
+                modify(state, |state| {
+                    if state.mode == Mode::Create {
+                        state.current_item.barcode = barcode;
+                    }
+                });
+            }
+            Message::AllocateBarcode(kind) => {
+                modify(state, |state| {
+                    state.status = String::new();
+                });
+                tasks.push(Task::perform(
+                    allocate_barcode(state.api(), kind),
+                    Message::BarcodeAllocated,
+                ));
+            }
+            Message::BarcodeAllocated(result) => {
+                modify(state, |state| match result {
+                    Ok(barcode) => state.current_item.barcode = barcode,
+                    Err(e) => state.status = e,
+                });
+            }
+            Message::Save => {
+                let mut item = None;
+                modify(state, |state| {
+                    if state.mode != Mode::Create {
+                        return;
+                    }
+                    match check_new_item(state) {
+                        Ok(()) => {
+                            state.status = "กำลังบันทึก...".to_string();
+                            item = Some(state.current_item.clone());
+                        }
+                        Err(e) => state.status = e,
+                    }
+                });
+                if let Some(item) = item {
+                    tasks.push(Task::perform(
+                        create_item(state.api(), item),
+                        Message::Saved,
+                    ));
+                }
+            }
+            Message::Saved(result) => {
+                modify(state, |state| match result {
+                    Ok(item) => {
+                        state.status = format!("เพิ่มสินค้า {} แล้ว", item.barcode);
+                        ItemEvent::Changed { item: item.clone() }.apply(&mut state.all_items);
+                        filter(state);
+                        state.current_item = item;
+                        state.mode = Mode::Search;
+                    }
+                    Err(e) => state.status = e,
                 });
             }
             Message::OnNameChange(name) => {
@@ -95,40 +221,51 @@ pub fn update(state: &mut crate::State, message: crate::Message) -> Task<Message
             }
             Message::OnCostChange(cost) => {
                 modify(state, |state| {
-                    if let Ok(cost) = cost.parse::<f32>() {
-                        if let Some(cost) = Decimal::from_f32(cost) {
-                            state.current_item.cost = cost;
-                        }
+                    state.draft.cost = cost.clone();
+                    if let Some(cost) = number::parse_decimal(&cost) {
+                        state.current_item.cost = cost;
                     }
                 });
             }
             Message::OnPriceChange(price) => {
                 modify(state, |state| {
-                    if let Ok(price) = price.parse::<f32>() {
-                        if let Some(price) = Decimal::from_f32(price) {
-                            state.current_item.price = price;
-                        }
+                    state.draft.price = price.clone();
+                    if let Some(price) = number::parse_decimal(&price) {
+                        state.current_item.price = price;
                     }
                 });
             }
             Message::OnQuantityChange(quantity) => {
                 modify(state, |state| {
-                    if let Ok(quantity) = quantity.parse::<i32>() {
-                        state.current_item.quantity = quantity;
+                    state.draft.quantity = quantity.clone();
+                    if let Some(quantity) = number::parse_decimal(&quantity) {
+                        state.current_item.quantity = quantity.normalize();
                     }
                 });
             }
+            Message::OnUnitSelect(unit) => {
+                modify(state, |state| {
+                    state.current_item.unit = unit;
+                });
+            }
+            Message::OnTaxClassSelect(tax_class) => {
+                modify(state, |state| {
+                    state.current_item.tax_class = tax_class;
+                });
+            }
             Message::Refresh => {
                 modify(state, |state| {
                     state.filtered_items = state.all_items.clone();
                     state.current_item = Item::default();
+                    state.history = Vec::new();
                     state.search.value = String::new();
+                    state.category = None;
                     state.position = 0;
                 });
                 tasks.push(text_input::focus(text_input::Id::new("search")))
             }
             Message::FetchItems => tasks.push(Task::perform(
-                fetch_items(state.setting.url.clone()),
+                fetch_items(state.api()),
                 Message::ItemsFetched,
             )),
             Message::ItemsFetched(items) => {
@@ -137,6 +274,16 @@ pub fn update(state: &mut crate::State, message: crate::Message) -> Task<Message
                     state.filtered_items = items;
                 });
             }
+            Message::HistoryFetched(history) => {
+                modify(state, |state| {
+                    state.history = history;
+                });
+            }
+            Message::CategoriesFetched(categories) => {
+                modify(state, |state| {
+                    state.categories = categories;
+                });
+            }
             Message::ChangePosition(action) => {
                 modify(state, |state| match action {
                     key::Named::ArrowDown => {
@@ -182,21 +329,216 @@ where
     }
 }
 
-pub(super) async fn fetch_items(url: String) -> Vec<Item> {
-    let mut output_items = Vec::new();
-    match reqwest::get(format!("{url}/items")).await {
+fn filter(state: &mut State) {
+    let categories = match &state.category {
+        Some(CategoryFilter::Category(category)) => {
+            shared::category_with_descendants(&state.categories, category.id)
+        }
+        _ => Vec::new(),
+    };
+
+    state.position = 0;
+    state.filtered_items = state
+        .all_items
+        .iter()
+        .filter(|item| {
+            item.barcode.contains(&state.search.value) || item.name.contains(&state.search.value)
+        })
+        .filter(|item| match &state.category {
+            None => true,
+            Some(CategoryFilter::Category(_)) => {
+                item.category_id.is_some_and(|id| categories.contains(&id))
+            }
+            Some(CategoryFilter::NoCategory) => item.category_id.is_none(),
+        })
+        .cloned()
+        .collect();
+}
+
+/// Checks an item before it is sent to the server, so a mistyped barcode is caught at the till.
+fn check_new_item(state: &State) -> Result<(), String> {
+    let item = &state.current_item;
+    barcode::validate(&item.barcode).map_err(barcode_error)?;
+    if state
+        .all_items
+        .iter()
+        .any(|other| other.barcode == item.barcode)
+    {
+        return Err(format!("มีสินค้า {} อยู่แล้ว", item.barcode));
+    }
+    if item.name.trim().is_empty() {
+        return Err("ยังไม่ได้ใส่ชื่อสินค้า".to_string());
+    }
+    for (field, amount) in [("ต้นทุน", item.cost), ("ราคา", item.price)] {
+        number::validate_money(amount).map_err(|e| money_error(field, e))?;
+    }
+    if !item.unit.allows(item.quantity) {
+        return Err(format!(
+            "จำนวน {} ไม่ถูกต้องสำหรับหน่วย{}",
+            item.quantity, item.unit
+        ));
+    }
+    Ok(())
+}
+
+fn barcode_error(error: BarcodeError) -> String {
+    match error {
+        BarcodeError::Empty => "ยังไม่ได้ใส่บาร์โค้ด".to_string(),
+        BarcodeError::TooLong => format!("บาร์โค้ดยาวเกิน {} ตัวอักษร", barcode::MAX_LENGTH),
+        BarcodeError::Character => "บาร์โค้ดมีตัวอักษรที่เครื่องสแกนอ่านไม่ได้".to_string(),
+        BarcodeError::CheckDigit => "เลขตรวจสอบหลักสุดท้ายของบาร์โค้ดไม่ถูกต้อง".to_string(),
+        BarcodeError::Embedded => {
+            "บาร์โค้ดนี้มีราคาหรือน้ำหนักของสินค้าชั่งน้ำหนักอยู่ ใช้บาร์โค้ดของสินค้าแทน".to_string()
+        }
+    }
+}
+
+fn money_error(field: &str, error: MoneyError) -> String {
+    match error {
+        MoneyError::Negative => format!("{field}ติดลบไม่ได้"),
+        MoneyError::TooLarge => format!("{field}เกิน {} บาท", number::MAX_MONEY),
+        MoneyError::Satang => format!("{field}มีทศนิยมได้ไม่เกิน 2 ตำแหน่ง"),
+    }
+}
+
+/// Applies a catalogue change pushed by the server, keeping the cursor where it was and leaving
+/// an item that is being edited alone. Returns `false` when the items have to be fetched again.
+pub(crate) fn apply_event(state: &mut State, event: &ItemEvent) -> bool {
+    if !event.apply(&mut state.all_items) {
+        return false;
+    }
+    let position = state.position;
+    filter(state);
+    state.position = position.min(state.filtered_items.len().saturating_sub(1));
+
+    if state.mode == Mode::Search {
+        match event {
+            ItemEvent::Changed { item } if item.barcode == state.current_item.barcode => {
+                state.current_item = item.clone();
+            }
+            ItemEvent::Deleted { barcode } if *barcode == state.current_item.barcode => {
+                state.current_item = Item::default();
+            }
+            _ => {}
+        }
+    }
+    true
+}
+
+pub(super) async fn fetch_categories(api: Api) -> Vec<Category> {
+    let mut output_categories = Vec::new();
+    match api.get("/categories").send().await {
         Err(e) => eprintln!("reqwest error: {e}"),
-        Ok(items) => match items.text().await {
-            Err(e) => eprintln!("test error: {e}"),
-            Ok(items) => {
-                output_items = serde_json::from_str(&items).unwrap();
+        Ok(categories) => match categories.json().await {
+            Err(e) => eprintln!("json error: {e}"),
+            Ok(categories) => output_categories = categories,
+        },
+    }
+    output_categories
+}
+
+/// Fetches the catalogue and keeps a copy on disk. When the server can't be reached the copy
+/// from the last successful fetch is used instead.
+pub(crate) async fn fetch_items(api: Api) -> Vec<Item> {
+    match api::send::<Vec<Item>>(api.get("/items")).await {
+        Ok(items) => {
+            if let Err(e) = cache::save_items(&items) {
+                eprintln!("cache error: {e}");
             }
+            items
+        }
+        Err(e) => {
+            eprintln!("reqwest error: {e}, using cached items");
+            cache::read_items()
+        }
+    }
+}
+
+async fn allocate_barcode(api: Api, kind: BarcodeKind) -> Result<String, String> {
+    api::send(api.post(&format!("/barcodes/{kind}"))).await
+}
+
+async fn create_item(api: Api, item: Item) -> Result<Item, String> {
+    api::send(api.post("/items").json(&item)).await
+}
+
+async fn fetch_history(api: Api, barcode: String) -> Vec<AuditEntry> {
+    let mut output_history = Vec::new();
+    match api.get(&format!("/items/{barcode}/history")).send().await {
+        Err(e) => eprintln!("reqwest error: {e}"),
+        Ok(history) => match history.json().await {
+            Err(e) => eprintln!("json error: {e}"),
+            Ok(history) => output_history = history,
         },
     }
-    output_items
+    output_history
+}
+
+fn history_line(entry: &AuditEntry) -> String {
+    let changes = entry
+        .changes()
+        .iter()
+        .map(|(field, before, after)| format!("{field}: {before} → {after}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "{} {} ({}) {} {}",
+        entry.created_at.format("%d/%m/%Y %H:%M"),
+        entry.user.as_deref().unwrap_or("-"),
+        entry.source,
+        entry.action,
+        changes
+    )
 }
 
 pub fn view(state: &State) -> Element<crate::Message> {
+    let editing = state.mode != Mode::Search;
+    let creating = state.mode == Mode::Create;
+    // The numbers of a new item are shown as typed
+    let number = |typed: &str, value: String| match creating {
+        true => typed.to_string(),
+        false => value,
+    };
+
+    let mut actions = column![
+        custom::button(
+            "แก้ไขข้อมูลสินค้า",
+            crate::Message::Inventory(Message::EnterEditMode)
+        )
+        .padding(20),
+        custom::button("เพิ่มรายการสินค้า", crate::Message::Inventory(Message::NewItem)).padding(20),
+        // custom::button("ลบสินค้า").padding(20),
+        // custom::button("เพิ่มจำนวนสินค้า").padding(20),
+    ]
+    .width(Length::FillPortion(2))
+    .align_x(Horizontal::Center)
+    .spacing(Pixels(20.0));
+    if creating {
+        actions = actions
+            .push(
+                custom::button(
+                    "สร้างบาร์โค้ดภายใน",
+                    crate::Message::Inventory(Message::AllocateBarcode(BarcodeKind::Internal)),
+                )
+                .padding(20),
+            )
+            .push(
+                custom::button(
+                    "สร้างบาร์โค้ดสินค้าชั่งน้ำหนัก",
+                    crate::Message::Inventory(Message::AllocateBarcode(BarcodeKind::Embedded)),
+                )
+                .padding(20),
+            )
+            .push(
+                custom::button(
+                    "สร้างบาร์โค้ดสินค้าขายตามน้ำหนัก",
+                    crate::Message::Inventory(Message::AllocateBarcode(BarcodeKind::Weight)),
+                )
+                .padding(20),
+            )
+            .push(custom::button("บันทึก", crate::Message::Inventory(Message::Save)).padding(20));
+    }
+
     column![
         vertical_space(),
         custom::title("คลังสินค้า"),
@@ -214,6 +556,13 @@ pub fn view(state: &State) -> Element<crate::Message> {
                             crate::Message::Inventory(Message::OnSearchChange(input))
                         })
                         .on_submit(crate::Message::Inventory(Message::OnSearchSubmit)),
+                    pick_list(
+                        category_filters(&state.categories),
+                        state.category.as_ref(),
+                        |category| crate::Message::Inventory(Message::OnCategorySelect(category))
+                    )
+                    .placeholder("หมวดหมู่")
+                    .text_shaping(text::Shaping::Advanced),
                     button("refresh").on_press(crate::Message::Inventory(Message::Refresh))
                 ]
                 .spacing(Pixels(10.0)),
@@ -247,55 +596,91 @@ pub fn view(state: &State) -> Element<crate::Message> {
             .width(Length::FillPortion(6))
             .spacing(Pixels(10.0)),
             horizontal_space(),
-            column![
-                custom::button(
-                    "แก้ไขข้อมูลสินค้า",
-                    crate::Message::Inventory(Message::EnterEditMode)
-                )
-                .padding(20),
-                // custom::button("ลบสินค้า").padding(20),
-                // custom::button("เพิ่มรายการสินค้า").padding(20),
-                // custom::button("เพิ่มจำนวนสินค้า").padding(20),
-                // custom::button("เพิ่มรายการสินค้า").padding(20),
-            ]
-            .width(Length::FillPortion(2))
-            .align_x(Horizontal::Center)
-            .spacing(Pixels(20.0)),
+            actions,
             horizontal_space(),
             column![
                 custom::labeled_text_input(
                     "รหัสสินค้า: ",
                     &state.current_item.barcode,
                     Some("barcode"),
-                    None
+                    None,
+                    creating.then_some(|input| {
+                        crate::Message::Inventory(Message::OnBarcodeChange(input))
+                    })
                 ),
                 custom::labeled_text_input(
                     "ชื่อ: ",
                     &state.current_item.name,
                     Some("name"),
-                    Some(text::Shaping::Advanced)
+                    Some(text::Shaping::Advanced),
+                    editing.then_some(|input| {
+                        crate::Message::Inventory(Message::OnNameChange(input))
+                    })
                 ),
                 custom::labeled_text_input(
                     "ต้นทุน: ",
-                    &state.current_item.cost.to_string(),
+                    &number(&state.draft.cost, state.current_item.cost.to_string()),
                     Some("cost"),
-                    None
+                    None,
+                    editing.then_some(|input| {
+                        crate::Message::Inventory(Message::OnCostChange(input))
+                    })
                 ),
                 custom::labeled_text_input(
                     "ราคา: ",
-                    &state.current_item.price.to_string(),
+                    &number(&state.draft.price, state.current_item.price.to_string()),
                     Some("price"),
-                    None
+                    None,
+                    editing.then_some(|input| {
+                        crate::Message::Inventory(Message::OnPriceChange(input))
+                    })
                 ),
                 custom::labeled_text_input(
                     "จำนวน: ",
-                    &state.current_item.quantity.to_string(),
+                    &number(
+                        &state.draft.quantity,
+                        state.current_item.quantity.to_string()
+                    ),
                     Some("quantity"),
-                    None
+                    None,
+                    editing.then_some(|input| {
+                        crate::Message::Inventory(Message::OnQuantityChange(input))
+                    })
                 ),
+                row![
+                    text("หน่วย: ")
+                        .line_height(LineHeight::Relative(2.0))
+                        .shaping(text::Shaping::Advanced)
+                        .align_y(Vertical::Center),
+                    pick_list(Unit::ALL, Some(state.current_item.unit), |unit| {
+                        crate::Message::Inventory(Message::OnUnitSelect(unit))
+                    })
+                    .text_shaping(text::Shaping::Advanced),
+                ]
+                .spacing(Pixels(10.0)),
+                row![
+                    text("ภาษี: ")
+                        .line_height(LineHeight::Relative(2.0))
+                        .shaping(text::Shaping::Advanced)
+                        .align_y(Vertical::Center),
+                    pick_list(
+                        TaxClass::ALL,
+                        Some(state.current_item.tax_class),
+                        |tax_class| crate::Message::Inventory(Message::OnTaxClassSelect(tax_class))
+                    )
+                    .text_shaping(text::Shaping::Advanced),
+                ]
+                .spacing(Pixels(10.0)),
                 custom::list(state.current_item.expire_date.clone(), |i, expire_date| {
                     row![text(format!("{i}: ")), text_input("", ""), button("x")].into()
                 }),
+                text(state.status.clone()).shaping(text::Shaping::Advanced),
+                text("ประวัติการแก้ไข").shaping(text::Shaping::Advanced),
+                custom::list(state.history.clone(), |_, entry| {
+                    text(history_line(entry))
+                        .shaping(text::Shaping::Advanced)
+                        .into()
+                }),
             ]
             .width(Length::FillPortion(6))
             .spacing(Pixels(10.0)),
@@ -336,6 +721,7 @@ pub(crate) fn subscription(state: &State) -> Subscription<crate::Message> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use rust_decimal::Decimal;
 
     fn init_state() -> crate::State {
         crate::State {
@@ -349,11 +735,13 @@ mod test {
             Item {
                 barcode: "0".to_string(),
                 name: "a".to_string(),
+                category_id: Some(1),
                 ..Default::default()
             },
             Item {
                 barcode: "1".to_string(),
                 name: "a".to_string(),
+                category_id: Some(2),
                 ..Default::default()
             },
             Item {
@@ -364,6 +752,21 @@ mod test {
         ]
     }
 
+    fn sample_categories() -> Vec<Category> {
+        vec![
+            Category {
+                id: 1,
+                parent_id: None,
+                name: "เครื่องดื่ม".to_string(),
+            },
+            Category {
+                id: 2,
+                parent_id: Some(1),
+                name: "น้ำอัดลม".to_string(),
+            },
+        ]
+    }
+
     fn test<F>(state: &crate::State, f: F)
     where
         F: FnOnce(&State),
@@ -448,6 +851,65 @@ mod test {
         });
     }
 
+    #[test]
+    fn category_filter() {
+        let items = sample_items();
+        let categories = sample_categories();
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Inventory(Message::ItemsFetched(
+            items.clone(),
+        )));
+        let _ = state.update(crate::Message::Inventory(Message::CategoriesFetched(
+            categories.clone(),
+        )));
+
+        // Parent category includes its subcategories
+        let _ = state.update(crate::Message::Inventory(Message::OnCategorySelect(
+            CategoryFilter::Category(categories[0].clone()),
+        )));
+        test(&state, |state| {
+            assert_eq!(
+                state.filtered_items,
+                vec![items[0].clone(), items[1].clone()]
+            );
+        });
+
+        // Subcategory
+        let _ = state.update(crate::Message::Inventory(Message::OnCategorySelect(
+            CategoryFilter::Category(categories[1].clone()),
+        )));
+        test(&state, |state| {
+            assert_eq!(state.filtered_items, vec![items[1].clone()]);
+        });
+
+        // Items without a category
+        let _ = state.update(crate::Message::Inventory(Message::OnCategorySelect(
+            CategoryFilter::NoCategory,
+        )));
+        test(&state, |state| {
+            assert_eq!(state.filtered_items, vec![items[2].clone()]);
+        });
+        assert_eq!(
+            category_filters(&categories).last(),
+            Some(&CategoryFilter::NoCategory)
+        );
+
+        // Combined with search
+        let _ = state.update(crate::Message::Inventory(Message::OnSearchChange(
+            "0".to_string(),
+        )));
+        test(&state, |state| {
+            assert!(state.filtered_items.is_empty());
+        });
+
+        // Refresh clears the category
+        let _ = state.update(crate::Message::Inventory(Message::Refresh));
+        test(&state, |state| {
+            assert_eq!(state.category, None);
+            assert_eq!(state.filtered_items, items);
+        });
+    }
+
     #[test]
     fn refresh() {
         let mut state = init_state();
@@ -460,7 +922,7 @@ mod test {
                 name: "test".to_string(),
                 cost: rust_decimal::dec!(5.6),
                 price: rust_decimal::dec!(8.2),
-                quantity: 34,
+                quantity: Decimal::from(34),
                 ..Default::default()
             };
         });
@@ -478,7 +940,7 @@ mod test {
             assert!(state.current_item.name.is_empty());
             assert!(state.current_item.cost.is_zero());
             assert!(state.current_item.price.is_zero());
-            assert_eq!(state.current_item.quantity, 0);
+            assert!(state.current_item.quantity.is_zero());
             assert!(state.current_item.image.is_none());
             assert!(state.current_item.expire_date.is_empty());
             assert!(state.current_item.bulk_item.is_empty());
@@ -574,7 +1036,224 @@ mod test {
             10.to_string(),
         )));
         test(&state, |state| {
-            assert_eq!(state.current_item.quantity, 10);
+            assert_eq!(state.current_item.quantity, Decimal::from(10));
+        });
+
+        // Sold by weight
+        let _ = state.update(crate::Message::Inventory(Message::OnUnitSelect(
+            Unit::Kilogram,
+        )));
+        let _ = state.update(crate::Message::Inventory(Message::OnQuantityChange(
+            "2.50".to_string(),
+        )));
+        test(&state, |state| {
+            assert_eq!(state.current_item.unit, Unit::Kilogram);
+            assert_eq!(state.current_item.quantity, Decimal::new(25, 1));
         })
     }
+
+    #[test]
+    fn new_item() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Inventory(Message::ItemsFetched(
+            sample_items(),
+        )));
+        let _ = state.update(crate::Message::Inventory(Message::NewItem));
+        let _ = state.update(crate::Message::Inventory(Message::OnNameChange(
+            "ส้มเขียวหวาน".to_string(),
+        )));
+        let _ = state.update(crate::Message::Inventory(Message::OnPriceChange(
+            "35.".to_string(),
+        )));
+        test(&state, |state| {
+            assert_eq!(state.mode, Mode::Create);
+            assert_eq!(state.draft.price, "35.");
+            assert_eq!(state.current_item.price, Decimal::new(35, 0));
+        });
+
+        // Thai digits and thousands separators
+        let _ = state.update(crate::Message::Inventory(Message::OnCostChange(
+            "๑,๒๕๐.๕๐".to_string(),
+        )));
+        test(&state, |state| {
+            assert_eq!(state.current_item.cost, Decimal::new(125050, 2));
+        });
+        let _ = state.update(crate::Message::Inventory(Message::OnCostChange(
+            "25.125".to_string(),
+        )));
+
+        // A mistyped check digit is caught before anything is sent
+        for (barcode, error) in [
+            ("", "ยังไม่ได้ใส่บาร์โค้ด"),
+            ("8850999320015", "เลขตรวจสอบ"),
+            ("1", "มีสินค้า 1 อยู่แล้ว"),
+        ] {
+            let _ = state.update(crate::Message::Inventory(Message::OnBarcodeChange(
+                barcode.to_string(),
+            )));
+            let _ = state.update(crate::Message::Inventory(Message::Save));
+            test(&state, |state| {
+                assert!(state.status.contains(error), "{}", state.status);
+                assert_eq!(state.mode, Mode::Create);
+            });
+        }
+
+        let barcode = barcode::internal(1).unwrap();
+        let _ = state.update(crate::Message::Inventory(Message::BarcodeAllocated(Ok(
+            barcode.clone(),
+        ))));
+        let _ = state.update(crate::Message::Inventory(Message::Save));
+        test(&state, |state| {
+            assert!(state.status.contains("ต้นทุนมีทศนิยม"), "{}", state.status);
+        });
+        let _ = state.update(crate::Message::Inventory(Message::OnCostChange(
+            "25".to_string(),
+        )));
+        let _ = state.update(crate::Message::Inventory(Message::OnPriceChange(
+            "100,000,000".to_string(),
+        )));
+        let _ = state.update(crate::Message::Inventory(Message::Save));
+        test(&state, |state| {
+            assert!(state.status.contains("ราคาเกิน"), "{}", state.status);
+        });
+        let _ = state.update(crate::Message::Inventory(Message::OnPriceChange(
+            "35".to_string(),
+        )));
+        // Oranges are sold by the piece here
+        let _ = state.update(crate::Message::Inventory(Message::OnQuantityChange(
+            "1.5".to_string(),
+        )));
+        let _ = state.update(crate::Message::Inventory(Message::Save));
+        test(&state, |state| {
+            assert!(state.status.contains("หน่วยชิ้น"), "{}", state.status);
+        });
+        let _ = state.update(crate::Message::Inventory(Message::OnQuantityChange(
+            "30".to_string(),
+        )));
+        let _ = state.update(crate::Message::Inventory(Message::Save));
+        test(&state, |state| {
+            assert_eq!(state.status, "กำลังบันทึก...");
+        });
+
+        let item = Item {
+            barcode: barcode.clone(),
+            name: "ส้มเขียวหวาน".to_string(),
+            price: Decimal::new(35, 0),
+            ..Default::default()
+        };
+        let _ = state.update(crate::Message::Inventory(Message::Saved(Ok(item.clone()))));
+        test(&state, |state| {
+            assert_eq!(state.mode, Mode::Search);
+            assert_eq!(state.current_item, item);
+            assert_eq!(state.all_items.len(), 4);
+        });
+
+        // The barcode is only typed in for a new item
+        let _ = state.update(crate::Message::Inventory(Message::OnBarcodeChange(
+            "2".to_string(),
+        )));
+        test(&state, |state| {
+            assert_eq!(state.current_item.barcode, barcode);
+        });
+    }
+
+    #[test]
+    fn history() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Inventory(Message::ItemsFetched(
+            sample_items(),
+        )));
+        let _ = state.update(crate::Message::Inventory(Message::OnSearchSubmit));
+
+        let entry = AuditEntry {
+            id: 1,
+            entity: "item".to_string(),
+            action: "update".to_string(),
+            before: Some(serde_json::json!({ "name": "a", "price": "10" })),
+            after: Some(serde_json::json!({ "name": "a", "price": "12" })),
+            user: Some("owner".to_string()),
+            source: "client".to_string(),
+            created_at: chrono::NaiveDate::from_ymd_opt(2026, 10, 19)
+                .unwrap()
+                .and_hms_opt(9, 30, 0)
+                .unwrap(),
+        };
+        let _ = state.update(crate::Message::Inventory(Message::HistoryFetched(vec![
+            entry.clone(),
+        ])));
+        test(&state, |state| {
+            assert_eq!(state.history, vec![entry.clone()]);
+        });
+        assert_eq!(
+            history_line(&entry),
+            "19/10/2026 09:30 owner (client) update price: 10 → 12"
+        );
+
+        // Selecting another item clears the old history
+        let _ = state.update(crate::Message::Inventory(Message::ChangePosition(
+            key::Named::ArrowDown,
+        )));
+        let _ = state.update(crate::Message::Inventory(Message::OnSearchSubmit));
+        test(&state, |state| {
+            assert!(state.history.is_empty());
+        });
+
+        let _ = state.update(crate::Message::Inventory(Message::Refresh));
+        test(&state, |state| {
+            assert!(state.history.is_empty());
+        });
+    }
+
+    #[test]
+    fn item_event() {
+        let items = sample_items();
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Inventory(Message::ItemsFetched(
+            items.clone(),
+        )));
+        let _ = state.update(crate::Message::Inventory(Message::ChangePosition(
+            key::Named::ArrowDown,
+        )));
+        let _ = state.update(crate::Message::Inventory(Message::OnSearchSubmit));
+
+        let changed = Item {
+            price: Decimal::new(15, 0),
+            ..items[1].clone()
+        };
+        let _ = state.update(crate::Message::Event(ItemEvent::Changed {
+            item: changed.clone(),
+        }));
+        test(&state, |state| {
+            assert_eq!(state.all_items[1], changed);
+            assert_eq!(state.filtered_items[1], changed);
+            assert_eq!(state.current_item, changed);
+            assert_eq!(state.position, 1);
+        });
+
+        let _ = state.update(crate::Message::Event(ItemEvent::Deleted {
+            barcode: "2".to_string(),
+        }));
+        let _ = state.update(crate::Message::Event(ItemEvent::Deleted {
+            barcode: "1".to_string(),
+        }));
+        test(&state, |state| {
+            assert_eq!(state.all_items, vec![items[0].clone()]);
+            assert_eq!(state.current_item, Item::default());
+            assert_eq!(state.position, 0);
+        });
+
+        // Edits in progress are not overwritten
+        let _ = state.update(crate::Message::Inventory(Message::OnSearchSubmit));
+        let _ = state.update(crate::Message::Inventory(Message::EnterEditMode));
+        let _ = state.update(crate::Message::Event(ItemEvent::Changed {
+            item: Item {
+                name: "c".to_string(),
+                ..items[0].clone()
+            },
+        }));
+        test(&state, |state| {
+            assert_eq!(state.current_item, items[0]);
+            assert_eq!(state.all_items[0].name, "c");
+        });
+    }
 }
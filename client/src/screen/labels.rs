@@ -0,0 +1,418 @@
+use std::path::PathBuf;
+
+use chrono::Local;
+use iced::alignment::{Horizontal, Vertical};
+use iced::keyboard::key;
+use iced::widget::text::LineHeight;
+use iced::widget::{
+    button, column, horizontal_space, pick_list, row, text, text_input, vertical_space,
+};
+use iced::{Element, Length, Pixels, Subscription, Task, keyboard};
+
+use crate::api::{self, Api};
+use crate::{custom, printer};
+use shared::{Item, LabelFormat, LabelQueueItem, LabelRequest};
+
+/// An item a shelf tag can be printed for, and whether it will be.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Entry {
+    pub item: LabelQueueItem,
+    pub selected: bool,
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub(crate) struct State {
+    /// The queue of the server first, then the items added by hand.
+    pub entries: Vec<Entry>,
+    pub items: Vec<Item>,
+    pub barcode: String,
+    pub format: LabelFormat,
+    pub status: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Back,
+    QueueFetched(Result<Vec<LabelQueueItem>, String>),
+    ItemsFetched(Vec<Item>),
+    Toggle(usize, bool),
+    SelectAll(bool),
+    OnBarcodeChange(String),
+    Add,
+    SelectFormat(LabelFormat),
+    Print,
+    Printed(Result<String, String>),
+}
+
+pub fn update(state: &mut crate::State, message: crate::Message) -> Task<Message> {
+    let crate::Message::Labels(message) = message else {
+        return Task::none();
+    };
+
+    let api = state.api();
+    let label_printer = state.setting.label_printer.clone();
+    let mut tasks = Vec::new();
+    match message {
+        Message::Back => {
+            state.screen = crate::Screen::Home;
+        }
+        Message::QueueFetched(result) => {
+            modify(state, |state| match result {
+                Ok(queue) => {
+                    state.entries = queue
+                        .into_iter()
+                        .map(|item| Entry {
+                            item,
+                            selected: true,
+                        })
+                        .collect();
+                }
+                Err(e) => state.status = e,
+            });
+        }
+        Message::ItemsFetched(items) => {
+            modify(state, |state| {
+                state.items = items;
+            });
+        }
+        Message::Toggle(i, selected) => {
+            modify(state, |state| {
+                if let Some(entry) = state.entries.get_mut(i) {
+                    entry.selected = selected;
+                }
+            });
+        }
+        Message::SelectAll(selected) => {
+            modify(state, |state| {
+                for entry in &mut state.entries {
+                    entry.selected = selected;
+                }
+            });
+        }
+        Message::OnBarcodeChange(barcode) => {
+            modify(state, |state| {
+                state.barcode = barcode;
+            });
+        }
+        Message::Add => {
+            modify(state, |state| {
+                let barcode = state.barcode.trim().to_string();
+                if let Some(entry) = state
+                    .entries
+                    .iter_mut()
+                    .find(|entry| entry.item.barcode == barcode)
+                {
+                    entry.selected = true;
+                } else if let Some(item) = state.items.iter().find(|item| item.barcode == barcode) {
+                    state.entries.push(Entry {
+                        item: LabelQueueItem {
+                            barcode,
+                            name: item.name.clone(),
+                            price: item.price,
+                            printed_price: None,
+                        },
+                        selected: true,
+                    });
+                } else {
+                    state.status = format!("ไม่พบสินค้า {barcode}");
+                    return;
+                }
+                state.barcode = String::new();
+                state.status = String::new();
+            });
+        }
+        Message::SelectFormat(format) => {
+            modify(state, |state| {
+                state.format = format;
+            });
+        }
+        Message::Print => {
+            modify(state, |state| {
+                let request = request(state);
+                if request.barcodes.is_empty() {
+                    state.status = "ยังไม่ได้เลือกสินค้าที่จะพิมพ์ป้ายราคา".to_string();
+                    return;
+                }
+                state.status = "กำลังพิมพ์ป้ายราคา...".to_string();
+                tasks.push(Task::perform(
+                    print(api, request, label_printer),
+                    Message::Printed,
+                ));
+            });
+        }
+        Message::Printed(result) => {
+            modify(state, |state| match result {
+                Ok(status) => {
+                    state.status = status;
+                    tasks.push(Task::perform(fetch_queue(api), Message::QueueFetched));
+                }
+                Err(e) => state.status = e,
+            });
+        }
+    }
+
+    Task::batch(tasks)
+}
+
+fn modify<F>(state: &mut crate::State, f: F)
+where
+    F: FnOnce(&mut State),
+{
+    if let crate::Screen::Labels(ref mut state) = state.screen {
+        f(state);
+    } else {
+        panic!("Screen error in labels");
+    }
+}
+
+/// The selected items, in the order they are listed.
+fn request(state: &State) -> LabelRequest {
+    LabelRequest {
+        barcodes: state
+            .entries
+            .iter()
+            .filter(|entry| entry.selected)
+            .map(|entry| entry.item.barcode.clone())
+            .collect(),
+        format: state.format,
+    }
+}
+
+pub(crate) async fn fetch_queue(api: Api) -> Result<Vec<LabelQueueItem>, String> {
+    api::send(api.get("/labels/queue")).await
+}
+
+/// Has the server draw the tags, then sends them to the label printer or saves the sheet to the
+/// downloads directory. Returns what to tell the user.
+async fn print(api: Api, request: LabelRequest, label_printer: String) -> Result<String, String> {
+    let count = request.barcodes.len();
+    let format = request.format;
+    let data = api::bytes(api.post("/labels").json(&request)).await?;
+    match format {
+        LabelFormat::Tspl => {
+            printer::send(label_printer, data).await?;
+            Ok(format!("ส่งป้ายราคา {count} ป้ายไปที่เครื่องพิมพ์ฉลากแล้ว"))
+        }
+        LabelFormat::Pdf | LabelFormat::Png => {
+            let path = path(format);
+            std::fs::write(&path, data).map_err(|e| e.to_string())?;
+            Ok(format!("บันทึกป้ายราคา {count} ป้ายที่ {}", path.display()))
+        }
+    }
+}
+
+/// A new file in the downloads directory, or the home directory without one.
+fn path(format: LabelFormat) -> PathBuf {
+    let dir = dirs::download_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default();
+    dir.join(format!(
+        "labels-{}.{}",
+        Local::now().format("%Y%m%d-%H%M%S"),
+        format.extension()
+    ))
+}
+
+fn cell<'a>(value: String) -> Element<'a, crate::Message> {
+    text(value)
+        .line_height(LineHeight::Relative(2.0))
+        .shaping(text::Shaping::Advanced)
+        .width(Length::Fill)
+        .align_x(Horizontal::Center)
+        .align_y(Vertical::Center)
+        .into()
+}
+
+pub fn view(state: &State) -> Element<crate::Message> {
+    let all = !state.entries.is_empty() && state.entries.iter().all(|entry| entry.selected);
+    column![
+        vertical_space(),
+        custom::title("ป้ายราคา"),
+        row![
+            horizontal_space(),
+            column![
+                row![
+                    text_input("บาร์โค้ด", &state.barcode)
+                        .id(text_input::Id::new("label_barcode"))
+                        .on_input(|input| crate::Message::Labels(Message::OnBarcodeChange(input)))
+                        .on_submit(crate::Message::Labels(Message::Add)),
+                    button(text("เพิ่ม").shaping(text::Shaping::Advanced))
+                        .on_press(crate::Message::Labels(Message::Add)),
+                ]
+                .spacing(Pixels(10.0)),
+                row![
+                    button(
+                        text(match all {
+                            true => "ไม่เลือกเลย",
+                            false => "เลือกทั้งหมด",
+                        })
+                        .shaping(text::Shaping::Advanced)
+                        .width(Length::Fill)
+                        .align_x(Horizontal::Center)
+                    )
+                    .on_press(crate::Message::Labels(Message::SelectAll(!all)))
+                    .width(Length::Fill),
+                    cell("บาร์โค้ด".to_string()),
+                    cell("สินค้า".to_string()),
+                    cell("ราคาบนป้าย".to_string()),
+                    cell("ราคาใหม่".to_string()),
+                ],
+                custom::list(state.entries.clone(), |i, entry| {
+                    row![
+                        button(
+                            text(match entry.selected {
+                                true => "พิมพ์",
+                                false => "ไม่พิมพ์",
+                            })
+                            .shaping(text::Shaping::Advanced)
+                            .width(Length::Fill)
+                            .align_x(Horizontal::Center)
+                        )
+                        .on_press(crate::Message::Labels(Message::Toggle(i, !entry.selected)))
+                        .width(Length::Fill),
+                        cell(entry.item.barcode.clone()),
+                        cell(entry.item.name.clone()),
+                        cell(
+                            entry
+                                .item
+                                .printed_price
+                                .map(|price| price.to_string())
+                                .unwrap_or_else(|| "-".to_string())
+                        ),
+                        cell(entry.item.price.to_string()),
+                    ]
+                    .into()
+                })
+                .height(Length::Fill),
+                row![
+                    pick_list(LabelFormat::ALL, Some(state.format), |format| {
+                        crate::Message::Labels(Message::SelectFormat(format))
+                    })
+                    .text_shaping(text::Shaping::Advanced),
+                    button(text("พิมพ์ป้ายราคา").shaping(text::Shaping::Advanced))
+                        .on_press(crate::Message::Labels(Message::Print)),
+                ]
+                .spacing(Pixels(10.0)),
+                text(state.status.clone()).shaping(text::Shaping::Advanced),
+            ]
+            .width(Length::FillPortion(10))
+            .spacing(Pixels(10.0)),
+            horizontal_space(),
+        ]
+        .height(Length::FillPortion(12)),
+        vertical_space()
+    ]
+    .height(Length::Fill)
+    .width(Length::Fill)
+    .into()
+}
+
+pub(crate) fn subscription(_state: &State) -> Subscription<crate::Message> {
+    keyboard::on_key_press(|keyboard, _| match keyboard {
+        keyboard::Key::Named(key::Named::Escape) => Some(crate::Message::Labels(Message::Back)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn init_state() -> crate::State {
+        crate::State {
+            screen: crate::Screen::Labels(Box::default()),
+            ..Default::default()
+        }
+    }
+
+    fn test<F>(state: &crate::State, f: F)
+    where
+        F: FnOnce(&State),
+    {
+        if let crate::Screen::Labels(state) = &state.screen {
+            f(state);
+        } else {
+            panic!("Screen error in labels");
+        }
+    }
+
+    fn queued(barcode: &str, price: i64, printed_price: Option<i64>) -> LabelQueueItem {
+        LabelQueueItem {
+            barcode: barcode.to_string(),
+            name: format!("สินค้า {barcode}"),
+            price: Decimal::new(price, 0),
+            printed_price: printed_price.map(|price| Decimal::new(price, 0)),
+        }
+    }
+
+    #[test]
+    fn back() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Labels(Message::Back));
+        assert_eq!(state.screen, crate::Screen::Home);
+    }
+
+    #[test]
+    fn queue_is_selected() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Labels(Message::QueueFetched(Ok(vec![
+            queued("0", 16, Some(15)),
+            queued("1", 7, None),
+        ]))));
+        let _ = state.update(crate::Message::Labels(Message::Toggle(0, false)));
+        test(&state, |state| {
+            assert_eq!(request(state).barcodes, vec!["1"]);
+        });
+
+        let _ = state.update(crate::Message::Labels(Message::SelectAll(false)));
+        let _ = state.update(crate::Message::Labels(Message::Print));
+        test(&state, |state| {
+            assert!(request(state).barcodes.is_empty());
+            assert!(state.status.contains("ยังไม่ได้เลือก"));
+        });
+    }
+
+    #[test]
+    fn add_by_barcode() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Labels(Message::QueueFetched(Ok(vec![
+            queued("0", 16, Some(15)),
+        ]))));
+        let _ = state.update(crate::Message::Labels(Message::ItemsFetched(vec![Item {
+            barcode: "2".to_string(),
+            name: "ยาสีฟัน".to_string(),
+            price: Decimal::new(45, 0),
+            ..Default::default()
+        }])));
+        let _ = state.update(crate::Message::Labels(Message::Toggle(0, false)));
+
+        for barcode in ["9", " 2 ", "0"] {
+            let _ = state.update(crate::Message::Labels(Message::OnBarcodeChange(
+                barcode.to_string(),
+            )));
+            let _ = state.update(crate::Message::Labels(Message::Add));
+        }
+        test(&state, |state| {
+            // The unknown barcode is left out, the queued one is picked again
+            assert_eq!(request(state).barcodes, vec!["0", "2"]);
+            assert_eq!(state.entries[1].item.name, "ยาสีฟัน");
+            assert!(state.barcode.is_empty());
+        });
+    }
+
+    #[test]
+    fn printed() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Labels(Message::SelectFormat(
+            LabelFormat::Tspl,
+        )));
+        let _ = state.update(crate::Message::Labels(Message::Printed(Err(
+            "ยังไม่ได้ตั้งค่าเครื่องพิมพ์".to_string(),
+        ))));
+        test(&state, |state| {
+            assert_eq!(request(state).format, LabelFormat::Tspl);
+            assert_eq!(state.status, "ยังไม่ได้ตั้งค่าเครื่องพิมพ์");
+        });
+    }
+}
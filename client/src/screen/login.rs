@@ -0,0 +1,197 @@
+use iced::widget::{button, column, container, horizontal_space, row, text, text_input};
+use iced::{Element, Length, Pixels, Task};
+
+use crate::api::{self, Api};
+use shared::{Login, Session};
+
+use super::setting;
+
+#[derive(Default, Debug, PartialEq)]
+pub(crate) struct State {
+    pub name: String,
+    pub password: String,
+    pub error: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    OnNameChange(String),
+    OnPasswordChange(String),
+    Submit,
+    LoggedIn(Result<Session, String>),
+    GotoSetting,
+}
+
+pub fn update(state: &mut crate::State, message: crate::Message) -> Task<Message> {
+    let api = state.api();
+    let crate::Message::Login(message) = message else {
+        return Task::none();
+    };
+    let crate::Screen::Login(login) = &mut state.screen else {
+        panic!("Screen error in login");
+    };
+
+    match message {
+        Message::OnNameChange(name) => login.name = name,
+        Message::OnPasswordChange(password) => login.password = password,
+        Message::Submit => {
+            return Task::perform(
+                self::login(
+                    api,
+                    Login {
+                        name: login.name.clone(),
+                        password: login.password.clone(),
+                    },
+                ),
+                Message::LoggedIn,
+            );
+        }
+        Message::LoggedIn(Ok(session)) => {
+            state.session = Some(session);
+            state.screen = crate::Screen::Home;
+        }
+        Message::LoggedIn(Err(e)) => {
+            login.password = String::new();
+            login.error = format!("เข้าสู่ระบบไม่สำเร็จ: {e}");
+        }
+        Message::GotoSetting => {
+            state.screen = crate::Screen::Setting(setting::State::new(&state.setting));
+        }
+    }
+    Task::none()
+}
+
+async fn login(api: Api, login: Login) -> Result<Session, String> {
+    api::send(api.post("/login").json(&login)).await
+}
+
+pub(crate) fn view<'a>(state: &State) -> Element<'a, crate::Message> {
+    row![
+        horizontal_space().width(Length::Fill),
+        container(
+            column![
+                text("เข้าสู่ระบบ")
+                    .shaping(text::Shaping::Advanced)
+                    .size(Pixels(30.0)),
+                row![
+                    text("ชื่อผู้ใช้: ")
+                        .shaping(text::Shaping::Advanced)
+                        .width(Length::Fill),
+                    text_input("", &state.name)
+                        .id(text_input::Id::new("login_name"))
+                        .on_input(|input| crate::Message::Login(Message::OnNameChange(input)))
+                        .on_submit(crate::Message::Login(Message::Submit))
+                        .width(Length::FillPortion(3)),
+                ],
+                row![
+                    text("รหัสผ่าน: ")
+                        .shaping(text::Shaping::Advanced)
+                        .width(Length::Fill),
+                    text_input("", &state.password)
+                        .secure(true)
+                        .on_input(|input| crate::Message::Login(Message::OnPasswordChange(input)))
+                        .on_submit(crate::Message::Login(Message::Submit))
+                        .width(Length::FillPortion(3)),
+                ],
+                text(state.error.clone()).shaping(text::Shaping::Advanced),
+                row![
+                    button("ตั้งค่า").on_press(crate::Message::Login(Message::GotoSetting)),
+                    horizontal_space(),
+                    button("เข้าสู่ระบบ").on_press(crate::Message::Login(Message::Submit)),
+                ],
+            ]
+            .spacing(Pixels(10.0))
+            .height(Length::Shrink),
+        )
+        .width(Length::FillPortion(3))
+        .center(Length::Fill),
+        horizontal_space().width(Length::Fill),
+    ]
+    .into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rust_decimal::Decimal;
+    use shared::User;
+
+    fn init_state() -> crate::State {
+        crate::State::default()
+    }
+
+    fn test<F>(state: &crate::State, f: F)
+    where
+        F: FnOnce(&State),
+    {
+        if let crate::Screen::Login(state) = &state.screen {
+            f(state);
+        } else {
+            panic!("Screen error in login");
+        }
+    }
+
+    #[test]
+    fn starts_at_login() {
+        let state = init_state();
+        assert_eq!(state.screen, crate::Screen::Login(State::default()));
+        assert_eq!(state.session, None);
+    }
+
+    #[test]
+    fn logged_in() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Login(Message::OnNameChange(
+            "owner".to_string(),
+        )));
+        let _ = state.update(crate::Message::Login(Message::OnPasswordChange(
+            "1234".to_string(),
+        )));
+        test(&state, |state| {
+            assert_eq!(state.name, "owner");
+            assert_eq!(state.password, "1234");
+        });
+
+        let session = Session {
+            token: "token".to_string(),
+            user: User {
+                id: 1,
+                name: "owner".to_string(),
+                ..Default::default()
+            },
+            vat_rate: Decimal::new(7, 0),
+        };
+        let _ = state.update(crate::Message::Login(Message::LoggedIn(
+            Ok(session.clone()),
+        )));
+        assert_eq!(state.screen, crate::Screen::Home);
+        assert_eq!(state.session, Some(session));
+        assert_eq!(state.api().token, Some("token".to_string()));
+    }
+
+    #[test]
+    fn login_failed() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Login(Message::OnPasswordChange(
+            "wrong".to_string(),
+        )));
+        let _ = state.update(crate::Message::Login(Message::LoggedIn(Err(
+            "401".to_string()
+        ))));
+        test(&state, |state| {
+            assert!(state.password.is_empty());
+            assert!(!state.error.is_empty());
+        });
+        assert_eq!(state.session, None);
+    }
+
+    #[test]
+    fn goto_setting() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Login(Message::GotoSetting));
+        assert_eq!(
+            state.screen,
+            crate::Screen::Setting(setting::State::new(&state.setting))
+        );
+    }
+}
@@ -1,3 +1,10 @@
+pub mod customer;
 pub mod home;
 pub mod inventory;
+pub mod labels;
+pub mod login;
+pub mod report;
+pub mod returns;
+pub mod sale;
 pub mod setting;
+pub mod stock_take;
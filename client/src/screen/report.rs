@@ -0,0 +1,296 @@
+use chrono::{Datelike, Local, NaiveDate};
+use iced::alignment::Horizontal;
+use iced::keyboard::key;
+use iced::widget::text::LineHeight;
+use iced::widget::{button, column, horizontal_space, row, text, text_input, vertical_space};
+use iced::{Element, Length, Pixels, Subscription, Task, keyboard};
+use rust_decimal::Decimal;
+
+use crate::api::{self, Api};
+use crate::custom;
+use shared::CategorySales;
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct State {
+    pub from: String,
+    pub to: String,
+    pub sales: Vec<CategorySales>,
+    pub status: String,
+}
+
+impl Default for State {
+    /// Sales of the month so far.
+    fn default() -> Self {
+        let today = Local::now().date_naive();
+        State {
+            from: today.with_day(1).unwrap_or(today).to_string(),
+            to: today.to_string(),
+            sales: Vec::new(),
+            status: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Back,
+    OnFromChange(String),
+    OnToChange(String),
+    Fetch,
+    Fetched(Result<Vec<CategorySales>, String>),
+}
+
+pub fn update(state: &mut crate::State, message: crate::Message) -> Task<Message> {
+    let crate::Message::Report(message) = message else {
+        return Task::none();
+    };
+
+    let api = state.api();
+    let mut tasks = Vec::new();
+    match message {
+        Message::Back => {
+            state.screen = crate::Screen::Home;
+        }
+        Message::OnFromChange(from) => {
+            modify(state, |state| {
+                state.from = from;
+            });
+        }
+        Message::OnToChange(to) => {
+            modify(state, |state| {
+                state.to = to;
+            });
+        }
+        Message::Fetch => {
+            modify(state, |state| {
+                tasks.push(fetch_sales(api, state));
+            });
+        }
+        Message::Fetched(result) => {
+            modify(state, |state| match result {
+                Ok(sales) => {
+                    state.status = match sales.is_empty() {
+                        true => "ไม่มียอดขายในช่วงนี้".to_string(),
+                        false => String::new(),
+                    };
+                    state.sales = sales;
+                }
+                Err(e) => state.status = e,
+            });
+        }
+    }
+
+    Task::batch(tasks)
+}
+
+fn modify<F>(state: &mut crate::State, f: F)
+where
+    F: FnOnce(&mut State),
+{
+    if let crate::Screen::Report(ref mut state) = state.screen {
+        f(state);
+    } else {
+        panic!("Screen error in report");
+    }
+}
+
+/// Fetches the sales between the dates of the screen, or says how to write them.
+pub(crate) fn fetch_sales(api: Api, state: &mut State) -> Task<Message> {
+    match (
+        state.from.trim().parse::<NaiveDate>(),
+        state.to.trim().parse::<NaiveDate>(),
+    ) {
+        (Ok(from), Ok(to)) => Task::perform(fetch(api, from, to), Message::Fetched),
+        _ => {
+            state.status = "วันที่ต้องเป็นแบบ 2026-01-31".to_string();
+            Task::none()
+        }
+    }
+}
+
+async fn fetch(api: Api, from: NaiveDate, to: NaiveDate) -> Result<Vec<CategorySales>, String> {
+    api::send(
+        api.get("/reports/categories")
+            .query(&[("from", from), ("to", to)]),
+    )
+    .await
+}
+
+fn name(sales: &CategorySales) -> String {
+    sales
+        .name
+        .clone()
+        .unwrap_or_else(|| "ไม่มีหมวดหมู่".to_string())
+}
+
+/// Quantity, revenue and profit of all the categories together.
+fn total(sales: &[CategorySales]) -> (Decimal, Decimal, Decimal) {
+    sales.iter().fold(
+        (Decimal::ZERO, Decimal::ZERO, Decimal::ZERO),
+        |(quantity, revenue, profit), sales| {
+            (
+                quantity + sales.quantity,
+                revenue + sales.revenue,
+                profit + sales.revenue - sales.cost,
+            )
+        },
+    )
+}
+
+fn cell<'a>(value: String) -> Element<'a, crate::Message> {
+    text(value)
+        .line_height(LineHeight::Relative(2.0))
+        .shaping(text::Shaping::Advanced)
+        .width(Length::Fill)
+        .align_x(Horizontal::Center)
+        .into()
+}
+
+pub fn view(state: &State) -> Element<crate::Message> {
+    let (quantity, revenue, profit) = total(&state.sales);
+    column![
+        vertical_space(),
+        custom::title("ยอดขายตามหมวดหมู่"),
+        row![
+            horizontal_space(),
+            column![
+                row![
+                    text_input("", &state.from)
+                        .on_input(|input| crate::Message::Report(Message::OnFromChange(input)))
+                        .on_submit(crate::Message::Report(Message::Fetch)),
+                    text("ถึง").shaping(text::Shaping::Advanced),
+                    text_input("", &state.to)
+                        .on_input(|input| crate::Message::Report(Message::OnToChange(input)))
+                        .on_submit(crate::Message::Report(Message::Fetch)),
+                    button(text("ดูรายงาน").shaping(text::Shaping::Advanced))
+                        .on_press(crate::Message::Report(Message::Fetch)),
+                ]
+                .spacing(Pixels(10.0)),
+                row![
+                    cell("หมวดหมู่".to_string()),
+                    cell("จำนวน".to_string()),
+                    cell("ยอดขาย".to_string()),
+                    cell("กำไร".to_string()),
+                ],
+                custom::list(state.sales.clone(), |_, sales| {
+                    row![
+                        cell(name(sales)),
+                        cell(sales.quantity.to_string()),
+                        cell(sales.revenue.to_string()),
+                        cell((sales.revenue - sales.cost).to_string()),
+                    ]
+                    .into()
+                })
+                .height(Length::Fill),
+                row![
+                    cell("รวม".to_string()),
+                    cell(quantity.to_string()),
+                    cell(revenue.to_string()),
+                    cell(profit.to_string()),
+                ],
+                text(state.status.clone()).shaping(text::Shaping::Advanced),
+            ]
+            .width(Length::FillPortion(10))
+            .spacing(Pixels(10.0)),
+            horizontal_space(),
+        ]
+        .height(Length::FillPortion(12)),
+        vertical_space()
+    ]
+    .height(Length::Fill)
+    .width(Length::Fill)
+    .into()
+}
+
+pub(crate) fn subscription(_state: &State) -> Subscription<crate::Message> {
+    keyboard::on_key_press(|keyboard, _| match keyboard {
+        keyboard::Key::Named(key::Named::Escape) => Some(crate::Message::Report(Message::Back)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn init_state() -> crate::State {
+        crate::State {
+            screen: crate::Screen::Report(Box::default()),
+            ..Default::default()
+        }
+    }
+
+    fn test<F>(state: &crate::State, f: F)
+    where
+        F: FnOnce(&State),
+    {
+        if let crate::Screen::Report(state) = &state.screen {
+            f(state);
+        } else {
+            panic!("Screen error in report");
+        }
+    }
+
+    fn sample_sales() -> Vec<CategorySales> {
+        vec![
+            CategorySales {
+                category_id: Some(1),
+                name: Some("เครื่องดื่ม".to_string()),
+                quantity: Decimal::new(10, 0),
+                cost: Decimal::new(100, 0),
+                revenue: Decimal::new(150, 0),
+            },
+            CategorySales {
+                category_id: None,
+                name: None,
+                quantity: Decimal::new(2, 0),
+                cost: Decimal::new(20, 0),
+                revenue: Decimal::new(25, 0),
+            },
+        ]
+    }
+
+    #[test]
+    fn back() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Report(Message::Back));
+        assert_eq!(state.screen, crate::Screen::Home);
+    }
+
+    #[test]
+    fn fetch_checks_dates() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Report(Message::OnFromChange(
+            "1/10/2026".to_string(),
+        )));
+        let _ = state.update(crate::Message::Report(Message::Fetch));
+        test(&state, |state| {
+            assert_eq!(state.status, "วันที่ต้องเป็นแบบ 2026-01-31");
+        });
+    }
+
+    #[test]
+    fn fetched() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Report(Message::Fetched(Ok(sample_sales()))));
+        test(&state, |state| {
+            assert_eq!(state.sales, sample_sales());
+            assert_eq!(state.status, "");
+            assert_eq!(name(&state.sales[1]), "ไม่มีหมวดหมู่");
+            assert_eq!(
+                total(&state.sales),
+                (
+                    Decimal::new(12, 0),
+                    Decimal::new(175, 0),
+                    Decimal::new(55, 0)
+                )
+            );
+        });
+
+        let _ = state.update(crate::Message::Report(Message::Fetched(Ok(Vec::new()))));
+        test(&state, |state| {
+            assert!(state.sales.is_empty());
+            assert_eq!(state.status, "ไม่มียอดขายในช่วงนี้");
+        });
+    }
+}
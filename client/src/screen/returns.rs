@@ -0,0 +1,542 @@
+use iced::alignment::{Horizontal, Vertical};
+use iced::keyboard::key;
+use iced::widget::text::LineHeight;
+use iced::widget::{
+    button, column, horizontal_space, pick_list, row, text, text_input, vertical_space,
+};
+use iced::{Element, Length, Pixels, Subscription, Task, keyboard};
+use rust_decimal::Decimal;
+
+use crate::api::{self, Api};
+use crate::custom;
+use shared::{
+    Disposition, PaymentMethod, ReturnLine, ReturnRequest, SaleRecord, SaleReturn, VoidRequest,
+    number,
+};
+
+/// Ways money is given back for a return. Credit takes it off what the customer of the sale owes.
+const REFUND_METHODS: [PaymentMethod; 4] = [
+    PaymentMethod::Cash,
+    PaymentMethod::PromptPay,
+    PaymentMethod::BankTransfer,
+    PaymentMethod::Credit,
+];
+
+/// An item of the receipt and how much of it is being brought back.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Line {
+    pub barcode: String,
+    pub name: String,
+    /// Units sold that were not returned yet.
+    pub left: Decimal,
+    pub quantity: String,
+    pub disposition: Disposition,
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub(crate) struct State {
+    pub receipt_id: String,
+    pub record: Option<SaleRecord>,
+    pub lines: Vec<Line>,
+    pub refund_method: PaymentMethod,
+    pub reason: String,
+    pub status: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Back,
+    OnReceiptIdChange(String),
+    Fetch,
+    Fetched(Result<SaleRecord, String>),
+    OnQuantityChange(usize, String),
+    SelectDisposition(usize, Disposition),
+    SelectRefundMethod(PaymentMethod),
+    OnReasonChange(String),
+    Return,
+    Void,
+    Done(Result<SaleReturn, String>),
+}
+
+pub fn update(state: &mut crate::State, message: crate::Message) -> Task<Message> {
+    let crate::Message::Returns(message) = message else {
+        return Task::none();
+    };
+
+    let api = state.api();
+    let mut tasks = Vec::new();
+    match message {
+        Message::Back => {
+            state.screen = crate::Screen::Home;
+        }
+        Message::OnReceiptIdChange(receipt_id) => {
+            modify(state, |state| {
+                state.receipt_id = receipt_id;
+            });
+        }
+        Message::Fetch => {
+            modify(state, |state| {
+                match state.receipt_id.trim().parse::<u32>() {
+                    Ok(id) => tasks.push(Task::perform(fetch(api, id), Message::Fetched)),
+                    Err(_) => state.status = format!("เลขที่ใบเสร็จไม่ถูกต้อง: {}", state.receipt_id),
+                }
+            });
+        }
+        Message::Fetched(result) => {
+            modify(state, |state| match result {
+                Ok(record) => {
+                    state.lines = lines(&record);
+                    state.status = match record.voided() {
+                        true => "ใบเสร็จนี้ถูกยกเลิกแล้ว".to_string(),
+                        false => String::new(),
+                    };
+                    state.record = Some(record);
+                }
+                Err(e) => {
+                    state.record = None;
+                    state.lines = Vec::new();
+                    state.status = e;
+                }
+            });
+        }
+        Message::OnQuantityChange(i, quantity) => {
+            modify(state, |state| {
+                if let Some(line) = state.lines.get_mut(i) {
+                    line.quantity = quantity;
+                }
+            });
+        }
+        Message::SelectDisposition(i, disposition) => {
+            modify(state, |state| {
+                if let Some(line) = state.lines.get_mut(i) {
+                    line.disposition = disposition;
+                }
+            });
+        }
+        Message::SelectRefundMethod(refund_method) => {
+            modify(state, |state| {
+                state.refund_method = refund_method;
+            });
+        }
+        Message::OnReasonChange(reason) => {
+            modify(state, |state| {
+                state.reason = reason;
+            });
+        }
+        Message::Return => {
+            modify(state, |state| {
+                let Some(id) = state.record.as_ref().map(|record| record.id) else {
+                    return;
+                };
+                match request(state) {
+                    Ok(request) => {
+                        tasks.push(Task::perform(submit(api, id, request), Message::Done))
+                    }
+                    Err(e) => state.status = e,
+                }
+            });
+        }
+        Message::Void => {
+            modify(state, |state| {
+                let Some(id) = state.record.as_ref().map(|record| record.id) else {
+                    return;
+                };
+                if state.reason.trim().is_empty() {
+                    state.status = "กรุณาใส่เหตุผลที่ยกเลิกใบเสร็จ".to_string();
+                    return;
+                }
+                let request = VoidRequest {
+                    reason: state.reason.trim().to_string(),
+                };
+                tasks.push(Task::perform(void(api, id, request), Message::Done));
+            });
+        }
+        Message::Done(result) => {
+            modify(state, |state| match result {
+                Ok(sale_return) => {
+                    state.status = match sale_return.void {
+                        true => format!(
+                            "ยกเลิกใบเสร็จ #{} แล้ว คืนเงิน {} บาท",
+                            sale_return.receipt_id,
+                            sale_return.refund()
+                        ),
+                        false => format!("คืนเงิน {} บาท", sale_return.refund()),
+                    };
+                    state.reason = String::new();
+                    tasks.push(Task::perform(
+                        fetch(api, sale_return.receipt_id),
+                        Message::Fetched,
+                    ));
+                }
+                Err(e) => state.status = e,
+            });
+        }
+    }
+
+    Task::batch(tasks)
+}
+
+fn modify<F>(state: &mut crate::State, f: F)
+where
+    F: FnOnce(&mut State),
+{
+    if let crate::Screen::Returns(ref mut state) = state.screen {
+        f(state);
+    } else {
+        panic!("Screen error in returns");
+    }
+}
+
+/// One line per item of the receipt, however many lines it was sold on.
+fn lines(record: &SaleRecord) -> Vec<Line> {
+    let mut lines: Vec<Line> = Vec::new();
+    for item in &record.receipt.items {
+        if lines.iter().any(|line| line.barcode == item.barcode) {
+            continue;
+        }
+        lines.push(Line {
+            barcode: item.barcode.clone(),
+            name: item.name.clone(),
+            left: record.sold(&item.barcode) - record.returned(&item.barcode),
+            quantity: String::new(),
+            disposition: Disposition::Restock,
+        });
+    }
+    lines
+}
+
+/// The lines with a quantity typed in, or what is wrong with one of them.
+fn request(state: &State) -> Result<ReturnRequest, String> {
+    let mut lines = Vec::new();
+    for line in &state.lines {
+        if line.quantity.trim().is_empty() {
+            continue;
+        }
+        match number::parse_decimal(&line.quantity) {
+            Some(quantity) if quantity.is_zero() => {}
+            Some(quantity) if quantity > Decimal::ZERO && quantity <= line.left => {
+                lines.push(ReturnLine {
+                    barcode: line.barcode.clone(),
+                    quantity: quantity.normalize(),
+                    disposition: line.disposition,
+                    ..Default::default()
+                })
+            }
+            _ => {
+                return Err(format!("จำนวนคืนของ {} ต้องไม่เกิน {}", line.name, line.left));
+            }
+        }
+    }
+    if lines.is_empty() {
+        return Err("ยังไม่ได้ใส่จำนวนสินค้าที่คืน".to_string());
+    }
+    Ok(ReturnRequest {
+        lines,
+        refund_method: state.refund_method,
+        reason: state.reason.trim().to_string(),
+    })
+}
+
+async fn fetch(api: Api, id: u32) -> Result<SaleRecord, String> {
+    api::send(api.get(&format!("/receipts/{id}"))).await
+}
+
+async fn submit(api: Api, id: u32, request: ReturnRequest) -> Result<SaleReturn, String> {
+    api::send(api.post(&format!("/receipts/{id}/returns")).json(&request)).await
+}
+
+/// Only the owner may void a receipt, the server refuses anyone else.
+async fn void(api: Api, id: u32, request: VoidRequest) -> Result<SaleReturn, String> {
+    api::send(api.post(&format!("/receipts/{id}/void")).json(&request)).await
+}
+
+fn cell<'a>(value: String) -> Element<'a, crate::Message> {
+    text(value)
+        .line_height(LineHeight::Relative(2.0))
+        .shaping(text::Shaping::Advanced)
+        .width(Length::Fill)
+        .align_x(Horizontal::Center)
+        .align_y(Vertical::Center)
+        .into()
+}
+
+/// The items of the receipt to pick the return from, and what was returned of it before.
+fn receipt(state: &State) -> Element<'_, crate::Message> {
+    let Some(record) = &state.record else {
+        return column![].into();
+    };
+    let mut receipt = column![
+        text(format!(
+            "ใบเสร็จ #{} วันที่ {} ยอด {} บาท",
+            record.id,
+            record.receipt.created_at.format("%Y-%m-%d %H:%M"),
+            record.receipt.total()
+        ))
+        .shaping(text::Shaping::Advanced),
+        row![
+            cell("สินค้า".to_string()),
+            cell("คืนได้".to_string()),
+            cell("จำนวนคืน".to_string()),
+            cell("สภาพ".to_string()),
+        ],
+        custom::list(state.lines.clone(), |i, line| {
+            row![
+                cell(line.name.clone()),
+                cell(line.left.to_string()),
+                text_input("0", &line.quantity)
+                    .on_input(move |input| {
+                        crate::Message::Returns(Message::OnQuantityChange(i, input))
+                    })
+                    .on_submit(crate::Message::Returns(Message::Return))
+                    .width(Length::Fill),
+                pick_list(
+                    Disposition::ALL,
+                    Some(line.disposition),
+                    move |disposition| {
+                        crate::Message::Returns(Message::SelectDisposition(i, disposition))
+                    }
+                )
+                .text_shaping(text::Shaping::Advanced)
+                .width(Length::Fill),
+            ]
+            .spacing(Pixels(10.0))
+            .into()
+        })
+        .height(Length::Fill),
+    ]
+    .spacing(Pixels(10.0));
+    for sale_return in &record.returns {
+        receipt = receipt.push(
+            text(format!(
+                "{} {} {} บาท {}",
+                sale_return.created_at.format("%Y-%m-%d %H:%M"),
+                match sale_return.void {
+                    true => "ยกเลิกใบเสร็จ",
+                    false => "คืนสินค้า",
+                },
+                sale_return.refund(),
+                sale_return.reason
+            ))
+            .shaping(text::Shaping::Advanced),
+        );
+    }
+    receipt
+        .push(
+            row![
+                pick_list(REFUND_METHODS, Some(state.refund_method), |method| {
+                    crate::Message::Returns(Message::SelectRefundMethod(method))
+                })
+                .text_shaping(text::Shaping::Advanced),
+                text_input("เหตุผล", &state.reason)
+                    .on_input(|input| crate::Message::Returns(Message::OnReasonChange(input))),
+                button(text("คืนสินค้า").shaping(text::Shaping::Advanced))
+                    .on_press(crate::Message::Returns(Message::Return)),
+                button(text("ยกเลิกทั้งใบ").shaping(text::Shaping::Advanced))
+                    .on_press(crate::Message::Returns(Message::Void)),
+            ]
+            .spacing(Pixels(10.0)),
+        )
+        .into()
+}
+
+pub fn view(state: &State) -> Element<crate::Message> {
+    column![
+        vertical_space(),
+        custom::title("คืนสินค้า"),
+        row![
+            horizontal_space(),
+            column![
+                row![
+                    text_input("เลขที่ใบเสร็จ", &state.receipt_id)
+                        .id(text_input::Id::new("return_receipt_id"))
+                        .on_input(|input| crate::Message::Returns(Message::OnReceiptIdChange(
+                            input
+                        )))
+                        .on_submit(crate::Message::Returns(Message::Fetch)),
+                    button(text("ค้นหา").shaping(text::Shaping::Advanced))
+                        .on_press(crate::Message::Returns(Message::Fetch)),
+                ]
+                .spacing(Pixels(10.0)),
+                receipt(state),
+                text(state.status.clone()).shaping(text::Shaping::Advanced),
+            ]
+            .width(Length::FillPortion(10))
+            .spacing(Pixels(10.0)),
+            horizontal_space(),
+        ]
+        .height(Length::FillPortion(12)),
+        vertical_space()
+    ]
+    .height(Length::Fill)
+    .width(Length::Fill)
+    .into()
+}
+
+pub(crate) fn subscription(_state: &State) -> Subscription<crate::Message> {
+    keyboard::on_key_press(|keyboard, _| match keyboard {
+        keyboard::Key::Named(key::Named::Escape) => Some(crate::Message::Returns(Message::Back)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use shared::{Receipt, ReceiptItem};
+
+    fn init_state() -> crate::State {
+        crate::State {
+            screen: crate::Screen::Returns(Box::default()),
+            ..Default::default()
+        }
+    }
+
+    fn test<F>(state: &crate::State, f: F)
+    where
+        F: FnOnce(&State),
+    {
+        if let crate::Screen::Returns(state) = &state.screen {
+            f(state);
+        } else {
+            panic!("Screen error in returns");
+        }
+    }
+
+    fn record() -> SaleRecord {
+        let item = |barcode: &str, name: &str, quantity: i64| ReceiptItem {
+            barcode: barcode.to_string(),
+            name: name.to_string(),
+            price: Decimal::new(15, 0),
+            quantity: quantity.into(),
+            ..Default::default()
+        };
+        SaleRecord {
+            id: 12,
+            receipt: Receipt {
+                items: vec![
+                    item("0", "โค้ก", 2),
+                    item("1", "น้ำดื่ม", 1),
+                    item("0", "โค้ก", 1),
+                ],
+                ..Default::default()
+            },
+            returns: vec![SaleReturn {
+                lines: vec![ReturnLine {
+                    barcode: "0".to_string(),
+                    quantity: Decimal::ONE,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn back() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Returns(Message::Back));
+        assert_eq!(state.screen, crate::Screen::Home);
+    }
+
+    #[test]
+    fn fetched() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Returns(Message::OnReceiptIdChange(
+            "abc".to_string(),
+        )));
+        let _ = state.update(crate::Message::Returns(Message::Fetch));
+        test(&state, |state| {
+            assert!(state.status.contains("abc"));
+        });
+
+        let _ = state.update(crate::Message::Returns(Message::Fetched(Ok(record()))));
+        test(&state, |state| {
+            let left: Vec<_> = state
+                .lines
+                .iter()
+                .map(|line| (line.name.as_str(), line.left))
+                .collect();
+            assert_eq!(
+                left,
+                vec![("โค้ก", Decimal::from(2)), ("น้ำดื่ม", Decimal::ONE)]
+            );
+            assert!(state.status.is_empty());
+        });
+    }
+
+    #[test]
+    fn return_lines() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Returns(Message::Fetched(Ok(record()))));
+        let _ = state.update(crate::Message::Returns(Message::Return));
+        test(&state, |state| {
+            assert_eq!(state.status, "ยังไม่ได้ใส่จำนวนสินค้าที่คืน");
+        });
+
+        let _ = state.update(crate::Message::Returns(Message::OnQuantityChange(
+            0,
+            "3".to_string(),
+        )));
+        let _ = state.update(crate::Message::Returns(Message::Return));
+        test(&state, |state| {
+            assert!(state.status.contains("ไม่เกิน 2"));
+        });
+
+        let _ = state.update(crate::Message::Returns(Message::OnQuantityChange(
+            0,
+            "2".to_string(),
+        )));
+        let _ = state.update(crate::Message::Returns(Message::SelectDisposition(
+            1,
+            Disposition::Waste,
+        )));
+        let _ = state.update(crate::Message::Returns(Message::OnQuantityChange(
+            1,
+            "1".to_string(),
+        )));
+        let _ = state.update(crate::Message::Returns(Message::SelectRefundMethod(
+            PaymentMethod::PromptPay,
+        )));
+        test(&state, |state| {
+            let request = request(state).unwrap();
+            let lines: Vec<_> = request
+                .lines
+                .iter()
+                .map(|line| (line.barcode.as_str(), line.quantity, line.disposition))
+                .collect();
+            assert_eq!(
+                lines,
+                vec![
+                    ("0", Decimal::from(2), Disposition::Restock),
+                    ("1", Decimal::ONE, Disposition::Waste)
+                ]
+            );
+            assert_eq!(request.refund_method, PaymentMethod::PromptPay);
+        });
+    }
+
+    #[test]
+    fn void_needs_a_reason() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Returns(Message::Fetched(Ok(record()))));
+        let _ = state.update(crate::Message::Returns(Message::Void));
+        test(&state, |state| {
+            assert!(state.status.contains("เหตุผล"));
+        });
+
+        let voided = SaleReturn {
+            receipt_id: 12,
+            void: true,
+            refunds: vec![shared::Payment {
+                amount: Decimal::new(60, 0),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let _ = state.update(crate::Message::Returns(Message::Done(Ok(voided))));
+        test(&state, |state| {
+            assert!(state.status.contains("#12"));
+            assert!(state.status.contains("60"));
+        });
+    }
+}
@@ -0,0 +1,1240 @@
+use chrono::Local;
+use iced::alignment::{Horizontal, Vertical};
+use iced::keyboard::key;
+use iced::widget::text::LineHeight;
+use iced::widget::{
+    button, column, horizontal_space, pick_list, qr_code, row, text, text_input, vertical_space,
+};
+use iced::{Element, Length, Pixels, Subscription, Task, keyboard};
+use rust_decimal::Decimal;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::customer;
+use crate::api::{self, Api};
+use crate::{cache, connection, custom};
+use shared::{
+    Customer, Discount, Item, ItemEvent, Payment, PaymentMethod, Promotion, Receipt, ReceiptItem,
+    barcode, number,
+};
+
+#[derive(Default, Debug, PartialEq)]
+pub(crate) struct State {
+    pub items: Vec<Item>,
+    pub barcode: String,
+    /// When the last key went into `barcode`, where the keys typed since the last pause start in
+    /// it and whether they came within the scan interval of each other, from the scanner.
+    pub typed_at: Option<Instant>,
+    pub run_start: usize,
+    pub scanned: bool,
+    pub lines: Vec<ReceiptItem>,
+    pub promotions: Vec<Promotion>,
+    /// What the running promotions take off the lines, worked out again whenever they change.
+    pub discounts: Vec<Discount>,
+    pub status: String,
+    /// Taking the payment of the lines, which may be split over several methods.
+    pub paying: bool,
+    pub payments: Vec<Payment>,
+    pub method: PaymentMethod,
+    pub amount: String,
+    pub reference: String,
+    pub qr: Option<Qr>,
+    /// The customer the sale is for, needed to pay on credit or with points.
+    pub customer: Option<Customer>,
+    pub customer_search: String,
+    /// Customers found by the search, to pick from when there are several.
+    pub customers: Vec<Customer>,
+}
+
+/// The PromptPay code of the amount being paid. Compared by its payload, as the drawing has no
+/// equality.
+pub(crate) struct Qr {
+    pub payload: String,
+    data: qr_code::Data,
+}
+
+impl PartialEq for Qr {
+    fn eq(&self, other: &Self) -> bool {
+        self.payload == other.payload
+    }
+}
+
+impl std::fmt::Debug for Qr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Qr").field(&self.payload).finish()
+    }
+}
+
+impl State {
+    fn total(&self) -> Decimal {
+        let discount: Decimal = self.discounts.iter().map(|discount| discount.amount).sum();
+        self.lines.iter().map(ReceiptItem::total).sum::<Decimal>() - discount
+    }
+
+    /// The VAT included in the total at `rate` percent, the way the server works it out.
+    fn tax(&self, rate: Decimal) -> Decimal {
+        let mut receipt = Receipt {
+            items: self.lines.clone(),
+            discounts: self.discounts.clone(),
+            ..Default::default()
+        };
+        receipt.apply_tax(rate);
+        receipt.tax()
+    }
+
+    fn reprice(&mut self) {
+        self.discounts =
+            shared::apply_promotions(&self.promotions, &self.lines, Local::now().naive_local());
+    }
+
+    fn remaining(&self) -> Decimal {
+        let paid: Decimal = self.payments.iter().map(|payment| payment.amount).sum();
+        (self.total() - paid).max(Decimal::ZERO)
+    }
+
+    fn paid_with(&self, method: PaymentMethod) -> Decimal {
+        self.payments
+            .iter()
+            .filter(|payment| payment.method == method)
+            .map(|payment| payment.amount)
+            .sum()
+    }
+
+    /// The code of a scan that came after something typed by hand, other than a quantity, which
+    /// it replaces, like a code that was not found.
+    fn scanned_over(&self) -> Option<String> {
+        let typed = self.barcode.get(..self.run_start)?.trim();
+        let scanned = self.barcode.get(self.run_start..)?;
+        (self.scanned && !typed.is_empty() && !typed.ends_with('*')).then(|| scanned.to_string())
+    }
+
+    fn attach(&mut self, customer: Customer) {
+        self.customer = Some(customer);
+        self.customers = Vec::new();
+        self.customer_search = String::new();
+        self.status = String::new();
+    }
+
+    /// Shows the code to scan when PromptPay is picked, for the amount typed in.
+    fn refresh_qr(&mut self, promptpay_id: &str) {
+        self.qr = None;
+        if self.method != PaymentMethod::PromptPay {
+            return;
+        }
+        if promptpay_id.is_empty() {
+            self.status = "ยังไม่ได้ตั้งค่าพร้อมเพย์".to_string();
+            return;
+        }
+        let Some(amount) = number::parse_decimal(&self.amount) else {
+            return;
+        };
+        match shared::promptpay_payload(promptpay_id, Some(amount)) {
+            Ok(payload) => match qr_code::Data::new(&payload) {
+                Ok(data) => self.qr = Some(Qr { payload, data }),
+                Err(e) => self.status = e.to_string(),
+            },
+            Err(e) => self.status = e,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Back,
+    ItemsFetched(Vec<Item>),
+    PromotionsFetched(Vec<Promotion>),
+    OnBarcodeChange(String, Instant),
+    OnBarcodeSubmit,
+    Remove(usize),
+    Checkout,
+    SelectMethod(PaymentMethod),
+    OnAmountChange(String),
+    OnReferenceChange(String),
+    Pay,
+    RemovePayment(usize),
+    CancelPayment,
+    OnCustomerSearchChange(String),
+    SearchCustomer,
+    CustomersFound(Result<Vec<Customer>, String>),
+    SelectCustomer(usize),
+    RemoveCustomer,
+}
+
+pub fn update(state: &mut crate::State, message: crate::Message) -> Task<crate::Message> {
+    let crate::Message::Sale(message) = message else {
+        return Task::none();
+    };
+
+    let promptpay_id = state.setting.promptpay_id.clone();
+    let scan_interval = Duration::from_millis(state.setting.scan_interval_ms);
+    let api = state.api();
+    let mut receipt = None;
+    let mut tasks = Vec::new();
+    // Looking up the customer keeps the focus in their search box
+    let searching = matches!(
+        message,
+        Message::OnCustomerSearchChange(_) | Message::SearchCustomer | Message::CustomersFound(_)
+    );
+    match message {
+        Message::Back => {
+            state.screen = crate::Screen::Home;
+        }
+        Message::ItemsFetched(items) => {
+            modify(state, |state| {
+                state.items = items;
+            });
+        }
+        Message::PromotionsFetched(promotions) => {
+            modify(state, |state| {
+                state.promotions = promotions;
+                state.reprice();
+            });
+        }
+        Message::OnBarcodeChange(barcode, at) => {
+            modify(state, |state| {
+                let fast = state
+                    .typed_at
+                    .is_some_and(|typed_at| at.saturating_duration_since(typed_at) < scan_interval);
+                if !fast {
+                    state.run_start = state.barcode.len();
+                }
+                state.scanned = fast;
+                state.typed_at = Some(at);
+                state.barcode = barcode;
+            });
+        }
+        Message::OnBarcodeSubmit => {
+            modify(state, |state| {
+                if let Some(scanned) = state.scanned_over() {
+                    state.barcode = scanned;
+                }
+                if state.barcode.is_empty() {
+                    return;
+                }
+                let (typed, code) = match state.barcode.split_once('*') {
+                    Some((quantity, code)) => (Some(quantity.trim()), code.trim()),
+                    None => (None, state.barcode.trim()),
+                };
+                let typed = match typed.map(number::parse_decimal) {
+                    None => None,
+                    Some(Some(quantity)) if quantity > Decimal::ZERO => Some(quantity.normalize()),
+                    Some(_) => {
+                        state.status = format!("จำนวนไม่ถูกต้อง {}", state.barcode);
+                        return;
+                    }
+                };
+                // A lot off the scale comes with its price or its weight, whatever was typed
+                let (barcode, lot_price, weight) =
+                    if let Some((barcode, price)) = barcode::parse_embedded(code) {
+                        (barcode, Some(price), None)
+                    } else if let Some((barcode, weight)) = barcode::parse_weight(code) {
+                        (barcode, None, Some(weight))
+                    } else {
+                        (code.to_string(), None, None)
+                    };
+                let Some(item) = state.items.iter().find(|item| item.barcode == barcode) else {
+                    state.status = format!("ไม่พบสินค้า {}", state.barcode);
+                    return;
+                };
+                let quantity = match (lot_price, weight) {
+                    (Some(_), _) => Decimal::ONE,
+                    (None, Some(weight)) => weight,
+                    (None, None) => typed.unwrap_or(Decimal::ONE),
+                };
+                if !item.unit.allows(quantity) {
+                    state.status = format!("ขาย{} {quantity} {} ไม่ได้", item.name, item.unit);
+                    return;
+                }
+                let line = state
+                    .lines
+                    .iter_mut()
+                    .find(|line| line.barcode == item.barcode && !from_scale(&line.barcode));
+                match line {
+                    Some(line) => line.quantity += quantity,
+                    None => state.lines.push(ReceiptItem {
+                        barcode: item.barcode.clone(),
+                        name: item.name.clone(),
+                        price: lot_price.unwrap_or(item.price),
+                        quantity,
+                        tax_class: item.tax_class,
+                        tax: Decimal::ZERO,
+                    }),
+                }
+                state.barcode = String::new();
+                state.typed_at = None;
+                state.status = String::new();
+                state.reprice();
+            });
+        }
+        Message::Remove(i) => {
+            modify(state, |state| {
+                if i < state.lines.len() {
+                    state.lines.remove(i);
+                    state.reprice();
+                }
+            });
+        }
+        Message::Checkout => {
+            modify(state, |state| {
+                if state.lines.is_empty() {
+                    return;
+                }
+                state.paying = true;
+                state.status = String::new();
+                state.amount = state.remaining().to_string();
+                state.refresh_qr(&promptpay_id);
+            });
+        }
+        Message::SelectMethod(method) => {
+            modify(state, |state| {
+                state.method = method;
+                state.status = String::new();
+                state.refresh_qr(&promptpay_id);
+            });
+        }
+        Message::OnAmountChange(amount) => {
+            modify(state, |state| {
+                state.amount = amount;
+                state.refresh_qr(&promptpay_id);
+            });
+        }
+        Message::OnReferenceChange(reference) => {
+            modify(state, |state| {
+                state.reference = reference;
+            });
+        }
+        Message::Pay => {
+            modify(state, |state| {
+                if !state.paying {
+                    return;
+                }
+                if let Err(e) = add_payment(state) {
+                    state.status = e;
+                    return;
+                }
+                if state.remaining() > Decimal::ZERO {
+                    state.amount = state.remaining().to_string();
+                    state.refresh_qr(&promptpay_id);
+                    return;
+                }
+                let new_receipt = Receipt {
+                    key: Uuid::new_v4().to_string(),
+                    created_at: Local::now().naive_local(),
+                    items: std::mem::take(&mut state.lines),
+                    discounts: std::mem::take(&mut state.discounts),
+                    payments: std::mem::take(&mut state.payments),
+                    customer_id: state.customer.take().map(|customer| customer.id),
+                    ..Default::default()
+                };
+                state.status = match new_receipt.change() {
+                    change if change > Decimal::ZERO => {
+                        format!("ขายแล้ว {} บาท ทอน {change} บาท", new_receipt.total())
+                    }
+                    _ => format!("ขายแล้ว {} บาท", new_receipt.total()),
+                };
+                stop_paying(state);
+                receipt = Some(new_receipt);
+            });
+        }
+        Message::RemovePayment(i) => {
+            modify(state, |state| {
+                if i < state.payments.len() {
+                    state.payments.remove(i);
+                    state.amount = state.remaining().to_string();
+                    state.refresh_qr(&promptpay_id);
+                }
+            });
+        }
+        Message::CancelPayment => {
+            modify(state, |state| {
+                state.payments.clear();
+                state.status = String::new();
+                stop_paying(state);
+            });
+        }
+        Message::OnCustomerSearchChange(search) => {
+            modify(state, |state| {
+                state.customer_search = search;
+            });
+        }
+        Message::SearchCustomer => {
+            modify(state, |state| {
+                let keyword = state.customer_search.trim().to_string();
+                if keyword.is_empty() {
+                    return;
+                }
+                tasks.push(Task::perform(customer::search(api, keyword), |result| {
+                    crate::Message::Sale(Message::CustomersFound(result))
+                }));
+            });
+        }
+        Message::CustomersFound(result) => {
+            modify(state, |state| match result {
+                Ok(mut customers) if customers.len() == 1 => state.attach(customers.remove(0)),
+                Ok(customers) if customers.is_empty() => {
+                    state.status = format!("ไม่พบลูกค้า {}", state.customer_search);
+                }
+                Ok(customers) => state.customers = customers,
+                Err(e) => state.status = e,
+            });
+        }
+        Message::SelectCustomer(i) => {
+            modify(state, |state| {
+                if i < state.customers.len() {
+                    let customer = state.customers.remove(i);
+                    state.attach(customer);
+                }
+            });
+        }
+        Message::RemoveCustomer => {
+            modify(state, |state| {
+                state.customer = None;
+                // What the customer was paying with goes with them
+                state.payments.retain(|payment| {
+                    !matches!(
+                        payment.method,
+                        PaymentMethod::Credit | PaymentMethod::Points
+                    )
+                });
+                if state.paying {
+                    state.amount = state.remaining().to_string();
+                    state.refresh_qr(&promptpay_id);
+                }
+            });
+        }
+    }
+
+    if !searching {
+        let paying = matches!(&state.screen, crate::Screen::Sale(sale) if sale.paying);
+        tasks.push(match paying {
+            true => text_input::focus(text_input::Id::new("sale_amount")),
+            false => text_input::focus(text_input::Id::new("sale_barcode")),
+        });
+    }
+    if let Some(receipt) = receipt {
+        tasks.push(connection::enqueue(state, receipt).map(crate::Message::Connection));
+    }
+    Task::batch(tasks)
+}
+
+/// Adds the amount typed in as a payment by the picked method. Only cash can be more than what
+/// is left to pay, the rest is change. Credit and points are checked against the customer as they
+/// were looked up, the server takes the sale whatever they owe by now.
+fn add_payment(state: &mut State) -> Result<(), String> {
+    let amount = match number::parse_decimal(&state.amount) {
+        Some(amount) if amount > Decimal::ZERO => amount.round_dp(2),
+        _ => return Err(format!("จำนวนเงินไม่ถูกต้อง: {}", state.amount)),
+    };
+    if state.method != PaymentMethod::Cash && amount > state.remaining() {
+        return Err("ชำระเกินยอดได้เฉพาะเงินสด".to_string());
+    }
+    match (state.method, &state.customer) {
+        (PaymentMethod::Credit | PaymentMethod::Points, None) => {
+            return Err(format!("{} ต้องเลือกลูกค้าก่อน", state.method));
+        }
+        (PaymentMethod::Credit, Some(customer))
+            if state.paid_with(PaymentMethod::Credit) + amount > customer.available_credit() =>
+        {
+            return Err(format!(
+                "เกินวงเงินเชื่อ เชื่อได้อีก {} บาท",
+                customer.available_credit()
+            ));
+        }
+        (PaymentMethod::Points, _) if !amount.fract().is_zero() => {
+            return Err("แต้มสะสมใช้ได้เป็นบาทเต็ม".to_string());
+        }
+        (PaymentMethod::Points, Some(customer))
+            if state.paid_with(PaymentMethod::Points) + amount > Decimal::from(customer.points) =>
+        {
+            return Err(format!("แต้มสะสมไม่พอ มี {} แต้ม", customer.points));
+        }
+        _ => {}
+    }
+    let reference = state.reference.trim().to_string();
+    state.payments.push(Payment {
+        method: state.method,
+        amount,
+        reference: (!reference.is_empty()).then_some(reference),
+    });
+    state.reference = String::new();
+    state.status = String::new();
+    Ok(())
+}
+
+fn stop_paying(state: &mut State) {
+    state.paying = false;
+    state.method = PaymentMethod::default();
+    state.amount = String::new();
+    state.reference = String::new();
+    state.qr = None;
+}
+
+fn modify<F>(state: &mut crate::State, f: F)
+where
+    F: FnOnce(&mut State),
+{
+    if let crate::Screen::Sale(ref mut state) = state.screen {
+        f(state);
+    } else {
+        panic!("Screen error in sale");
+    }
+}
+
+/// Applies a catalogue change pushed by the server to the items and the open cart. Returns
+/// `false` when the items have to be fetched again.
+pub(crate) fn apply_event(state: &mut State, event: &ItemEvent) -> bool {
+    if !event.apply(&mut state.items) {
+        return false;
+    }
+    match event {
+        ItemEvent::Changed { item } => {
+            for line in state
+                .lines
+                .iter_mut()
+                .filter(|line| line.barcode == item.barcode)
+            {
+                line.name = item.name.clone();
+                if !priced_by_scale(&line.barcode) {
+                    line.price = item.price;
+                }
+                line.tax_class = item.tax_class;
+            }
+        }
+        ItemEvent::Deleted { barcode } => {
+            if state.lines.iter().any(|line| &line.barcode == barcode) {
+                state.lines.retain(|line| &line.barcode != barcode);
+                state.status = format!("สินค้า {barcode} ถูกลบออกจากระบบ");
+            }
+        }
+        ItemEvent::Reload => {}
+    }
+    state.reprice();
+    true
+}
+
+/// Whether the line is a weighed lot, priced by the scale rather than the catalogue.
+fn priced_by_scale(barcode: &str) -> bool {
+    barcode.starts_with(barcode::EMBEDDED_PREFIX) && barcode::is_ean13(barcode)
+}
+
+/// Whether the line is a lot off the scale, priced or weighed there. Each lot stays a line of
+/// its own.
+fn from_scale(barcode: &str) -> bool {
+    (priced_by_scale(barcode) || barcode.starts_with(barcode::WEIGHT_PREFIX))
+        && barcode::is_ean13(barcode)
+}
+
+/// Fetches the promotions and keeps a copy on disk, used when the server can't be reached.
+pub(crate) async fn fetch_promotions(api: Api) -> Vec<Promotion> {
+    match api::send::<Vec<Promotion>>(api.get("/promotions")).await {
+        Ok(promotions) => {
+            if let Err(e) = cache::save_promotions(&promotions) {
+                eprintln!("cache error: {e}");
+            }
+            promotions
+        }
+        Err(e) => {
+            eprintln!("reqwest error: {e}, using cached promotions");
+            cache::read_promotions()
+        }
+    }
+}
+
+fn cell<'a>(value: String) -> Element<'a, crate::Message> {
+    text(value)
+        .line_height(LineHeight::Relative(2.0))
+        .shaping(text::Shaping::Advanced)
+        .width(Length::Fill)
+        .align_x(Horizontal::Center)
+        .into()
+}
+
+/// The payments taken so far and the next one, with the PromptPay code when it is picked.
+fn payment(state: &State) -> Element<'_, crate::Message> {
+    let payments = state.payments.iter().enumerate().map(|(i, payment)| {
+        row![
+            cell(payment.method.to_string()),
+            cell(payment.amount.to_string()),
+            button("x")
+                .on_press(crate::Message::Sale(Message::RemovePayment(i)))
+                .width(Length::Fixed(40.0)),
+        ]
+        .into()
+    });
+
+    let mut next = column![
+        text(format!("ค้างชำระ: {} บาท", state.remaining())).shaping(text::Shaping::Advanced),
+        column(payments),
+        pick_list(PaymentMethod::ALL, Some(state.method), |method| {
+            crate::Message::Sale(Message::SelectMethod(method))
+        })
+        .text_shaping(text::Shaping::Advanced),
+        text_input("", &state.amount)
+            .id(text_input::Id::new("sale_amount"))
+            .on_input(|input| crate::Message::Sale(Message::OnAmountChange(input)))
+            .on_submit(crate::Message::Sale(Message::Pay)),
+    ]
+    .spacing(Pixels(10.0));
+    if state.method == PaymentMethod::BankTransfer {
+        next = next.push(
+            text_input("เลขที่อ้างอิง", &state.reference)
+                .on_input(|input| crate::Message::Sale(Message::OnReferenceChange(input)))
+                .on_submit(crate::Message::Sale(Message::Pay)),
+        );
+    }
+    if let Some(qr) = &state.qr {
+        next = next.push(qr_code(&qr.data).cell_size(4));
+    }
+    next.push(custom::button("รับเงิน", crate::Message::Sale(Message::Pay)))
+        .push(custom::button(
+            "ยกเลิก",
+            crate::Message::Sale(Message::CancelPayment),
+        ))
+        .into()
+}
+
+/// The customer of the sale, or the search for one.
+fn customer_lookup(state: &State) -> Element<'_, crate::Message> {
+    if let Some(customer) = &state.customer {
+        return row![
+            text(format!(
+                "ลูกค้า: {customer} ค้างชำระ {} บาท แต้มสะสม {}",
+                customer.balance, customer.points
+            ))
+            .line_height(LineHeight::Relative(2.0))
+            .shaping(text::Shaping::Advanced)
+            .width(Length::Fill),
+            button("x")
+                .on_press(crate::Message::Sale(Message::RemoveCustomer))
+                .width(Length::Fixed(40.0)),
+        ]
+        .into();
+    }
+    let found = state.customers.iter().enumerate().map(|(i, customer)| {
+        button(text(customer.to_string()).shaping(text::Shaping::Advanced))
+            .on_press(crate::Message::Sale(Message::SelectCustomer(i)))
+            .into()
+    });
+    column![
+        row![
+            text("ลูกค้า: ")
+                .shaping(text::Shaping::Advanced)
+                .line_height(LineHeight::Relative(2.0))
+                .align_y(Vertical::Center),
+            text_input("ชื่อหรือเบอร์โทร", &state.customer_search)
+                .id(text_input::Id::new("sale_customer"))
+                .on_input(|input| crate::Message::Sale(Message::OnCustomerSearchChange(input)))
+                .on_submit(crate::Message::Sale(Message::SearchCustomer)),
+        ]
+        .spacing(Pixels(10.0)),
+        row(found).spacing(Pixels(10.0)),
+    ]
+    .into()
+}
+
+pub fn view<'a>(
+    state: &'a State,
+    connection: &connection::State,
+    vat_rate: Decimal,
+) -> Element<'a, crate::Message> {
+    let total = state.total();
+    let tax = state.tax(vat_rate);
+    let side = match state.paying {
+        true => payment(state),
+        false => custom::button("ชำระเงิน", crate::Message::Sale(Message::Checkout))
+            .padding(20)
+            .into(),
+    };
+
+    column![
+        vertical_space(),
+        custom::title("ขายสินค้า"),
+        row![
+            horizontal_space(),
+            column![
+                text(connection::status(connection)).shaping(text::Shaping::Advanced),
+                row![
+                    text("รหัสสินค้า: ")
+                        .line_height(LineHeight::Relative(2.0))
+                        .align_y(Vertical::Center),
+                    text_input("", &state.barcode)
+                        .id(text_input::Id::new("sale_barcode"))
+                        .on_input(|input| {
+                            crate::Message::Sale(Message::OnBarcodeChange(input, Instant::now()))
+                        })
+                        .on_submit(crate::Message::Sale(Message::OnBarcodeSubmit)),
+                ]
+                .spacing(Pixels(10.0)),
+                customer_lookup(state),
+                row![
+                    cell("รหัสสินค้า".to_string()),
+                    cell("ชื่อ".to_string()),
+                    cell("ราคา".to_string()),
+                    cell("จำนวน".to_string()),
+                    cell("รวม".to_string()),
+                    horizontal_space().width(Length::Fixed(40.0)),
+                ],
+                custom::list(state.lines.clone(), |i, line| {
+                    row![
+                        cell(line.barcode.clone()),
+                        cell(line.name.clone()),
+                        cell(line.price.to_string()),
+                        cell(line.quantity.to_string()),
+                        cell(line.total().to_string()),
+                        button("x")
+                            .on_press(crate::Message::Sale(Message::Remove(i)))
+                            .width(Length::Fixed(40.0)),
+                    ]
+                    .into()
+                })
+                .height(Length::Fill),
+                column(state.discounts.iter().map(|discount| {
+                    row![
+                        cell(discount.barcode.clone()),
+                        cell(discount.name.clone()),
+                        cell(String::new()),
+                        cell(String::new()),
+                        cell(format!("-{}", discount.amount)),
+                        horizontal_space().width(Length::Fixed(40.0)),
+                    ]
+                    .into()
+                })),
+                text(format!("รวม: {total} บาท"))
+                    .shaping(text::Shaping::Advanced)
+                    .size(Pixels(30.0)),
+                text(format!("ภาษีมูลค่าเพิ่ม {vat_rate}% รวมอยู่แล้ว: {tax} บาท"))
+                    .shaping(text::Shaping::Advanced),
+                text(state.status.clone()).shaping(text::Shaping::Advanced),
+            ]
+            .width(Length::FillPortion(12))
+            .spacing(Pixels(10.0)),
+            horizontal_space(),
+            column![side]
+                .width(Length::FillPortion(3))
+                .spacing(Pixels(20.0)),
+            horizontal_space(),
+        ]
+        .height(Length::FillPortion(12)),
+        vertical_space()
+    ]
+    .height(Length::Fill)
+    .width(Length::Fill)
+    .into()
+}
+
+/// Escape cancels the payment being taken, or leaves the screen.
+pub(crate) fn subscription(state: &State) -> Subscription<crate::Message> {
+    match state.paying {
+        true => keyboard::on_key_press(|keyboard, _| match keyboard {
+            keyboard::Key::Named(key::Named::Escape) => {
+                Some(crate::Message::Sale(Message::CancelPayment))
+            }
+            _ => None,
+        }),
+        false => keyboard::on_key_press(|keyboard, _| match keyboard {
+            keyboard::Key::Named(key::Named::Escape) => Some(crate::Message::Sale(Message::Back)),
+            _ => None,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn init_state() -> crate::State {
+        let mut state = crate::State {
+            screen: crate::Screen::Sale(Box::default()),
+            session: Some(shared::Session::default()),
+            ..Default::default()
+        };
+        let _ = state.update(crate::Message::Sale(Message::ItemsFetched(vec![
+            Item {
+                barcode: "0".to_string(),
+                name: "a".to_string(),
+                price: Decimal::new(10, 0),
+                ..Default::default()
+            },
+            Item {
+                barcode: "1".to_string(),
+                name: "b".to_string(),
+                price: Decimal::new(25, 1),
+                ..Default::default()
+            },
+        ])));
+        state
+    }
+
+    fn test<F>(state: &crate::State, f: F)
+    where
+        F: FnOnce(&State),
+    {
+        if let crate::Screen::Sale(state) = &state.screen {
+            f(state);
+        } else {
+            panic!("Screen error in sale");
+        }
+    }
+
+    fn scan(state: &mut crate::State, barcode: &str) {
+        let _ = state.update(crate::Message::Sale(Message::OnBarcodeChange(
+            barcode.to_string(),
+            Instant::now(),
+        )));
+        let _ = state.update(crate::Message::Sale(Message::OnBarcodeSubmit));
+    }
+
+    /// Pays the whole receipt in cash.
+    fn checkout(state: &mut crate::State) {
+        let _ = state.update(crate::Message::Sale(Message::Checkout));
+        let _ = state.update(crate::Message::Sale(Message::Pay));
+    }
+
+    fn pay(state: &mut crate::State, method: PaymentMethod, amount: &str) {
+        let _ = state.update(crate::Message::Sale(Message::SelectMethod(method)));
+        let _ = state.update(crate::Message::Sale(Message::OnAmountChange(
+            amount.to_string(),
+        )));
+        let _ = state.update(crate::Message::Sale(Message::Pay));
+    }
+
+    #[test]
+    fn back() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Sale(Message::Back));
+        assert_eq!(state.screen, crate::Screen::Home);
+    }
+
+    #[test]
+    fn tax() {
+        let mut state = init_state();
+        scan(&mut state, "0");
+        scan(&mut state, "1");
+        test(&state, |state| {
+            assert_eq!(state.lines[0].tax_class, shared::TaxClass::Standard);
+            // 0.65 on the 10 baht line and 0.16 on the 2.50 one
+            assert_eq!(state.tax(Decimal::new(7, 0)), Decimal::new(81, 2));
+            assert_eq!(state.tax(Decimal::ZERO), Decimal::ZERO);
+        });
+
+        let _ = state.update(crate::Message::Event(ItemEvent::Changed {
+            item: Item {
+                barcode: "1".to_string(),
+                name: "b".to_string(),
+                price: Decimal::new(25, 1),
+                tax_class: shared::TaxClass::Exempt,
+                ..Default::default()
+            },
+        }));
+        test(&state, |state| {
+            assert_eq!(state.lines[1].tax_class, shared::TaxClass::Exempt);
+            assert_eq!(state.tax(Decimal::new(7, 0)), Decimal::new(65, 2));
+        });
+    }
+
+    #[test]
+    fn scans_replace_what_was_typed() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Sale(Message::ItemsFetched(vec![Item {
+            barcode: "10".to_string(),
+            name: "c".to_string(),
+            price: Decimal::new(10, 0),
+            ..Default::default()
+        }])));
+        let start = Instant::now();
+        let mut keys = |typed: &[(&str, u64)]| {
+            for &(barcode, ms) in typed {
+                let _ = state.update(crate::Message::Sale(Message::OnBarcodeChange(
+                    barcode.to_string(),
+                    start + Duration::from_millis(ms),
+                )));
+            }
+            let _ = state.update(crate::Message::Sale(Message::OnBarcodeSubmit));
+        };
+
+        // A code typed by hand that is not found, then a scan
+        keys(&[("9", 0)]);
+        keys(&[("91", 1000), ("910", 1010)]);
+        // A quantity typed by hand stays
+        keys(&[("3", 2000), ("3*", 2300), ("3*1", 3000), ("3*10", 3010)]);
+        // Typed slowly, it is not a scan
+        keys(&[("9", 4000)]);
+        keys(&[("91", 5000), ("910", 5200)]);
+        test(&state, |state| {
+            assert_eq!(state.lines.len(), 1);
+            assert_eq!(state.lines[0].quantity, Decimal::from(4));
+            assert_eq!(state.barcode, "910");
+        });
+    }
+
+    #[test]
+    fn scan_items() {
+        let mut state = init_state();
+        scan(&mut state, "0");
+        scan(&mut state, "1");
+        scan(&mut state, "0");
+        test(&state, |state| {
+            assert_eq!(state.lines.len(), 2);
+            assert_eq!(state.lines[0].quantity, Decimal::from(2));
+            assert_eq!(state.lines[1].quantity, Decimal::ONE);
+            assert!(state.barcode.is_empty());
+        });
+
+        scan(&mut state, "9");
+        test(&state, |state| {
+            assert_eq!(state.lines.len(), 2);
+            assert_eq!(state.barcode, "9");
+            assert!(!state.status.is_empty());
+        });
+
+        let _ = state.update(crate::Message::Sale(Message::Remove(0)));
+        test(&state, |state| {
+            assert_eq!(state.lines.len(), 1);
+            assert_eq!(state.lines[0].barcode, "1");
+        });
+    }
+
+    #[test]
+    fn weighed_lots() {
+        let mut state = init_state();
+        let pork = barcode::embedded(1).unwrap();
+        let _ = state.update(crate::Message::Sale(Message::ItemsFetched(vec![Item {
+            barcode: pork.clone(),
+            name: "หมูสามชั้น".to_string(),
+            price: Decimal::new(180, 0),
+            ..Default::default()
+        }])));
+        let lot = |digits: &str| barcode::with_check_digit(digits).unwrap();
+        // 90.50 and 45.25 baht from the scale
+        scan(&mut state, &lot("210000109050"));
+        scan(&mut state, &lot("210000104525"));
+        test(&state, |state| {
+            assert_eq!(state.lines.len(), 2);
+            assert!(state.lines.iter().all(|line| line.barcode == pork));
+            assert_eq!(state.lines[0].price, Decimal::new(9050, 2));
+            assert_eq!(state.lines[1].price, Decimal::new(4525, 2));
+        });
+
+        // A new price per kilo doesn't change lots already weighed
+        let _ = state.update(crate::Message::Event(ItemEvent::Changed {
+            item: Item {
+                barcode: pork.clone(),
+                name: "หมูสามชั้น".to_string(),
+                price: Decimal::new(200, 0),
+                ..Default::default()
+            },
+        }));
+        test(&state, |state| {
+            assert_eq!(state.lines[0].price, Decimal::new(9050, 2));
+        });
+
+        // An item number that is not in the catalogue
+        scan(&mut state, &lot("210000209050"));
+        test(&state, |state| {
+            assert_eq!(state.lines.len(), 2);
+            assert!(state.status.contains("ไม่พบสินค้า"));
+        });
+    }
+
+    #[test]
+    fn quantities_by_the_unit() {
+        let mut state = init_state();
+        let pork = barcode::weight(1).unwrap();
+        let _ = state.update(crate::Message::Sale(Message::ItemsFetched(vec![
+            Item {
+                barcode: "0".to_string(),
+                name: "a".to_string(),
+                price: Decimal::new(10, 0),
+                ..Default::default()
+            },
+            Item {
+                barcode: pork.clone(),
+                name: "หมูสามชั้น".to_string(),
+                price: Decimal::new(180, 0),
+                unit: shared::Unit::Kilogram,
+                ..Default::default()
+            },
+        ])));
+        let lot = |digits: &str| barcode::with_check_digit(digits).unwrap();
+        // 750 and 500 grams from the scale, and 1.25 kilos typed in
+        scan(&mut state, &lot("220000100750"));
+        scan(&mut state, &lot("220000100500"));
+        scan(&mut state, &format!("1.25*{pork}"));
+        test(&state, |state| {
+            assert_eq!(state.lines.len(), 3);
+            assert!(state.lines.iter().all(|line| line.barcode == pork));
+            assert_eq!(state.lines[0].quantity, Decimal::new(75, 2));
+            assert_eq!(state.lines[0].total(), Decimal::new(135, 0));
+            assert_eq!(state.lines[2].quantity, Decimal::new(125, 2));
+        });
+
+        scan(&mut state, "3*0");
+        scan(&mut state, "0");
+        test(&state, |state| {
+            assert_eq!(state.lines.len(), 4);
+            assert_eq!(state.lines[3].quantity, Decimal::from(4));
+        });
+
+        // Pieces come whole
+        scan(&mut state, "0.5*0");
+        test(&state, |state| {
+            assert_eq!(state.lines[3].quantity, Decimal::from(4));
+            assert_eq!(state.barcode, "0.5*0");
+            assert!(!state.status.is_empty());
+        });
+        scan(&mut state, "x*0");
+        test(&state, |state| {
+            assert_eq!(state.lines.len(), 4);
+            assert!(state.status.contains("จำนวนไม่ถูกต้อง"));
+        });
+    }
+
+    #[test]
+    fn checkout_offline() {
+        let mut state = init_state();
+        checkout(&mut state);
+        assert!(state.connection.queue.is_empty());
+
+        scan(&mut state, "0");
+        scan(&mut state, "1");
+        checkout(&mut state);
+        test(&state, |state| {
+            assert!(state.lines.is_empty());
+            assert!(!state.paying);
+        });
+        assert_eq!(state.connection.queue.len(), 1);
+        assert_eq!(state.connection.queue[0].items.len(), 2);
+        assert_eq!(state.connection.queue[0].total(), Decimal::new(125, 1));
+        assert_eq!(state.connection.queue[0].paid(), Decimal::new(125, 1));
+        assert!(!state.connection.replaying);
+
+        scan(&mut state, "0");
+        checkout(&mut state);
+        assert_eq!(state.connection.queue.len(), 2);
+        assert_ne!(state.connection.queue[0].key, state.connection.queue[1].key);
+        assert_eq!(
+            connection::status(&state.connection),
+            "ออฟไลน์ (รอส่ง 2 ใบเสร็จ)"
+        );
+    }
+
+    #[test]
+    fn checkout_online() {
+        let mut state = init_state();
+        state.connection.online = true;
+        scan(&mut state, "0");
+        checkout(&mut state);
+        assert_eq!(state.connection.queue.len(), 1);
+        assert!(state.connection.replaying);
+    }
+
+    #[test]
+    fn promotions() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Sale(Message::PromotionsFetched(vec![
+            Promotion {
+                id: 1,
+                name: "ซื้อ 2 แถม 1".to_string(),
+                rule: shared::PromotionRule::BuyGet { buy: 2, get: 1 },
+                barcodes: vec!["1".to_string()],
+                ..Default::default()
+            },
+        ])));
+        scan(&mut state, "1");
+        scan(&mut state, "1");
+        scan(&mut state, "0");
+        test(&state, |state| {
+            assert!(state.discounts.is_empty());
+            assert_eq!(state.total(), Decimal::new(15, 0));
+        });
+
+        scan(&mut state, "1");
+        test(&state, |state| {
+            assert_eq!(state.discounts.len(), 1);
+            assert_eq!(state.discounts[0].amount, Decimal::new(25, 1));
+            assert_eq!(state.total(), Decimal::new(15, 0));
+        });
+
+        checkout(&mut state);
+        let receipt = &state.connection.queue[0];
+        assert_eq!(receipt.discounts.len(), 1);
+        assert_eq!(receipt.total(), Decimal::new(15, 0));
+        assert_eq!(receipt.paid(), Decimal::new(15, 0));
+        test(&state, |state| {
+            assert!(state.discounts.is_empty());
+        });
+    }
+
+    #[test]
+    fn split_payment() {
+        let mut state = init_state();
+        scan(&mut state, "0");
+        scan(&mut state, "1");
+        scan(&mut state, "1");
+        let _ = state.update(crate::Message::Sale(Message::Checkout));
+        test(&state, |state| {
+            assert!(state.paying);
+            assert_eq!(state.amount, "15.0");
+        });
+
+        // Only cash can be more than what is left, and credit needs the customer
+        pay(&mut state, PaymentMethod::BankTransfer, "20");
+        pay(&mut state, PaymentMethod::Credit, "5");
+        pay(&mut state, PaymentMethod::Cash, "abc");
+        test(&state, |state| {
+            assert!(state.payments.is_empty());
+            assert!(!state.status.is_empty());
+        });
+
+        let _ = state.update(crate::Message::Sale(Message::OnReferenceChange(
+            "1234".to_string(),
+        )));
+        pay(&mut state, PaymentMethod::BankTransfer, "5");
+        test(&state, |state| {
+            assert_eq!(state.payments.len(), 1);
+            assert_eq!(state.payments[0].reference.as_deref(), Some("1234"));
+            assert_eq!(state.amount, "10.0");
+        });
+        assert!(state.connection.queue.is_empty());
+
+        pay(&mut state, PaymentMethod::Cash, "20");
+        test(&state, |state| {
+            assert!(!state.paying);
+            assert!(state.payments.is_empty());
+            assert!(state.status.contains("ทอน 10"));
+        });
+        let receipt = &state.connection.queue[0];
+        assert_eq!(receipt.payments.len(), 2);
+        assert_eq!(receipt.change(), Decimal::new(10, 0));
+    }
+
+    #[test]
+    fn customer_payments() {
+        let mut state = init_state();
+        scan(&mut state, "0");
+        scan(&mut state, "0");
+        let _ = state.update(crate::Message::Sale(Message::CustomersFound(Ok(vec![
+            Customer {
+                id: 7,
+                name: "ป้าแดง".to_string(),
+                credit_limit: Decimal::new(100, 0),
+                balance: Decimal::new(90, 0),
+                points: 3,
+                ..Default::default()
+            },
+        ]))));
+        test(&state, |state| {
+            assert_eq!(state.customer.as_ref().map(|customer| customer.id), Some(7));
+        });
+        let _ = state.update(crate::Message::Sale(Message::Checkout));
+
+        // 10 baht of credit and 3 points are left
+        pay(&mut state, PaymentMethod::Credit, "15");
+        pay(&mut state, PaymentMethod::Points, "4");
+        pay(&mut state, PaymentMethod::Points, "1.5");
+        test(&state, |state| {
+            assert!(state.payments.is_empty());
+            assert!(!state.status.is_empty());
+        });
+        pay(&mut state, PaymentMethod::Credit, "10");
+        pay(&mut state, PaymentMethod::Points, "3");
+        test(&state, |state| {
+            assert_eq!(state.payments.len(), 2);
+            assert_eq!(state.amount, "7");
+        });
+
+        // Without the customer their credit and points go too
+        let _ = state.update(crate::Message::Sale(Message::RemoveCustomer));
+        test(&state, |state| {
+            assert!(state.payments.is_empty());
+            assert_eq!(state.amount, "20");
+        });
+        let _ = state.update(crate::Message::Sale(Message::CustomersFound(Ok(vec![
+            Customer {
+                id: 7,
+                credit_limit: Decimal::new(100, 0),
+                ..Default::default()
+            },
+        ]))));
+        pay(&mut state, PaymentMethod::Credit, "20");
+        let receipt = &state.connection.queue[0];
+        assert_eq!(receipt.customer_id, Some(7));
+        assert_eq!(
+            receipt.paid_with(PaymentMethod::Credit),
+            Decimal::new(20, 0)
+        );
+        test(&state, |state| {
+            assert_eq!(state.customer, None);
+        });
+    }
+
+    #[test]
+    fn promptpay_qr() {
+        let mut state = init_state();
+        scan(&mut state, "0");
+        let _ = state.update(crate::Message::Sale(Message::Checkout));
+        let _ = state.update(crate::Message::Sale(Message::SelectMethod(
+            PaymentMethod::PromptPay,
+        )));
+        test(&state, |state| {
+            assert!(state.qr.is_none());
+            assert!(!state.status.is_empty());
+        });
+
+        state.setting.promptpay_id = "0812345678".to_string();
+        let _ = state.update(crate::Message::Sale(Message::OnAmountChange(
+            "10".to_string(),
+        )));
+        test(&state, |state| {
+            let qr = state.qr.as_ref().unwrap();
+            assert_eq!(
+                Some(&qr.payload),
+                shared::promptpay_payload("0812345678", Some(Decimal::new(10, 0)))
+                    .ok()
+                    .as_ref()
+            );
+        });
+
+        let _ = state.update(crate::Message::Sale(Message::CancelPayment));
+        test(&state, |state| {
+            assert!(!state.paying);
+            assert!(state.qr.is_none());
+            assert_eq!(state.lines.len(), 1);
+        });
+        assert!(state.connection.queue.is_empty());
+    }
+
+    #[test]
+    fn item_event() {
+        let mut state = init_state();
+        scan(&mut state, "0");
+        scan(&mut state, "1");
+
+        let _ = state.update(crate::Message::Event(ItemEvent::Changed {
+            item: Item {
+                barcode: "0".to_string(),
+                name: "a".to_string(),
+                price: Decimal::new(12, 0),
+                ..Default::default()
+            },
+        }));
+        test(&state, |state| {
+            assert_eq!(state.items[0].price, Decimal::new(12, 0));
+            assert_eq!(state.lines[0].price, Decimal::new(12, 0));
+        });
+
+        let _ = state.update(crate::Message::Event(ItemEvent::Deleted {
+            barcode: "1".to_string(),
+        }));
+        test(&state, |state| {
+            assert_eq!(state.items.len(), 1);
+            assert_eq!(state.lines.len(), 1);
+            assert!(!state.status.is_empty());
+        });
+
+        let _ = state.update(crate::Message::Event(ItemEvent::Reload));
+        test(&state, |state| {
+            assert_eq!(state.items.len(), 1);
+        });
+    }
+}
@@ -1,57 +1,421 @@
 use iced::{
-    Element, Length, Pixels, Subscription,
+    Element, Length, Pixels, Subscription, Task,
     keyboard::{self, key::Named},
-    widget::{button, container, horizontal_space, row, text_input},
+    widget::{button, column, container, horizontal_space, pick_list, row, text, text_input},
 };
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use shared::Health;
 use std::fs;
-use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::net::UdpSocket;
 
-pub(crate) const PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/asset/setting.json");
+use crate::api::{self, Api};
+use crate::cache;
 
+/// Where settings were kept before they moved to the config directory. Only read, to migrate.
+const LEGACY_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/asset/setting.json");
+/// Version of the settings file written by this client, bumped with every change to [`Setting`].
+const VERSION: u64 = 5;
+
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(3);
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Settings saved to disk.
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
-pub(crate) struct State {
+#[serde(default)]
+pub(crate) struct Setting {
+    pub(crate) version: u64,
     pub(crate) url: String,
+    pub(crate) shop_name: String,
+    pub(crate) shop_address: String,
+    /// Device path or `host:port` of the receipt printer.
+    pub(crate) printer: String,
+    /// Device path or `host:port` of the thermal printer shelf tags are printed on.
+    pub(crate) label_printer: String,
+    pub(crate) theme: Theme,
+    pub(crate) language: Language,
+    /// Keys typed faster than this many milliseconds apart come from the barcode scanner.
+    pub(crate) scan_interval_ms: u64,
+    /// Mobile number or tax id the shop is paid at by PromptPay, empty when it is not.
+    pub(crate) promptpay_id: String,
 }
 
-impl Default for State {
+impl Default for Setting {
     fn default() -> Self {
-        read()
+        Setting {
+            version: VERSION,
+            url: String::new(),
+            shop_name: String::new(),
+            shop_address: String::new(),
+            printer: String::new(),
+            label_printer: String::new(),
+            theme: Theme::default(),
+            language: Language::default(),
+            scan_interval_ms: 50,
+            promptpay_id: String::new(),
+        }
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Theme {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl Theme {
+    const ALL: [Theme; 2] = [Theme::Light, Theme::Dark];
+}
+
+impl std::fmt::Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Theme::Light => write!(f, "สว่าง"),
+            Theme::Dark => write!(f, "มืด"),
+        }
+    }
+}
+
+impl From<Theme> for iced::Theme {
+    fn from(theme: Theme) -> Self {
+        match theme {
+            Theme::Light => iced::Theme::Light,
+            Theme::Dark => iced::Theme::Dark,
+        }
+    }
+}
+
+/// Language of the labels of the menus and the setting screen.
+#[derive(Default, Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Language {
+    #[default]
+    Thai,
+    English,
+}
+
+impl Language {
+    const ALL: [Language; 2] = [Language::Thai, Language::English];
+
+    /// The label in this language.
+    pub(crate) fn pick<'a>(self, thai: &'a str, english: &'a str) -> &'a str {
+        match self {
+            Language::Thai => thai,
+            Language::English => english,
+        }
+    }
+}
+
+impl std::fmt::Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Language::Thai => write!(f, "ไทย"),
+            Language::English => write!(f, "English"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct State {
+    pub(crate) draft: Setting,
+    pub(crate) scan_interval: String,
+    pub(crate) status: String,
+    pub(crate) servers: Vec<String>,
+}
+
+impl State {
+    pub(crate) fn new(setting: &Setting) -> Self {
+        State {
+            draft: setting.clone(),
+            scan_interval: setting.scan_interval_ms.to_string(),
+            status: String::new(),
+            servers: Vec::new(),
+        }
     }
 }
 
 #[derive(Clone, Debug)]
 pub enum Message {
     OnIPChange(String),
+    OnShopNameChange(String),
+    OnShopAddressChange(String),
+    OnPrinterChange(String),
+    OnLabelPrinterChange(String),
+    OnThemeSelect(Theme),
+    OnLanguageSelect(Language),
+    OnScanIntervalChange(String),
+    OnPromptPayChange(String),
     Connect,
+    Checked(Result<Health, String>),
+    Discover,
+    Discovered(Vec<String>),
+    SelectServer(String),
     Back,
 }
 
-pub(crate) fn update(state: &mut crate::State, message: crate::Message) {
-    if let crate::Message::Setting(message) = message {
-        match message {
-            Message::OnIPChange(ip) => state.screen = crate::Screen::Setting(State { url: ip }),
-            Message::Connect => {
-                if let crate::Screen::Setting(setting) = &state.screen {
-                    state.setting.url = setting.url.clone();
-                    let _ = save(setting);
-                } else {
-                    panic!("panic at screens::setting::update, Message::Submit");
-                }
+pub(crate) fn update(state: &mut crate::State, message: crate::Message) -> Task<Message> {
+    let crate::Message::Setting(message) = message else {
+        return Task::none();
+    };
+    if let Message::Back = message {
+        match state.session {
+            Some(_) => state.screen = crate::Screen::Home,
+            None => state.screen = crate::Screen::Login(Default::default()),
+        }
+        return Task::none();
+    }
+    let crate::Screen::Setting(setting) = &mut state.screen else {
+        panic!("Screen error in setting");
+    };
+
+    match message {
+        Message::OnIPChange(ip) => setting.draft.url = ip,
+        Message::OnShopNameChange(name) => setting.draft.shop_name = name,
+        Message::OnShopAddressChange(address) => setting.draft.shop_address = address,
+        Message::OnPrinterChange(printer) => setting.draft.printer = printer,
+        Message::OnLabelPrinterChange(printer) => setting.draft.label_printer = printer,
+        Message::OnThemeSelect(theme) => setting.draft.theme = theme,
+        Message::OnLanguageSelect(language) => setting.draft.language = language,
+        Message::OnScanIntervalChange(interval) => setting.scan_interval = interval,
+        Message::OnPromptPayChange(id) => setting.draft.promptpay_id = id,
+        Message::Connect => match validate(setting) {
+            Err(e) => setting.status = e,
+            Ok(draft) => {
+                setting.draft = draft.clone();
+                setting.status = "กำลังทดสอบการเชื่อมต่อ...".to_string();
+                state.setting = draft.clone();
+                return Task::perform(save_and_check(draft), Message::Checked);
             }
-            Message::Back => state.screen = crate::Screen::Home,
+        },
+        Message::Checked(Ok(health)) => {
+            let database = match health.database {
+                true => "ปกติ",
+                false => "ขัดข้อง",
+            };
+            let schema = health
+                .schema_version
+                .map(|version| version.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            setting.status = format!(
+                "เชื่อมต่อสำเร็จ: เซิร์ฟเวอร์ {}, ฐานข้อมูล{database}, schema {schema}",
+                health.version
+            );
         }
+        Message::Checked(Err(e)) => setting.status = format!("เชื่อมต่อไม่สำเร็จ: {e}"),
+        Message::Discover => {
+            setting.status = "กำลังค้นหาเซิร์ฟเวอร์...".to_string();
+            return Task::perform(discover(), Message::Discovered);
+        }
+        Message::Discovered(servers) => {
+            setting.status = match servers.is_empty() {
+                true => "ไม่พบเซิร์ฟเวอร์ในเครือข่าย".to_string(),
+                false => String::new(),
+            };
+            setting.servers = servers;
+        }
+        Message::SelectServer(url) => setting.draft.url = url,
+        Message::Back => {}
+    }
+    Task::none()
+}
+
+/// Checks the edited settings and returns them ready to be saved.
+fn validate(state: &State) -> Result<Setting, String> {
+    let scan_interval_ms = state
+        .scan_interval
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| format!("ระยะเวลาสแกนไม่ถูกต้อง: {}", state.scan_interval))?;
+    let promptpay_id = state.draft.promptpay_id.trim().to_string();
+    if !promptpay_id.is_empty() {
+        shared::promptpay_payload(&promptpay_id, None)
+            .map_err(|_| format!("พร้อมเพย์ไม่ถูกต้อง: {promptpay_id}"))?;
     }
+    Ok(Setting {
+        url: normalize(&state.draft.url)?,
+        scan_interval_ms,
+        promptpay_id,
+        ..state.draft.clone()
+    })
 }
 
-pub(crate) fn view<'a>(state: &State) -> Element<'a, crate::Message> {
+/// Checks the URL before it is saved. A bare `host:port` is taken to be plain http.
+fn normalize(url: &str) -> Result<String, String> {
+    let url = url.trim().trim_end_matches('/');
+    let url = match url.contains("://") {
+        true => url.to_string(),
+        false => format!("http://{url}"),
+    };
+    match reqwest::Url::parse(&url) {
+        Ok(parsed)
+            if matches!(parsed.scheme(), "http" | "https") && parsed.host_str().is_some() =>
+        {
+            Ok(url)
+        }
+        _ => Err(format!("ที่อยู่เซิร์ฟเวอร์ไม่ถูกต้อง: {url}")),
+    }
+}
+
+async fn save_and_check(setting: Setting) -> Result<Health, String> {
+    save(&setting).map_err(|e| format!("บันทึกการตั้งค่าไม่สำเร็จ: {e}"))?;
+    let api = Api {
+        url: setting.url,
+        token: None,
+    };
+    api::send(api.get("/health").timeout(HEALTH_TIMEOUT)).await
+}
+
+async fn discover() -> Vec<String> {
+    match find_servers().await {
+        Ok(servers) => servers,
+        Err(e) => {
+            eprintln!("discovery error: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// Broadcasts a discovery request on the LAN and collects the servers that answer with their
+/// HTTP port.
+async fn find_servers() -> std::io::Result<Vec<String>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.set_broadcast(true)?;
+    socket
+        .send_to(
+            shared::DISCOVERY_REQUEST,
+            ("255.255.255.255", shared::DISCOVERY_PORT),
+        )
+        .await?;
+
+    let mut servers = Vec::new();
+    let mut buffer = [0u8; 16];
+    let deadline = tokio::time::Instant::now() + DISCOVERY_TIMEOUT;
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await
+    {
+        let (length, address) = received?;
+        let port = std::str::from_utf8(&buffer[..length])
+            .ok()
+            .and_then(|port| port.parse::<u16>().ok());
+        if let Some(port) = port {
+            let url = format!("http://{}:{port}", address.ip());
+            if !servers.contains(&url) {
+                servers.push(url);
+            }
+        }
+    }
+    Ok(servers)
+}
+
+fn labeled<'a>(
+    label: &'a str,
+    input: impl Into<Element<'a, crate::Message>>,
+) -> Element<'a, crate::Message> {
+    row![
+        text(label)
+            .shaping(text::Shaping::Advanced)
+            .width(Length::FillPortion(2)),
+        container(input).width(Length::FillPortion(5)),
+    ]
+    .spacing(Pixels(10.0))
+    .into()
+}
+
+/// `vat_rate` is the one of the server logged in to, shown but not edited here as the server taxes
+/// the receipts.
+pub(crate) fn view(state: &State, vat_rate: Option<Decimal>) -> Element<crate::Message> {
+    let language = state.draft.language;
+    let servers = state.servers.iter().map(|url| {
+        button(text(url.clone()))
+            .on_press(crate::Message::Setting(Message::SelectServer(url.clone())))
+            .into()
+    });
+
     row![
         horizontal_space().width(Length::Fill),
         container(
-            row![
-                text_input("", &state.url)
-                    .on_input(|input| { crate::Message::Setting(Message::OnIPChange(input)) }),
-                button("บันทึก").on_press(crate::Message::Setting(Message::Connect))
+            column![
+                labeled(
+                    language.pick("ที่อยู่เซิร์ฟเวอร์: ", "Server: "),
+                    row![
+                        text_input("http://192.168.1.2:3000", &state.draft.url)
+                            .on_input(|input| crate::Message::Setting(Message::OnIPChange(input)))
+                            .on_submit(crate::Message::Setting(Message::Connect)),
+                        button(
+                            text(language.pick("ค้นหา", "Search")).shaping(text::Shaping::Advanced)
+                        )
+                        .on_press(crate::Message::Setting(Message::Discover)),
+                    ]
+                    .spacing(Pixels(10.0)),
+                ),
+                column(servers).spacing(Pixels(5.0)),
+                labeled(
+                    language.pick("ชื่อร้าน: ", "Shop name: "),
+                    text_input("", &state.draft.shop_name).on_input(|input| {
+                        crate::Message::Setting(Message::OnShopNameChange(input))
+                    }),
+                ),
+                labeled(
+                    language.pick("ที่อยู่ร้าน: ", "Shop address: "),
+                    text_input("", &state.draft.shop_address).on_input(|input| {
+                        crate::Message::Setting(Message::OnShopAddressChange(input))
+                    }),
+                ),
+                labeled(
+                    language.pick("เครื่องพิมพ์: ", "Printer: "),
+                    text_input("/dev/usb/lp0", &state.draft.printer).on_input(|input| {
+                        crate::Message::Setting(Message::OnPrinterChange(input))
+                    }),
+                ),
+                labeled(
+                    language.pick("เครื่องพิมพ์ฉลาก: ", "Label printer: "),
+                    text_input("/dev/usb/lp1", &state.draft.label_printer).on_input(|input| {
+                        crate::Message::Setting(Message::OnLabelPrinterChange(input))
+                    }),
+                ),
+                labeled(
+                    language.pick("ธีม: ", "Theme: "),
+                    pick_list(Theme::ALL, Some(state.draft.theme), |theme| {
+                        crate::Message::Setting(Message::OnThemeSelect(theme))
+                    })
+                    .text_shaping(text::Shaping::Advanced),
+                ),
+                labeled(
+                    language.pick("ภาษา: ", "Language: "),
+                    pick_list(Language::ALL, Some(language), |language| {
+                        crate::Message::Setting(Message::OnLanguageSelect(language))
+                    })
+                    .text_shaping(text::Shaping::Advanced),
+                ),
+                labeled(
+                    language.pick("ระยะเวลาสแกน (ms): ", "Scan interval (ms): "),
+                    text_input("50", &state.scan_interval).on_input(|input| {
+                        crate::Message::Setting(Message::OnScanIntervalChange(input))
+                    }),
+                ),
+                labeled(
+                    language.pick("ภาษีมูลค่าเพิ่ม (%): ", "VAT (%): "),
+                    text(match vat_rate {
+                        Some(rate) => format!(
+                            "{rate} {}",
+                            language.pick("(ตามเซิร์ฟเวอร์)", "(set on the server)")
+                        ),
+                        None => "-".to_string(),
+                    })
+                    .shaping(text::Shaping::Advanced),
+                ),
+                labeled(
+                    language.pick("พร้อมเพย์: ", "PromptPay: "),
+                    text_input("0812345678", &state.draft.promptpay_id).on_input(|input| {
+                        crate::Message::Setting(Message::OnPromptPayChange(input))
+                    }),
+                ),
+                button(text(language.pick("บันทึก", "Save")).shaping(text::Shaping::Advanced))
+                    .on_press(crate::Message::Setting(Message::Connect)),
+                text(state.status.clone()).shaping(text::Shaping::Advanced),
             ]
             .spacing(Pixels(10.0)),
         )
@@ -69,25 +433,58 @@ pub(crate) fn subscription(_state: &State) -> Subscription<crate::Message> {
     })
 }
 
-pub(crate) fn read() -> State {
-    let mut default_setting = State {
-        url: "".to_string(),
+/// `setting.json` next to the other files of the client, see [`cache::path`].
+fn path() -> Option<PathBuf> {
+    cache::path("setting.json")
+}
+
+/// Brings settings written by an older client up to [`VERSION`]. Settings that did not exist
+/// yet start at their defaults.
+fn migrate(mut value: serde_json::Value) -> Result<Setting, String> {
+    let version = value
+        .get("version")
+        .and_then(serde_json::Value::as_u64)
+        .unwrap_or(0);
+    if version > VERSION {
+        eprintln!("setting.json is version {version}, newer than this client");
+    }
+    // Version 0 was `{ "url": ... }` in the asset directory. The tax rate of version 3 is left
+    // out, the VAT rate comes from the server. Version 4 had no language and scan interval, they
+    // start at their defaults
+    if let Some(object) = value.as_object_mut() {
+        object.insert("version".to_string(), VERSION.into());
+    }
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+pub(crate) fn read() -> Setting {
+    let saved = path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .or_else(|| fs::read_to_string(LEGACY_PATH).ok());
+    let Some(data) = saved else {
+        return Setting::default();
     };
 
-    if let Ok(data) = fs::read_to_string(PATH) {
-        if let Ok(setting) = serde_json::from_str(&data) {
-            default_setting = setting
+    match serde_json::from_str(&data)
+        .map_err(|e| e.to_string())
+        .and_then(migrate)
+    {
+        Ok(setting) => setting,
+        Err(e) => {
+            eprintln!("setting error: {e}");
+            Setting::default()
         }
     }
-
-    default_setting
 }
 
-fn save(setting: &State) -> std::io::Result<()> {
-    let json_data = serde_json::to_string_pretty(&setting).unwrap();
-    let mut file = fs::File::create(PATH)?;
-    file.write_all(json_data.as_bytes())?;
-    Ok(())
+fn save(setting: &Setting) -> std::io::Result<()> {
+    let path = path()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no config directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let json_data = serde_json::to_string_pretty(&setting)?;
+    fs::write(path, json_data)
 }
 
 #[cfg(test)]
@@ -96,11 +493,23 @@ mod test {
 
     fn init_state() -> crate::State {
         crate::State {
-            screen: crate::Screen::Setting(State::default()),
+            screen: crate::Screen::Setting(State::new(&Setting::default())),
+            session: Some(shared::Session::default()),
             ..Default::default()
         }
     }
 
+    fn test<F>(state: &crate::State, f: F)
+    where
+        F: FnOnce(&State),
+    {
+        if let crate::Screen::Setting(state) = &state.screen {
+            f(state);
+        } else {
+            panic!("Screen error in setting");
+        }
+    }
+
     #[test]
     fn back() {
         let mut state = init_state();
@@ -108,23 +517,171 @@ mod test {
         assert_eq!(state.screen, crate::Screen::Home);
     }
 
+    #[test]
+    fn back_to_login() {
+        let mut state = crate::State {
+            screen: crate::Screen::Setting(State::new(&Setting::default())),
+            ..Default::default()
+        };
+        let _ = state.update(crate::Message::Setting(Message::Back));
+        assert_eq!(state.screen, crate::Screen::Login(Default::default()));
+    }
+
     #[test]
     fn change_ip() {
         let ip = "192.168.1.45:3000".to_string();
-        let original_ip = read();
 
         let mut state = init_state();
 
         let _ = state.update(crate::Message::Setting(Message::OnIPChange(ip.clone())));
-        if let crate::Screen::Setting(state) = &state.screen {
-            assert_eq!(state.url, ip);
-        } else {
-            panic!("test: change_ip");
-        }
+        test(&state, |state| {
+            assert_eq!(state.draft.url, ip);
+        });
 
         let _ = state.update(crate::Message::Setting(Message::Connect));
-        assert_eq!(state.setting.url, ip);
+        assert_eq!(state.setting.url, format!("http://{ip}"));
+    }
 
-        let _ = save(&original_ip);
+    #[test]
+    fn invalid_url() {
+        let mut state = init_state();
+
+        let _ = state.update(crate::Message::Setting(Message::OnIPChange(
+            "ftp://192.168.1.45".to_string(),
+        )));
+        let _ = state.update(crate::Message::Setting(Message::Connect));
+        assert_eq!(state.setting, Setting::default());
+        test(&state, |state| {
+            assert!(!state.status.is_empty());
+        });
+
+        assert_eq!(
+            normalize(" https://shop.local:3000/ "),
+            Ok("https://shop.local:3000".to_string())
+        );
+        assert!(normalize("").is_err());
+    }
+
+    #[test]
+    fn edit_settings() {
+        let mut state = init_state();
+        let messages = [
+            Message::OnIPChange("192.168.1.2:3000".to_string()),
+            Message::OnShopNameChange("ซันมินิมาร์ท".to_string()),
+            Message::OnShopAddressChange("1 ถนนสุขุมวิท".to_string()),
+            Message::OnPrinterChange("192.168.1.50:9100".to_string()),
+            Message::OnLabelPrinterChange("/dev/usb/lp1".to_string()),
+            Message::OnThemeSelect(Theme::Dark),
+            Message::OnLanguageSelect(Language::English),
+            Message::OnScanIntervalChange("30".to_string()),
+            Message::OnPromptPayChange(" 081-234-5678 ".to_string()),
+            Message::Connect,
+        ];
+        for message in messages {
+            let _ = state.update(crate::Message::Setting(message));
+        }
+        assert_eq!(
+            state.setting,
+            Setting {
+                version: VERSION,
+                url: "http://192.168.1.2:3000".to_string(),
+                shop_name: "ซันมินิมาร์ท".to_string(),
+                shop_address: "1 ถนนสุขุมวิท".to_string(),
+                printer: "192.168.1.50:9100".to_string(),
+                label_printer: "/dev/usb/lp1".to_string(),
+                theme: Theme::Dark,
+                language: Language::English,
+                scan_interval_ms: 30,
+                promptpay_id: "081-234-5678".to_string(),
+            }
+        );
+        assert_eq!(state.theme(), iced::Theme::Dark);
+
+        // An invalid scan interval is not saved
+        for message in [
+            Message::OnScanIntervalChange("fast".to_string()),
+            Message::Connect,
+        ] {
+            let _ = state.update(crate::Message::Setting(message));
+        }
+        assert_eq!(state.setting.scan_interval_ms, 30);
+        let _ = state.update(crate::Message::Setting(Message::OnScanIntervalChange(
+            "30".to_string(),
+        )));
+
+        // An invalid PromptPay id is not saved
+        for message in [
+            Message::OnPromptPayChange("12345".to_string()),
+            Message::Connect,
+        ] {
+            let _ = state.update(crate::Message::Setting(message));
+        }
+        assert_eq!(state.setting.promptpay_id, "081-234-5678");
+    }
+
+    #[test]
+    fn migrate_legacy() {
+        let setting = migrate(serde_json::json!({ "url": "http://192.168.1.45:3000" })).unwrap();
+        assert_eq!(
+            setting,
+            Setting {
+                url: "http://192.168.1.45:3000".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn migrate_older_versions() {
+        // Version 3 kept a tax rate, version 4 had no language nor scan interval
+        let setting = migrate(serde_json::json!({
+            "version": 3,
+            "url": "http://192.168.1.45:3000",
+            "language": "english",
+            "tax_rate": "7",
+        }))
+        .unwrap();
+        assert_eq!(setting.language, Language::English);
+        let setting = migrate(serde_json::json!({ "version": 4, "theme": "dark" })).unwrap();
+        assert_eq!(setting.language, Language::Thai);
+        assert_eq!(setting.scan_interval_ms, 50);
+        assert_eq!(setting.version, VERSION);
+    }
+
+    #[test]
+    fn checked() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::Setting(Message::Checked(Ok(Health {
+            version: "0.1.0".to_string(),
+            database: true,
+            schema_version: Some(20261019130000),
+        }))));
+        test(&state, |state| {
+            assert!(state.status.contains("0.1.0"));
+            assert!(state.status.contains("20261019130000"));
+        });
+
+        let _ = state.update(crate::Message::Setting(Message::Checked(Err(
+            "timeout".to_string()
+        ))));
+        test(&state, |state| {
+            assert!(state.status.contains("timeout"));
+        });
+    }
+
+    #[test]
+    fn discovered() {
+        let mut state = init_state();
+        let servers = vec!["http://192.168.1.2:3000".to_string()];
+        let _ = state.update(crate::Message::Setting(Message::Discovered(
+            servers.clone(),
+        )));
+        let _ = state.update(crate::Message::Setting(Message::SelectServer(
+            servers[0].clone(),
+        )));
+        test(&state, |state| {
+            assert_eq!(state.servers, servers);
+            assert_eq!(state.draft.url, servers[0]);
+        });
     }
 }
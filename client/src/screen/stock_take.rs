@@ -0,0 +1,520 @@
+use iced::alignment::{Horizontal, Vertical};
+use iced::keyboard::key;
+use iced::widget::text::LineHeight;
+use iced::widget::{button, column, horizontal_space, row, text, text_input, vertical_space};
+use iced::{Element, Length, Pixels, Subscription, Task, keyboard};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::api::{self, Api};
+use crate::{cache, custom};
+use shared::number;
+use shared::{Item, StockCount, StockTake, StockTakeLine, StockTakeReport};
+
+const DRAFT: &str = "stock_take.json";
+
+/// Counts of an uncommitted stock-take, kept on disk so counting can go on without a
+/// connection and survive a restart of the client.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub(crate) struct Draft {
+    pub stock_take: StockTake,
+    pub counts: Vec<StockCount>,
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub(crate) struct State {
+    pub draft: Option<Draft>,
+    pub items: Vec<Item>,
+    pub barcode: String,
+    pub quantity: String,
+    pub status: String,
+    pub report: Option<StockTakeReport>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Back,
+    Started(Result<Draft, String>),
+    ItemsFetched(Vec<Item>),
+    DraftSaved(Result<(), String>),
+
+    OnBarcodeChange(String),
+    OnBarcodeSubmit,
+    OnQuantityChange(String),
+    OnQuantitySubmit,
+    Remove(String),
+
+    Upload,
+    Uploaded(Result<(), String>),
+    Commit,
+    Committed(Result<StockTakeReport, String>),
+}
+
+pub fn update(state: &mut crate::State, message: crate::Message) -> Task<Message> {
+    let mut tasks = Vec::new();
+    let api = state.api();
+
+    if let crate::Message::StockTake(message) = message {
+        match message {
+            Message::Back => {
+                state.screen = crate::Screen::Home;
+            }
+            Message::Started(draft) => {
+                modify(state, |state| match draft {
+                    Ok(draft) => {
+                        tasks.push(Task::perform(
+                            save_draft(Some(draft.clone())),
+                            Message::DraftSaved,
+                        ));
+                        state.draft = Some(draft);
+                        tasks.push(text_input::focus(text_input::Id::new("stock_take_barcode")));
+                    }
+                    Err(e) => state.status = e,
+                });
+            }
+            Message::ItemsFetched(items) => {
+                modify(state, |state| {
+                    state.items = items;
+                });
+            }
+            Message::DraftSaved(result) => {
+                if let Err(e) = result {
+                    modify(state, |state| {
+                        state.status = e;
+                    });
+                }
+            }
+            Message::OnBarcodeChange(barcode) => {
+                modify(state, |state| {
+                    state.barcode = barcode;
+                });
+            }
+            Message::OnBarcodeSubmit => {
+                modify(state, |state| {
+                    if state.barcode.is_empty() {
+                        return;
+                    }
+                    if state.items.iter().any(|item| item.barcode == state.barcode) {
+                        state.status = String::new();
+                        tasks.push(text_input::focus(text_input::Id::new(
+                            "stock_take_quantity",
+                        )));
+                    } else {
+                        state.status = format!("ไม่พบสินค้า {}", state.barcode);
+                    }
+                });
+            }
+            Message::OnQuantityChange(quantity) => {
+                modify(state, |state| {
+                    state.quantity = quantity;
+                });
+            }
+            Message::OnQuantitySubmit => {
+                modify(state, |state| {
+                    let Some(draft) = &mut state.draft else {
+                        return;
+                    };
+                    // Goods sold by weight are counted on the scale, to the gram
+                    let unit = state
+                        .items
+                        .iter()
+                        .find(|item| item.barcode == state.barcode)
+                        .map(|item| item.unit)
+                        .unwrap_or_default();
+                    match number::parse_decimal(&state.quantity) {
+                        Some(quantity) if quantity >= Decimal::ZERO && unit.allows(quantity) => {
+                            let quantity = quantity.normalize();
+                            // The same item may sit on several shelves, so counts add up
+                            match draft
+                                .counts
+                                .iter_mut()
+                                .find(|count| count.barcode == state.barcode)
+                            {
+                                Some(count) => count.counted += quantity,
+                                None => draft.counts.push(StockCount {
+                                    barcode: state.barcode.clone(),
+                                    counted: quantity,
+                                }),
+                            }
+                            tasks.push(Task::perform(
+                                save_draft(Some(draft.clone())),
+                                Message::DraftSaved,
+                            ));
+                            state.barcode = String::new();
+                            state.quantity = String::new();
+                            state.status = String::new();
+                            tasks
+                                .push(text_input::focus(text_input::Id::new("stock_take_barcode")));
+                        }
+                        _ => state.status = format!("จำนวนไม่ถูกต้อง {}", state.quantity),
+                    }
+                });
+            }
+            Message::Remove(barcode) => {
+                modify(state, |state| {
+                    if let Some(draft) = &mut state.draft {
+                        draft.counts.retain(|count| count.barcode != barcode);
+                        tasks.push(Task::perform(
+                            save_draft(Some(draft.clone())),
+                            Message::DraftSaved,
+                        ));
+                    }
+                });
+            }
+            Message::Upload => {
+                modify(state, |state| {
+                    if let Some(draft) = &state.draft {
+                        tasks.push(Task::perform(
+                            upload_counts(api, draft.clone()),
+                            Message::Uploaded,
+                        ));
+                    }
+                });
+            }
+            Message::Uploaded(result) => {
+                modify(state, |state| match result {
+                    Ok(()) => state.status = "อัปโหลดแล้ว".to_string(),
+                    Err(e) => state.status = e,
+                });
+            }
+            Message::Commit => {
+                modify(state, |state| {
+                    if let Some(draft) = &state.draft {
+                        tasks.push(Task::perform(
+                            commit(api, draft.clone()),
+                            Message::Committed,
+                        ));
+                    }
+                });
+            }
+            Message::Committed(result) => {
+                modify(state, |state| match result {
+                    Ok(report) => {
+                        state.draft = None;
+                        state.report = Some(report);
+                        state.status = String::new();
+                        tasks.push(Task::perform(save_draft(None), Message::DraftSaved));
+                    }
+                    Err(e) => state.status = e,
+                });
+            }
+        }
+    } else {
+        panic!("Message error in stock_take");
+    }
+
+    Task::batch(tasks)
+}
+
+fn modify<F>(state: &mut crate::State, f: F)
+where
+    F: FnOnce(&mut State),
+{
+    if let crate::Screen::StockTake(ref mut state) = state.screen {
+        f(state);
+    } else {
+        panic!("Screen error in stock_take");
+    }
+}
+
+/// Variance lines of the counts so far, compared against the quantities last fetched.
+fn lines(state: &State) -> Vec<StockTakeLine> {
+    if let Some(report) = &state.report {
+        return report.lines.clone();
+    }
+    let Some(draft) = &state.draft else {
+        return Vec::new();
+    };
+    draft
+        .counts
+        .iter()
+        .map(|count| {
+            let item = state
+                .items
+                .iter()
+                .find(|item| item.barcode == count.barcode);
+            StockTakeLine {
+                barcode: count.barcode.clone(),
+                name: item.map(|item| item.name.clone()).unwrap_or_default(),
+                system_quantity: item.map(|item| item.quantity).unwrap_or_default(),
+                counted_quantity: count.counted,
+                cost: item.map(|item| item.cost).unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+fn read_draft() -> Option<Draft> {
+    cache::read(DRAFT)
+}
+
+async fn save_draft(draft: Option<Draft>) -> Result<(), String> {
+    match draft {
+        Some(draft) => cache::write(DRAFT, &draft),
+        None => cache::remove(DRAFT),
+    }
+}
+
+/// Resumes the stock-take saved on disk, or starts a new one on the server.
+pub(super) async fn start(api: Api) -> Result<Draft, String> {
+    if let Some(draft) = read_draft() {
+        return Ok(draft);
+    }
+    let stock_take = api::send::<StockTake>(api.post("/stock-takes")).await?;
+    Ok(Draft {
+        stock_take,
+        counts: Vec::new(),
+    })
+}
+
+async fn upload_counts(api: Api, draft: Draft) -> Result<(), String> {
+    api::execute(
+        api.put(&format!("/stock-takes/{}/counts", draft.stock_take.id))
+            .json(&draft.counts),
+    )
+    .await
+}
+
+async fn commit(api: Api, draft: Draft) -> Result<StockTakeReport, String> {
+    upload_counts(api.clone(), draft.clone()).await?;
+    api::send(api.post(&format!("/stock-takes/{}/commit", draft.stock_take.id))).await
+}
+
+fn cell<'a>(value: String) -> Element<'a, crate::Message> {
+    text(value)
+        .line_height(LineHeight::Relative(2.0))
+        .shaping(text::Shaping::Advanced)
+        .width(Length::Fill)
+        .align_x(Horizontal::Center)
+        .into()
+}
+
+pub fn view(state: &State) -> Element<crate::Message> {
+    let lines = lines(state);
+    let total: Decimal = lines.iter().map(StockTakeLine::variance_value).sum();
+    let header = match (&state.report, &state.draft) {
+        (Some(report), _) => format!("ใบตรวจนับ #{} (ยืนยันแล้ว)", report.stock_take.id),
+        (None, Some(draft)) => format!("ใบตรวจนับ #{}", draft.stock_take.id),
+        (None, None) => String::new(),
+    };
+
+    column![
+        vertical_space(),
+        custom::title("ตรวจนับสต็อก"),
+        row![
+            horizontal_space(),
+            column![
+                text(header).shaping(text::Shaping::Advanced),
+                row![
+                    text("รหัสสินค้า: ")
+                        .line_height(LineHeight::Relative(2.0))
+                        .align_y(Vertical::Center),
+                    text_input("", &state.barcode)
+                        .id(text_input::Id::new("stock_take_barcode"))
+                        .on_input(|input| {
+                            crate::Message::StockTake(Message::OnBarcodeChange(input))
+                        })
+                        .on_submit(crate::Message::StockTake(Message::OnBarcodeSubmit)),
+                    text("จำนวน: ")
+                        .line_height(LineHeight::Relative(2.0))
+                        .align_y(Vertical::Center),
+                    text_input("", &state.quantity)
+                        .id(text_input::Id::new("stock_take_quantity"))
+                        .on_input(|input| {
+                            crate::Message::StockTake(Message::OnQuantityChange(input))
+                        })
+                        .on_submit(crate::Message::StockTake(Message::OnQuantitySubmit)),
+                ]
+                .spacing(Pixels(10.0)),
+                row![
+                    cell("รหัสสินค้า".to_string()),
+                    cell("ชื่อ".to_string()),
+                    cell("ในระบบ".to_string()),
+                    cell("นับได้".to_string()),
+                    cell("ส่วนต่าง".to_string()),
+                    cell("มูลค่า".to_string()),
+                    horizontal_space().width(Length::Fixed(40.0)),
+                ],
+                custom::list(lines, |_, line| {
+                    row![
+                        cell(line.barcode.clone()),
+                        cell(line.name.clone()),
+                        cell(line.system_quantity.to_string()),
+                        cell(line.counted_quantity.to_string()),
+                        cell(line.variance().to_string()),
+                        cell(line.variance_value().to_string()),
+                        button("x")
+                            .on_press(crate::Message::StockTake(Message::Remove(
+                                line.barcode.clone()
+                            )))
+                            .width(Length::Fixed(40.0)),
+                    ]
+                    .into()
+                })
+                .height(Length::Fill),
+                text(format!("มูลค่าส่วนต่างรวม: {total}")).shaping(text::Shaping::Advanced),
+                text(state.status.clone()).shaping(text::Shaping::Advanced),
+            ]
+            .width(Length::FillPortion(12))
+            .spacing(Pixels(10.0)),
+            horizontal_space(),
+            column![
+                custom::button("อัปโหลด", crate::Message::StockTake(Message::Upload)).padding(20),
+                custom::button("ยืนยันยอด", crate::Message::StockTake(Message::Commit)).padding(20),
+            ]
+            .width(Length::FillPortion(2))
+            .spacing(Pixels(20.0)),
+            horizontal_space(),
+        ]
+        .height(Length::FillPortion(12)),
+        vertical_space()
+    ]
+    .height(Length::Fill)
+    .width(Length::Fill)
+    .into()
+}
+
+pub(crate) fn subscription(_state: &State) -> Subscription<crate::Message> {
+    keyboard::on_key_press(|keyboard, _| match keyboard {
+        keyboard::Key::Named(key::Named::Escape) => Some(crate::Message::StockTake(Message::Back)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn init_state() -> crate::State {
+        let mut state = crate::State {
+            screen: crate::Screen::StockTake(Box::default()),
+            ..Default::default()
+        };
+        let _ = state.update(crate::Message::StockTake(Message::Started(Ok(Draft {
+            stock_take: StockTake {
+                id: 1,
+                ..Default::default()
+            },
+            counts: Vec::new(),
+        }))));
+        let _ = state.update(crate::Message::StockTake(Message::ItemsFetched(vec![
+            Item {
+                barcode: "0".to_string(),
+                name: "a".to_string(),
+                quantity: Decimal::from(10),
+                cost: Decimal::new(5, 0),
+                ..Default::default()
+            },
+        ])));
+        state
+    }
+
+    fn test<F>(state: &crate::State, f: F)
+    where
+        F: FnOnce(&State),
+    {
+        if let crate::Screen::StockTake(state) = &state.screen {
+            f(state);
+        } else {
+            panic!("Screen error in stock_take");
+        }
+    }
+
+    fn count(state: &mut crate::State, barcode: &str, quantity: &str) {
+        let _ = state.update(crate::Message::StockTake(Message::OnBarcodeChange(
+            barcode.to_string(),
+        )));
+        let _ = state.update(crate::Message::StockTake(Message::OnBarcodeSubmit));
+        let _ = state.update(crate::Message::StockTake(Message::OnQuantityChange(
+            quantity.to_string(),
+        )));
+        let _ = state.update(crate::Message::StockTake(Message::OnQuantitySubmit));
+    }
+
+    #[test]
+    fn back() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::StockTake(Message::Back));
+        assert_eq!(state.screen, crate::Screen::Home);
+    }
+
+    #[test]
+    fn count_items() {
+        let mut state = init_state();
+
+        count(&mut state, "0", "4");
+        count(&mut state, "0", "3");
+        test(&state, |state| {
+            assert_eq!(
+                state.draft.as_ref().unwrap().counts,
+                vec![StockCount {
+                    barcode: "0".to_string(),
+                    counted: Decimal::from(7),
+                }]
+            );
+            assert!(state.barcode.is_empty());
+            assert!(state.quantity.is_empty());
+
+            let lines = lines(state);
+            assert_eq!(lines[0].variance(), Decimal::from(-3));
+            assert_eq!(lines[0].variance_value(), Decimal::new(-15, 0));
+        });
+
+        // Invalid quantity is not counted, nor half a piece
+        for invalid in ["-1", "0.5"] {
+            count(&mut state, "0", invalid);
+            test(&state, |state| {
+                assert_eq!(
+                    state.draft.as_ref().unwrap().counts[0].counted,
+                    Decimal::from(7)
+                );
+                assert!(!state.status.is_empty());
+            });
+        }
+
+        let _ = state.update(crate::Message::StockTake(Message::Remove("0".to_string())));
+        test(&state, |state| {
+            assert!(state.draft.as_ref().unwrap().counts.is_empty());
+        });
+    }
+
+    #[test]
+    fn unknown_barcode() {
+        let mut state = init_state();
+        let _ = state.update(crate::Message::StockTake(Message::OnBarcodeChange(
+            "404".to_string(),
+        )));
+        let _ = state.update(crate::Message::StockTake(Message::OnBarcodeSubmit));
+        test(&state, |state| {
+            assert!(state.status.contains("404"));
+        });
+    }
+
+    #[test]
+    fn committed() {
+        let mut state = init_state();
+        count(&mut state, "0", "10");
+
+        let report = StockTakeReport {
+            stock_take: StockTake {
+                id: 1,
+                ..Default::default()
+            },
+            lines: vec![StockTakeLine {
+                barcode: "0".to_string(),
+                name: "a".to_string(),
+                system_quantity: Decimal::from(10),
+                counted_quantity: Decimal::from(10),
+                cost: Decimal::new(5, 0),
+            }],
+        };
+        let _ = state.update(crate::Message::StockTake(Message::Committed(Ok(
+            report.clone()
+        ))));
+        test(&state, |state| {
+            assert_eq!(state.draft, None);
+            assert_eq!(state.report, Some(report.clone()));
+            assert_eq!(lines(state), report.lines);
+        });
+    }
+}
@@ -1,5 +1,4 @@
 -- Add migration script here
-USE sunminimart;
 
 CREATE TABLE IF NOT EXISTS items
 (
@@ -0,0 +1,13 @@
+-- Add migration script here
+
+CREATE TABLE IF NOT EXISTS categories
+(
+    id        INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    parent_id INT UNSIGNED,
+    name      VARCHAR(64) NOT NULL,
+    FOREIGN KEY (parent_id) REFERENCES categories (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+ALTER TABLE items
+    ADD COLUMN category_id INT UNSIGNED,
+    ADD FOREIGN KEY (category_id) REFERENCES categories (id) ON DELETE SET NULL ON UPDATE CASCADE;
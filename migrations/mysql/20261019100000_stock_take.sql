@@ -0,0 +1,31 @@
+-- Add migration script here
+
+CREATE TABLE IF NOT EXISTS stock_movements
+(
+    id           INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    barcode      VARCHAR(64) NOT NULL,
+    quantity     INT         NOT NULL,
+    reason       VARCHAR(16) NOT NULL,
+    reference_id INT UNSIGNED,
+    created_at   DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (barcode) REFERENCES items (barcode) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS stock_takes
+(
+    id           INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    started_at   DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    committed_at DATETIME
+);
+
+CREATE TABLE IF NOT EXISTS stock_take_lines
+(
+    stock_take_id    INT UNSIGNED NOT NULL,
+    barcode          VARCHAR(64)  NOT NULL,
+    counted_quantity INT          NOT NULL,
+    system_quantity  INT,
+    cost             DECIMAL(6, 2) UNSIGNED,
+    PRIMARY KEY (stock_take_id, barcode),
+    FOREIGN KEY (stock_take_id) REFERENCES stock_takes (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY (barcode) REFERENCES items (barcode) ON DELETE CASCADE ON UPDATE CASCADE
+);
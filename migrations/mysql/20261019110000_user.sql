@@ -0,0 +1,18 @@
+-- Add migration script here
+
+CREATE TABLE IF NOT EXISTS users
+(
+    id            INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    name          VARCHAR(64)  NOT NULL UNIQUE,
+    password_hash VARCHAR(255) NOT NULL,
+    role          VARCHAR(16)  NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sessions
+(
+    token      CHAR(64) PRIMARY KEY,
+    user_id    INT UNSIGNED NOT NULL,
+    created_at DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at DATETIME     NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
@@ -0,0 +1,16 @@
+-- Add migration script here
+
+CREATE TABLE IF NOT EXISTS audit_log
+(
+    id           INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    entity       VARCHAR(16) NOT NULL,
+    barcode      VARCHAR(64) NOT NULL,
+    action       VARCHAR(16) NOT NULL,
+    before_value JSON,
+    after_value  JSON,
+    user_id      INT UNSIGNED,
+    source       VARCHAR(16) NOT NULL,
+    created_at   DATETIME    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX (barcode),
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
@@ -0,0 +1,12 @@
+-- Add migration script here
+
+-- receipt_id was the primary key, which allowed only one line per receipt
+ALTER TABLE receipt_items
+    ADD INDEX (receipt_id),
+    DROP PRIMARY KEY,
+    ADD COLUMN id INT UNSIGNED AUTO_INCREMENT PRIMARY KEY FIRST;
+
+ALTER TABLE receipts
+    ADD COLUMN idempotency_key CHAR(36) UNIQUE,
+    ADD COLUMN user_id         INT UNSIGNED,
+    ADD FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL ON UPDATE CASCADE;
@@ -0,0 +1,17 @@
+-- Add migration script here
+
+-- When an item last changed, so a two-way sync can tell changes made here since the last sync
+ALTER TABLE items
+    ADD COLUMN updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP;
+
+-- The rows of the old program as they were after the last two-way sync, to tell what changed
+-- there since
+CREATE TABLE IF NOT EXISTS legacy_items
+(
+    barcode   VARCHAR(64) PRIMARY KEY,
+    name      VARCHAR(64)   NOT NULL,
+    cost      DECIMAL(6, 2) NOT NULL,
+    price     DECIMAL(6, 2) NOT NULL,
+    quantity  INT           NOT NULL,
+    synced_at DATETIME      NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
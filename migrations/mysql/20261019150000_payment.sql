@@ -0,0 +1,19 @@
+-- Add migration script here
+
+-- How each receipt was paid, one row per method of a split payment. Amounts are net of change.
+CREATE TABLE IF NOT EXISTS receipt_payments
+(
+    id         INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    receipt_id INT UNSIGNED            NOT NULL,
+    method     VARCHAR(16)             NOT NULL,
+    amount     DECIMAL(10, 2) UNSIGNED NOT NULL,
+    reference  VARCHAR(64),
+    INDEX (receipt_id),
+    FOREIGN KEY (receipt_id) REFERENCES receipts (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- Every earlier sale was paid in cash
+INSERT INTO receipt_payments (receipt_id, method, amount)
+SELECT receipt_id, 'cash', SUM(price * quantity)
+FROM receipt_items
+GROUP BY receipt_id;
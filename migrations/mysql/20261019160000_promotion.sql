@@ -0,0 +1,34 @@
+-- Add migration script here
+
+-- `rule` is a shared::PromotionRule, tagged by its type
+CREATE TABLE IF NOT EXISTS promotions
+(
+    id        INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    name      VARCHAR(100) NOT NULL,
+    rule      JSON         NOT NULL,
+    starts_at DATETIME,
+    ends_at   DATETIME
+);
+
+CREATE TABLE IF NOT EXISTS promotion_items
+(
+    promotion_id INT UNSIGNED NOT NULL,
+    barcode      VARCHAR(64)  NOT NULL,
+    PRIMARY KEY (promotion_id, barcode),
+    FOREIGN KEY (promotion_id) REFERENCES promotions (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY (barcode) REFERENCES items (barcode) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- The name is kept so old receipts still read right after the promotion is deleted
+CREATE TABLE IF NOT EXISTS receipt_discounts
+(
+    id           INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    receipt_id   INT UNSIGNED            NOT NULL,
+    promotion_id INT UNSIGNED,
+    name         VARCHAR(100)            NOT NULL,
+    barcode      VARCHAR(64)             NOT NULL,
+    amount       DECIMAL(10, 2) UNSIGNED NOT NULL,
+    INDEX (receipt_id),
+    FOREIGN KEY (receipt_id) REFERENCES receipts (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY (promotion_id) REFERENCES promotions (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
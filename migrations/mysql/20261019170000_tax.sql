@@ -0,0 +1,28 @@
+-- Add migration script here
+
+-- A shared::TaxClass. Prices include the VAT.
+ALTER TABLE items
+    ADD COLUMN tax_class VARCHAR(16) NOT NULL DEFAULT 'standard';
+
+-- The VAT included in each line after its discounts. Sales made before VAT was worked out
+-- have none.
+ALTER TABLE receipt_items
+    ADD COLUMN tax_class VARCHAR(16)             NOT NULL DEFAULT 'standard',
+    ADD COLUMN tax       DECIMAL(10, 2) UNSIGNED NOT NULL DEFAULT 0;
+
+-- At most one per receipt. Full invoices, the ones with a buyer, and abbreviated ones are
+-- numbered separately by `sequence`.
+CREATE TABLE IF NOT EXISTS tax_invoices
+(
+    id            INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    receipt_id    INT UNSIGNED NOT NULL UNIQUE,
+    kind          VARCHAR(16)  NOT NULL,
+    sequence      INT UNSIGNED NOT NULL,
+    buyer_name    VARCHAR(200),
+    buyer_tax_id  CHAR(13),
+    buyer_address VARCHAR(500),
+    buyer_branch  VARCHAR(50),
+    issued_at     DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (kind, sequence),
+    FOREIGN KEY (receipt_id) REFERENCES receipts (id) ON UPDATE CASCADE
+);
@@ -0,0 +1,36 @@
+-- Add migration script here
+
+CREATE TABLE IF NOT EXISTS customers
+(
+    id           INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    name         VARCHAR(100)            NOT NULL,
+    phone        VARCHAR(20)             NOT NULL DEFAULT '',
+    address      VARCHAR(500)            NOT NULL DEFAULT '',
+    credit_limit DECIMAL(10, 2) UNSIGNED NOT NULL DEFAULT 0,
+    created_at   DATETIME                NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX (phone)
+);
+
+ALTER TABLE receipts
+    ADD COLUMN customer_id INT UNSIGNED,
+    ADD FOREIGN KEY (customer_id) REFERENCES customers (id) ON UPDATE CASCADE;
+
+-- What each customer owes and their points, as the sum of the changes to them. Sales on credit
+-- and points earned have the receipt, payments against the balance the method they were paid
+-- with.
+CREATE TABLE IF NOT EXISTS customer_ledger
+(
+    id          INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    customer_id INT UNSIGNED   NOT NULL,
+    receipt_id  INT UNSIGNED,
+    method      VARCHAR(16),
+    amount      DECIMAL(10, 2) NOT NULL,
+    points      INT            NOT NULL,
+    note        VARCHAR(200)   NOT NULL DEFAULT '',
+    user_id     INT UNSIGNED,
+    created_at  DATETIME       NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX (customer_id, created_at),
+    FOREIGN KEY (customer_id) REFERENCES customers (id) ON UPDATE CASCADE,
+    FOREIGN KEY (receipt_id) REFERENCES receipts (id) ON DELETE SET NULL ON UPDATE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
@@ -0,0 +1,49 @@
+-- Add migration script here
+
+-- Goods brought back from a receipt, or the whole receipt voided. The receipt and its lines are
+-- kept as they were sold, reports take the returns off on the day they were made.
+CREATE TABLE IF NOT EXISTS returns
+(
+    id         INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    receipt_id INT UNSIGNED NOT NULL,
+    void       BOOLEAN      NOT NULL DEFAULT FALSE,
+    reason     VARCHAR(200) NOT NULL,
+    user_id    INT UNSIGNED,
+    created_at DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX (receipt_id),
+    FOREIGN KEY (receipt_id) REFERENCES receipts (id) ON UPDATE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+-- A shared::Disposition, restocked units went back into items.quantity. cost, tax_class and tax
+-- are those of the line they were sold on.
+CREATE TABLE IF NOT EXISTS return_items
+(
+    id          INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    return_id   INT UNSIGNED            NOT NULL,
+    barcode     VARCHAR(64)             NOT NULL,
+    quantity    SMALLINT UNSIGNED       NOT NULL,
+    disposition VARCHAR(16)             NOT NULL,
+    cost        DECIMAL(10, 2) UNSIGNED NOT NULL,
+    refund      DECIMAL(10, 2) UNSIGNED NOT NULL,
+    tax_class   VARCHAR(16)             NOT NULL,
+    tax         DECIMAL(10, 2) UNSIGNED NOT NULL,
+    INDEX (return_id),
+    FOREIGN KEY (return_id) REFERENCES returns (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- How the money was given back, like receipt_payments
+CREATE TABLE IF NOT EXISTS return_refunds
+(
+    id        INT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    return_id INT UNSIGNED            NOT NULL,
+    method    VARCHAR(16)             NOT NULL,
+    amount    DECIMAL(10, 2) UNSIGNED NOT NULL,
+    INDEX (return_id),
+    FOREIGN KEY (return_id) REFERENCES returns (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- Credit given back and points taken back by a return
+ALTER TABLE customer_ledger
+    ADD COLUMN return_id INT UNSIGNED AFTER receipt_id,
+    ADD FOREIGN KEY (return_id) REFERENCES returns (id) ON DELETE SET NULL ON UPDATE CASCADE;
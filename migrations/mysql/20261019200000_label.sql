@@ -0,0 +1,14 @@
+-- Add migration script here
+
+-- The price on the shelf tag of each item. Items without a row, or whose price is no longer the
+-- one printed, wait for a new tag. The tags already on the shelf are taken to be right.
+CREATE TABLE IF NOT EXISTS label_prints
+(
+    barcode    VARCHAR(64) PRIMARY KEY,
+    price      DECIMAL(6, 2) UNSIGNED NOT NULL,
+    printed_at DATETIME               NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (barcode) REFERENCES items (barcode) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+INSERT INTO label_prints (barcode, price)
+SELECT barcode, price FROM items;
@@ -0,0 +1,27 @@
+-- Add migration script here
+
+-- A shared::Unit. Goods sold by weight or volume are counted to the gram or millilitre, so
+-- every quantity takes three decimals.
+ALTER TABLE items
+    ADD COLUMN unit VARCHAR(16) NOT NULL DEFAULT 'piece' AFTER quantity,
+    MODIFY quantity DECIMAL(10, 3) NOT NULL;
+
+ALTER TABLE bulk_items
+    MODIFY quantity DECIMAL(10, 3) NOT NULL;
+
+ALTER TABLE receipt_items
+    MODIFY quantity DECIMAL(10, 3) UNSIGNED NOT NULL;
+
+ALTER TABLE return_items
+    MODIFY quantity DECIMAL(10, 3) UNSIGNED NOT NULL;
+
+ALTER TABLE stock_movements
+    MODIFY quantity DECIMAL(10, 3) NOT NULL;
+
+ALTER TABLE stock_take_lines
+    MODIFY counted_quantity DECIMAL(10, 3) NOT NULL,
+    MODIFY system_quantity DECIMAL(10, 3);
+
+-- Item numbers of weighed goods whose scale prints the weight
+INSERT INTO barcode_sequences (prefix)
+VALUES ('22');
@@ -0,0 +1,24 @@
+-- Add migration script here
+
+-- Prices and costs up to 99,999,999.99 like the other amounts of money, rather than 9,999.99.
+-- The server refuses anything larger instead of letting MySQL cut it to fit.
+ALTER TABLE items
+    MODIFY cost DECIMAL(10, 2) UNSIGNED NOT NULL,
+    MODIFY price DECIMAL(10, 2) UNSIGNED NOT NULL;
+
+ALTER TABLE bulk_items
+    MODIFY price DECIMAL(10, 2) UNSIGNED NOT NULL;
+
+ALTER TABLE receipt_items
+    MODIFY cost DECIMAL(10, 2) UNSIGNED NOT NULL,
+    MODIFY price DECIMAL(10, 2) UNSIGNED NOT NULL;
+
+ALTER TABLE stock_take_lines
+    MODIFY cost DECIMAL(10, 2) UNSIGNED;
+
+ALTER TABLE label_prints
+    MODIFY price DECIMAL(10, 2) UNSIGNED NOT NULL;
+
+ALTER TABLE legacy_items
+    MODIFY cost DECIMAL(10, 2) NOT NULL,
+    MODIFY price DECIMAL(10, 2) NOT NULL;
@@ -0,0 +1,129 @@
+-- Add migration script here
+
+-- The schema of migrations/mysql up to the same version. Money is TEXT because SQLite would
+-- store DECIMAL columns as floating point.
+
+CREATE TABLE IF NOT EXISTS categories
+(
+    id        INTEGER PRIMARY KEY AUTOINCREMENT,
+    parent_id INTEGER,
+    name      TEXT NOT NULL,
+    FOREIGN KEY (parent_id) REFERENCES categories (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS items
+(
+    barcode     TEXT PRIMARY KEY,
+    name        TEXT    NOT NULL,
+    cost        TEXT    NOT NULL,
+    price       TEXT    NOT NULL,
+    quantity    INTEGER NOT NULL,
+    image       BLOB,
+    category_id INTEGER,
+    FOREIGN KEY (category_id) REFERENCES categories (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS expire_dates
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    ref_barcode TEXT NOT NULL,
+    expire_date TEXT NOT NULL,
+    FOREIGN KEY (ref_barcode) REFERENCES items (barcode) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS bulk_items
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    barcode     TEXT UNIQUE,
+    ref_barcode TEXT    NOT NULL,
+    name        TEXT    NOT NULL,
+    price       TEXT    NOT NULL,
+    quantity    INTEGER NOT NULL,
+    image       BLOB,
+    FOREIGN KEY (ref_barcode) REFERENCES items (barcode) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS users
+(
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    name          TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    role          TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sessions
+(
+    token      TEXT PRIMARY KEY,
+    user_id    INTEGER NOT NULL,
+    created_at TEXT    NOT NULL DEFAULT (datetime('now', 'localtime')),
+    expires_at TEXT    NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS receipts
+(
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at      TEXT DEFAULT (datetime('now', 'localtime')),
+    idempotency_key TEXT UNIQUE,
+    user_id         INTEGER,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS receipt_items
+(
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    receipt_id INTEGER NOT NULL,
+    barcode    TEXT    NOT NULL,
+    cost       TEXT    NOT NULL,
+    price      TEXT    NOT NULL,
+    quantity   INTEGER NOT NULL,
+    FOREIGN KEY (receipt_id) REFERENCES receipts (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS receipt_items_receipt_id ON receipt_items (receipt_id);
+
+CREATE TABLE IF NOT EXISTS stock_movements
+(
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    barcode      TEXT    NOT NULL,
+    quantity     INTEGER NOT NULL,
+    reason       TEXT    NOT NULL,
+    reference_id INTEGER,
+    created_at   TEXT DEFAULT (datetime('now', 'localtime')),
+    FOREIGN KEY (barcode) REFERENCES items (barcode) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS stock_takes
+(
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at   TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+    committed_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS stock_take_lines
+(
+    stock_take_id    INTEGER NOT NULL,
+    barcode          TEXT    NOT NULL,
+    counted_quantity INTEGER NOT NULL,
+    system_quantity  INTEGER,
+    cost             TEXT,
+    PRIMARY KEY (stock_take_id, barcode),
+    FOREIGN KEY (stock_take_id) REFERENCES stock_takes (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY (barcode) REFERENCES items (barcode) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS audit_log
+(
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    entity       TEXT NOT NULL,
+    barcode      TEXT NOT NULL,
+    action       TEXT NOT NULL,
+    before_value TEXT,
+    after_value  TEXT,
+    user_id      INTEGER,
+    source       TEXT NOT NULL,
+    created_at   TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_barcode ON audit_log (barcode);
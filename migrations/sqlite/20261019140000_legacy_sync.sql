@@ -0,0 +1,42 @@
+-- Add migration script here
+
+-- When an item last changed, so a two-way sync can tell changes made here since the last sync.
+-- SQLite cannot add a column with a default of the current time, so triggers keep it instead.
+ALTER TABLE items
+    ADD COLUMN updated_at TEXT NOT NULL DEFAULT '1970-01-01 00:00:00';
+
+UPDATE items
+SET updated_at = datetime('now', 'localtime');
+
+CREATE TRIGGER IF NOT EXISTS items_inserted
+    AFTER INSERT
+    ON items
+BEGIN
+    UPDATE items SET updated_at = datetime('now', 'localtime') WHERE barcode = NEW.barcode;
+END;
+
+CREATE TRIGGER IF NOT EXISTS items_updated
+    AFTER UPDATE OF barcode, name, cost, price, quantity, image, category_id
+    ON items
+    WHEN OLD.barcode IS NOT NEW.barcode
+        OR OLD.name IS NOT NEW.name
+        OR OLD.cost IS NOT NEW.cost
+        OR OLD.price IS NOT NEW.price
+        OR OLD.quantity IS NOT NEW.quantity
+        OR OLD.image IS NOT NEW.image
+        OR OLD.category_id IS NOT NEW.category_id
+BEGIN
+    UPDATE items SET updated_at = datetime('now', 'localtime') WHERE barcode = NEW.barcode;
+END;
+
+-- The rows of the old program as they were after the last two-way sync, to tell what changed
+-- there since
+CREATE TABLE IF NOT EXISTS legacy_items
+(
+    barcode   TEXT PRIMARY KEY,
+    name      TEXT    NOT NULL,
+    cost      TEXT    NOT NULL,
+    price     TEXT    NOT NULL,
+    quantity  INTEGER NOT NULL,
+    synced_at TEXT    NOT NULL DEFAULT (datetime('now', 'localtime'))
+);
@@ -0,0 +1,20 @@
+-- Add migration script here
+
+-- How each receipt was paid, one row per method of a split payment. Amounts are net of change.
+CREATE TABLE IF NOT EXISTS receipt_payments
+(
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    receipt_id INTEGER NOT NULL,
+    method     TEXT    NOT NULL,
+    amount     TEXT    NOT NULL,
+    reference  TEXT,
+    FOREIGN KEY (receipt_id) REFERENCES receipts (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS receipt_payments_receipt_id ON receipt_payments (receipt_id);
+
+-- Every earlier sale was paid in cash. Money is kept as text, so it is summed in cents.
+INSERT INTO receipt_payments (receipt_id, method, amount)
+SELECT receipt_id, 'cash', printf('%.2f', SUM(ROUND(price * 100) * quantity) / 100.0)
+FROM receipt_items
+GROUP BY receipt_id;
@@ -0,0 +1,35 @@
+-- Add migration script here
+
+-- `rule` is a shared::PromotionRule as JSON, tagged by its type
+CREATE TABLE IF NOT EXISTS promotions
+(
+    id        INTEGER PRIMARY KEY AUTOINCREMENT,
+    name      TEXT NOT NULL,
+    rule      TEXT NOT NULL,
+    starts_at TEXT,
+    ends_at   TEXT
+);
+
+CREATE TABLE IF NOT EXISTS promotion_items
+(
+    promotion_id INTEGER NOT NULL,
+    barcode      TEXT    NOT NULL,
+    PRIMARY KEY (promotion_id, barcode),
+    FOREIGN KEY (promotion_id) REFERENCES promotions (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY (barcode) REFERENCES items (barcode) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- The name is kept so old receipts still read right after the promotion is deleted
+CREATE TABLE IF NOT EXISTS receipt_discounts
+(
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    receipt_id   INTEGER NOT NULL,
+    promotion_id INTEGER,
+    name         TEXT    NOT NULL,
+    barcode      TEXT    NOT NULL,
+    amount       TEXT    NOT NULL,
+    FOREIGN KEY (receipt_id) REFERENCES receipts (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY (promotion_id) REFERENCES promotions (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS receipt_discounts_receipt_id ON receipt_discounts (receipt_id);
@@ -0,0 +1,30 @@
+-- Add migration script here
+
+-- A shared::TaxClass. Prices include the VAT.
+ALTER TABLE items
+    ADD COLUMN tax_class TEXT NOT NULL DEFAULT 'standard';
+
+-- The VAT included in each line after its discounts. Sales made before VAT was worked out
+-- have none.
+ALTER TABLE receipt_items
+    ADD COLUMN tax_class TEXT NOT NULL DEFAULT 'standard';
+
+ALTER TABLE receipt_items
+    ADD COLUMN tax TEXT NOT NULL DEFAULT '0';
+
+-- At most one per receipt. Full invoices, the ones with a buyer, and abbreviated ones are
+-- numbered separately by `sequence`.
+CREATE TABLE IF NOT EXISTS tax_invoices
+(
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    receipt_id    INTEGER NOT NULL UNIQUE,
+    kind          TEXT    NOT NULL,
+    sequence      INTEGER NOT NULL,
+    buyer_name    TEXT,
+    buyer_tax_id  TEXT,
+    buyer_address TEXT,
+    buyer_branch  TEXT,
+    issued_at     TEXT    NOT NULL DEFAULT (datetime('now', 'localtime')),
+    UNIQUE (kind, sequence),
+    FOREIGN KEY (receipt_id) REFERENCES receipts (id) ON UPDATE CASCADE
+);
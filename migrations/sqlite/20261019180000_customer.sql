@@ -0,0 +1,37 @@
+-- Add migration script here
+
+CREATE TABLE IF NOT EXISTS customers
+(
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    name         TEXT NOT NULL,
+    phone        TEXT NOT NULL DEFAULT '',
+    address      TEXT NOT NULL DEFAULT '',
+    credit_limit TEXT NOT NULL DEFAULT '0',
+    created_at   TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+);
+
+CREATE INDEX IF NOT EXISTS customers_phone ON customers (phone);
+
+ALTER TABLE receipts
+    ADD COLUMN customer_id INTEGER REFERENCES customers (id) ON UPDATE CASCADE;
+
+-- What each customer owes and their points, as the sum of the changes to them. Sales on credit
+-- and points earned have the receipt, payments against the balance the method they were paid
+-- with.
+CREATE TABLE IF NOT EXISTS customer_ledger
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    customer_id INTEGER NOT NULL,
+    receipt_id  INTEGER,
+    method      TEXT,
+    amount      TEXT    NOT NULL,
+    points      INTEGER NOT NULL,
+    note        TEXT    NOT NULL DEFAULT '',
+    user_id     INTEGER,
+    created_at  TEXT    NOT NULL DEFAULT (datetime('now', 'localtime')),
+    FOREIGN KEY (customer_id) REFERENCES customers (id) ON UPDATE CASCADE,
+    FOREIGN KEY (receipt_id) REFERENCES receipts (id) ON DELETE SET NULL ON UPDATE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS customer_ledger_customer_id ON customer_ledger (customer_id, created_at);
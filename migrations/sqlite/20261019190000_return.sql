@@ -0,0 +1,51 @@
+-- Add migration script here
+
+-- Goods brought back from a receipt, or the whole receipt voided. The receipt and its lines are
+-- kept as they were sold, reports take the returns off on the day they were made.
+CREATE TABLE IF NOT EXISTS returns
+(
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    receipt_id INTEGER NOT NULL,
+    void       INTEGER NOT NULL DEFAULT 0,
+    reason     TEXT    NOT NULL,
+    user_id    INTEGER,
+    created_at TEXT    NOT NULL DEFAULT (datetime('now', 'localtime')),
+    FOREIGN KEY (receipt_id) REFERENCES receipts (id) ON UPDATE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS returns_receipt_id ON returns (receipt_id);
+
+-- A shared::Disposition, restocked units went back into items.quantity. cost, tax_class and tax
+-- are those of the line they were sold on.
+CREATE TABLE IF NOT EXISTS return_items
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    return_id   INTEGER NOT NULL,
+    barcode     TEXT    NOT NULL,
+    quantity    INTEGER NOT NULL,
+    disposition TEXT    NOT NULL,
+    cost        TEXT    NOT NULL,
+    refund      TEXT    NOT NULL,
+    tax_class   TEXT    NOT NULL,
+    tax         TEXT    NOT NULL,
+    FOREIGN KEY (return_id) REFERENCES returns (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS return_items_return_id ON return_items (return_id);
+
+-- How the money was given back, like receipt_payments
+CREATE TABLE IF NOT EXISTS return_refunds
+(
+    id        INTEGER PRIMARY KEY AUTOINCREMENT,
+    return_id INTEGER NOT NULL,
+    method    TEXT    NOT NULL,
+    amount    TEXT    NOT NULL,
+    FOREIGN KEY (return_id) REFERENCES returns (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS return_refunds_return_id ON return_refunds (return_id);
+
+-- Credit given back and points taken back by a return
+ALTER TABLE customer_ledger
+    ADD COLUMN return_id INTEGER REFERENCES returns (id) ON DELETE SET NULL ON UPDATE CASCADE;
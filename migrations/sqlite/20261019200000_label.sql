@@ -0,0 +1,14 @@
+-- Add migration script here
+
+-- The price on the shelf tag of each item. Items without a row, or whose price is no longer the
+-- one printed, wait for a new tag. The tags already on the shelf are taken to be right.
+CREATE TABLE IF NOT EXISTS label_prints
+(
+    barcode    TEXT PRIMARY KEY,
+    price      TEXT NOT NULL,
+    printed_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+    FOREIGN KEY (barcode) REFERENCES items (barcode) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+INSERT INTO label_prints (barcode, price)
+SELECT barcode, price FROM items;
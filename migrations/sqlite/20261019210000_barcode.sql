@@ -0,0 +1,13 @@
+-- Add migration script here
+
+-- The next number to hand out for each prefix of the shop's own barcodes: 20 for goods sold by
+-- the piece, 21 for weighed goods. A number whose barcode is already taken is skipped.
+CREATE TABLE IF NOT EXISTS barcode_sequences
+(
+    prefix     TEXT PRIMARY KEY,
+    next_value INTEGER NOT NULL DEFAULT 1
+);
+
+INSERT INTO barcode_sequences (prefix)
+VALUES ('20'),
+       ('21');
@@ -0,0 +1,11 @@
+-- Add migration script here
+
+-- A shared::Unit. Goods sold by weight or volume are counted to the gram or millilitre. The
+-- quantity columns keep their INTEGER affinity, which stores a quantity with decimals as a
+-- REAL, and are read back rounded to three decimals.
+ALTER TABLE items
+    ADD COLUMN unit TEXT NOT NULL DEFAULT 'piece';
+
+-- Item numbers of weighed goods whose scale prints the weight
+INSERT INTO barcode_sequences (prefix)
+VALUES ('22');
@@ -0,0 +1,58 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+use shared::{AuditEntry, User};
+
+use crate::{AppError, Database};
+
+/// Where a change to the catalogue came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Source {
+    Client,
+    Sync,
+    Import,
+    StockTake,
+}
+
+impl Source {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Source::Client => "client",
+            Source::Sync => "sync",
+            Source::Import => "import",
+            Source::StockTake => "stock_take",
+        }
+    }
+}
+
+/// Who made a change and through what, recorded with every audit log entry. Changes made by the
+/// server itself, like the startup sync, have no user.
+#[derive(Debug, Clone)]
+pub(crate) struct Actor {
+    pub(crate) user_id: Option<u32>,
+    pub(crate) source: Source,
+}
+
+impl Actor {
+    pub(crate) fn user(user: &User, source: Source) -> Self {
+        Actor {
+            user_id: Some(user.id),
+            source,
+        }
+    }
+
+    pub(crate) fn server(source: Source) -> Self {
+        Actor {
+            user_id: None,
+            source,
+        }
+    }
+}
+
+pub async fn get_item_history(
+    State(db): State<Database>,
+    Path(barcode): Path<String>,
+) -> Result<Json<Vec<AuditEntry>>, AppError> {
+    Ok(Json(db.select_audit_log(&barcode).await?))
+}
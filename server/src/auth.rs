@@ -0,0 +1,154 @@
+use argon2::Argon2;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use axum::{
+    extract::{Extension, FromRequestParts, Request, State},
+    http::{StatusCode, header, request::Parts},
+    middleware::Next,
+    response::{Json, Response},
+};
+use shared::{Login, NewUser, Role, Session, User};
+
+use crate::{AppError, AppState, Database};
+
+const SESSION_HOURS: i64 = 24;
+
+fn bearer_token(request: &Request) -> Option<String> {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::to_string)
+}
+
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn hash_password(password: &str) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+/// Rejects requests without a valid session token and makes the logged in [`User`] available
+/// to the handlers as an extension.
+pub async fn authenticate(
+    State(db): State<Database>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let token = bearer_token(&request).ok_or(AppError::Unauthorized)?;
+    let user = db
+        .select_session_user(&token)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+    request.extensions_mut().insert(user);
+    Ok(next.run(request).await)
+}
+
+fn validate(new_user: &NewUser) -> Result<(), AppError> {
+    if new_user.name.trim().is_empty() {
+        return Err(AppError::InvalidInput("user name is empty".to_string()));
+    }
+    if new_user.password.chars().count() < 4 {
+        return Err(AppError::InvalidInput(
+            "password must be at least 4 characters".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn login(
+    State(state): State<AppState>,
+    Json(login): Json<Login>,
+) -> Result<Json<Session>, AppError> {
+    let db = &state.db;
+    let (user, password_hash) = db
+        .select_credential(&login.name)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+    let password_hash = PasswordHash::new(&password_hash)?;
+    Argon2::default()
+        .verify_password(login.password.as_bytes(), &password_hash)
+        .map_err(|_| AppError::Unauthorized)?;
+
+    let token = new_token();
+    db.insert_session(&token, user.id, SESSION_HOURS).await?;
+    Ok(Json(Session {
+        token,
+        user,
+        vat_rate: state.vat_rate,
+    }))
+}
+
+pub async fn logout(State(db): State<Database>, request: Request) -> Result<StatusCode, AppError> {
+    if let Some(token) = bearer_token(&request) {
+        db.delete_session(&token).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_me(Extension(user): Extension<User>) -> Json<User> {
+    Json(user)
+}
+
+pub async fn get_users(
+    State(db): State<Database>,
+    _owner: Owner,
+) -> Result<Json<Vec<User>>, AppError> {
+    Ok(Json(db.select_users().await?))
+}
+
+pub async fn post_user(
+    State(db): State<Database>,
+    _owner: Owner,
+    Json(new_user): Json<NewUser>,
+) -> Result<Json<User>, AppError> {
+    create_user(&db, new_user).await
+}
+
+async fn create_user(db: &Database, new_user: NewUser) -> Result<Json<User>, AppError> {
+    validate(&new_user)?;
+    let password_hash = hash_password(&new_user.password)?;
+    let id = db
+        .insert_user(new_user.name.trim(), &password_hash, new_user.role)
+        .await?;
+    Ok(Json(User {
+        id,
+        name: new_user.name.trim().to_string(),
+        role: new_user.role,
+    }))
+}
+
+/// Creates the first owner account. Only allowed while there are no users at all.
+pub async fn setup(
+    State(db): State<Database>,
+    Json(mut new_user): Json<NewUser>,
+) -> Result<Json<User>, AppError> {
+    if db.count_users().await? > 0 {
+        return Err(AppError::Forbidden);
+    }
+    new_user.role = Role::Owner;
+    create_user(&db, new_user).await
+}
+
+/// Extractor for handlers only the shop owner may use.
+pub struct Owner(pub User);
+
+impl<S: Send + Sync> FromRequestParts<S> for Owner {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<User>() {
+            Some(user) if user.role == Role::Owner => Ok(Owner(user.clone())),
+            Some(_) => Err(AppError::Forbidden),
+            None => Err(AppError::Unauthorized),
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Json},
+};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use rust_xlsxwriter::Workbook;
+use serde::Deserialize;
+use shared::number;
+use shared::{BulkItem, ImportReport, ImportRowError, Item, ItemEvent, TaxClass, Unit};
+
+use crate::audit::{Actor, Source};
+use crate::database::{self, CatalogueEntry, Refusal};
+use crate::{AppError, Database, Owner, events, load_items};
+
+const HEADERS: [&str; 10] = [
+    "barcode",
+    "ref_barcode",
+    "name",
+    "cost",
+    "price",
+    "quantity",
+    "unit",
+    "category_id",
+    "tax_class",
+    "expire_dates",
+];
+const NUMERIC_COLUMNS: [usize; 4] = [3, 4, 5, 7];
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// One line of the catalogue spreadsheet. Items leave `ref_barcode` empty, bulk items put the
+/// barcode of the item they contain there and leave `cost`, `unit`, `category_id`, `tax_class`
+/// and `expire_dates` empty. Expiry dates are joined with `;`. An empty unit or tax class keeps
+/// the one of an existing item.
+#[derive(Debug, Default, Deserialize)]
+struct Row {
+    #[serde(default)]
+    barcode: String,
+    #[serde(default)]
+    ref_barcode: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    cost: String,
+    #[serde(default)]
+    price: String,
+    #[serde(default)]
+    quantity: String,
+    #[serde(default)]
+    unit: String,
+    #[serde(default)]
+    category_id: String,
+    #[serde(default)]
+    tax_class: String,
+    #[serde(default)]
+    expire_dates: String,
+}
+
+impl Row {
+    fn values(&self) -> [&str; 10] {
+        [
+            self.barcode.as_str(),
+            self.ref_barcode.as_str(),
+            self.name.as_str(),
+            self.cost.as_str(),
+            self.price.as_str(),
+            self.quantity.as_str(),
+            self.unit.as_str(),
+            self.category_id.as_str(),
+            self.tax_class.as_str(),
+            self.expire_dates.as_str(),
+        ]
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ImportOptions {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+fn rows(items: Vec<Item>) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for item in items {
+        rows.push(Row {
+            barcode: item.barcode.clone(),
+            ref_barcode: String::new(),
+            name: item.name,
+            cost: item.cost.to_string(),
+            price: item.price.to_string(),
+            quantity: item.quantity.to_string(),
+            unit: item.unit.as_str().to_string(),
+            category_id: item
+                .category_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            tax_class: item.tax_class.as_str().to_string(),
+            expire_dates: item
+                .expire_date
+                .iter()
+                .map(|date| date.format(DATE_FORMAT).to_string())
+                .collect::<Vec<_>>()
+                .join(";"),
+        });
+        for bulk_item in item.bulk_item {
+            rows.push(Row {
+                barcode: bulk_item.barcode.unwrap_or_default(),
+                ref_barcode: item.barcode.clone(),
+                name: bulk_item.name,
+                price: bulk_item.price.to_string(),
+                quantity: bulk_item.quantity.to_string(),
+                ..Default::default()
+            });
+        }
+    }
+    rows
+}
+
+/// The catalogue as CSV, with a byte order mark so Excel opens the Thai names correctly.
+pub(crate) async fn csv(db: &Database) -> Result<Vec<u8>, AppError> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(BOM.to_vec());
+    writer.write_record(HEADERS)?;
+    for row in rows(load_items(db, None).await?) {
+        writer.write_record(row.values())?;
+    }
+    Ok(writer
+        .into_inner()
+        .map_err(|e| csv::Error::from(e.into_error()))?)
+}
+
+pub async fn export_csv(
+    State(db): State<Database>,
+    _owner: Owner,
+) -> Result<impl IntoResponse, AppError> {
+    let body = csv(&db).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"items.csv\"",
+            ),
+        ],
+        body,
+    ))
+}
+
+pub(crate) async fn xlsx(db: &Database) -> Result<Vec<u8>, AppError> {
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    for (col, header) in HEADERS.iter().enumerate() {
+        worksheet.write_string(0, col as u16, *header)?;
+    }
+    for (i, row) in rows(load_items(db, None).await?).iter().enumerate() {
+        for (col, value) in row.values().iter().enumerate() {
+            match value.parse::<f64>() {
+                Ok(number) if NUMERIC_COLUMNS.contains(&col) => {
+                    worksheet.write_number(i as u32 + 1, col as u16, number)?
+                }
+                _ => worksheet.write_string(i as u32 + 1, col as u16, *value)?,
+            };
+        }
+    }
+    Ok(workbook.save_to_buffer()?)
+}
+
+pub async fn export_xlsx(
+    State(db): State<Database>,
+    _owner: Owner,
+) -> Result<impl IntoResponse, AppError> {
+    let body = xlsx(&db).await?;
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"items.xlsx\"",
+            ),
+        ],
+        body,
+    ))
+}
+
+/// Reads money the way the spreadsheet may show it, like `1,250.00`, refusing amounts the
+/// database would have to cut to fit.
+fn parse_money(field: &str, value: &str) -> Result<Decimal, String> {
+    let amount = number::parse_decimal(value)
+        .ok_or_else(|| format!("{field} is not a number: \"{value}\""))?;
+    number::validate_money(amount).map_err(|e| format!("{field} {e}: \"{value}\""))?;
+    Ok(amount)
+}
+
+fn parse_row(row: Row) -> Result<CatalogueEntry, String> {
+    let barcode = row.barcode.trim().to_string();
+    let name = row.name.trim().to_string();
+    if name.is_empty() {
+        return Err("name is empty".to_string());
+    }
+    let price = parse_money("price", &row.price)?;
+    let quantity = number::parse_decimal(&row.quantity)
+        .ok_or_else(|| format!("quantity is not a number: \"{}\"", row.quantity))?
+        .normalize();
+    if quantity.abs() > number::MAX_QUANTITY {
+        return Err(format!(
+            "quantity is more than {}: \"{}\"",
+            number::MAX_QUANTITY,
+            row.quantity
+        ));
+    }
+
+    if !row.ref_barcode.trim().is_empty() {
+        return Ok(CatalogueEntry::Bulk(
+            row.ref_barcode.trim().to_string(),
+            BulkItem {
+                barcode: (!barcode.is_empty()).then_some(barcode),
+                name,
+                price,
+                quantity,
+                image: None,
+            },
+        ));
+    }
+
+    if barcode.is_empty() {
+        return Err("barcode is empty".to_string());
+    }
+    let cost = parse_money("cost", &row.cost)?;
+    let category_id = match row.category_id.trim() {
+        "" => None,
+        id => Some(
+            id.parse::<u32>()
+                .map_err(|_| format!("category_id is not an id: \"{id}\""))?,
+        ),
+    };
+    let unit = match row.unit.trim() {
+        "" => None,
+        unit => Some(
+            unit.parse::<Unit>()
+                .map_err(|_| format!("unit is not piece, kilogram or litre: \"{unit}\""))?,
+        ),
+    };
+    // Without a unit the item may be one weighed already, so only the finest scale is checked
+    if !unit.unwrap_or(Unit::Kilogram).allows(quantity) {
+        return Err(format!(
+            "quantity does not fit the unit: \"{}\"",
+            row.quantity
+        ));
+    }
+    let tax_class = match row.tax_class.trim() {
+        "" => None,
+        class => Some(
+            class
+                .parse::<TaxClass>()
+                .map_err(|_| format!("tax_class is not standard, zero or exempt: \"{class}\""))?,
+        ),
+    };
+    let expire_dates = row
+        .expire_dates
+        .split(';')
+        .map(str::trim)
+        .filter(|date| !date.is_empty())
+        .map(|date| {
+            NaiveDate::parse_from_str(date, DATE_FORMAT)
+                .map_err(|_| format!("expire date is not yyyy-mm-dd: \"{date}\""))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(CatalogueEntry::Item(
+        database::Item {
+            barcode,
+            name,
+            cost,
+            price,
+            quantity,
+            unit,
+            image: None,
+            category_id,
+            tax_class,
+        },
+        expire_dates,
+    ))
+}
+
+pub async fn import_items(
+    State(db): State<Database>,
+    Owner(user): Owner,
+    Query(options): Query<ImportOptions>,
+    body: String,
+) -> Result<Json<ImportReport>, AppError> {
+    let actor = Actor::user(&user, Source::Import);
+    Ok(Json(import(&db, &body, options.dry_run, &actor).await?))
+}
+
+/// Imports the catalogue from CSV in a single transaction. Nothing is committed on a dry run or
+/// when any row has an error or conflict, but the report still counts what would have changed.
+pub(crate) async fn import(
+    db: &Database,
+    body: &str,
+    dry_run: bool,
+    actor: &Actor,
+) -> Result<ImportReport, AppError> {
+    let mut report = ImportReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    let body = body.strip_prefix('\u{feff}').unwrap_or(body);
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::Headers)
+        .from_reader(body.as_bytes());
+
+    let mut entries = Vec::new();
+    let mut barcodes = HashSet::new();
+    let mut bulk_names = HashSet::new();
+    for (i, row) in reader.deserialize::<Row>().enumerate() {
+        // Row 1 is the header
+        let row_number = i + 2;
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                report.errors.push(ImportRowError {
+                    row: row_number,
+                    barcode: String::new(),
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let barcode = row.barcode.trim().to_string();
+        match parse_row(row) {
+            Err(message) => report.errors.push(ImportRowError {
+                row: row_number,
+                barcode,
+                message,
+            }),
+            Ok(entry) => {
+                let duplicate = match &entry {
+                    CatalogueEntry::Item(item, _) => !barcodes.insert(item.barcode.clone()),
+                    CatalogueEntry::Bulk(ref_barcode, bulk_item) => {
+                        !bulk_names.insert((ref_barcode.clone(), bulk_item.name.clone()))
+                    }
+                };
+                if duplicate {
+                    report.conflicts.push(ImportRowError {
+                        row: row_number,
+                        barcode,
+                        message: "appears more than once in the file".to_string(),
+                    });
+                } else {
+                    entries.push((row_number, barcode, entry));
+                }
+            }
+        }
+    }
+
+    let (rows, entries): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .map(|(row_number, barcode, entry)| ((row_number, barcode), entry))
+        .unzip();
+    let commit = !dry_run && report.errors.is_empty() && report.conflicts.is_empty();
+    let results = db.write_catalogue(&entries, actor, commit).await?;
+    for ((row_number, barcode), result) in rows.into_iter().zip(results) {
+        match result {
+            Ok(true) => report.inserted += 1,
+            Ok(false) => report.updated += 1,
+            Err(Refusal::Conflict(message)) => report.conflicts.push(ImportRowError {
+                row: row_number,
+                barcode,
+                message,
+            }),
+            Err(Refusal::Invalid(message)) => report.errors.push(ImportRowError {
+                row: row_number,
+                barcode,
+                message,
+            }),
+        }
+    }
+
+    if commit && report.errors.is_empty() && report.conflicts.is_empty() {
+        report.committed = true;
+        events::publish(ItemEvent::Reload);
+    }
+
+    Ok(report)
+}
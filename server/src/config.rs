@@ -0,0 +1,359 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::NaiveTime;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use dotenv::dotenv;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+const DEFAULT_PATH: &str = "server.toml";
+const DEFAULT_BIND: &str = "0.0.0.0:3000";
+const DEFAULT_POOL_SIZE: u32 = 10;
+const DEFAULT_EXPIRY_CHECK_INTERVAL_MINUTES: u64 = 24 * 60;
+const DEFAULT_VAT_RATE: Decimal = rust_decimal::dec!(7);
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Sunminimart server")]
+pub struct Cli {
+    /// Configuration file [default: server.toml in the working directory, if there is one]
+    #[arg(short, long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Database of the shop [env: DATABASE_URL]
+    #[arg(long, global = true)]
+    pub database_url: Option<String>,
+    /// Database of the old program the catalogue is synced from [env: OLD_DATABASE_URL]
+    #[arg(long, global = true)]
+    pub old_database_url: Option<String>,
+    /// Most connections kept open to the database [env: SUNMINIMART_POOL_SIZE]
+    #[arg(long, global = true)]
+    pub pool_size: Option<u32>,
+    /// Whether a sync also writes back to the old database [env: SUNMINIMART_SYNC_MODE]
+    /// [default: pull]
+    #[arg(long, global = true)]
+    pub sync_mode: Option<SyncMode>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Serve the API, the default when no command is given
+    Serve(ServeArgs),
+    /// Sync the catalogue from the old database once and exit
+    Sync,
+    /// Apply the pending database migrations and exit
+    Migrate,
+    /// Import the catalogue from a CSV file
+    Import {
+        file: PathBuf,
+        /// Check the file and report what would change without saving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Export the catalogue to a .csv or .xlsx file
+    Export { file: PathBuf },
+}
+
+#[derive(Debug, Default, Args)]
+pub struct ServeArgs {
+    /// Address to listen on [env: SUNMINIMART_BIND] [default: 0.0.0.0:3000]
+    #[arg(long)]
+    pub bind: Option<String>,
+    /// Sync from the old database before serving [env: SUNMINIMART_SYNC_ON_START]
+    /// [default: true when the old database is set]
+    #[arg(long)]
+    pub sync_on_start: Option<bool>,
+    /// Sync from the old database every this many minutes, 0 turns it off
+    /// [env: SUNMINIMART_SYNC_INTERVAL_MINUTES] [default: 0]
+    #[arg(long)]
+    pub sync_interval_minutes: Option<u64>,
+    /// Look for stock about to expire every this many minutes, 0 turns it off
+    /// [env: SUNMINIMART_EXPIRY_CHECK_INTERVAL_MINUTES] [default: 1440]
+    #[arg(long)]
+    pub expiry_check_interval_minutes: Option<u64>,
+    /// Sum up the sales of the day at this time, as HH:MM [env: SUNMINIMART_NIGHTLY_REPORT_AT]
+    /// [default: off]
+    #[arg(long)]
+    pub nightly_report_at: Option<String>,
+    /// VAT included in the prices of standard rated items, in percent, 0 when the shop is not
+    /// registered for VAT [env: SUNMINIMART_VAT_RATE] [default: 7]
+    #[arg(long)]
+    pub vat_rate: Option<Decimal>,
+    /// Customers earn a loyalty point for every this many baht they spend, 0 turns points off
+    /// [env: SUNMINIMART_BAHT_PER_POINT] [default: 0]
+    #[arg(long)]
+    pub baht_per_point: Option<Decimal>,
+}
+
+/// How a sync treats the old database.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncMode {
+    /// Copy the catalogue from the old database, overwriting what changed here
+    #[default]
+    Pull,
+    /// Also write quantities and new items back to the old database, for while both programs
+    /// are in use
+    TwoWay,
+}
+
+impl FromStr for SyncMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pull" => Ok(SyncMode::Pull),
+            "two-way" => Ok(SyncMode::TwoWay),
+            _ => Err("expected pull or two-way".to_string()),
+        }
+    }
+}
+
+/// Settings of the server after the file, the environment and the command line flags have been
+/// merged, in that order of increasing priority.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub bind: SocketAddr,
+    pub database_url: String,
+    pub old_database_url: Option<String>,
+    pub pool_size: u32,
+    pub sync_on_start: bool,
+    pub sync_interval: Option<Duration>,
+    pub sync_mode: SyncMode,
+    pub expiry_check_interval: Option<Duration>,
+    pub nightly_report_at: Option<NaiveTime>,
+    pub vat_rate: Decimal,
+    pub baht_per_point: Decimal,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Missing(&'static str),
+    Invalid(&'static str, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(path, e) => write!(f, "cannot read {}: {e}", path.display()),
+            Self::Parse(path, e) => write!(f, "{} is not valid: {e}", path.display()),
+            Self::Missing(key) => write!(f, "{key} is not set"),
+            Self::Invalid(key, e) => write!(f, "{key} is not valid: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// One source of settings. Anything left out falls through to the next source.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Layer {
+    bind: Option<String>,
+    database_url: Option<String>,
+    old_database_url: Option<String>,
+    pool_size: Option<u32>,
+    sync_on_start: Option<bool>,
+    sync_interval_minutes: Option<u64>,
+    sync_mode: Option<SyncMode>,
+    expiry_check_interval_minutes: Option<u64>,
+    nightly_report_at: Option<String>,
+    vat_rate: Option<Decimal>,
+    baht_per_point: Option<Decimal>,
+}
+
+impl Layer {
+    fn or(self, other: Layer) -> Layer {
+        Layer {
+            bind: self.bind.or(other.bind),
+            database_url: self.database_url.or(other.database_url),
+            old_database_url: self.old_database_url.or(other.old_database_url),
+            pool_size: self.pool_size.or(other.pool_size),
+            sync_on_start: self.sync_on_start.or(other.sync_on_start),
+            sync_interval_minutes: self.sync_interval_minutes.or(other.sync_interval_minutes),
+            sync_mode: self.sync_mode.or(other.sync_mode),
+            expiry_check_interval_minutes: self
+                .expiry_check_interval_minutes
+                .or(other.expiry_check_interval_minutes),
+            nightly_report_at: self.nightly_report_at.or(other.nightly_report_at),
+            vat_rate: self.vat_rate.or(other.vat_rate),
+            baht_per_point: self.baht_per_point.or(other.baht_per_point),
+        }
+    }
+
+    fn file(path: Option<&PathBuf>) -> Result<Layer, ConfigError> {
+        let (path, required) = match path {
+            Some(path) => (path.clone(), true),
+            None => (PathBuf::from(DEFAULT_PATH), false),
+        };
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Layer::default());
+            }
+            Err(e) => return Err(ConfigError::Read(path, e)),
+        };
+        toml::from_str(&data).map_err(|e| ConfigError::Parse(path, e))
+    }
+
+    fn env() -> Result<Layer, ConfigError> {
+        Ok(Layer {
+            bind: env("SUNMINIMART_BIND"),
+            database_url: env("DATABASE_URL"),
+            old_database_url: env("OLD_DATABASE_URL"),
+            pool_size: env_parse("SUNMINIMART_POOL_SIZE")?,
+            sync_on_start: env_parse("SUNMINIMART_SYNC_ON_START")?,
+            sync_interval_minutes: env_parse("SUNMINIMART_SYNC_INTERVAL_MINUTES")?,
+            sync_mode: env_parse("SUNMINIMART_SYNC_MODE")?,
+            expiry_check_interval_minutes: env_parse("SUNMINIMART_EXPIRY_CHECK_INTERVAL_MINUTES")?,
+            nightly_report_at: env("SUNMINIMART_NIGHTLY_REPORT_AT"),
+            vat_rate: env_parse("SUNMINIMART_VAT_RATE")?,
+            baht_per_point: env_parse("SUNMINIMART_BAHT_PER_POINT")?,
+        })
+    }
+
+    fn validate(self) -> Result<Config, ConfigError> {
+        let bind = self.bind.as_deref().unwrap_or(DEFAULT_BIND);
+        let bind = bind
+            .parse::<SocketAddr>()
+            .map_err(|e| ConfigError::Invalid("bind", format!("\"{bind}\": {e}")))?;
+
+        let database_url = self
+            .database_url
+            .filter(|url| !url.is_empty())
+            .ok_or(ConfigError::Missing("database_url"))?;
+        if !["mysql://", "mariadb://", "sqlite:"]
+            .iter()
+            .any(|scheme| database_url.starts_with(scheme))
+        {
+            return Err(ConfigError::Invalid(
+                "database_url",
+                "only mysql://, mariadb:// and sqlite: databases are supported".to_string(),
+            ));
+        }
+        let old_database_url = self.old_database_url.filter(|url| !url.is_empty());
+
+        let pool_size = self.pool_size.unwrap_or(DEFAULT_POOL_SIZE);
+        if pool_size == 0 {
+            return Err(ConfigError::Invalid(
+                "pool_size",
+                "must be at least 1".to_string(),
+            ));
+        }
+
+        let sync_on_start = self.sync_on_start.unwrap_or(old_database_url.is_some());
+        let sync_interval = minutes(self.sync_interval_minutes.unwrap_or(0));
+        if old_database_url.is_none() && (sync_on_start || sync_interval.is_some()) {
+            return Err(ConfigError::Missing("old_database_url"));
+        }
+
+        let expiry_check_interval = minutes(
+            self.expiry_check_interval_minutes
+                .unwrap_or(DEFAULT_EXPIRY_CHECK_INTERVAL_MINUTES),
+        );
+        let nightly_report_at = self
+            .nightly_report_at
+            .filter(|time| !time.is_empty())
+            .map(|time| {
+                NaiveTime::parse_from_str(&time, "%H:%M").map_err(|e| {
+                    ConfigError::Invalid("nightly_report_at", format!("\"{time}\": {e}"))
+                })
+            })
+            .transpose()?;
+
+        let vat_rate = self.vat_rate.unwrap_or(DEFAULT_VAT_RATE);
+        if vat_rate.is_sign_negative() || vat_rate >= Decimal::ONE_HUNDRED {
+            return Err(ConfigError::Invalid(
+                "vat_rate",
+                "must be from 0 up to 100".to_string(),
+            ));
+        }
+
+        let baht_per_point = self.baht_per_point.unwrap_or_default();
+        if baht_per_point.is_sign_negative() {
+            return Err(ConfigError::Invalid(
+                "baht_per_point",
+                "cannot be negative".to_string(),
+            ));
+        }
+
+        Ok(Config {
+            bind,
+            database_url,
+            old_database_url,
+            pool_size,
+            sync_on_start,
+            sync_interval,
+            sync_mode: self.sync_mode.unwrap_or_default(),
+            expiry_check_interval,
+            nightly_report_at,
+            vat_rate,
+            baht_per_point,
+        })
+    }
+}
+
+/// An interval in minutes, where 0 means never.
+fn minutes(minutes: u64) -> Option<Duration> {
+    match minutes {
+        0 => None,
+        minutes => Some(Duration::from_secs(minutes * 60)),
+    }
+}
+
+fn env(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+fn env_parse<T>(key: &'static str) -> Result<Option<T>, ConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    env(key)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|e| ConfigError::Invalid(key, format!("\"{value}\": {e}")))
+        })
+        .transpose()
+}
+
+impl Cli {
+    /// Reads `.env`, the configuration file and the environment and merges them with the flags.
+    /// Running without a command serves the API.
+    pub fn load(self) -> Result<(Config, Command), ConfigError> {
+        dotenv().ok();
+        let command = self
+            .command
+            .unwrap_or_else(|| Command::Serve(ServeArgs::default()));
+        let mut flags = Layer {
+            database_url: self.database_url,
+            old_database_url: self.old_database_url,
+            pool_size: self.pool_size,
+            sync_mode: self.sync_mode,
+            ..Default::default()
+        };
+        if let Command::Serve(args) = &command {
+            flags.bind = args.bind.clone();
+            flags.sync_on_start = args.sync_on_start;
+            flags.sync_interval_minutes = args.sync_interval_minutes;
+            flags.expiry_check_interval_minutes = args.expiry_check_interval_minutes;
+            flags.nightly_report_at = args.nightly_report_at.clone();
+            flags.vat_rate = args.vat_rate;
+            flags.baht_per_point = args.baht_per_point;
+        }
+
+        let config = flags
+            .or(Layer::env()?)
+            .or(Layer::file(self.config.as_ref())?)
+            .validate()?;
+        Ok((config, command))
+    }
+}
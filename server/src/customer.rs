@@ -0,0 +1,146 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use shared::{Customer, CustomerPayment, PaymentMethod, Role, Statement, User};
+
+use crate::{AppError, Database, DateRange, Owner};
+
+fn validate(customer: &mut Customer) -> Result<(), AppError> {
+    customer.name = customer.name.trim().to_string();
+    customer.phone = customer.phone.trim().to_string();
+    customer.address = customer.address.trim().to_string();
+    if customer.name.is_empty() {
+        return Err(AppError::InvalidInput("customer name is empty".to_string()));
+    }
+    if !customer
+        .phone
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == '-' || c == ' ')
+    {
+        return Err(AppError::InvalidInput(
+            "phone number may only have digits".to_string(),
+        ));
+    }
+    if customer.credit_limit < Decimal::ZERO {
+        return Err(AppError::InvalidInput(
+            "credit limit cannot be negative".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct CustomerSearch {
+    #[serde(default)]
+    keyword: String,
+}
+
+/// Customers whose name or phone number contains the keyword, every customer without one.
+pub async fn get_customers(
+    State(db): State<Database>,
+    Query(search): Query<CustomerSearch>,
+) -> Result<Json<Vec<Customer>>, AppError> {
+    Ok(Json(db.search_customers(search.keyword.trim()).await?))
+}
+
+pub async fn get_customer(
+    State(db): State<Database>,
+    Path(id): Path<u32>,
+) -> Result<Json<Customer>, AppError> {
+    db.select_customer(id)
+        .await?
+        .map(Json)
+        .ok_or(AppError::NotFound)
+}
+
+/// Any user may sign up a customer at the till. Their credit limit is up to the owner, so a new
+/// customer cannot buy on credit until the owner sets one.
+pub async fn post_customer(
+    State(db): State<Database>,
+    Extension(user): Extension<User>,
+    Json(mut customer): Json<Customer>,
+) -> Result<(StatusCode, Json<Customer>), AppError> {
+    if user.role != Role::Owner {
+        customer.credit_limit = Decimal::ZERO;
+    }
+    validate(&mut customer)?;
+    customer.id = db.insert_customer(&customer).await?;
+    customer.balance = Decimal::ZERO;
+    customer.points = 0;
+    Ok((StatusCode::CREATED, Json(customer)))
+}
+
+pub async fn put_customer(
+    State(db): State<Database>,
+    _owner: Owner,
+    Path(id): Path<u32>,
+    Json(mut customer): Json<Customer>,
+) -> Result<Json<Customer>, AppError> {
+    customer.id = id;
+    validate(&mut customer)?;
+    if db.update_customer(&customer).await? == 0 {
+        return Err(AppError::NotFound);
+    }
+    get_customer(State(db), Path(id)).await
+}
+
+/// What the customer owed at the start of the period and every sale on credit, point and
+/// payment during it, for printing at the end of the month.
+pub async fn get_statement(
+    State(db): State<Database>,
+    Path(id): Path<u32>,
+    Query(range): Query<DateRange>,
+) -> Result<Json<Statement>, AppError> {
+    if range.from > range.to {
+        return Err(AppError::InvalidInput(
+            "statement ends before it starts".to_string(),
+        ));
+    }
+    let customer = db.select_customer(id).await?.ok_or(AppError::NotFound)?;
+    let (opening_balance, entries) = db.select_ledger(id, range.from, range.to).await?;
+    Ok(Json(Statement {
+        customer,
+        from: range.from,
+        to: range.to,
+        opening_balance,
+        entries,
+    }))
+}
+
+/// Takes a payment off what the customer owes and answers with the customer as they are after
+/// it. Paying more than the balance leaves the customer in credit for their next purchases.
+pub async fn post_customer_payment(
+    State(db): State<Database>,
+    Extension(user): Extension<User>,
+    Path(id): Path<u32>,
+    Json(mut payment): Json<CustomerPayment>,
+) -> Result<(StatusCode, Json<Customer>), AppError> {
+    if payment.amount <= Decimal::ZERO {
+        return Err(AppError::InvalidInput(
+            "payment must be positive".to_string(),
+        ));
+    }
+    if matches!(
+        payment.method,
+        PaymentMethod::Credit | PaymentMethod::Points
+    ) {
+        return Err(AppError::InvalidInput(format!(
+            "a balance cannot be paid with {}",
+            payment.method.as_str()
+        )));
+    }
+    payment.note = payment.note.trim().to_string();
+    match db.insert_customer_payment(id, &payment, user.id).await {
+        Ok(()) => {}
+        Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => {
+            return Err(AppError::NotFound);
+        }
+        Err(e) => return Err(e.into()),
+    }
+    let customer = db.select_customer(id).await?.ok_or(AppError::NotFound)?;
+    Ok((StatusCode::CREATED, Json(customer)))
+}
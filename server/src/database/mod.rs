@@ -0,0 +1,413 @@
+mod mysql;
+mod sqlite;
+
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::AppError;
+use crate::audit::Actor;
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Item {
+    pub(crate) barcode: String,
+    pub(crate) name: String,
+    pub(crate) cost: Decimal,
+    pub(crate) price: Decimal,
+    pub(crate) quantity: Decimal,
+    pub(crate) unit: Option<shared::Unit>,
+    #[serde(skip)]
+    pub(crate) image: Option<Vec<u8>>,
+    pub(crate) category_id: Option<u32>,
+    pub(crate) tax_class: Option<shared::TaxClass>,
+}
+
+#[derive(Debug)]
+pub(crate) struct ItemHeader {
+    pub(crate) barcode: String,
+    pub(crate) name: String,
+    pub(crate) category_id: Option<u32>,
+}
+
+/// One row written to the catalogue by an import or a sync: an item with the expiry dates to
+/// add to it, or a bulk item under the barcode of the item it contains.
+#[derive(Debug)]
+pub(crate) enum CatalogueEntry {
+    Item(Item, Vec<NaiveDate>),
+    Bulk(String, shared::BulkItem),
+}
+
+/// Why the database refused an entry of the catalogue.
+#[derive(Debug)]
+pub(crate) enum Refusal {
+    /// The entry clashes with a row already there, like a bulk item with the barcode of another.
+    Conflict(String),
+    /// Anything else the database would not take, like a category that doesn't exist.
+    Invalid(String),
+}
+
+impl From<Refusal> for AppError {
+    fn from(refusal: Refusal) -> Self {
+        match refusal {
+            Refusal::Conflict(message) => AppError::Conflict(message),
+            Refusal::Invalid(message) => AppError::InvalidInput(message),
+        }
+    }
+}
+
+/// A row of the catalogue of the old program. Two-way syncs keep a copy of each as it was after
+/// the sync, to tell what changed there since.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LegacyItem {
+    pub(crate) barcode: String,
+    pub(crate) name: String,
+    pub(crate) cost: Decimal,
+    pub(crate) price: Decimal,
+    pub(crate) quantity: i32,
+}
+
+/// Everything the server stores. Each database the server can run on implements it, see
+/// [`Database::connect`] for how one is picked.
+#[async_trait]
+pub(crate) trait Repository: Send + Sync {
+    /// Applies the pending migrations. Refuses to touch a database migrated by a newer server.
+    async fn migrate(&self) -> Result<(), AppError>;
+
+    /// Newest migration this server knows about.
+    fn latest_schema_version(&self) -> i64;
+
+    /// The latest migration applied by sqlx.
+    async fn select_schema_version(&self) -> sqlx::Result<Option<i64>>;
+
+    /// Writes the entries in one transaction and returns what happened to each of them:
+    /// `Ok(true)` when a new row was inserted, `Ok(false)` when an existing one was updated and
+    /// the [`Refusal`] when the database refused the row. Nothing is committed unless `commit`
+    /// is set and every entry was written. The image and category of an item are kept when the
+    /// new values are empty, and expiry dates the item already has are skipped.
+    async fn write_catalogue(
+        &self,
+        entries: &[CatalogueEntry],
+        actor: &Actor,
+        commit: bool,
+    ) -> sqlx::Result<Vec<Result<bool, Refusal>>>;
+
+    async fn search_headers(&self, keyword: &str) -> sqlx::Result<Vec<ItemHeader>>;
+
+    async fn select_items(&self) -> sqlx::Result<Vec<Item>>;
+
+    async fn select_item(&self, barcode: &str) -> sqlx::Result<Option<Item>>;
+
+    async fn select_expire_dates(&self, barcode: &str) -> sqlx::Result<Vec<NaiveDate>>;
+
+    /// Barcode, name and expiry date of stock on the shelf that expires by `until`, soonest
+    /// first.
+    async fn select_expiring(
+        &self,
+        until: NaiveDate,
+    ) -> sqlx::Result<Vec<(String, String, NaiveDate)>>;
+
+    async fn select_bulk_items(&self, ref_barcode: &str) -> sqlx::Result<Vec<shared::BulkItem>>;
+
+    async fn update_item(&self, item: &Item, actor: &Actor) -> sqlx::Result<u64>;
+
+    async fn delete_item(&self, barcode: &str, actor: &Actor) -> sqlx::Result<u64>;
+
+    /// Items whose shelf tag is out of date, by name: their price is not the one last printed,
+    /// or no tag was printed for them yet.
+    async fn select_label_queue(&self) -> sqlx::Result<Vec<shared::LabelQueueItem>>;
+
+    /// Records the prices just printed on shelf tags, which takes the items off the label queue
+    /// until their price changes again.
+    async fn upsert_label_prints(&self, prices: &[(String, Decimal)]) -> sqlx::Result<()>;
+
+    /// Takes the next number of the shop's own barcodes with `prefix`. No two calls get the
+    /// same number, even from two tills at once.
+    async fn next_barcode_number(&self, prefix: &str) -> sqlx::Result<u64>;
+
+    async fn select_categories(&self) -> sqlx::Result<Vec<shared::Category>>;
+
+    async fn insert_category(&self, category: &shared::Category) -> sqlx::Result<u32>;
+
+    async fn update_category(&self, category: &shared::Category) -> sqlx::Result<u64>;
+
+    async fn delete_category(&self, id: u32) -> sqlx::Result<u64>;
+
+    async fn select_promotions(&self) -> sqlx::Result<Vec<shared::Promotion>>;
+
+    async fn insert_promotion(&self, promotion: &shared::Promotion) -> sqlx::Result<u32>;
+
+    /// Replaces the promotion and its items. Returns the number of promotions changed.
+    async fn update_promotion(&self, promotion: &shared::Promotion) -> sqlx::Result<u64>;
+
+    async fn delete_promotion(&self, id: u32) -> sqlx::Result<u64>;
+
+    /// Sales between the dates by category, with revenue net of discounts. Returns come off on
+    /// the day they were made, and only restocked ones give back their cost.
+    async fn select_category_sales(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> sqlx::Result<Vec<shared::CategorySales>>;
+
+    /// Takings between the dates by payment method less what was refunded with it, for the
+    /// methods that took or refunded any.
+    async fn select_payment_sales(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> sqlx::Result<Vec<shared::PaymentSales>>;
+
+    /// Sales and their VAT between the dates by month less returns, see [`vat_summaries`].
+    async fn select_vat_sales(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> sqlx::Result<Vec<shared::VatSummary>>;
+
+    async fn insert_stock_take(&self) -> sqlx::Result<u32>;
+
+    async fn select_stock_takes(&self) -> sqlx::Result<Vec<shared::StockTake>>;
+
+    async fn select_stock_take(&self, id: u32) -> sqlx::Result<Option<shared::StockTake>>;
+
+    /// Stores the counts in one transaction, replacing earlier counts of the same items. Each
+    /// line keeps the system quantity of the item when it was counted, or recounted to a
+    /// different value, since the shop keeps selling during the count.
+    async fn upsert_stock_counts(
+        &self,
+        stock_take_id: u32,
+        counts: &[shared::StockCount],
+    ) -> sqlx::Result<()>;
+
+    /// Lines of a stock-take. Open sessions compare against the live item quantity and cost,
+    /// while committed ones use the values captured at commit time.
+    async fn select_stock_take_lines(
+        &self,
+        stock_take_id: u32,
+    ) -> sqlx::Result<Vec<shared::StockTakeLine>>;
+
+    /// Marks the stock-take committed, records an adjustment movement for every line whose
+    /// count differs from the system quantity and adds that variance to the item quantities, so
+    /// sales made since the count still come off.
+    /// Returns `false` when the stock-take does not exist or was already committed.
+    async fn commit_stock_take(&self, stock_take_id: u32, actor: &Actor) -> sqlx::Result<bool>;
+
+    async fn count_users(&self) -> sqlx::Result<i64>;
+
+    async fn select_users(&self) -> sqlx::Result<Vec<shared::User>>;
+
+    async fn insert_user(
+        &self,
+        name: &str,
+        password_hash: &str,
+        role: shared::Role,
+    ) -> sqlx::Result<u32>;
+
+    /// The user with this name together with their password hash.
+    async fn select_credential(&self, name: &str) -> sqlx::Result<Option<(shared::User, String)>>;
+
+    async fn insert_session(&self, token: &str, user_id: u32, hours: i64) -> sqlx::Result<()>;
+
+    async fn select_session_user(&self, token: &str) -> sqlx::Result<Option<shared::User>>;
+
+    async fn delete_session(&self, token: &str) -> sqlx::Result<()>;
+
+    async fn select_audit_log(&self, barcode: &str) -> sqlx::Result<Vec<shared::AuditEntry>>;
+
+    /// When each item last changed, by anyone.
+    async fn select_item_updated_at(&self) -> sqlx::Result<Vec<(String, NaiveDateTime)>>;
+
+    /// The rows of the old program after the last two-way sync, with the time of that sync.
+    async fn select_legacy_items(&self) -> sqlx::Result<Vec<(LegacyItem, NaiveDateTime)>>;
+
+    /// Records a row of the old program as it is after a two-way sync, at the current time.
+    async fn upsert_legacy_item(&self, item: &LegacyItem) -> sqlx::Result<()>;
+
+    /// Writes a two-way sync of an item in one transaction: the name, cost and price of `item`,
+    /// `quantity_change` added to the stock so sales made meanwhile still count, the expiry date
+    /// and `legacy` as the row of the old program. `push` writes the change to the old program
+    /// last, and nothing is committed when it fails.
+    async fn merge_legacy_item(
+        &self,
+        item: &LegacyItem,
+        quantity_change: Decimal,
+        expire_date: Option<NaiveDate>,
+        legacy: &LegacyItem,
+        actor: &Actor,
+        push: BoxFuture<'_, sqlx::Result<()>>,
+    ) -> sqlx::Result<()>;
+
+    async fn select_receipt_id(&self, key: &str) -> sqlx::Result<Option<u32>>;
+
+    /// Records a sale with its discounts and payments at the current cost of each item and takes the sold
+    /// quantities off the shelf. Items deleted since the till sold them are recorded all the same,
+    /// see `sale_cost`, as the money was taken.
+    /// What the customer of the sale owes and earned goes into their ledger, see [`ledger_change`].
+    async fn insert_receipt(&self, receipt: &shared::Receipt, user_id: u32) -> sqlx::Result<u32>;
+
+    /// A recorded sale with the names of its items as they are now, the points it earned and
+    /// its returns, oldest first.
+    async fn select_sale(&self, receipt_id: u32) -> sqlx::Result<Option<shared::SaleRecord>>;
+
+    /// Records a return at the cost each line was sold at. Restocked lines go back on the shelf
+    /// while wasted ones do not, and what the customer of the sale gets back goes into their
+    /// ledger, see [`return_ledger_change`]. Returns `None` without writing anything when a line
+    /// was not sold on the receipt, or the receipt no longer has `returns` returns because
+    /// another one was recorded since it was read.
+    async fn insert_return(
+        &self,
+        sale_return: &shared::SaleReturn,
+        customer_id: Option<u32>,
+        returns: usize,
+        user_id: u32,
+    ) -> sqlx::Result<Option<u32>>;
+
+    async fn select_tax_invoice(&self, receipt_id: u32)
+    -> sqlx::Result<Option<shared::TaxInvoice>>;
+
+    /// Issues the tax invoice of a receipt under the next number of its kind. Fails with a
+    /// unique violation when the receipt already has one, or another invoice took the number.
+    async fn insert_tax_invoice(
+        &self,
+        receipt_id: u32,
+        buyer: Option<&shared::Buyer>,
+    ) -> sqlx::Result<()>;
+
+    /// Customers whose name or phone contains `keyword`, by name, with their balance and points.
+    async fn search_customers(&self, keyword: &str) -> sqlx::Result<Vec<shared::Customer>>;
+
+    async fn select_customer(&self, id: u32) -> sqlx::Result<Option<shared::Customer>>;
+
+    async fn insert_customer(&self, customer: &shared::Customer) -> sqlx::Result<u32>;
+
+    async fn update_customer(&self, customer: &shared::Customer) -> sqlx::Result<u64>;
+
+    /// What the customer owed before `from`, and the entries of their ledger between the dates,
+    /// oldest first.
+    async fn select_ledger(
+        &self,
+        customer_id: u32,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> sqlx::Result<(Decimal, Vec<shared::LedgerEntry>)>;
+
+    /// Takes a payment off the balance of the customer. Fails with a foreign key violation when
+    /// there is no such customer.
+    async fn insert_customer_payment(
+        &self,
+        customer_id: u32,
+        payment: &shared::CustomerPayment,
+        user_id: u32,
+    ) -> sqlx::Result<()>;
+}
+
+/// The database of the shop, shared by every request.
+#[derive(Clone)]
+pub(crate) struct Database(Arc<dyn Repository>);
+
+impl Deref for Database {
+    type Target = dyn Repository;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl Database {
+    /// Connects to MySQL/MariaDB for `mysql://` and `mariadb://` urls and to SQLite for
+    /// `sqlite:` ones, such as `sqlite://shop.db` or `sqlite::memory:`.
+    pub(crate) async fn connect(url: &str, pool_size: u32) -> Result<Database, AppError> {
+        let repository: Arc<dyn Repository> = if url.starts_with("sqlite:") {
+            Arc::new(sqlite::SqliteRepository::connect(url, pool_size).await?)
+        } else {
+            Arc::new(mysql::MySqlRepository::connect(url, pool_size).await?)
+        };
+        Ok(Database(repository))
+    }
+}
+
+/// Tells an entry of the catalogue the database refused from a database that failed, which is
+/// passed on.
+fn refusal(error: sqlx::Error) -> sqlx::Result<Refusal> {
+    match error {
+        sqlx::Error::Database(e) if e.is_unique_violation() => {
+            Ok(Refusal::Conflict(e.message().to_string()))
+        }
+        sqlx::Error::Database(e) => Ok(Refusal::Invalid(e.message().to_string())),
+        e => Err(e),
+    }
+}
+
+/// Shared by the migrations of every database, `applied` is the newest migration in it.
+fn check_schema_version(applied: Option<i64>, latest: i64) -> Result<(), AppError> {
+    match applied {
+        Some(applied) if applied > latest => Err(AppError::SchemaTooNew(applied, latest)),
+        _ => Ok(()),
+    }
+}
+
+/// Adds up sales as `(month, tax class, amount, VAT)` into one summary per month, oldest first.
+/// Discounts and returns come in as negative amounts.
+fn vat_summaries(
+    sales: impl IntoIterator<Item = (NaiveDate, shared::TaxClass, Decimal, Decimal)>,
+) -> Vec<shared::VatSummary> {
+    let mut summaries: BTreeMap<NaiveDate, shared::VatSummary> = BTreeMap::new();
+    for (month, class, amount, vat) in sales {
+        let summary = summaries
+            .entry(month)
+            .or_insert_with(|| shared::VatSummary {
+                month,
+                ..Default::default()
+            });
+        match class {
+            shared::TaxClass::Standard => summary.standard += amount,
+            shared::TaxClass::Zero => summary.zero_rated += amount,
+            shared::TaxClass::Exempt => summary.exempt += amount,
+        }
+        summary.vat += vat;
+    }
+    summaries.into_values().collect()
+}
+
+/// The kind of a tax invoice as stored, which numbers it.
+fn invoice_kind(buyer: Option<&shared::Buyer>) -> &'static str {
+    match buyer {
+        Some(_) => "full",
+        None => "abbreviated",
+    }
+}
+
+/// What a sale changes in the ledger of its customer as `(customer, amount owed, points)`, or
+/// nothing when it neither was on credit nor earned or spent points.
+fn ledger_change(receipt: &shared::Receipt) -> Option<(u32, Decimal, i64)> {
+    let customer_id = receipt.customer_id?;
+    let amount = receipt.paid_with(shared::PaymentMethod::Credit);
+    let points = receipt.points - receipt.redeemed_points();
+    (!amount.is_zero() || points != 0).then_some((customer_id, amount, points))
+}
+
+/// What a return changes in the ledger of the customer of the sale as `(customer, amount owed,
+/// points)`: the credit refunded comes off what they owe and the points go as the return says.
+fn return_ledger_change(
+    sale_return: &shared::SaleReturn,
+    customer_id: Option<u32>,
+) -> Option<(u32, Decimal, i64)> {
+    let customer_id = customer_id?;
+    let amount = -sale_return
+        .refunds
+        .iter()
+        .filter(|refund| refund.method == shared::PaymentMethod::Credit)
+        .map(|refund| refund.amount)
+        .sum::<Decimal>();
+    (!amount.is_zero() || sale_return.points != 0).then_some((
+        customer_id,
+        amount,
+        sale_return.points,
+    ))
+}
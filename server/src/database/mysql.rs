@@ -0,0 +1,2049 @@
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::json;
+use sqlx::migrate::Migrator;
+use sqlx::mysql::MySqlPoolOptions;
+use sqlx::{FromRow, MySqlConnection, MySqlPool};
+
+use super::{
+    CatalogueEntry, Item, ItemHeader, LegacyItem, Refusal, Repository, check_schema_version,
+    invoice_kind, ledger_change, refusal, return_ledger_change, vat_summaries,
+};
+use crate::AppError;
+use crate::audit::Actor;
+
+static MIGRATOR: Migrator = sqlx::migrate!("../migrations/mysql");
+
+/// SHA-384 checksums of the scripts that still had `USE sunminimart;` when they were applied by
+/// hand with sqlx-cli. Only rows with one of these are taken for the scripts of today, any
+/// other difference is a script edited after it was applied and stops the migration.
+const MANUAL_MIGRATIONS: [(i64, &str); 7] = [
+    (
+        20250614082934,
+        "24d98cab1914862086c0d249cfc606c11af0f4a748e56d9031c6c6f72f1433559913d0ba89973925da5d1d788987aacf",
+    ),
+    (
+        20250614082942,
+        "d6ba60ea1c0408c48bd8c6c64cf4c82bdbac16d507347a1533c832e90f860dd5459c97b534fff21287533f88bffd2502",
+    ),
+    (
+        20261019090000,
+        "8df739cedbf5a9fb7263a6975e83d0e30683781db9b2115cea653a6d9e2f59c6717534f25eddbad900f514e1eb2a5b57",
+    ),
+    (
+        20261019100000,
+        "80dca89ef659b7dd2bdb10892882fd349f31e27f75420d6ee9b7885a18c41e4e186b573291e24c2cc996f56b4f6ea502",
+    ),
+    (
+        20261019110000,
+        "b1527a494071ea491eedc4175205488de43d473cbda51edbf66f5733017a143429f7cc164b7953c3feddb5d4e7a22818",
+    ),
+    (
+        20261019120000,
+        "9282055ac72d980eaaff127a83d2c7f91232b59b1f07f0427e2cf13f0d564448086779a830b10950ec6da80cb7d083ad",
+    ),
+    (
+        20261019130000,
+        "a4ffa22832a5c04c7c475a8fe45cdcb447a6c8c1d592ab663620f75bf068e41adb262500f9ae227d3363900d62d258de",
+    ),
+];
+
+/// The database of the shop in MySQL or MariaDB, see `compose.yaml`.
+pub(crate) struct MySqlRepository {
+    pool: MySqlPool,
+}
+
+impl MySqlRepository {
+    pub(crate) async fn connect(url: &str, pool_size: u32) -> sqlx::Result<Self> {
+        let pool = MySqlPoolOptions::new()
+            .max_connections(pool_size)
+            .connect(url)
+            .await?;
+        Ok(MySqlRepository { pool })
+    }
+}
+
+/// Records a change of a catalogue row in the audit log, unless nothing changed. A missing
+/// `before` is an insert and a missing `after` a delete.
+async fn audit<T: Serialize>(
+    connection: &mut MySqlConnection,
+    actor: &Actor,
+    entity: &str,
+    barcode: &str,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> sqlx::Result<()> {
+    let before = before.map(|before| serde_json::to_value(before).unwrap_or_default());
+    let after = after.map(|after| serde_json::to_value(after).unwrap_or_default());
+    let action = match (&before, &after) {
+        (Some(before), Some(after)) if before == after => return Ok(()),
+        (None, _) => "insert",
+        (_, None) => "delete",
+        _ => "update",
+    };
+
+    sqlx::query(
+        "
+        INSERT INTO audit_log (entity, barcode, action, before_value, after_value, user_id, source)
+        VALUES (?, ?, ?, ?, ?, ?, ?);
+        ",
+    )
+    .bind(entity)
+    .bind(barcode)
+    .bind(action)
+    .bind(before.map(|before| before.to_string()))
+    .bind(after.map(|after| after.to_string()))
+    .bind(actor.user_id)
+    .bind(actor.source.as_str())
+    .execute(connection)
+    .await?;
+    Ok(())
+}
+
+fn rule(promotion: &shared::Promotion) -> sqlx::Result<String> {
+    serde_json::to_string(&promotion.rule).map_err(|e| sqlx::Error::Encode(e.into()))
+}
+
+async fn insert_promotion_items(
+    connection: &mut MySqlConnection,
+    promotion_id: u32,
+    barcodes: &[String],
+) -> sqlx::Result<()> {
+    for barcode in barcodes {
+        sqlx::query("INSERT INTO promotion_items (promotion_id, barcode) VALUES (?, ?);")
+            .bind(promotion_id)
+            .bind(barcode)
+            .execute(&mut *connection)
+            .await?;
+    }
+    Ok(())
+}
+
+#[derive(FromRow)]
+struct ItemRow {
+    barcode: String,
+    name: String,
+    cost: Decimal,
+    price: Decimal,
+    quantity: Decimal,
+    unit: String,
+    image: Option<Vec<u8>>,
+    category_id: Option<u32>,
+    tax_class: String,
+}
+
+impl From<ItemRow> for Item {
+    fn from(row: ItemRow) -> Self {
+        Item {
+            barcode: row.barcode,
+            name: row.name,
+            cost: row.cost,
+            price: row.price,
+            quantity: row.quantity.normalize(),
+            unit: Some(row.unit.parse().unwrap_or_default()),
+            image: row.image,
+            category_id: row.category_id,
+            tax_class: Some(row.tax_class.parse().unwrap_or_default()),
+        }
+    }
+}
+
+/// The customers matching `id` when given and `keyword` otherwise, with what their ledger adds
+/// up to.
+async fn select_customers(
+    pool: &MySqlPool,
+    id: Option<u32>,
+    keyword: &str,
+) -> sqlx::Result<Vec<shared::Customer>> {
+    let pattern = format!("%{keyword}%");
+    let rows: Vec<(u32, String, String, String, Decimal, Decimal, i64)> = sqlx::query_as(
+        "
+        SELECT customers.id, customers.name, customers.phone, customers.address,
+            customers.credit_limit,
+            COALESCE(SUM(customer_ledger.amount), 0),
+            CAST(COALESCE(SUM(customer_ledger.points), 0) AS SIGNED)
+        FROM customers
+        LEFT JOIN customer_ledger ON customer_ledger.customer_id = customers.id
+        WHERE customers.id = ?
+            OR (? IS NULL AND (customers.name LIKE ? OR customers.phone LIKE ?))
+        GROUP BY customers.id
+        ORDER BY customers.name, customers.id;
+        ",
+    )
+    .bind(id)
+    .bind(id)
+    .bind(&pattern)
+    .bind(&pattern)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(
+            |(id, name, phone, address, credit_limit, balance, points)| shared::Customer {
+                id,
+                name,
+                phone,
+                address,
+                credit_limit,
+                balance,
+                points,
+            },
+        )
+        .collect())
+}
+
+/// The cost a line of a sale is recorded at: the cost of the item, or for an item deleted since
+/// a till that was offline sold it, the cost it was last sold at.
+async fn sale_cost(connection: &mut MySqlConnection, barcode: &str) -> sqlx::Result<Decimal> {
+    let cost: Option<Decimal> = sqlx::query_scalar("SELECT cost FROM items WHERE barcode = ?;")
+        .bind(barcode)
+        .fetch_optional(&mut *connection)
+        .await?;
+    let cost = match cost {
+        Some(cost) => Some(cost),
+        None => {
+            sqlx::query_scalar(
+                "SELECT cost FROM receipt_items WHERE barcode = ? ORDER BY id DESC LIMIT 1;",
+            )
+            .bind(barcode)
+            .fetch_optional(&mut *connection)
+            .await?
+        }
+    };
+    Ok(cost.unwrap_or_default())
+}
+
+async fn upsert_legacy(connection: &mut MySqlConnection, item: &LegacyItem) -> sqlx::Result<()> {
+    sqlx::query(
+        "
+        INSERT INTO legacy_items (barcode, name, cost, price, quantity)
+        VALUES (?, ?, ?, ?, ?)
+        ON DUPLICATE KEY UPDATE
+        name = VALUES(name),
+        cost = VALUES(cost),
+        price = VALUES(price),
+        quantity = VALUES(quantity),
+        synced_at = NOW();
+        ",
+    )
+    .bind(&item.barcode)
+    .bind(&item.name)
+    .bind(item.cost)
+    .bind(item.price)
+    .bind(item.quantity)
+    .execute(connection)
+    .await?;
+    Ok(())
+}
+
+async fn fetch_item(connection: &mut MySqlConnection, barcode: &str) -> sqlx::Result<Option<Item>> {
+    let row: Option<ItemRow> = sqlx::query_as(
+        "
+        SELECT barcode, name, cost, price, quantity, unit, image, category_id, tax_class
+        FROM items
+        WHERE barcode = ?;
+        ",
+    )
+    .bind(barcode)
+    .fetch_optional(connection)
+    .await?;
+    Ok(row.map(Item::from))
+}
+
+/// Inserts the item or updates the existing one with the same barcode, returning `true` when a
+/// new row was inserted. The image, category and tax class are kept when the new values are
+/// empty.
+async fn upsert_item(
+    connection: &mut MySqlConnection,
+    item: &Item,
+    actor: &Actor,
+) -> sqlx::Result<bool> {
+    let before = fetch_item(&mut *connection, &item.barcode).await?;
+
+    sqlx::query(
+        "
+            INSERT INTO items (barcode, name, cost, price, quantity, unit, image, category_id,
+                tax_class)
+            VALUES (?, ?, ?, ?, ?, COALESCE(?, 'piece'), ?, ?, COALESCE(?, 'standard'))
+            ON DUPLICATE KEY UPDATE
+            name = VALUES(name),
+            cost = VALUES(cost),
+            price = VALUES(price),
+            quantity = VALUES(quantity),
+            unit = COALESCE(?, unit),
+            image = COALESCE(VALUES(image), image),
+            category_id = COALESCE(VALUES(category_id), category_id),
+            tax_class = COALESCE(?, tax_class);
+        ",
+    )
+    .bind(&item.barcode)
+    .bind(&item.name)
+    .bind(item.cost)
+    .bind(item.price)
+    .bind(item.quantity)
+    .bind(item.unit.map(|unit| unit.as_str()))
+    .bind(&item.image)
+    .bind(item.category_id)
+    .bind(item.tax_class.map(|class| class.as_str()))
+    .bind(item.unit.map(|unit| unit.as_str()))
+    .bind(item.tax_class.map(|class| class.as_str()))
+    .execute(&mut *connection)
+    .await?;
+
+    let after = Item {
+        unit: item
+            .unit
+            .or(before.as_ref().and_then(|before| before.unit))
+            .or(Some(shared::Unit::Piece)),
+        category_id: item
+            .category_id
+            .or(before.as_ref().and_then(|before| before.category_id)),
+        tax_class: item
+            .tax_class
+            .or(before.as_ref().and_then(|before| before.tax_class))
+            .or(Some(shared::TaxClass::Standard)),
+        ..item.clone()
+    };
+    audit(
+        connection,
+        actor,
+        "item",
+        &item.barcode,
+        before.as_ref(),
+        Some(&after),
+    )
+    .await?;
+
+    Ok(before.is_none())
+}
+
+fn bulk_item_snapshot(bulk_item: &shared::BulkItem) -> serde_json::Value {
+    json!({
+        "barcode": bulk_item.barcode,
+        "name": bulk_item.name,
+        "price": bulk_item.price,
+        "quantity": bulk_item.quantity,
+    })
+}
+
+/// Inserts or updates the bulk item with the same name under `ref_barcode`, returning `true`
+/// when a new row was inserted.
+async fn upsert_bulk_item(
+    connection: &mut MySqlConnection,
+    ref_barcode: &str,
+    bulk_item: &shared::BulkItem,
+    actor: &Actor,
+) -> sqlx::Result<bool> {
+    let before: Option<(u32, Option<String>, String, Decimal, Decimal)> = sqlx::query_as(
+        "
+        SELECT id, barcode, name, price, quantity FROM bulk_items
+        WHERE ref_barcode = ? AND name = ?;
+        ",
+    )
+    .bind(ref_barcode)
+    .bind(&bulk_item.name)
+    .fetch_optional(&mut *connection)
+    .await?;
+
+    match &before {
+        Some((id, ..)) => {
+            sqlx::query(
+                "
+                UPDATE bulk_items SET barcode = ?, price = ?, quantity = ?
+                WHERE id = ?;
+                ",
+            )
+            .bind(&bulk_item.barcode)
+            .bind(bulk_item.price)
+            .bind(bulk_item.quantity)
+            .bind(id)
+            .execute(&mut *connection)
+            .await?;
+        }
+        None => {
+            sqlx::query(
+                "
+                INSERT INTO bulk_items (barcode, ref_barcode, name, price, quantity)
+                VALUES (?, ?, ?, ?, ?);
+                ",
+            )
+            .bind(&bulk_item.barcode)
+            .bind(ref_barcode)
+            .bind(&bulk_item.name)
+            .bind(bulk_item.price)
+            .bind(bulk_item.quantity)
+            .execute(&mut *connection)
+            .await?;
+        }
+    }
+
+    let before = before.map(|(_, barcode, name, price, quantity)| {
+        bulk_item_snapshot(&shared::BulkItem {
+            barcode,
+            name,
+            price,
+            quantity: quantity.normalize(),
+            image: None,
+        })
+    });
+    audit(
+        connection,
+        actor,
+        "bulk_item",
+        ref_barcode,
+        before.as_ref(),
+        Some(&bulk_item_snapshot(bulk_item)),
+    )
+    .await?;
+
+    Ok(before.is_none())
+}
+
+/// Adds an expiry date to an item unless the item already has it.
+async fn insert_expire_date(
+    connection: &mut MySqlConnection,
+    ref_barcode: &str,
+    expire_date: NaiveDate,
+    actor: &Actor,
+) -> sqlx::Result<()> {
+    let result = sqlx::query(
+        "
+            INSERT INTO expire_dates (ref_barcode, expire_date)
+            SELECT ?, ? FROM DUAL
+            WHERE NOT EXISTS (
+                SELECT 1 FROM expire_dates WHERE ref_barcode = ? AND expire_date = ?
+            );
+        ",
+    )
+    .bind(ref_barcode)
+    .bind(expire_date)
+    .bind(ref_barcode)
+    .bind(expire_date)
+    .execute(&mut *connection)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        audit(
+            connection,
+            actor,
+            "expire_date",
+            ref_barcode,
+            None,
+            Some(&json!({ "expire_date": expire_date })),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn upsert_stock_count(
+    connection: &mut MySqlConnection,
+    stock_take_id: u32,
+    count: &shared::StockCount,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "
+        INSERT INTO stock_take_lines (stock_take_id, barcode, system_quantity, counted_quantity)
+        VALUES (?, ?, (SELECT quantity FROM items WHERE barcode = ?), ?)
+        ON DUPLICATE KEY UPDATE
+        system_quantity = IF(
+            counted_quantity = VALUES(counted_quantity),
+            system_quantity,
+            VALUES(system_quantity)
+        ),
+        counted_quantity = VALUES(counted_quantity);
+        ",
+    )
+    .bind(stock_take_id)
+    .bind(&count.barcode)
+    .bind(&count.barcode)
+    .bind(count.counted)
+    .execute(connection)
+    .await?;
+    Ok(())
+}
+
+async fn write_item(
+    connection: &mut MySqlConnection,
+    item: &Item,
+    expire_dates: &[NaiveDate],
+    actor: &Actor,
+) -> sqlx::Result<bool> {
+    let inserted = upsert_item(&mut *connection, item, actor).await?;
+    for expire_date in expire_dates {
+        insert_expire_date(&mut *connection, &item.barcode, *expire_date, actor).await?;
+    }
+    Ok(inserted)
+}
+
+#[derive(FromRow)]
+struct UserRow {
+    id: u32,
+    name: String,
+    role: String,
+}
+
+impl From<UserRow> for shared::User {
+    fn from(row: UserRow) -> Self {
+        shared::User {
+            id: row.id,
+            name: row.name,
+            role: row.role.parse().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(FromRow)]
+struct TaxInvoiceRow {
+    receipt_id: u32,
+    sequence: u32,
+    buyer_name: Option<String>,
+    buyer_tax_id: Option<String>,
+    buyer_address: Option<String>,
+    buyer_branch: Option<String>,
+    issued_at: NaiveDateTime,
+    items: Decimal,
+    discount: Decimal,
+    vat: Decimal,
+}
+
+#[async_trait]
+impl Repository for MySqlRepository {
+    async fn migrate(&self) -> Result<(), AppError> {
+        let applied = match sqlx::query_as::<_, (i64, Vec<u8>)>(
+            "SELECT version, checksum FROM _sqlx_migrations WHERE success",
+        )
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(applied) => applied,
+            // Table not found, nothing has been migrated yet
+            Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("42S02") => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        check_schema_version(
+            applied.iter().map(|(version, _)| *version).max(),
+            self.latest_schema_version(),
+        )?;
+
+        for (version, checksum) in &applied {
+            let Some(migration) = MIGRATOR.iter().find(|m| m.version == *version) else {
+                continue;
+            };
+            let hex: String = checksum.iter().map(|byte| format!("{byte:02x}")).collect();
+            if *checksum != *migration.checksum
+                && MANUAL_MIGRATIONS.contains(&(*version, hex.as_str()))
+            {
+                sqlx::query("UPDATE _sqlx_migrations SET checksum = ? WHERE version = ?")
+                    .bind(&*migration.checksum)
+                    .bind(version)
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+
+        MIGRATOR.run(&self.pool).await?;
+        Ok(())
+    }
+
+    fn latest_schema_version(&self) -> i64 {
+        MIGRATOR.iter().map(|m| m.version).max().unwrap_or(0)
+    }
+
+    async fn write_catalogue(
+        &self,
+        entries: &[CatalogueEntry],
+        actor: &Actor,
+        commit: bool,
+    ) -> sqlx::Result<Vec<Result<bool, Refusal>>> {
+        let mut transaction = self.pool.begin().await?;
+        let mut results = Vec::new();
+        for entry in entries {
+            let result = match entry {
+                CatalogueEntry::Item(item, expire_dates) => {
+                    write_item(&mut transaction, item, expire_dates, actor).await
+                }
+                CatalogueEntry::Bulk(ref_barcode, bulk_item) => {
+                    upsert_bulk_item(&mut transaction, ref_barcode, bulk_item, actor).await
+                }
+            };
+            results.push(match result {
+                Ok(inserted) => Ok(inserted),
+                Err(e) => Err(refusal(e)?),
+            });
+        }
+
+        if commit && results.iter().all(Result::is_ok) {
+            transaction.commit().await?;
+        } else {
+            transaction.rollback().await?;
+        }
+        Ok(results)
+    }
+
+    async fn upsert_stock_counts(
+        &self,
+        stock_take_id: u32,
+        counts: &[shared::StockCount],
+    ) -> sqlx::Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        for count in counts {
+            upsert_stock_count(&mut transaction, stock_take_id, count).await?;
+        }
+        transaction.commit().await
+    }
+
+    async fn search_headers(&self, keyword: &str) -> sqlx::Result<Vec<ItemHeader>> {
+        let pattern = format!("%{keyword}%");
+        let rows: Vec<(String, String, Option<u32>)> = sqlx::query_as(
+            "
+            SELECT barcode, name, category_id FROM items
+            WHERE barcode LIKE ? OR name LIKE ?;
+            ",
+        )
+        .bind(&pattern)
+        .bind(&pattern)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(barcode, name, category_id)| ItemHeader {
+                barcode,
+                name,
+                category_id,
+            })
+            .collect())
+    }
+
+    async fn select_items(&self) -> sqlx::Result<Vec<Item>> {
+        let rows: Vec<ItemRow> = sqlx::query_as(
+            "
+            SELECT barcode, name, cost, price, quantity, unit, image, category_id, tax_class
+            FROM items
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Item::from).collect())
+    }
+
+    async fn select_expire_dates(&self, barcode: &str) -> sqlx::Result<Vec<NaiveDate>> {
+        sqlx::query_scalar(
+            "
+            SELECT expire_date FROM expire_dates
+            WHERE ref_barcode = ?;
+            ",
+        )
+        .bind(barcode)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn select_expiring(
+        &self,
+        until: NaiveDate,
+    ) -> sqlx::Result<Vec<(String, String, NaiveDate)>> {
+        sqlx::query_as(
+            "
+            SELECT items.barcode, items.name, expire_dates.expire_date FROM expire_dates
+            JOIN items ON items.barcode = expire_dates.ref_barcode
+            WHERE expire_dates.expire_date <= ? AND items.quantity > 0
+            ORDER BY expire_dates.expire_date, items.name;
+            ",
+        )
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn select_bulk_items(&self, ref_barcode: &str) -> sqlx::Result<Vec<shared::BulkItem>> {
+        let rows: Vec<(Option<String>, String, Decimal, Decimal, Option<Vec<u8>>)> =
+            sqlx::query_as(
+                "
+            SELECT barcode, name, price, quantity, image FROM bulk_items
+            WHERE ref_barcode = ?;
+            ",
+            )
+            .bind(ref_barcode)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(barcode, name, price, quantity, image)| shared::BulkItem {
+                barcode,
+                name,
+                price,
+                quantity: quantity.normalize(),
+                image,
+            })
+            .collect())
+    }
+
+    async fn select_categories(&self) -> sqlx::Result<Vec<shared::Category>> {
+        let rows: Vec<(u32, Option<u32>, String)> =
+            sqlx::query_as("SELECT id, parent_id, name FROM categories")
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(id, parent_id, name)| shared::Category {
+                id,
+                parent_id,
+                name,
+            })
+            .collect())
+    }
+
+    async fn insert_category(&self, category: &shared::Category) -> sqlx::Result<u32> {
+        let result = sqlx::query(
+            "
+            INSERT INTO categories (parent_id, name)
+            VALUES (?, ?);
+            ",
+        )
+        .bind(category.parent_id)
+        .bind(&category.name)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_id() as u32)
+    }
+
+    async fn update_category(&self, category: &shared::Category) -> sqlx::Result<u64> {
+        let result = sqlx::query(
+            "
+            UPDATE categories SET parent_id = ?, name = ?
+            WHERE id = ?;
+            ",
+        )
+        .bind(category.parent_id)
+        .bind(&category.name)
+        .bind(category.id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_category(&self, id: u32) -> sqlx::Result<u64> {
+        let result = sqlx::query("DELETE FROM categories WHERE id = ?;")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn select_promotions(&self) -> sqlx::Result<Vec<shared::Promotion>> {
+        let rows: Vec<(
+            u32,
+            String,
+            String,
+            Option<NaiveDateTime>,
+            Option<NaiveDateTime>,
+        )> = sqlx::query_as(
+            "
+            SELECT id, name, CAST(rule AS CHAR), starts_at, ends_at
+            FROM promotions
+            ORDER BY id;
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let items: Vec<(u32, String)> =
+            sqlx::query_as("SELECT promotion_id, barcode FROM promotion_items ORDER BY barcode")
+                .fetch_all(&self.pool)
+                .await?;
+
+        rows.into_iter()
+            .map(|(id, name, rule, starts_at, ends_at)| {
+                Ok(shared::Promotion {
+                    id,
+                    name,
+                    rule: serde_json::from_str(&rule).map_err(|e| sqlx::Error::Decode(e.into()))?,
+                    barcodes: items
+                        .iter()
+                        .filter(|(promotion_id, _)| *promotion_id == id)
+                        .map(|(_, barcode)| barcode.clone())
+                        .collect(),
+                    starts_at,
+                    ends_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn insert_promotion(&self, promotion: &shared::Promotion) -> sqlx::Result<u32> {
+        let mut transaction = self.pool.begin().await?;
+        let id = sqlx::query(
+            "INSERT INTO promotions (name, rule, starts_at, ends_at) VALUES (?, ?, ?, ?);",
+        )
+        .bind(&promotion.name)
+        .bind(rule(promotion)?)
+        .bind(promotion.starts_at)
+        .bind(promotion.ends_at)
+        .execute(&mut *transaction)
+        .await?
+        .last_insert_id() as u32;
+        insert_promotion_items(&mut transaction, id, &promotion.barcodes).await?;
+        transaction.commit().await?;
+        Ok(id)
+    }
+
+    async fn update_promotion(&self, promotion: &shared::Promotion) -> sqlx::Result<u64> {
+        let mut transaction = self.pool.begin().await?;
+        let result = sqlx::query(
+            "UPDATE promotions SET name = ?, rule = ?, starts_at = ?, ends_at = ? WHERE id = ?;",
+        )
+        .bind(&promotion.name)
+        .bind(rule(promotion)?)
+        .bind(promotion.starts_at)
+        .bind(promotion.ends_at)
+        .bind(promotion.id)
+        .execute(&mut *transaction)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(0);
+        }
+        sqlx::query("DELETE FROM promotion_items WHERE promotion_id = ?;")
+            .bind(promotion.id)
+            .execute(&mut *transaction)
+            .await?;
+        insert_promotion_items(&mut transaction, promotion.id, &promotion.barcodes).await?;
+        transaction.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_promotion(&self, id: u32) -> sqlx::Result<u64> {
+        let result = sqlx::query("DELETE FROM promotions WHERE id = ?;")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn select_category_sales(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> sqlx::Result<Vec<shared::CategorySales>> {
+        let rows: Vec<(Option<u32>, Option<String>, Decimal, Decimal, Decimal)> = sqlx::query_as(
+            "
+            SELECT items.category_id, categories.name, SUM(sales.quantity), SUM(sales.cost),
+                SUM(sales.revenue)
+            FROM (
+                SELECT receipts.created_at, receipt_items.barcode, receipt_items.quantity,
+                    ROUND(receipt_items.cost * receipt_items.quantity, 2) AS cost,
+                    ROUND(receipt_items.price * receipt_items.quantity, 2) AS revenue
+                FROM receipt_items
+                JOIN receipts ON receipts.id = receipt_items.receipt_id
+                UNION ALL
+                SELECT receipts.created_at, receipt_discounts.barcode, 0, 0,
+                    -receipt_discounts.amount
+                FROM receipt_discounts
+                JOIN receipts ON receipts.id = receipt_discounts.receipt_id
+                UNION ALL
+                -- Wasted goods were lost, so they still cost what they cost
+                SELECT returns.created_at, return_items.barcode,
+                    -return_items.quantity,
+                    CASE return_items.disposition
+                        WHEN 'restock' THEN -ROUND(return_items.cost * return_items.quantity, 2)
+                        ELSE 0
+                    END,
+                    -return_items.refund
+                FROM return_items
+                JOIN returns ON returns.id = return_items.return_id
+            ) AS sales
+            JOIN items ON items.barcode = sales.barcode
+            LEFT JOIN categories ON categories.id = items.category_id
+            WHERE DATE(sales.created_at) BETWEEN ? AND ?
+            GROUP BY items.category_id, categories.name;
+            ",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(category_id, name, quantity, cost, revenue)| shared::CategorySales {
+                    category_id,
+                    name,
+                    quantity: quantity.normalize(),
+                    cost,
+                    revenue,
+                },
+            )
+            .collect())
+    }
+
+    async fn select_payment_sales(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> sqlx::Result<Vec<shared::PaymentSales>> {
+        let rows: Vec<(String, i64, Decimal)> = sqlx::query_as(
+            "
+            SELECT sales.method, COUNT(DISTINCT sales.receipt_id), SUM(sales.amount)
+            FROM (
+                SELECT receipt_payments.method, receipt_payments.receipt_id,
+                    receipt_payments.amount
+                FROM receipt_payments
+                JOIN receipts ON receipts.id = receipt_payments.receipt_id
+                WHERE DATE(receipts.created_at) BETWEEN ? AND ?
+                UNION ALL
+                SELECT return_refunds.method, NULL, -return_refunds.amount
+                FROM return_refunds
+                JOIN returns ON returns.id = return_refunds.return_id
+                WHERE DATE(returns.created_at) BETWEEN ? AND ?
+            ) AS sales
+            GROUP BY sales.method
+            ORDER BY sales.method;
+            ",
+        )
+        .bind(from)
+        .bind(to)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(method, receipts, amount)| shared::PaymentSales {
+                method: method.parse().unwrap_or_default(),
+                receipts,
+                amount,
+            })
+            .collect())
+    }
+
+    async fn select_vat_sales(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> sqlx::Result<Vec<shared::VatSummary>> {
+        // A discount counts in the class of the line it was given on
+        let rows: Vec<(NaiveDate, String, Decimal, Decimal)> = sqlx::query_as(
+            "
+            SELECT sales.month, sales.tax_class, SUM(sales.amount), SUM(sales.tax)
+            FROM (
+                SELECT DATE(DATE_FORMAT(receipts.created_at, '%Y-%m-01')) AS month,
+                    receipt_items.tax_class,
+                    ROUND(receipt_items.price * receipt_items.quantity, 2) AS amount,
+                    receipt_items.tax
+                FROM receipt_items
+                JOIN receipts ON receipts.id = receipt_items.receipt_id
+                WHERE DATE(receipts.created_at) BETWEEN ? AND ?
+                UNION ALL
+                SELECT DATE(DATE_FORMAT(receipts.created_at, '%Y-%m-01')),
+                    COALESCE((
+                        SELECT MIN(receipt_items.tax_class) FROM receipt_items
+                        WHERE receipt_items.receipt_id = receipt_discounts.receipt_id
+                            AND receipt_items.barcode = receipt_discounts.barcode
+                    ), 'standard'),
+                    -receipt_discounts.amount, 0
+                FROM receipt_discounts
+                JOIN receipts ON receipts.id = receipt_discounts.receipt_id
+                WHERE DATE(receipts.created_at) BETWEEN ? AND ?
+                UNION ALL
+                SELECT DATE(DATE_FORMAT(returns.created_at, '%Y-%m-01')), return_items.tax_class,
+                    -return_items.refund, -return_items.tax
+                FROM return_items
+                JOIN returns ON returns.id = return_items.return_id
+                WHERE DATE(returns.created_at) BETWEEN ? AND ?
+            ) AS sales
+            GROUP BY sales.month, sales.tax_class
+            ORDER BY sales.month;
+            ",
+        )
+        .bind(from)
+        .bind(to)
+        .bind(from)
+        .bind(to)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(vat_summaries(rows.into_iter().map(
+            |(month, tax_class, amount, tax)| {
+                (month, tax_class.parse().unwrap_or_default(), amount, tax)
+            },
+        )))
+    }
+
+    async fn insert_stock_take(&self) -> sqlx::Result<u32> {
+        let result = sqlx::query("INSERT INTO stock_takes () VALUES ();")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.last_insert_id() as u32)
+    }
+
+    async fn select_stock_takes(&self) -> sqlx::Result<Vec<shared::StockTake>> {
+        let rows: Vec<(u32, NaiveDateTime, Option<NaiveDateTime>)> =
+            sqlx::query_as("SELECT id, started_at, committed_at FROM stock_takes ORDER BY id DESC")
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(id, started_at, committed_at)| shared::StockTake {
+                id,
+                started_at,
+                committed_at,
+            })
+            .collect())
+    }
+
+    async fn select_stock_take(&self, id: u32) -> sqlx::Result<Option<shared::StockTake>> {
+        let row: Option<(u32, NaiveDateTime, Option<NaiveDateTime>)> =
+            sqlx::query_as("SELECT id, started_at, committed_at FROM stock_takes WHERE id = ?")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.map(|(id, started_at, committed_at)| shared::StockTake {
+            id,
+            started_at,
+            committed_at,
+        }))
+    }
+
+    async fn select_stock_take_lines(
+        &self,
+        stock_take_id: u32,
+    ) -> sqlx::Result<Vec<shared::StockTakeLine>> {
+        let rows: Vec<(String, String, Decimal, Decimal, Decimal)> = sqlx::query_as(
+            "
+            SELECT stock_take_lines.barcode, items.name,
+                COALESCE(stock_take_lines.system_quantity, items.quantity),
+                stock_take_lines.counted_quantity,
+                COALESCE(stock_take_lines.cost, items.cost)
+            FROM stock_take_lines
+            JOIN items ON items.barcode = stock_take_lines.barcode
+            WHERE stock_take_lines.stock_take_id = ?
+            ORDER BY items.name;
+            ",
+        )
+        .bind(stock_take_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(barcode, name, system_quantity, counted_quantity, cost)| shared::StockTakeLine {
+                    barcode,
+                    name,
+                    system_quantity: system_quantity.normalize(),
+                    counted_quantity: counted_quantity.normalize(),
+                    cost,
+                },
+            )
+            .collect())
+    }
+
+    async fn commit_stock_take(&self, stock_take_id: u32, actor: &Actor) -> sqlx::Result<bool> {
+        let mut transaction = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "
+            UPDATE stock_takes SET committed_at = NOW()
+            WHERE id = ? AND committed_at IS NULL;
+            ",
+        )
+        .bind(stock_take_id)
+        .execute(&mut *transaction)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "
+            UPDATE stock_take_lines
+            JOIN items ON items.barcode = stock_take_lines.barcode
+            SET stock_take_lines.system_quantity =
+                    COALESCE(stock_take_lines.system_quantity, items.quantity),
+                stock_take_lines.cost = items.cost
+            WHERE stock_take_lines.stock_take_id = ?;
+            ",
+        )
+        .bind(stock_take_id)
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query(
+            "
+            INSERT INTO stock_movements (barcode, quantity, reason, reference_id)
+            SELECT barcode, counted_quantity - system_quantity, 'stock_take', stock_take_id
+            FROM stock_take_lines
+            WHERE stock_take_id = ? AND counted_quantity <> system_quantity;
+            ",
+        )
+        .bind(stock_take_id)
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query(
+            "
+            INSERT INTO audit_log (entity, barcode, action, before_value, after_value, user_id, source)
+            SELECT 'item', items.barcode, 'update',
+                JSON_OBJECT('quantity', items.quantity),
+                JSON_OBJECT('quantity', items.quantity + counted_quantity - system_quantity),
+                ?, ?
+            FROM stock_take_lines
+            JOIN items ON items.barcode = stock_take_lines.barcode
+            WHERE stock_take_id = ? AND counted_quantity <> system_quantity;
+            ",
+        )
+        .bind(actor.user_id)
+        .bind(actor.source.as_str())
+        .bind(stock_take_id)
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query(
+            "
+            UPDATE items
+            JOIN stock_take_lines ON stock_take_lines.barcode = items.barcode
+            SET items.quantity =
+                items.quantity + stock_take_lines.counted_quantity - stock_take_lines.system_quantity
+            WHERE stock_take_lines.stock_take_id = ?;
+            ",
+        )
+        .bind(stock_take_id)
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+        Ok(true)
+    }
+
+    async fn count_users(&self) -> sqlx::Result<i64> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    async fn select_users(&self) -> sqlx::Result<Vec<shared::User>> {
+        let users: Vec<UserRow> = sqlx::query_as("SELECT id, name, role FROM users ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(users.into_iter().map(shared::User::from).collect())
+    }
+
+    async fn insert_user(
+        &self,
+        name: &str,
+        password_hash: &str,
+        role: shared::Role,
+    ) -> sqlx::Result<u32> {
+        let result = sqlx::query(
+            "
+            INSERT INTO users (name, password_hash, role)
+            VALUES (?, ?, ?);
+            ",
+        )
+        .bind(name)
+        .bind(password_hash)
+        .bind(role.as_str())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_id() as u32)
+    }
+
+    async fn select_credential(&self, name: &str) -> sqlx::Result<Option<(shared::User, String)>> {
+        let row: Option<(u32, String, String, String)> =
+            sqlx::query_as("SELECT id, name, role, password_hash FROM users WHERE name = ?")
+                .bind(name)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.map(|(id, name, role, password_hash)| {
+            (
+                shared::User::from(UserRow { id, name, role }),
+                password_hash,
+            )
+        }))
+    }
+
+    async fn insert_session(&self, token: &str, user_id: u32, hours: i64) -> sqlx::Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO sessions (token, user_id, expires_at)
+            VALUES (?, ?, NOW() + INTERVAL ? HOUR);
+            ",
+        )
+        .bind(token)
+        .bind(user_id)
+        .bind(hours)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn select_session_user(&self, token: &str) -> sqlx::Result<Option<shared::User>> {
+        let user: Option<UserRow> = sqlx::query_as(
+            "
+            SELECT users.id, users.name, users.role FROM sessions
+            JOIN users ON users.id = sessions.user_id
+            WHERE sessions.token = ? AND sessions.expires_at > NOW();
+            ",
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(user.map(shared::User::from))
+    }
+
+    async fn delete_session(&self, token: &str) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM sessions WHERE token = ?")
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn select_item(&self, barcode: &str) -> sqlx::Result<Option<Item>> {
+        fetch_item(&mut *self.pool.acquire().await?, barcode).await
+    }
+
+    async fn update_item(&self, item: &Item, actor: &Actor) -> sqlx::Result<u64> {
+        let mut transaction = self.pool.begin().await?;
+        let before = fetch_item(&mut transaction, &item.barcode).await?;
+        let result = sqlx::query(
+            "
+            UPDATE items SET name = ?, cost = ?, price = ?, quantity = ?, unit = COALESCE(?, unit),
+            image = ?, category_id = ?, tax_class = COALESCE(?, tax_class)
+            WHERE barcode = ?;
+            ",
+        )
+        .bind(&item.name)
+        .bind(item.cost)
+        .bind(item.price)
+        .bind(item.quantity)
+        .bind(item.unit.map(|unit| unit.as_str()))
+        .bind(&item.image)
+        .bind(item.category_id)
+        .bind(item.tax_class.map(|class| class.as_str()))
+        .bind(&item.barcode)
+        .execute(&mut *transaction)
+        .await?;
+        if before.is_some() {
+            audit(
+                &mut transaction,
+                actor,
+                "item",
+                &item.barcode,
+                before.as_ref(),
+                Some(item),
+            )
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_item(&self, barcode: &str, actor: &Actor) -> sqlx::Result<u64> {
+        let mut transaction = self.pool.begin().await?;
+        let before = fetch_item(&mut transaction, barcode).await?;
+        let result = sqlx::query("DELETE FROM items WHERE barcode = ?")
+            .bind(barcode)
+            .execute(&mut *transaction)
+            .await?;
+        audit::<Item>(
+            &mut transaction,
+            actor,
+            "item",
+            barcode,
+            before.as_ref(),
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn select_label_queue(&self) -> sqlx::Result<Vec<shared::LabelQueueItem>> {
+        let rows: Vec<(String, String, Decimal, Option<Decimal>)> = sqlx::query_as(
+            "
+            SELECT items.barcode, items.name, items.price, label_prints.price
+            FROM items
+            LEFT JOIN label_prints ON label_prints.barcode = items.barcode
+            WHERE label_prints.price IS NULL OR label_prints.price <> items.price
+            ORDER BY items.name, items.barcode;
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(barcode, name, price, printed_price)| shared::LabelQueueItem {
+                    barcode,
+                    name,
+                    price,
+                    printed_price,
+                },
+            )
+            .collect())
+    }
+
+    async fn upsert_label_prints(&self, prices: &[(String, Decimal)]) -> sqlx::Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        for (barcode, price) in prices {
+            sqlx::query(
+                "
+                INSERT INTO label_prints (barcode, price) VALUES (?, ?)
+                ON DUPLICATE KEY UPDATE
+                price = VALUES(price),
+                printed_at = NOW();
+                ",
+            )
+            .bind(barcode)
+            .bind(price)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await
+    }
+
+    async fn next_barcode_number(&self, prefix: &str) -> sqlx::Result<u64> {
+        let mut transaction = self.pool.begin().await?;
+        let number: u64 = sqlx::query_scalar(
+            "SELECT next_value FROM barcode_sequences WHERE prefix = ? FOR UPDATE;",
+        )
+        .bind(prefix)
+        .fetch_one(&mut *transaction)
+        .await?;
+        sqlx::query("UPDATE barcode_sequences SET next_value = next_value + 1 WHERE prefix = ?;")
+            .bind(prefix)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(number)
+    }
+
+    async fn select_audit_log(&self, barcode: &str) -> sqlx::Result<Vec<shared::AuditEntry>> {
+        #[allow(clippy::type_complexity)]
+        let rows: Vec<(
+            u32,
+            String,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            String,
+            NaiveDateTime,
+        )> = sqlx::query_as(
+            "
+            SELECT audit_log.id, audit_log.entity, audit_log.action,
+                CAST(audit_log.before_value AS CHAR), CAST(audit_log.after_value AS CHAR),
+                users.name, audit_log.source, audit_log.created_at
+            FROM audit_log
+            LEFT JOIN users ON users.id = audit_log.user_id
+            WHERE audit_log.barcode = ?
+            ORDER BY audit_log.id DESC;
+            ",
+        )
+        .bind(barcode)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(id, entity, action, before, after, user, source, created_at)| {
+                    shared::AuditEntry {
+                        id,
+                        entity,
+                        action,
+                        before: before.and_then(|value| serde_json::from_str(&value).ok()),
+                        after: after.and_then(|value| serde_json::from_str(&value).ok()),
+                        user,
+                        source,
+                        created_at,
+                    }
+                },
+            )
+            .collect())
+    }
+
+    async fn select_item_updated_at(&self) -> sqlx::Result<Vec<(String, NaiveDateTime)>> {
+        sqlx::query_as("SELECT barcode, updated_at FROM items")
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    async fn select_legacy_items(&self) -> sqlx::Result<Vec<(LegacyItem, NaiveDateTime)>> {
+        let rows: Vec<(String, String, Decimal, Decimal, i32, NaiveDateTime)> = sqlx::query_as(
+            "SELECT barcode, name, cost, price, quantity, synced_at FROM legacy_items",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(barcode, name, cost, price, quantity, synced_at)| {
+                let item = LegacyItem {
+                    barcode,
+                    name,
+                    cost,
+                    price,
+                    quantity,
+                };
+                (item, synced_at)
+            })
+            .collect())
+    }
+
+    async fn upsert_legacy_item(&self, item: &LegacyItem) -> sqlx::Result<()> {
+        upsert_legacy(&mut *self.pool.acquire().await?, item).await
+    }
+
+    async fn merge_legacy_item(
+        &self,
+        item: &LegacyItem,
+        quantity_change: Decimal,
+        expire_date: Option<NaiveDate>,
+        legacy: &LegacyItem,
+        actor: &Actor,
+        push: BoxFuture<'_, sqlx::Result<()>>,
+    ) -> sqlx::Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        let before = fetch_item(&mut transaction, &item.barcode).await?;
+        sqlx::query(
+            "
+            UPDATE items SET name = ?, cost = ?, price = ?, quantity = quantity + ?
+            WHERE barcode = ?;
+            ",
+        )
+        .bind(&item.name)
+        .bind(item.cost)
+        .bind(item.price)
+        .bind(quantity_change)
+        .bind(&item.barcode)
+        .execute(&mut *transaction)
+        .await?;
+        // Deleted here since the sync read it
+        if before.is_some() {
+            let after = fetch_item(&mut transaction, &item.barcode).await?;
+            audit(
+                &mut transaction,
+                actor,
+                "item",
+                &item.barcode,
+                before.as_ref(),
+                after.as_ref(),
+            )
+            .await?;
+            if let Some(expire_date) = expire_date {
+                insert_expire_date(&mut transaction, &item.barcode, expire_date, actor).await?;
+            }
+        }
+        upsert_legacy(&mut transaction, legacy).await?;
+        push.await?;
+        transaction.commit().await
+    }
+
+    async fn select_receipt_id(&self, key: &str) -> sqlx::Result<Option<u32>> {
+        sqlx::query_scalar("SELECT id FROM receipts WHERE idempotency_key = ?")
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn insert_receipt(&self, receipt: &shared::Receipt, user_id: u32) -> sqlx::Result<u32> {
+        let mut transaction = self.pool.begin().await?;
+
+        let receipt_id = sqlx::query(
+            "
+            INSERT INTO receipts (idempotency_key, created_at, user_id, customer_id)
+            VALUES (?, ?, ?, ?);
+            ",
+        )
+        .bind(&receipt.key)
+        .bind(receipt.created_at)
+        .bind(user_id)
+        .bind(receipt.customer_id)
+        .execute(&mut *transaction)
+        .await?
+        .last_insert_id() as u32;
+
+        for item in &receipt.items {
+            let cost = sale_cost(&mut transaction, &item.barcode).await?;
+            sqlx::query(
+                "
+                INSERT INTO receipt_items (receipt_id, barcode, cost, price, quantity, tax_class,
+                    tax)
+                VALUES (?, ?, ?, ?, ?, ?, ?);
+                ",
+            )
+            .bind(receipt_id)
+            .bind(&item.barcode)
+            .bind(cost)
+            .bind(item.price)
+            .bind(item.quantity)
+            .bind(item.tax_class.as_str())
+            .bind(item.tax)
+            .execute(&mut *transaction)
+            .await?;
+
+            sqlx::query("UPDATE items SET quantity = quantity - ? WHERE barcode = ?;")
+                .bind(item.quantity)
+                .bind(&item.barcode)
+                .execute(&mut *transaction)
+                .await?;
+
+            sqlx::query(
+                "
+                INSERT INTO stock_movements (barcode, quantity, reason, reference_id)
+                SELECT barcode, ?, 'sale', ? FROM items WHERE barcode = ?;
+                ",
+            )
+            .bind(-item.quantity)
+            .bind(receipt_id)
+            .bind(&item.barcode)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        // A till that was offline may send a discount of a promotion deleted since
+        for discount in &receipt.discounts {
+            sqlx::query(
+                "
+                INSERT INTO receipt_discounts (receipt_id, promotion_id, name, barcode, amount)
+                VALUES (?, (SELECT id FROM promotions WHERE id = ?), ?, ?, ?);
+                ",
+            )
+            .bind(receipt_id)
+            .bind(discount.promotion_id)
+            .bind(&discount.name)
+            .bind(&discount.barcode)
+            .bind(discount.amount)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        for payment in &receipt.payments {
+            sqlx::query(
+                "
+                INSERT INTO receipt_payments (receipt_id, method, amount, reference)
+                VALUES (?, ?, ?, ?);
+                ",
+            )
+            .bind(receipt_id)
+            .bind(payment.method.as_str())
+            .bind(payment.amount)
+            .bind(&payment.reference)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        if let Some((customer_id, amount, points)) = ledger_change(receipt) {
+            sqlx::query(
+                "
+                INSERT INTO customer_ledger (customer_id, receipt_id, amount, points, user_id,
+                    created_at)
+                VALUES (?, ?, ?, ?, ?, ?);
+                ",
+            )
+            .bind(customer_id)
+            .bind(receipt_id)
+            .bind(amount)
+            .bind(points)
+            .bind(user_id)
+            .bind(receipt.created_at)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+        Ok(receipt_id)
+    }
+
+    async fn select_sale(&self, receipt_id: u32) -> sqlx::Result<Option<shared::SaleRecord>> {
+        let row: Option<(Option<String>, Option<NaiveDateTime>, Option<u32>)> = sqlx::query_as(
+            "SELECT idempotency_key, created_at, customer_id FROM receipts WHERE id = ?;",
+        )
+        .bind(receipt_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some((key, created_at, customer_id)) = row else {
+            return Ok(None);
+        };
+
+        let items: Vec<(String, Option<String>, Decimal, Decimal, String, Decimal)> =
+            sqlx::query_as(
+                "
+                SELECT receipt_items.barcode, items.name, receipt_items.price,
+                    receipt_items.quantity, receipt_items.tax_class, receipt_items.tax
+                FROM receipt_items
+                LEFT JOIN items ON items.barcode = receipt_items.barcode
+                WHERE receipt_items.receipt_id = ?
+                ORDER BY receipt_items.id;
+                ",
+            )
+            .bind(receipt_id)
+            .fetch_all(&self.pool)
+            .await?;
+        let discounts: Vec<(Option<u32>, String, String, Decimal)> = sqlx::query_as(
+            "
+            SELECT promotion_id, name, barcode, amount FROM receipt_discounts
+            WHERE receipt_id = ?
+            ORDER BY id;
+            ",
+        )
+        .bind(receipt_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let payments: Vec<(String, Decimal, Option<String>)> = sqlx::query_as(
+            "
+            SELECT method, amount, reference FROM receipt_payments
+            WHERE receipt_id = ?
+            ORDER BY id;
+            ",
+        )
+        .bind(receipt_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let points: i64 = sqlx::query_scalar(
+            "
+            SELECT CAST(COALESCE(SUM(points), 0) AS SIGNED)
+            FROM customer_ledger
+            WHERE receipt_id = ? AND return_id IS NULL;
+            ",
+        )
+        .bind(receipt_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let mut receipt = shared::Receipt {
+            key: key.unwrap_or_default(),
+            created_at: created_at.unwrap_or_default(),
+            items: items
+                .into_iter()
+                .map(
+                    |(barcode, name, price, quantity, tax_class, tax)| shared::ReceiptItem {
+                        barcode,
+                        name: name.unwrap_or_default(),
+                        price,
+                        quantity: quantity.normalize(),
+                        tax_class: tax_class.parse().unwrap_or_default(),
+                        tax,
+                    },
+                )
+                .collect(),
+            discounts: discounts
+                .into_iter()
+                .map(|(promotion_id, name, barcode, amount)| shared::Discount {
+                    promotion_id: promotion_id.unwrap_or_default(),
+                    name,
+                    barcode,
+                    amount,
+                })
+                .collect(),
+            payments: payments
+                .into_iter()
+                .map(|(method, amount, reference)| shared::Payment {
+                    method: method.parse().unwrap_or_default(),
+                    amount,
+                    reference,
+                })
+                .collect(),
+            customer_id,
+            points: 0,
+        };
+        // The ledger has what was earned less what was redeemed
+        receipt.points = points + receipt.redeemed_points();
+
+        let rows: Vec<(u32, NaiveDateTime, bool, String)> = sqlx::query_as(
+            "
+            SELECT id, created_at, void, reason FROM returns
+            WHERE receipt_id = ?
+            ORDER BY id;
+            ",
+        )
+        .bind(receipt_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut returns = Vec::with_capacity(rows.len());
+        for (id, created_at, void, reason) in rows {
+            let lines: Vec<(String, Decimal, String, Decimal, Decimal)> = sqlx::query_as(
+                "
+                SELECT barcode, quantity, disposition, refund, tax FROM return_items
+                WHERE return_id = ?
+                ORDER BY id;
+                ",
+            )
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await?;
+            let refunds: Vec<(String, Decimal)> = sqlx::query_as(
+                "SELECT method, amount FROM return_refunds WHERE return_id = ? ORDER BY id;",
+            )
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await?;
+            let points: i64 = sqlx::query_scalar(
+                "
+                SELECT CAST(COALESCE(SUM(points), 0) AS SIGNED)
+                FROM customer_ledger
+                WHERE return_id = ?;
+                ",
+            )
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+            returns.push(shared::SaleReturn {
+                id,
+                receipt_id,
+                created_at,
+                void,
+                reason,
+                lines: lines
+                    .into_iter()
+                    .map(
+                        |(barcode, quantity, disposition, refund, tax)| shared::ReturnLine {
+                            barcode,
+                            quantity: quantity.normalize(),
+                            disposition: disposition.parse().unwrap_or_default(),
+                            refund,
+                            tax,
+                        },
+                    )
+                    .collect(),
+                refunds: refunds
+                    .into_iter()
+                    .map(|(method, amount)| shared::Payment {
+                        method: method.parse().unwrap_or_default(),
+                        amount,
+                        reference: None,
+                    })
+                    .collect(),
+                points,
+            });
+        }
+
+        Ok(Some(shared::SaleRecord {
+            id: receipt_id,
+            receipt,
+            returns,
+        }))
+    }
+
+    async fn insert_return(
+        &self,
+        sale_return: &shared::SaleReturn,
+        customer_id: Option<u32>,
+        returns: usize,
+        user_id: u32,
+    ) -> sqlx::Result<Option<u32>> {
+        let mut transaction = self.pool.begin().await?;
+
+        // Returns of the same receipt wait for each other here
+        sqlx::query("SELECT id FROM receipts WHERE id = ? FOR UPDATE;")
+            .bind(sale_return.receipt_id)
+            .fetch_optional(&mut *transaction)
+            .await?;
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM returns WHERE receipt_id = ?;")
+            .bind(sale_return.receipt_id)
+            .fetch_one(&mut *transaction)
+            .await?;
+        if count as usize != returns {
+            return Ok(None);
+        }
+
+        let return_id = sqlx::query(
+            "
+            INSERT INTO returns (receipt_id, void, reason, user_id, created_at)
+            VALUES (?, ?, ?, ?, ?);
+            ",
+        )
+        .bind(sale_return.receipt_id)
+        .bind(sale_return.void)
+        .bind(&sale_return.reason)
+        .bind(user_id)
+        .bind(sale_return.created_at)
+        .execute(&mut *transaction)
+        .await?
+        .last_insert_id() as u32;
+
+        for line in &sale_return.lines {
+            let result = sqlx::query(
+                "
+                INSERT INTO return_items (return_id, barcode, quantity, disposition, cost, refund,
+                    tax_class, tax)
+                SELECT ?, barcode, ?, ?, cost, ?, tax_class, ?
+                FROM receipt_items
+                WHERE receipt_id = ? AND barcode = ?
+                ORDER BY id
+                LIMIT 1;
+                ",
+            )
+            .bind(return_id)
+            .bind(line.quantity)
+            .bind(line.disposition.as_str())
+            .bind(line.refund)
+            .bind(line.tax)
+            .bind(sale_return.receipt_id)
+            .bind(&line.barcode)
+            .execute(&mut *transaction)
+            .await?;
+            if result.rows_affected() == 0 {
+                return Ok(None);
+            }
+            if line.disposition == shared::Disposition::Waste {
+                continue;
+            }
+
+            // An item deleted from the catalogue since has no shelf to go back on
+            let result = sqlx::query("UPDATE items SET quantity = quantity + ? WHERE barcode = ?;")
+                .bind(line.quantity)
+                .bind(&line.barcode)
+                .execute(&mut *transaction)
+                .await?;
+            if result.rows_affected() > 0 {
+                sqlx::query(
+                    "
+                    INSERT INTO stock_movements (barcode, quantity, reason, reference_id)
+                    VALUES (?, ?, 'return', ?);
+                    ",
+                )
+                .bind(&line.barcode)
+                .bind(line.quantity)
+                .bind(return_id)
+                .execute(&mut *transaction)
+                .await?;
+            }
+        }
+
+        for refund in &sale_return.refunds {
+            sqlx::query("INSERT INTO return_refunds (return_id, method, amount) VALUES (?, ?, ?);")
+                .bind(return_id)
+                .bind(refund.method.as_str())
+                .bind(refund.amount)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        if let Some((customer_id, amount, points)) = return_ledger_change(sale_return, customer_id)
+        {
+            sqlx::query(
+                "
+                INSERT INTO customer_ledger (customer_id, receipt_id, return_id, amount, points,
+                    note, user_id, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?);
+                ",
+            )
+            .bind(customer_id)
+            .bind(sale_return.receipt_id)
+            .bind(return_id)
+            .bind(amount)
+            .bind(points)
+            .bind(&sale_return.reason)
+            .bind(user_id)
+            .bind(sale_return.created_at)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+        Ok(Some(return_id))
+    }
+
+    async fn select_schema_version(&self) -> sqlx::Result<Option<i64>> {
+        sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    async fn select_tax_invoice(
+        &self,
+        receipt_id: u32,
+    ) -> sqlx::Result<Option<shared::TaxInvoice>> {
+        let row: Option<TaxInvoiceRow> = sqlx::query_as(
+            "
+            SELECT receipt_id, sequence, buyer_name, buyer_tax_id, buyer_address, buyer_branch,
+                issued_at,
+                (
+                    SELECT COALESCE(SUM(ROUND(price * quantity, 2)), 0) FROM receipt_items
+                    WHERE receipt_items.receipt_id = tax_invoices.receipt_id
+                ) AS items,
+                (
+                    SELECT COALESCE(SUM(amount), 0) FROM receipt_discounts
+                    WHERE receipt_discounts.receipt_id = tax_invoices.receipt_id
+                ) AS discount,
+                (
+                    SELECT COALESCE(SUM(tax), 0) FROM receipt_items
+                    WHERE receipt_items.receipt_id = tax_invoices.receipt_id
+                ) AS vat
+            FROM tax_invoices
+            WHERE receipt_id = ?;
+            ",
+        )
+        .bind(receipt_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|row| shared::TaxInvoice {
+            receipt_id: row.receipt_id,
+            sequence: row.sequence,
+            buyer: row.buyer_name.map(|name| shared::Buyer {
+                name,
+                tax_id: row.buyer_tax_id.unwrap_or_default(),
+                address: row.buyer_address.unwrap_or_default(),
+                branch: row.buyer_branch.unwrap_or_default(),
+            }),
+            issued_at: row.issued_at,
+            total: row.items - row.discount,
+            vat: row.vat,
+        }))
+    }
+
+    async fn insert_tax_invoice(
+        &self,
+        receipt_id: u32,
+        buyer: Option<&shared::Buyer>,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO tax_invoices (receipt_id, kind, sequence, buyer_name, buyer_tax_id,
+                buyer_address, buyer_branch)
+            SELECT ?, ?, COALESCE(MAX(sequence), 0) + 1, ?, ?, ?, ?
+            FROM tax_invoices
+            WHERE kind = ?;
+            ",
+        )
+        .bind(receipt_id)
+        .bind(invoice_kind(buyer))
+        .bind(buyer.map(|buyer| &buyer.name))
+        .bind(buyer.map(|buyer| &buyer.tax_id))
+        .bind(buyer.map(|buyer| &buyer.address))
+        .bind(buyer.map(|buyer| &buyer.branch))
+        .bind(invoice_kind(buyer))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn search_customers(&self, keyword: &str) -> sqlx::Result<Vec<shared::Customer>> {
+        select_customers(&self.pool, None, keyword).await
+    }
+
+    async fn select_customer(&self, id: u32) -> sqlx::Result<Option<shared::Customer>> {
+        Ok(select_customers(&self.pool, Some(id), "")
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    async fn insert_customer(&self, customer: &shared::Customer) -> sqlx::Result<u32> {
+        let result = sqlx::query(
+            "
+            INSERT INTO customers (name, phone, address, credit_limit)
+            VALUES (?, ?, ?, ?);
+            ",
+        )
+        .bind(&customer.name)
+        .bind(&customer.phone)
+        .bind(&customer.address)
+        .bind(customer.credit_limit)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_id() as u32)
+    }
+
+    async fn update_customer(&self, customer: &shared::Customer) -> sqlx::Result<u64> {
+        let result = sqlx::query(
+            "
+            UPDATE customers SET name = ?, phone = ?, address = ?, credit_limit = ?
+            WHERE id = ?;
+            ",
+        )
+        .bind(&customer.name)
+        .bind(&customer.phone)
+        .bind(&customer.address)
+        .bind(customer.credit_limit)
+        .bind(customer.id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn select_ledger(
+        &self,
+        customer_id: u32,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> sqlx::Result<(Decimal, Vec<shared::LedgerEntry>)> {
+        let opening_balance: Decimal = sqlx::query_scalar(
+            "
+            SELECT COALESCE(SUM(amount), 0)
+            FROM customer_ledger
+            WHERE customer_id = ? AND DATE(created_at) < ?;
+            ",
+        )
+        .bind(customer_id)
+        .bind(from)
+        .fetch_one(&self.pool)
+        .await?;
+        let rows: Vec<(
+            u32,
+            NaiveDateTime,
+            Option<u32>,
+            Option<u32>,
+            Option<String>,
+            Decimal,
+            i64,
+            String,
+        )> = sqlx::query_as(
+            "
+            SELECT id, created_at, receipt_id, return_id, method, amount, points, note
+            FROM customer_ledger
+            WHERE customer_id = ? AND DATE(created_at) BETWEEN ? AND ?
+            ORDER BY created_at, id;
+            ",
+        )
+        .bind(customer_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+        let entries = rows
+            .into_iter()
+            .map(
+                |(id, created_at, receipt_id, return_id, method, amount, points, note)| {
+                    shared::LedgerEntry {
+                        id,
+                        created_at,
+                        receipt_id,
+                        return_id,
+                        method: method.map(|method| method.parse().unwrap_or_default()),
+                        amount,
+                        points,
+                        note,
+                    }
+                },
+            )
+            .collect();
+        Ok((opening_balance, entries))
+    }
+
+    async fn insert_customer_payment(
+        &self,
+        customer_id: u32,
+        payment: &shared::CustomerPayment,
+        user_id: u32,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO customer_ledger (customer_id, method, amount, points, note, user_id)
+            VALUES (?, ?, ?, 0, ?, ?);
+            ",
+        )
+        .bind(customer_id)
+        .bind(payment.method.as_str())
+        .bind(-payment.amount)
+        .bind(&payment.note)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
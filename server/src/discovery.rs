@@ -0,0 +1,28 @@
+use tokio::net::UdpSocket;
+
+/// Answers discovery broadcasts from clients on the LAN with the port of the HTTP server, so the
+/// owner can pick the server from a list instead of typing its address.
+pub(crate) async fn respond(http_port: u16) -> std::io::Result<()> {
+    let socket = UdpSocket::bind(("0.0.0.0", shared::DISCOVERY_PORT)).await?;
+    let mut buffer = [0u8; 64];
+    // A client that went away before the answer, for one, fails the next receive on some
+    // systems, which is no reason to stop answering the others
+    loop {
+        let (length, address) = match socket.recv_from(&mut buffer).await {
+            Ok(received) => received,
+            Err(e) => {
+                eprintln!("discovery error: {e}");
+                continue;
+            }
+        };
+        if &buffer[..length] != shared::DISCOVERY_REQUEST {
+            continue;
+        }
+        if let Err(e) = socket
+            .send_to(http_port.to_string().as_bytes(), address)
+            .await
+        {
+            eprintln!("discovery error: {address}: {e}");
+        }
+    }
+}
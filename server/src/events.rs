@@ -0,0 +1,46 @@
+use std::sync::OnceLock;
+
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::stream::{self, Stream};
+use shared::ItemEvent;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{Database, load_item};
+
+/// How many events a slow client may fall behind before it is told to reload everything.
+const CAPACITY: usize = 256;
+
+static SENDER: OnceLock<broadcast::Sender<ItemEvent>> = OnceLock::new();
+
+fn sender() -> &'static broadcast::Sender<ItemEvent> {
+    SENDER.get_or_init(|| broadcast::channel(CAPACITY).0)
+}
+
+pub(crate) fn publish(event: ItemEvent) {
+    // Sending only fails when no client is listening
+    let _ = sender().send(event);
+}
+
+/// Publishes the item as it is now stored, or that it is gone.
+pub(crate) async fn publish_item(db: &Database, barcode: &str) {
+    match load_item(db, barcode).await {
+        Ok(Some(item)) => publish(ItemEvent::Changed { item }),
+        Ok(None) => publish(ItemEvent::Deleted {
+            barcode: barcode.to_string(),
+        }),
+        Err(_) => publish(ItemEvent::Reload),
+    }
+}
+
+/// Server-sent events with every change to the catalogue, so all tills show the same prices.
+pub async fn get_events() -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let stream = stream::unfold(sender().subscribe(), |mut receiver| async move {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(_)) => ItemEvent::Reload,
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Event::default().json_data(event), receiver))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
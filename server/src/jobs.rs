@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{Days, Local, NaiveDateTime, NaiveTime};
+use rust_decimal::Decimal;
+use shared::{Job, JobStatus};
+
+use crate::audit::{Actor, Source};
+use crate::auth::Owner;
+use crate::config::Config;
+use crate::{AppError, AppState, Database, sync};
+
+/// How many days ahead the expiry check looks.
+const EXPIRY_WARNING_DAYS: u64 = 7;
+
+fn now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+/// Status of every background job, shared by the scheduler and the API so a job started by
+/// one is seen running by the other.
+#[derive(Clone, Default)]
+pub(crate) struct Jobs(Arc<Mutex<HashMap<Job, JobStatus>>>);
+
+impl Jobs {
+    fn update<T>(&self, job: Job, f: impl FnOnce(&mut JobStatus) -> T) -> T {
+        let mut jobs = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        f(jobs.entry(job).or_insert_with(|| JobStatus {
+            job,
+            ..Default::default()
+        }))
+    }
+
+    fn status(&self, job: Job) -> JobStatus {
+        self.update(job, |status| status.clone())
+    }
+
+    /// Marks the job running. Returns `None` when it already is, so runs never overlap.
+    fn start(&self, job: Job) -> Option<Running> {
+        let started = self.update(job, |status| {
+            if status.running {
+                return false;
+            }
+            status.running = true;
+            status.started_at = Some(now());
+            true
+        });
+        started.then(|| Running {
+            jobs: self.clone(),
+            job,
+            finished: false,
+        })
+    }
+
+    /// Runs `work` as the job unless it is running already, and records how it went.
+    pub(crate) async fn run<T: fmt::Display>(
+        &self,
+        job: Job,
+        work: impl Future<Output = Result<T, AppError>>,
+    ) -> Result<T, AppError> {
+        let running = self
+            .start(job)
+            .ok_or_else(|| AppError::Conflict(format!("{job} is already running")))?;
+        let result = work.await;
+        running.finish(&result);
+        result
+    }
+}
+
+/// A job that is running. Dropped without [`Running::finish`] the job was cut short.
+struct Running {
+    jobs: Jobs,
+    job: Job,
+    finished: bool,
+}
+
+impl Running {
+    fn finish<T: fmt::Display>(mut self, result: &Result<T, AppError>) {
+        self.jobs.update(self.job, |status| {
+            status.finished_at = Some(now());
+            status.failed = result.is_err();
+            status.message = Some(match result {
+                Ok(message) => message.to_string(),
+                Err(e) => e.to_string(),
+            });
+        });
+        self.finished = true;
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        let finished = self.finished;
+        self.jobs.update(self.job, |status| {
+            status.running = false;
+            if !finished {
+                status.finished_at = Some(now());
+                status.failed = true;
+                status.message = Some("stopped before it finished".to_string());
+            }
+        });
+    }
+}
+
+/// Does one run of the job and sums up what it did.
+async fn execute(state: &AppState, job: Job) -> Result<String, AppError> {
+    match job {
+        Job::Sync => {
+            let report = state.sync(&Actor::server(Source::Sync)).await?;
+            sync::print_conflicts(&report);
+            Ok(report.to_string())
+        }
+        Job::ExpiryCheck => expiry_check(&state.db).await,
+        Job::NightlyReport => nightly_report(&state.db).await,
+    }
+}
+
+async fn expiry_check(db: &Database) -> Result<String, AppError> {
+    let until = Local::now().date_naive() + Days::new(EXPIRY_WARNING_DAYS);
+    let expiring = db.select_expiring(until).await?;
+    if expiring.is_empty() {
+        return Ok(format!("nothing expires by {until}"));
+    }
+    let items: Vec<String> = expiring
+        .iter()
+        .map(|(_, name, expire_date)| format!("{name} ({expire_date})"))
+        .collect();
+    Ok(format!(
+        "{} expire by {until}: {}",
+        items.len(),
+        items.join(", ")
+    ))
+}
+
+async fn nightly_report(db: &Database) -> Result<String, AppError> {
+    let today = Local::now().date_naive();
+    let sales = db.select_category_sales(today, today).await?;
+    let quantity: Decimal = sales.iter().map(|sales| sales.quantity).sum();
+    let revenue: Decimal = sales.iter().map(|sales| sales.revenue).sum();
+    let cost: Decimal = sales.iter().map(|sales| sales.cost).sum();
+    let mut report = format!(
+        "{today}: {quantity} sold for {revenue}, {} profit",
+        revenue - cost
+    );
+    for payment in db.select_payment_sales(today, today).await? {
+        report += &format!(", {} {}", payment.method.as_str(), payment.amount);
+    }
+    Ok(report)
+}
+
+async fn run_scheduled(state: &AppState, job: Job) {
+    let Some(running) = state.jobs.start(job) else {
+        println!("{job} is still running, skipped");
+        return;
+    };
+    let result = execute(state, job).await;
+    match &result {
+        Ok(message) => println!("{job}: {message}"),
+        Err(e) => eprintln!("{job} failed: {e}"),
+    }
+    running.finish(&result);
+}
+
+async fn every(state: AppState, job: Job, interval: Duration, right_away: bool) {
+    let mut wait = if right_away { Duration::ZERO } else { interval };
+    loop {
+        state.jobs.update(job, |status| {
+            status.next_run = chrono::Duration::from_std(wait)
+                .ok()
+                .map(|wait| now() + wait)
+        });
+        tokio::time::sleep(wait).await;
+        run_scheduled(&state, job).await;
+        wait = interval;
+    }
+}
+
+async fn daily(state: AppState, job: Job, at: NaiveTime) {
+    loop {
+        let today = now().date().and_time(at);
+        let next = match today > now() {
+            true => today,
+            false => today + Days::new(1),
+        };
+        state
+            .jobs
+            .update(job, |status| status.next_run = Some(next));
+        tokio::time::sleep((next - now()).to_std().unwrap_or_default()).await;
+        run_scheduled(&state, job).await;
+    }
+}
+
+/// Starts the jobs the configuration asks for in the background, so serving does not wait for
+/// them.
+pub(crate) fn schedule(state: &AppState, config: &Config) {
+    match config.sync_interval {
+        Some(interval) => {
+            tokio::spawn(every(
+                state.clone(),
+                Job::Sync,
+                interval,
+                config.sync_on_start,
+            ));
+        }
+        None if config.sync_on_start => {
+            let state = state.clone();
+            tokio::spawn(async move { run_scheduled(&state, Job::Sync).await });
+        }
+        None => {}
+    }
+    if let Some(interval) = config.expiry_check_interval {
+        tokio::spawn(every(state.clone(), Job::ExpiryCheck, interval, true));
+    }
+    if let Some(at) = config.nightly_report_at {
+        tokio::spawn(daily(state.clone(), Job::NightlyReport, at));
+    }
+}
+
+pub async fn get_jobs(State(state): State<AppState>, _owner: Owner) -> Json<Vec<JobStatus>> {
+    Json(Job::ALL.map(|job| state.jobs.status(job)).to_vec())
+}
+
+/// Starts a run of the job now and answers right away. Follow it with `GET /jobs`.
+pub async fn post_job(
+    State(state): State<AppState>,
+    _owner: Owner,
+    Path(job): Path<Job>,
+) -> Result<(StatusCode, Json<JobStatus>), AppError> {
+    let running = state
+        .jobs
+        .start(job)
+        .ok_or_else(|| AppError::Conflict(format!("{job} is already running")))?;
+    let status = state.jobs.status(job);
+    tokio::spawn(async move {
+        let result = execute(&state, job).await;
+        running.finish(&result);
+    });
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
@@ -0,0 +1,562 @@
+use std::io::Write;
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Json},
+};
+use flate2::{Compression, write::ZlibEncoder};
+use rust_decimal::Decimal;
+use shared::{LabelFormat, LabelQueueItem, LabelRequest};
+
+use crate::{AppError, Database};
+
+/// The size of one shelf tag in millimetres, and how many dots a millimetre is printed with.
+struct Layout {
+    width: u32,
+    height: u32,
+    dots_per_mm: u32,
+}
+
+impl Layout {
+    fn dots(&self, mm: f32) -> u32 {
+        (mm * self.dots_per_mm as f32).round() as u32
+    }
+}
+
+/// Tags on an A4 sheet of 3 by 8 stickers, drawn at 300 dpi.
+const SHEET_TAG: Layout = Layout {
+    width: 70,
+    height: 37,
+    dots_per_mm: 12,
+};
+const SHEET_COLUMNS: u32 = 3;
+const SHEET_ROWS: u32 = 8;
+const A4: (u32, u32) = (210, 297);
+
+/// Tags on a roll of 50 x 30 mm labels, for 203 dpi thermal printers.
+const THERMAL_TAG: Layout = Layout {
+    width: 50,
+    height: 30,
+    dots_per_mm: 8,
+};
+const THERMAL_GAP_MM: u32 = 2;
+
+/// A black and white picture, one `bool` per dot, `true` for black.
+struct Bitmap {
+    width: u32,
+    height: u32,
+    dots: Vec<bool>,
+}
+
+impl Bitmap {
+    fn new(width: u32, height: u32) -> Self {
+        Bitmap {
+            width,
+            height,
+            dots: vec![false; (width * height) as usize],
+        }
+    }
+
+    fn set(&mut self, x: i64, y: i64) {
+        if (0..self.width as i64).contains(&x) && (0..self.height as i64).contains(&y) {
+            self.dots[(y as u32 * self.width + x as u32) as usize] = true;
+        }
+    }
+
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        for y in y..y + height {
+            for x in x..x + width {
+                self.set(x as i64, y as i64);
+            }
+        }
+    }
+
+    fn draw(&mut self, other: &Bitmap, x: u32, y: u32) {
+        for (i, _) in other.dots.iter().enumerate().filter(|(_, black)| **black) {
+            let i = i as u32;
+            self.set((x + i % other.width) as i64, (y + i / other.width) as i64);
+        }
+    }
+
+    /// The rows packed 8 dots to a byte, first dot in the highest bit, with `black` as the bit
+    /// of a black dot. Rows are padded to whole bytes with white.
+    fn packed(&self, black: bool) -> Vec<u8> {
+        let row_bytes = self.width.div_ceil(8) as usize;
+        let mut bytes = vec![if black { 0x00 } else { 0xFF }; row_bytes * self.height as usize];
+        for y in 0..self.height as usize {
+            for x in 0..self.width as usize {
+                if self.dots[y * self.width as usize + x] != black {
+                    bytes[y * row_bytes + x / 8] ^= 0x80 >> (x % 8);
+                }
+            }
+        }
+        bytes
+    }
+}
+
+fn font() -> FontRef<'static> {
+    FontRef::try_from_slice(shared::FONT).expect("the bundled font is a valid TrueType font")
+}
+
+fn text_width(font: &FontRef, size: f32, text: &str) -> f32 {
+    let font = font.as_scaled(PxScale::from(size));
+    text.chars().map(|c| font.h_advance(font.glyph_id(c))).sum()
+}
+
+/// Draws `text` with its top at `top`. Thai vowels and tone marks above and below have no
+/// advance of their own, so they land on the letter before them.
+fn draw_text(bitmap: &mut Bitmap, font: &FontRef, size: f32, x: f32, top: f32, text: &str) {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let baseline = top + scaled.ascent();
+    let mut caret = x;
+    for c in text.chars() {
+        let glyph = scaled
+            .glyph_id(c)
+            .with_scale_and_position(size, point(caret, baseline));
+        caret += scaled.h_advance(glyph.id);
+        if let Some(outline) = font.outline_glyph(glyph) {
+            let bounds = outline.px_bounds();
+            outline.draw(|x, y, coverage| {
+                if coverage >= 0.5 {
+                    bitmap.set(
+                        bounds.min.x as i64 + x as i64,
+                        bounds.min.y as i64 + y as i64,
+                    );
+                }
+            });
+        }
+    }
+}
+
+/// Thai marks that sit above or below the letter before them, which a line must not start with.
+fn is_mark(c: char) -> bool {
+    matches!(c, '\u{0E31}' | '\u{0E34}'..='\u{0E3A}' | '\u{0E47}'..='\u{0E4E}')
+}
+
+/// Breaks `text` into lines no wider than `width`, at spaces where it can and between letters
+/// where it must, since Thai is written without spaces between words. Lines past `lines` are
+/// dropped and the last one kept ends in `…`.
+fn wrap(font: &FontRef, size: f32, text: &str, width: f32, lines: usize) -> Vec<String> {
+    let mut wrapped: Vec<String> = Vec::new();
+    let mut line = String::new();
+    let chars: Vec<char> = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect();
+    let mut i = 0;
+    while i < chars.len() {
+        // A letter with its marks is never split
+        let mut end = i + 1;
+        while end < chars.len() && is_mark(chars[end]) {
+            end += 1;
+        }
+        let cluster: String = chars[i..end].iter().collect();
+        if !line.is_empty() && text_width(font, size, &(line.clone() + &cluster)) > width {
+            let rest = match line.rfind(' ') {
+                Some(space) if cluster != " " => line.split_off(space + 1),
+                _ => String::new(),
+            };
+            wrapped.push(line.trim_end().to_string());
+            line = rest;
+        }
+        if !(line.is_empty() && cluster == " ") {
+            line += &cluster;
+        }
+        i = end;
+    }
+    if !line.is_empty() {
+        wrapped.push(line);
+    }
+    if wrapped.len() > lines {
+        wrapped.truncate(lines);
+        let last = &mut wrapped[lines - 1];
+        while !last.is_empty() && text_width(font, size, &format!("{last}…")) > width {
+            last.pop();
+            while last.ends_with(is_mark) {
+                last.pop();
+            }
+        }
+        last.push('…');
+    }
+    wrapped
+}
+
+const EAN_L: [u8; 10] = [
+    0b0001101, 0b0011001, 0b0010011, 0b0111101, 0b0100011, 0b0110001, 0b0101111, 0b0111011,
+    0b0110111, 0b0001011,
+];
+const EAN_G: [u8; 10] = [
+    0b0100111, 0b0110011, 0b0011011, 0b0100001, 0b0011101, 0b0111001, 0b0000101, 0b0010001,
+    0b0001001, 0b0010111,
+];
+/// Which of the left digits are written with the G codes, by the first digit of the barcode.
+const EAN_PARITY: [u8; 10] = [
+    0b000000, 0b001011, 0b001101, 0b001110, 0b010011, 0b011001, 0b011100, 0b010101, 0b010110,
+    0b011010,
+];
+
+/// The modules of an EAN-13 barcode, `true` for a bar.
+fn ean13(barcode: &str) -> Vec<bool> {
+    let digits: Vec<usize> = barcode
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .map(|digit| digit as usize)
+        .collect();
+    let mut modules = Vec::with_capacity(95);
+    let mut push = |bits: u8, count: u32| {
+        for i in (0..count).rev() {
+            modules.push(bits >> i & 1 == 1);
+        }
+    };
+    push(0b101, 3);
+    for (i, &digit) in digits[1..7].iter().enumerate() {
+        let code = match EAN_PARITY[digits[0]] >> (5 - i) & 1 {
+            0 => EAN_L[digit],
+            _ => EAN_G[digit],
+        };
+        push(code, 7);
+    }
+    push(0b01010, 5);
+    for &digit in &digits[7..] {
+        // The right digits are the L codes in negative
+        push(!EAN_L[digit] & 0x7F, 7);
+    }
+    push(0b101, 3);
+    modules
+}
+
+/// Widths of the bars and spaces of each Code 128 symbol, by value.
+const CODE128: [&str; 107] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212",
+    "221213", "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221",
+    "223211", "221132", "221231", "213212", "223112", "312131", "311222", "321122", "321221",
+    "312212", "322112", "322211", "212123", "212321", "232121", "111323", "131123", "131321",
+    "112313", "132113", "132311", "211313", "231113", "231311", "112133", "112331", "132131",
+    "113123", "113321", "133121", "313121", "211331", "231131", "213113", "213311", "213131",
+    "311123", "311321", "331121", "312113", "312311", "332111", "314111", "221411", "431111",
+    "111224", "111422", "121124", "121421", "141122", "141221", "112214", "112412", "122114",
+    "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111", "111242",
+    "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+    "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311",
+    "113141", "114131", "311141", "411131", "211412", "211214", "211232", "2331112",
+];
+const CODE128_START_B: usize = 104;
+const CODE128_START_C: usize = 105;
+const CODE128_STOP: usize = 106;
+
+/// The modules of a Code 128 barcode, `true` for a bar. Even runs of digits are packed two to a
+/// symbol with code set C, anything else is written with code set B. `None` when the barcode
+/// has characters Code 128 cannot write.
+fn code128(barcode: &str) -> Option<Vec<bool>> {
+    let values: Vec<usize> = match barcode.len().is_multiple_of(2)
+        && barcode.len() >= 4
+        && barcode.bytes().all(|b| b.is_ascii_digit())
+    {
+        true => std::iter::once(CODE128_START_C)
+            .chain(
+                barcode
+                    .as_bytes()
+                    .chunks(2)
+                    .map(|pair| ((pair[0] - b'0') * 10 + pair[1] - b'0') as usize),
+            )
+            .collect(),
+        false => {
+            if !barcode.bytes().all(|b| (b' '..=b'~').contains(&b)) {
+                return None;
+            }
+            std::iter::once(CODE128_START_B)
+                .chain(barcode.bytes().map(|b| (b - b' ') as usize))
+                .collect()
+        }
+    };
+    let checksum = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| i.max(1) * value)
+        .sum::<usize>()
+        % 103;
+
+    let mut modules = Vec::new();
+    for value in values.into_iter().chain([checksum, CODE128_STOP]) {
+        for (i, width) in CODE128[value].bytes().enumerate() {
+            modules.extend(std::iter::repeat_n(i % 2 == 0, (width - b'0') as usize));
+        }
+    }
+    Some(modules)
+}
+
+/// Draws one shelf tag: the name on up to two lines, the price under it and the barcode at the
+/// bottom with its number below. Barcodes that are valid EAN-13 are drawn as EAN-13, others as
+/// Code 128, and ones neither can write only as text.
+fn render_tag(font: &FontRef, layout: &Layout, item: &LabelQueueItem) -> Bitmap {
+    let (width, height) = (
+        layout.dots(layout.width as f32),
+        layout.dots(layout.height as f32),
+    );
+    let mut tag = Bitmap::new(width, height);
+    let margin = layout.dots(2.0);
+    let inner = (width - 2 * margin) as f32;
+
+    let name_size = height as f32 * 0.13;
+    let mut top = margin as f32;
+    for line in wrap(font, name_size, &item.name, inner, 2) {
+        draw_text(&mut tag, font, name_size, margin as f32, top, &line);
+        top += name_size;
+    }
+
+    let price = format!("฿{:.2}", item.price.round_dp(2));
+    let price_size = height as f32 * 0.26;
+    let price_width = text_width(font, price_size, &price);
+    draw_text(
+        &mut tag,
+        font,
+        price_size,
+        (width - margin) as f32 - price_width,
+        top,
+        &price,
+    );
+    top += price_size;
+
+    let digits_size = height as f32 * 0.09;
+    let bottom = (height - margin) as f32 - digits_size;
+    let modules = match shared::barcode::is_ean13(&item.barcode) {
+        true => Some(ean13(&item.barcode)),
+        false => code128(&item.barcode),
+    };
+    if let Some(modules) = modules {
+        // Whole dots per module, no wider than what scanners expect of a tag
+        let module = (inner as u32 / modules.len() as u32).clamp(1, layout.dots(0.5));
+        let bars_width = module * modules.len() as u32;
+        let x = (width - bars_width.min(width)) / 2;
+        let bars_height = (bottom - top) as u32;
+        for (i, _) in modules.iter().enumerate().filter(|(_, bar)| **bar) {
+            tag.fill(x + i as u32 * module, top as u32, module, bars_height);
+        }
+    }
+    let digits_width = text_width(font, digits_size, &item.barcode);
+    draw_text(
+        &mut tag,
+        font,
+        digits_size,
+        (width as f32 - digits_width) / 2.0,
+        bottom,
+        &item.barcode,
+    );
+    tag
+}
+
+/// The A4 pages of tags, filled left to right and top to bottom.
+fn sheets(items: &[LabelQueueItem]) -> Vec<Bitmap> {
+    let font = font();
+    let layout = &SHEET_TAG;
+    let (tag_width, tag_height) = (
+        layout.dots(layout.width as f32),
+        layout.dots(layout.height as f32),
+    );
+    let (page_width, page_height) = (layout.dots(A4.0 as f32), layout.dots(A4.1 as f32));
+    let left = (page_width - SHEET_COLUMNS * tag_width) / 2;
+    let top = (page_height - SHEET_ROWS * tag_height) / 2;
+    items
+        .chunks((SHEET_COLUMNS * SHEET_ROWS) as usize)
+        .map(|page_items| {
+            let mut page = Bitmap::new(page_width, page_height);
+            for (i, item) in page_items.iter().enumerate() {
+                let (column, row) = (i as u32 % SHEET_COLUMNS, i as u32 / SHEET_COLUMNS);
+                page.draw(
+                    &render_tag(&font, layout, item),
+                    left + column * tag_width,
+                    top + row * tag_height,
+                );
+            }
+            page
+        })
+        .collect()
+}
+
+/// The pages as one picture, one under the other.
+fn png(pages: &[Bitmap]) -> Result<Vec<u8>, AppError> {
+    let width = pages[0].width;
+    let mut image = Bitmap::new(width, pages.iter().map(|page| page.height).sum());
+    let mut top = 0;
+    for page in pages {
+        image.draw(page, 0, top);
+        top += page.height;
+    }
+
+    let mut body = Vec::new();
+    let mut encoder = png::Encoder::new(&mut body, image.width, image.height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::One);
+    // So that it prints at the size of the sheet
+    let dots_per_metre = SHEET_TAG.dots_per_mm * 1000;
+    encoder.set_pixel_dims(Some(png::PixelDimensions {
+        xppu: dots_per_metre,
+        yppu: dots_per_metre,
+        unit: png::Unit::Meter,
+    }));
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&image.packed(false)))
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    Ok(body)
+}
+
+/// A PDF with each page a picture of the whole A4 sheet, so it prints the same from any viewer
+/// without the fonts of the computer it is printed from.
+fn pdf(pages: &[Bitmap]) -> Result<Vec<u8>, AppError> {
+    // Points are 1/72 inch
+    let size = |mm: u32| mm as f32 * 72.0 / 25.4;
+    let (width, height) = (size(A4.0), size(A4.1));
+
+    // Objects 1 and 2 are the catalog and the page tree, then a page, its content and its
+    // picture for each sheet
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len())
+                .map(|i| format!("{} 0 R", 3 + i * 3))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+    ];
+    for (i, page) in pages.iter().enumerate() {
+        let id = 3 + i * 3;
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {width:.2} {height:.2}] \
+                 /Resources << /XObject << /Sheet {} 0 R >> >> /Contents {} 0 R >>",
+                id + 2,
+                id + 1
+            )
+            .into_bytes(),
+        );
+        let content = format!("q {width:.2} 0 0 {height:.2} 0 0 cm /Sheet Do Q");
+        objects.push(
+            format!(
+                "<< /Length {} >>\nstream\n{content}\nendstream",
+                content.len()
+            )
+            .into_bytes(),
+        );
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        // DeviceGray takes 1 for white
+        encoder.write_all(&page.packed(false))?;
+        let data = encoder.finish()?;
+        let mut image = format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray \
+             /BitsPerComponent 1 /Filter /FlateDecode /Length {} >>\nstream\n",
+            page.width,
+            page.height,
+            data.len()
+        )
+        .into_bytes();
+        image.extend(data);
+        image.extend(b"\nendstream");
+        objects.push(image);
+    }
+
+    let mut body = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(body.len());
+        body.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+        body.extend(object);
+        body.extend(b"\nendobj\n");
+    }
+    let xref = body.len();
+    body.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        body.extend(format!("{offset:010} 00000 n \n").into_bytes());
+    }
+    body.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .into_bytes(),
+    );
+    Ok(body)
+}
+
+/// TSPL commands printing one label per tag. The printer has no Thai font, so each tag is sent
+/// as a picture.
+fn tspl(items: &[LabelQueueItem]) -> Vec<u8> {
+    let font = font();
+    let layout = &THERMAL_TAG;
+    let mut body = format!(
+        "SIZE {} mm,{} mm\r\nGAP {THERMAL_GAP_MM} mm,0 mm\r\nDIRECTION 1\r\n",
+        layout.width, layout.height
+    )
+    .into_bytes();
+    for item in items {
+        let tag = render_tag(&font, layout, item);
+        body.extend(b"CLS\r\n");
+        body.extend(format!("BITMAP 0,0,{},{},0,", tag.width.div_ceil(8), tag.height).into_bytes());
+        // TSPL prints the dots whose bit is 0
+        body.extend(tag.packed(false));
+        body.extend(b"\r\nPRINT 1,1\r\n");
+    }
+    body
+}
+
+/// The shelf tags that are out of date, for the owner to pick which to print.
+pub async fn get_label_queue(
+    State(db): State<Database>,
+) -> Result<Json<Vec<LabelQueueItem>>, AppError> {
+    Ok(Json(db.select_label_queue().await?))
+}
+
+/// Draws a shelf tag for each item of the request with its current name and price, in the
+/// format asked for. The prices drawn are recorded as printed, taking the items off the queue.
+pub async fn post_labels(
+    State(db): State<Database>,
+    Json(request): Json<LabelRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if request.barcodes.is_empty() {
+        return Err(AppError::InvalidInput("no items to label".to_string()));
+    }
+    let mut items = Vec::new();
+    for barcode in &request.barcodes {
+        let item = db
+            .select_item(barcode)
+            .await?
+            .ok_or_else(|| AppError::InvalidInput(format!("{barcode} is not in the catalogue")))?;
+        items.push(LabelQueueItem {
+            barcode: item.barcode,
+            name: item.name,
+            price: item.price,
+            printed_price: None,
+        });
+    }
+
+    let (content_type, body) = match request.format {
+        LabelFormat::Pdf => ("application/pdf", pdf(&sheets(&items))?),
+        LabelFormat::Png => ("image/png", png(&sheets(&items))?),
+        LabelFormat::Tspl => ("application/octet-stream", tspl(&items)),
+    };
+    let prices: Vec<(String, Decimal)> = items
+        .into_iter()
+        .map(|item| (item.barcode, item.price))
+        .collect();
+    db.upsert_label_prints(&prices).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"labels.{}\"",
+                    request.format.extension()
+                ),
+            ),
+        ],
+        body,
+    ))
+}
@@ -1,20 +1,89 @@
+mod audit;
+mod auth;
+mod catalogue;
+pub mod config;
+mod customer;
 mod database;
+mod discovery;
+mod events;
+mod jobs;
+mod label;
+mod promotion;
+mod receipt;
+mod returns;
+mod stock_take;
+mod sync;
 
+use std::fmt;
 use std::num::ParseIntError;
 
 use axum::{
+    Router,
+    extract::{Extension, FromRef, Path, Query, State},
     http::StatusCode,
+    middleware,
     response::{IntoResponse, Json},
+    routing::{get, post, put},
 };
 use chrono::NaiveDate;
 use futures::future::try_join_all;
+use rust_decimal::Decimal;
+use serde::Deserialize;
 use serde_json::json;
-use shared::{BulkItem, Item};
+use shared::barcode::BarcodeKind;
+use shared::number;
+use shared::{
+    BulkItem, Category, CategorySales, Header, Health, Item, ItemEvent, Job, PaymentSales, Role,
+    SyncReport, User, VatSummary,
+};
+
+use audit::{Actor, Source};
+use auth::Owner;
+use config::{Cli, Command, Config, ConfigError, SyncMode};
+use database::CatalogueEntry;
+use database::Database;
 
 #[derive(Debug)]
 pub enum AppError {
     ParseIntError(ParseIntError),
     DatabaseError(sqlx::Error),
+    CsvError(csv::Error),
+    XlsxError(rust_xlsxwriter::XlsxError),
+    InvalidInput(String),
+    NotFound,
+    Conflict(String),
+    Unauthorized,
+    Forbidden,
+    PasswordHashError(argon2::password_hash::Error),
+    MigrateError(sqlx::migrate::MigrateError),
+    IoError(std::io::Error),
+    ConfigError(ConfigError),
+    SchemaTooNew(i64, i64),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ParseIntError(e) => write!(f, "{e}"),
+            Self::DatabaseError(e) => write!(f, "database: {e}"),
+            Self::CsvError(e) => write!(f, "csv: {e}"),
+            Self::XlsxError(e) => write!(f, "xlsx: {e}"),
+            Self::InvalidInput(e) => write!(f, "{e}"),
+            Self::NotFound => write!(f, "not found"),
+            Self::Conflict(e) => write!(f, "{e}"),
+            Self::Unauthorized => write!(f, "login required"),
+            Self::Forbidden => write!(f, "not allowed for this user"),
+            Self::PasswordHashError(e) => write!(f, "password hash: {e}"),
+            Self::MigrateError(e) => write!(f, "migration: {e}"),
+            Self::IoError(e) => write!(f, "{e}"),
+            Self::ConfigError(e) => write!(f, "configuration: {e}"),
+            Self::SchemaTooNew(database, server) => write!(
+                f,
+                "the database schema ({database}) is newer than this server understands \
+                 ({server}), update the server"
+            ),
+        }
+    }
 }
 
 impl From<ParseIntError> for AppError {
@@ -29,6 +98,42 @@ impl From<sqlx::Error> for AppError {
     }
 }
 
+impl From<csv::Error> for AppError {
+    fn from(error: csv::Error) -> Self {
+        AppError::CsvError(error)
+    }
+}
+
+impl From<rust_xlsxwriter::XlsxError> for AppError {
+    fn from(error: rust_xlsxwriter::XlsxError) -> Self {
+        AppError::XlsxError(error)
+    }
+}
+
+impl From<argon2::password_hash::Error> for AppError {
+    fn from(error: argon2::password_hash::Error) -> Self {
+        AppError::PasswordHashError(error)
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for AppError {
+    fn from(error: sqlx::migrate::MigrateError) -> Self {
+        AppError::MigrateError(error)
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        AppError::IoError(error)
+    }
+}
+
+impl From<ConfigError> for AppError {
+    fn from(error: ConfigError) -> Self {
+        AppError::ConfigError(error)
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
         match self {
@@ -40,35 +145,601 @@ impl IntoResponse for AppError {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({ "error": e.to_string() })),
             ),
+            Self::CsvError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": e.to_string() })),
+            ),
+            Self::XlsxError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": e.to_string() })),
+            ),
+            Self::InvalidInput(e) => (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))),
+            Self::NotFound => (StatusCode::NOT_FOUND, Json(json!({ "error": "not found" }))),
+            Self::Conflict(e) => (StatusCode::CONFLICT, Json(json!({ "error": e }))),
+            Self::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({ "error": "login required" })),
+            ),
+            Self::Forbidden => (
+                StatusCode::FORBIDDEN,
+                Json(json!({ "error": "not allowed for this user" })),
+            ),
+            Self::PasswordHashError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": e.to_string() })),
+            ),
+            Self::MigrateError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": e.to_string() })),
+            ),
+            Self::IoError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": e.to_string() })),
+            ),
+            Self::ConfigError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": e.to_string() })),
+            ),
+            Self::SchemaTooNew(..) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": self.to_string() })),
+            ),
         }
         .into_response()
     }
 }
 
-pub async fn sync_database() {
-    let _ = database::sync_database().await;
+/// What every handler can reach.
+#[derive(Clone)]
+pub struct AppState {
+    db: Database,
+    old_database_url: Option<String>,
+    sync_mode: SyncMode,
+    vat_rate: Decimal,
+    baht_per_point: Decimal,
+    jobs: jobs::Jobs,
+}
+
+impl AppState {
+    /// Connects to the database of the shop, see [`database::Database::connect`] for the urls
+    /// it understands.
+    pub async fn connect(
+        database_url: &str,
+        pool_size: u32,
+        old_database_url: Option<String>,
+        sync_mode: SyncMode,
+        vat_rate: Decimal,
+        baht_per_point: Decimal,
+    ) -> Result<Self, AppError> {
+        Ok(AppState {
+            db: Database::connect(database_url, pool_size).await?,
+            old_database_url,
+            sync_mode,
+            vat_rate,
+            baht_per_point,
+            jobs: jobs::Jobs::default(),
+        })
+    }
+
+    /// Applies the pending migrations, see [`database::Repository::migrate`].
+    pub async fn migrate(&self) -> Result<(), AppError> {
+        self.db.migrate().await
+    }
+
+    /// Syncs the catalogue with the old program, see [`sync::sync`], and tells the clients to
+    /// reload it.
+    async fn sync(&self, actor: &Actor) -> Result<SyncReport, AppError> {
+        let old_database_url = self
+            .old_database_url
+            .as_deref()
+            .ok_or(ConfigError::Missing("old_database_url"))?;
+        let report = sync::sync(&self.db, old_database_url, self.sync_mode, actor).await?;
+        events::publish(ItemEvent::Reload);
+        Ok(report)
+    }
+}
+
+impl FromRef<AppState> for Database {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
+/// Runs one command of the command line, see [`Cli`].
+pub async fn run(cli: Cli) -> Result<(), AppError> {
+    let (config, command) = cli.load()?;
+    let state = AppState::connect(
+        &config.database_url,
+        config.pool_size,
+        config.old_database_url.clone(),
+        config.sync_mode,
+        config.vat_rate,
+        config.baht_per_point,
+    )
+    .await?;
+    state.migrate().await?;
+
+    match command {
+        Command::Serve(_) => serve(config, state).await,
+        Command::Sync => {
+            let report = state.sync(&Actor::server(Source::Sync)).await?;
+            sync::print_conflicts(&report);
+            println!("{report}");
+            Ok(())
+        }
+        Command::Migrate => {
+            println!("schema version {}", state.db.latest_schema_version());
+            Ok(())
+        }
+        Command::Import { file, dry_run } => {
+            let body = std::fs::read_to_string(&file)?;
+            let actor = Actor::server(Source::Import);
+            let report = catalogue::import(&state.db, &body, dry_run, &actor).await?;
+            for error in report.errors.iter().chain(&report.conflicts) {
+                eprintln!("row {} {}: {}", error.row, error.barcode, error.message);
+            }
+            println!(
+                "{} inserted, {} updated, {} errors, {} conflicts, {}",
+                report.inserted,
+                report.updated,
+                report.errors.len(),
+                report.conflicts.len(),
+                match report.committed {
+                    true => "saved",
+                    false => "nothing saved",
+                }
+            );
+            Ok(())
+        }
+        Command::Export { file } => {
+            let body = match file.extension().and_then(|e| e.to_str()) {
+                Some("csv") => catalogue::csv(&state.db).await?,
+                Some("xlsx") => catalogue::xlsx(&state.db).await?,
+                _ => {
+                    return Err(AppError::InvalidInput(format!(
+                        "{} does not end with .csv or .xlsx",
+                        file.display()
+                    )));
+                }
+            };
+            std::fs::write(&file, body)?;
+            Ok(())
+        }
+    }
+}
+
+async fn serve(config: Config, state: AppState) -> Result<(), AppError> {
+    jobs::schedule(&state, &config);
+    tokio::spawn(answer_discovery(config.bind.port()));
+
+    let listener = tokio::net::TcpListener::bind(config.bind).await?;
+    println!("listening on {}", config.bind);
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
+
+/// Every route of the server. All of them except logging in and the first-run setup require a
+/// session token, see [`auth::authenticate`].
+pub fn router(state: AppState) -> Router {
+    let protected = Router::new()
+        .route("/me", get(auth::get_me))
+        .route("/logout", post(auth::logout))
+        .route("/users", get(auth::get_users).post(auth::post_user))
+        .route("/sync", post(post_sync))
+        .route("/jobs", get(jobs::get_jobs))
+        .route("/jobs/{job}", post(jobs::post_job))
+        .route("/items", get(get_items).post(post_item))
+        .route("/items/search", get(search_items))
+        .route("/events", get(events::get_events))
+        .route("/items/{barcode}", put(put_item).delete(delete_item))
+        .route("/items/{barcode}/history", get(audit::get_item_history))
+        .route("/barcodes/{kind}", post(post_barcode))
+        .route("/categories", get(get_categories).post(post_category))
+        .route(
+            "/categories/{id}",
+            put(put_category).delete(delete_category),
+        )
+        .route(
+            "/promotions",
+            get(promotion::get_promotions).post(promotion::post_promotion),
+        )
+        .route(
+            "/promotions/{id}",
+            put(promotion::put_promotion).delete(promotion::delete_promotion),
+        )
+        .route("/receipts", post(receipt::post_receipt))
+        .route("/receipts/{id}", get(returns::get_sale))
+        .route("/receipts/{id}/returns", post(returns::post_return))
+        .route("/receipts/{id}/void", post(returns::post_void))
+        .route(
+            "/receipts/{id}/tax-invoice",
+            get(receipt::get_tax_invoice).post(receipt::post_tax_invoice),
+        )
+        .route(
+            "/customers",
+            get(customer::get_customers).post(customer::post_customer),
+        )
+        .route(
+            "/customers/{id}",
+            get(customer::get_customer).put(customer::put_customer),
+        )
+        .route("/customers/{id}/statement", get(customer::get_statement))
+        .route(
+            "/customers/{id}/payments",
+            post(customer::post_customer_payment),
+        )
+        .route("/reports/categories", get(get_category_sales))
+        .route("/reports/payments", get(get_payment_sales))
+        .route("/reports/vat", get(get_vat_sales))
+        .route("/labels", post(label::post_labels))
+        .route("/labels/queue", get(label::get_label_queue))
+        .route("/export/items.csv", get(catalogue::export_csv))
+        .route("/export/items.xlsx", get(catalogue::export_xlsx))
+        .route("/import/items", post(catalogue::import_items))
+        .route(
+            "/stock-takes",
+            get(stock_take::get_stock_takes).post(stock_take::post_stock_take),
+        )
+        .route("/stock-takes/{id}", get(stock_take::get_stock_take))
+        .route(
+            "/stock-takes/{id}/counts",
+            put(stock_take::put_stock_counts),
+        )
+        .route(
+            "/stock-takes/{id}/commit",
+            post(stock_take::commit_stock_take),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::authenticate,
+        ));
+
+    Router::new()
+        .route("/", get(|| async { "Hello, World!" }))
+        .route("/health", get(get_health))
+        .route("/login", post(auth::login))
+        .route("/setup", post(auth::setup))
+        .merge(protected)
+        .with_state(state)
+}
+
+async fn answer_discovery(http_port: u16) {
+    if let Err(e) = discovery::respond(http_port).await {
+        eprintln!("discovery stopped: {e}");
+    }
+}
+
+/// Reports whether the server can reach its database, for the connection test in the client.
+pub(crate) async fn get_health(State(db): State<Database>) -> Json<Health> {
+    let schema_version = db.select_schema_version().await;
+    Json(Health {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        database: schema_version.is_ok(),
+        schema_version: schema_version.ok().flatten(),
+    })
 }
 
-pub async fn get_items() -> Result<Json<Vec<Item>>, AppError> {
-    let item_details = database::select_items().await?;
+/// Syncs right away and answers with what changed. Fails when a sync is already running.
+pub(crate) async fn post_sync(
+    State(state): State<AppState>,
+    Owner(user): Owner,
+) -> Result<Json<SyncReport>, AppError> {
+    let actor = Actor::user(&user, Source::Sync);
+    let report = state.jobs.run(Job::Sync, state.sync(&actor)).await?;
+    Ok(Json(report))
+}
 
-    let items = item_details.into_iter().map(|item| async move {
-        let bulk_items: Vec<BulkItem> = database::select_bulk_items(&item.barcode).await?;
-        let expire_dates: Vec<NaiveDate> = database::select_expire_dates(&item.barcode).await?;
+#[derive(Deserialize)]
+pub struct ItemFilter {
+    category: Option<u32>,
+}
 
-        Ok::<Item, AppError>(Item {
-            barcode: item.barcode,
-            name: item.name,
-            cost: item.cost,
-            price: item.price,
-            quantity: item.quantity,
-            image: item.image,
-            expire_date: expire_dates,
-            bulk_item: bulk_items,
+#[derive(Deserialize)]
+pub struct Search {
+    keyword: String,
+    category: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct DateRange {
+    from: NaiveDate,
+    to: NaiveDate,
+}
+
+/// Resolves a category filter to the category and all of its subcategories.
+async fn category_filter(
+    db: &Database,
+    category: Option<u32>,
+) -> Result<Option<Vec<u32>>, AppError> {
+    match category {
+        None => Ok(None),
+        Some(id) => {
+            let categories = db.select_categories().await?;
+            Ok(Some(shared::category_with_descendants(&categories, id)))
+        }
+    }
+}
+
+fn in_category(category_id: Option<u32>, filter: &Option<Vec<u32>>) -> bool {
+    match filter {
+        None => true,
+        Some(ids) => category_id.is_some_and(|id| ids.contains(&id)),
+    }
+}
+
+pub(crate) async fn get_items(
+    State(db): State<Database>,
+    Query(filter): Query<ItemFilter>,
+) -> Result<Json<Vec<Item>>, AppError> {
+    Ok(Json(load_items(&db, filter.category).await?))
+}
+
+pub(crate) async fn load_items(
+    db: &Database,
+    category: Option<u32>,
+) -> Result<Vec<Item>, AppError> {
+    let filter = category_filter(db, category).await?;
+    let item_details = db
+        .select_items()
+        .await?
+        .into_iter()
+        .filter(|item| in_category(item.category_id, &filter));
+
+    try_join_all(item_details.map(|item| with_details(db, item))).await
+}
+
+pub(crate) async fn load_item(db: &Database, barcode: &str) -> Result<Option<Item>, AppError> {
+    match db.select_item(barcode).await? {
+        Some(item) => Ok(Some(with_details(db, item).await?)),
+        None => Ok(None),
+    }
+}
+
+async fn with_details(db: &Database, item: database::Item) -> Result<Item, AppError> {
+    let bulk_items: Vec<BulkItem> = db.select_bulk_items(&item.barcode).await?;
+    let expire_dates: Vec<NaiveDate> = db.select_expire_dates(&item.barcode).await?;
+
+    Ok(Item {
+        barcode: item.barcode,
+        name: item.name,
+        cost: item.cost,
+        price: item.price,
+        quantity: item.quantity,
+        unit: item.unit.unwrap_or_default(),
+        image: item.image,
+        category_id: item.category_id,
+        tax_class: item.tax_class.unwrap_or_default(),
+        expire_date: expire_dates,
+        bulk_item: bulk_items,
+    })
+}
+
+fn item_detail(item: Item) -> database::Item {
+    database::Item {
+        barcode: item.barcode,
+        name: item.name,
+        cost: item.cost,
+        price: item.price,
+        quantity: item.quantity.normalize(),
+        unit: Some(item.unit),
+        image: item.image,
+        category_id: item.category_id,
+        tax_class: Some(item.tax_class),
+    }
+}
+
+fn validate_item(item: &Item) -> Result<(), AppError> {
+    if item.barcode.trim().is_empty() {
+        return Err(AppError::InvalidInput("barcode is empty".to_string()));
+    }
+    if item.name.trim().is_empty() {
+        return Err(AppError::InvalidInput("name is empty".to_string()));
+    }
+    let prices = item
+        .bulk_item
+        .iter()
+        .map(|bulk_item| ("bulk price", bulk_item.price));
+    for (field, amount) in [("cost", item.cost), ("price", item.price)]
+        .into_iter()
+        .chain(prices)
+    {
+        number::validate_money(amount)
+            .map_err(|e| AppError::InvalidInput(format!("{field} {amount} {e}")))?;
+    }
+    if item.quantity.abs() > number::MAX_QUANTITY {
+        return Err(AppError::InvalidInput(format!(
+            "quantity {} is more than {}",
+            item.quantity,
+            number::MAX_QUANTITY
+        )));
+    }
+    if !item.unit.allows(item.quantity) {
+        return Err(AppError::InvalidInput(format!(
+            "quantity {} does not fit the unit {}",
+            item.quantity,
+            item.unit.as_str()
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) async fn post_item(
+    State(db): State<Database>,
+    Owner(user): Owner,
+    Json(item): Json<Item>,
+) -> Result<Json<Item>, AppError> {
+    validate_item(&item)?;
+    // Items already in the catalogue keep the barcodes they came with
+    shared::barcode::validate(&item.barcode)
+        .map_err(|e| AppError::InvalidInput(format!("{}: {e}", item.barcode)))?;
+    if db.select_item(&item.barcode).await?.is_some() {
+        return Err(AppError::Conflict(format!(
+            "item {} already exists",
+            item.barcode
+        )));
+    }
+    let entry = CatalogueEntry::Item(item_detail(item.clone()), Vec::new());
+    let actor = Actor::user(&user, Source::Client);
+    if let Some(Err(refusal)) = db.write_catalogue(&[entry], &actor, true).await?.pop() {
+        return Err(refusal.into());
+    }
+    events::publish_item(&db, &item.barcode).await;
+    Ok(Json(item))
+}
+
+/// Hands out a barcode of the shop's own for an item that has none. Numbers whose barcode an
+/// item already has, say one typed in by hand, are passed over.
+pub(crate) async fn post_barcode(
+    State(db): State<Database>,
+    _owner: Owner,
+    Path(kind): Path<BarcodeKind>,
+) -> Result<Json<String>, AppError> {
+    loop {
+        let number = db.next_barcode_number(kind.prefix()).await?;
+        let barcode = kind
+            .barcode(number)
+            .ok_or_else(|| AppError::Conflict(format!("no {kind} barcodes are left")))?;
+        if db.select_item(&barcode).await?.is_none() {
+            return Ok(Json(barcode));
+        }
+    }
+}
+
+/// Updates an item. Cashiers may fix names, prices and quantities but not the cost.
+pub(crate) async fn put_item(
+    State(db): State<Database>,
+    Extension(user): Extension<User>,
+    Path(barcode): Path<String>,
+    Json(mut item): Json<Item>,
+) -> Result<Json<Item>, AppError> {
+    item.barcode = barcode;
+    validate_item(&item)?;
+    let current = db
+        .select_item(&item.barcode)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if user.role == Role::Cashier && current.cost != item.cost {
+        return Err(AppError::Forbidden);
+    }
+    db.update_item(
+        &item_detail(item.clone()),
+        &Actor::user(&user, Source::Client),
+    )
+    .await?;
+    events::publish_item(&db, &item.barcode).await;
+    Ok(Json(item))
+}
+
+pub(crate) async fn delete_item(
+    State(db): State<Database>,
+    Owner(user): Owner,
+    Path(barcode): Path<String>,
+) -> Result<StatusCode, AppError> {
+    match db
+        .delete_item(&barcode, &Actor::user(&user, Source::Client))
+        .await?
+    {
+        0 => Err(AppError::NotFound),
+        _ => {
+            events::publish(ItemEvent::Deleted { barcode });
+            Ok(StatusCode::NO_CONTENT)
+        }
+    }
+}
+
+pub(crate) async fn search_items(
+    State(db): State<Database>,
+    Query(search): Query<Search>,
+) -> Result<Json<Vec<Header>>, AppError> {
+    let filter = category_filter(&db, search.category).await?;
+    let headers = db
+        .search_headers(&search.keyword)
+        .await?
+        .into_iter()
+        .filter(|header| in_category(header.category_id, &filter))
+        .map(|header| Header {
+            barcode: header.barcode,
+            name: header.name,
         })
-    });
+        .collect();
+
+    Ok(Json(headers))
+}
+
+pub(crate) async fn get_categories(
+    State(db): State<Database>,
+) -> Result<Json<Vec<Category>>, AppError> {
+    Ok(Json(db.select_categories().await?))
+}
+
+pub(crate) async fn post_category(
+    State(db): State<Database>,
+    _owner: Owner,
+    Json(mut category): Json<Category>,
+) -> Result<Json<Category>, AppError> {
+    if category.name.trim().is_empty() {
+        return Err(AppError::InvalidInput("category name is empty".to_string()));
+    }
+    category.id = db.insert_category(&category).await?;
+    Ok(Json(category))
+}
+
+pub(crate) async fn put_category(
+    State(db): State<Database>,
+    _owner: Owner,
+    Path(id): Path<u32>,
+    Json(mut category): Json<Category>,
+) -> Result<Json<Category>, AppError> {
+    category.id = id;
+    if category.name.trim().is_empty() {
+        return Err(AppError::InvalidInput("category name is empty".to_string()));
+    }
+    if let Some(parent_id) = category.parent_id {
+        let categories = db.select_categories().await?;
+        if shared::category_with_descendants(&categories, id).contains(&parent_id) {
+            return Err(AppError::InvalidInput(
+                "category cannot be nested under itself".to_string(),
+            ));
+        }
+    }
+    match db.update_category(&category).await? {
+        0 => Err(AppError::NotFound),
+        _ => Ok(Json(category)),
+    }
+}
 
-    let items: Vec<Item> = try_join_all(items).await?;
+pub(crate) async fn delete_category(
+    State(db): State<Database>,
+    _owner: Owner,
+    Path(id): Path<u32>,
+) -> Result<StatusCode, AppError> {
+    match db.delete_category(id).await? {
+        0 => Err(AppError::NotFound),
+        _ => Ok(StatusCode::NO_CONTENT),
+    }
+}
+
+pub(crate) async fn get_category_sales(
+    State(db): State<Database>,
+    _owner: Owner,
+    Query(range): Query<DateRange>,
+) -> Result<Json<Vec<CategorySales>>, AppError> {
+    Ok(Json(db.select_category_sales(range.from, range.to).await?))
+}
+
+pub(crate) async fn get_payment_sales(
+    State(db): State<Database>,
+    _owner: Owner,
+    Query(range): Query<DateRange>,
+) -> Result<Json<Vec<PaymentSales>>, AppError> {
+    Ok(Json(db.select_payment_sales(range.from, range.to).await?))
+}
 
-    Ok(Json(items))
+/// Output VAT by month for the VAT return, counting sales between the dates.
+pub(crate) async fn get_vat_sales(
+    State(db): State<Database>,
+    _owner: Owner,
+    Query(range): Query<DateRange>,
+) -> Result<Json<Vec<VatSummary>>, AppError> {
+    Ok(Json(db.select_vat_sales(range.from, range.to).await?))
 }
@@ -0,0 +1,102 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use rust_decimal::Decimal;
+use shared::{Promotion, PromotionRule};
+
+use crate::{AppError, Database, Owner};
+
+fn validate(promotion: &mut Promotion) -> Result<(), AppError> {
+    promotion.name = promotion.name.trim().to_string();
+    if promotion.name.is_empty() {
+        return Err(AppError::InvalidInput(
+            "promotion name is empty".to_string(),
+        ));
+    }
+    promotion.barcodes.sort();
+    promotion.barcodes.dedup();
+    if promotion.barcodes.is_empty() {
+        return Err(AppError::InvalidInput("promotion has no items".to_string()));
+    }
+    let valid = match promotion.rule {
+        PromotionRule::Percent { percent } => {
+            percent > Decimal::ZERO && percent <= Decimal::ONE_HUNDRED
+        }
+        PromotionRule::Amount { amount } => amount > Decimal::ZERO,
+        PromotionRule::BuyGet { buy, get } => buy > 0 && get > 0,
+        PromotionRule::Bundle { quantity, price } => quantity > 1 && price >= Decimal::ZERO,
+    };
+    if !valid {
+        return Err(AppError::InvalidInput(format!(
+            "invalid promotion rule: {:?}",
+            promotion.rule
+        )));
+    }
+    if let (Some(starts_at), Some(ends_at)) = (promotion.starts_at, promotion.ends_at)
+        && starts_at >= ends_at
+    {
+        return Err(AppError::InvalidInput(
+            "promotion ends before it starts".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn unknown_item(error: sqlx::Error) -> AppError {
+    match error {
+        sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+            AppError::InvalidInput("promotion has an item that is not in the catalogue".to_string())
+        }
+        e => e.into(),
+    }
+}
+
+/// Every promotion, running or not. Tills apply the running ones to the cart themselves with
+/// [`shared::apply_promotions`].
+pub async fn get_promotions(State(db): State<Database>) -> Result<Json<Vec<Promotion>>, AppError> {
+    Ok(Json(db.select_promotions().await?))
+}
+
+pub async fn post_promotion(
+    State(db): State<Database>,
+    _owner: Owner,
+    Json(mut promotion): Json<Promotion>,
+) -> Result<(StatusCode, Json<Promotion>), AppError> {
+    validate(&mut promotion)?;
+    promotion.id = db
+        .insert_promotion(&promotion)
+        .await
+        .map_err(unknown_item)?;
+    Ok((StatusCode::CREATED, Json(promotion)))
+}
+
+pub async fn put_promotion(
+    State(db): State<Database>,
+    _owner: Owner,
+    Path(id): Path<u32>,
+    Json(mut promotion): Json<Promotion>,
+) -> Result<Json<Promotion>, AppError> {
+    promotion.id = id;
+    validate(&mut promotion)?;
+    match db
+        .update_promotion(&promotion)
+        .await
+        .map_err(unknown_item)?
+    {
+        0 => Err(AppError::NotFound),
+        _ => Ok(Json(promotion)),
+    }
+}
+
+pub async fn delete_promotion(
+    State(db): State<Database>,
+    _owner: Owner,
+    Path(id): Path<u32>,
+) -> Result<StatusCode, AppError> {
+    match db.delete_promotion(id).await? {
+        0 => Err(AppError::NotFound),
+        _ => Ok(StatusCode::NO_CONTENT),
+    }
+}
@@ -0,0 +1,267 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use rust_decimal::Decimal;
+use shared::number;
+use shared::{
+    Buyer, Payment, PaymentMethod, Receipt, ReceiptItem, TaxInvoice, User, points_earned,
+};
+
+use crate::{AppError, AppState, Database, events};
+
+fn validate(receipt: &Receipt) -> Result<(), AppError> {
+    if receipt.key.is_empty() || receipt.key.len() > 36 {
+        return Err(AppError::InvalidInput(
+            "receipt key must be 1 to 36 characters".to_string(),
+        ));
+    }
+    if receipt.items.is_empty() {
+        return Err(AppError::InvalidInput("receipt has no items".to_string()));
+    }
+    for item in &receipt.items {
+        if item.quantity <= Decimal::ZERO {
+            return Err(AppError::InvalidInput(format!(
+                "quantity of {} must be positive",
+                item.barcode
+            )));
+        }
+        if item.quantity > number::MAX_QUANTITY {
+            return Err(AppError::InvalidInput(format!(
+                "quantity of {} is more than {}",
+                item.barcode,
+                number::MAX_QUANTITY
+            )));
+        }
+        if item.quantity.normalize().scale() > 3 {
+            return Err(AppError::InvalidInput(format!(
+                "quantity of {} has more than 3 decimals",
+                item.barcode
+            )));
+        }
+        number::validate_money(item.price)
+            .map_err(|e| AppError::InvalidInput(format!("price of {} {e}", item.barcode)))?;
+    }
+    // Discounts are taken as the till worked them out, which may have been offline with older
+    // promotions, as long as no line goes below zero
+    for discount in &receipt.discounts {
+        let total: Decimal = receipt
+            .items
+            .iter()
+            .filter(|item| item.barcode == discount.barcode)
+            .map(ReceiptItem::total)
+            .sum();
+        let discounted: Decimal = receipt
+            .discounts
+            .iter()
+            .filter(|other| other.barcode == discount.barcode)
+            .map(|other| other.amount)
+            .sum();
+        if discount.amount <= Decimal::ZERO || discounted > total {
+            return Err(AppError::InvalidInput(format!(
+                "invalid discount on {}",
+                discount.barcode
+            )));
+        }
+    }
+    for payment in &receipt.payments {
+        if payment.amount <= Decimal::ZERO {
+            return Err(AppError::InvalidInput(format!(
+                "{} payment must be positive",
+                payment.method.as_str()
+            )));
+        }
+        number::validate_money(payment.amount).map_err(|e| {
+            AppError::InvalidInput(format!("{} payment {e}", payment.method.as_str()))
+        })?;
+        if payment.method == PaymentMethod::Credit && receipt.customer_id.is_none() {
+            return Err(AppError::InvalidInput(
+                "credit payment needs a customer".to_string(),
+            ));
+        }
+        if payment.method == PaymentMethod::Points {
+            if receipt.customer_id.is_none() {
+                return Err(AppError::InvalidInput(
+                    "points payment needs a customer".to_string(),
+                ));
+            }
+            if !payment.amount.fract().is_zero() {
+                return Err(AppError::InvalidInput(
+                    "points are redeemed in whole baht".to_string(),
+                ));
+            }
+        }
+    }
+    if receipt.payments.is_empty() {
+        return Ok(());
+    }
+    let total = receipt.total();
+    let not_cash: Decimal = receipt
+        .payments
+        .iter()
+        .filter(|payment| payment.method != PaymentMethod::Cash)
+        .map(|payment| payment.amount)
+        .sum();
+    if not_cash > total {
+        return Err(AppError::InvalidInput(
+            "only cash can be paid over the total".to_string(),
+        ));
+    }
+    if receipt.paid() < total {
+        return Err(AppError::InvalidInput(format!(
+            "receipt is {} short",
+            total - receipt.paid()
+        )));
+    }
+    Ok(())
+}
+
+/// Leaves the payments adding up to the total: the change is taken off the cash paid, and a
+/// receipt without payments was paid in cash.
+fn settle(receipt: &mut Receipt) {
+    if receipt.payments.is_empty() {
+        receipt.payments.push(Payment {
+            method: PaymentMethod::Cash,
+            amount: receipt.total(),
+            reference: None,
+        });
+        return;
+    }
+    let mut change = receipt.change();
+    for payment in receipt.payments.iter_mut().rev() {
+        if payment.method == PaymentMethod::Cash {
+            let returned = change.min(payment.amount);
+            payment.amount -= returned;
+            change -= returned;
+        }
+    }
+    receipt
+        .payments
+        .retain(|payment| payment.amount > Decimal::ZERO);
+}
+
+/// Works out the VAT of every line by the tax class of the item in the catalogue, whatever the
+/// till sent.
+async fn tax(db: &Database, receipt: &mut Receipt, vat_rate: Decimal) -> Result<(), AppError> {
+    for line in &mut receipt.items {
+        if let Some(item) = db.select_item(&line.barcode).await? {
+            line.tax_class = item.tax_class.unwrap_or_default();
+        }
+    }
+    receipt.apply_tax(vat_rate);
+    Ok(())
+}
+
+/// Records a sale and answers with the receipt id. Tills queue receipts while the server is out
+/// of reach and send them again on reconnect, so a key that was already recorded gets the
+/// existing id back with `200 OK` instead of being counted twice.
+///
+/// For the same reason a sale over the credit limit of its customer, or paid with more points
+/// than they have, is recorded anyway. Tills check both before taking the payment.
+pub async fn post_receipt(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Json(mut receipt): Json<Receipt>,
+) -> Result<(StatusCode, Json<u32>), AppError> {
+    let db = state.db;
+    // A receipt already recorded is answered even when it would not pass the checks of today
+    if let Some(id) = db.select_receipt_id(&receipt.key).await? {
+        return Ok((StatusCode::OK, Json(id)));
+    }
+    validate(&receipt)?;
+    settle(&mut receipt);
+    tax(&db, &mut receipt, state.vat_rate).await?;
+    // Points are earned on what was paid for, not on what was paid with points
+    receipt.points = match receipt.customer_id {
+        Some(_) => points_earned(
+            receipt.total() - receipt.paid_with(PaymentMethod::Points),
+            state.baht_per_point,
+        ),
+        None => 0,
+    };
+
+    match db.insert_receipt(&receipt, user.id).await {
+        Ok(id) => {
+            for item in &receipt.items {
+                events::publish_item(&db, &item.barcode).await;
+            }
+            Ok((StatusCode::CREATED, Json(id)))
+        }
+        Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => Err(
+            AppError::InvalidInput("receipt is for a customer that does not exist".to_string()),
+        ),
+        // The same receipt was sent twice at once and the other request won
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            let id = db
+                .select_receipt_id(&receipt.key)
+                .await?
+                .ok_or(AppError::NotFound)?;
+            Ok((StatusCode::OK, Json(id)))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn validate_buyer(buyer: &Buyer) -> Result<(), AppError> {
+    if buyer.name.trim().is_empty() || buyer.address.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "a full tax invoice needs the name and address of the buyer".to_string(),
+        ));
+    }
+    if buyer.tax_id.len() != 13 || !buyer.tax_id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AppError::InvalidInput(
+            "tax id of the buyer must be 13 digits".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn get_tax_invoice(
+    State(db): State<Database>,
+    Path(id): Path<u32>,
+) -> Result<Json<TaxInvoice>, AppError> {
+    db.select_tax_invoice(id)
+        .await?
+        .map(Json)
+        .ok_or(AppError::NotFound)
+}
+
+/// Issues the tax invoice of a receipt, a full one when the body names the buyer and an
+/// abbreviated one for `null`. Asking again answers with the invoice already issued with
+/// `200 OK`, as long as it is of the same kind.
+pub async fn post_tax_invoice(
+    State(db): State<Database>,
+    Path(id): Path<u32>,
+    Json(buyer): Json<Option<Buyer>>,
+) -> Result<(StatusCode, Json<TaxInvoice>), AppError> {
+    if let Some(buyer) = &buyer {
+        validate_buyer(buyer)?;
+    }
+    // Invoices issued at the same time may take the same number, the one that lost tries again
+    for _ in 0..3 {
+        if let Some(invoice) = db.select_tax_invoice(id).await? {
+            if invoice.buyer.is_some() != buyer.is_some() {
+                return Err(AppError::Conflict(format!(
+                    "receipt {id} already has tax invoice {}",
+                    invoice.number()
+                )));
+            }
+            return Ok((StatusCode::OK, Json(invoice)));
+        }
+        match db.insert_tax_invoice(id, buyer.as_ref()).await {
+            Ok(()) => {
+                let invoice = db.select_tax_invoice(id).await?.ok_or(AppError::NotFound)?;
+                return Ok((StatusCode::CREATED, Json(invoice)));
+            }
+            Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => {
+                return Err(AppError::NotFound);
+            }
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(AppError::Conflict(
+        "tax invoice could not be numbered, try again".to_string(),
+    ))
+}
@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::Local;
+use rust_decimal::Decimal;
+use shared::{
+    Disposition, Payment, PaymentMethod, ReturnLine, ReturnRequest, SaleRecord, SaleReturn, User,
+    VoidRequest, points_earned,
+};
+
+use crate::{AppError, AppState, Database, Owner, events};
+
+fn validate(record: &SaleRecord, request: &ReturnRequest) -> Result<(), AppError> {
+    if record.voided() {
+        return Err(AppError::Conflict(format!(
+            "receipt {} was voided",
+            record.id
+        )));
+    }
+    if request.lines.is_empty() {
+        return Err(AppError::InvalidInput("return has no items".to_string()));
+    }
+    let mut barcodes = HashSet::new();
+    for line in &request.lines {
+        if !barcodes.insert(&line.barcode) {
+            return Err(AppError::InvalidInput(format!(
+                "{} is returned twice",
+                line.barcode
+            )));
+        }
+        if line.quantity <= Decimal::ZERO {
+            return Err(AppError::InvalidInput(format!(
+                "quantity of {} must be positive",
+                line.barcode
+            )));
+        }
+        if line.quantity.normalize().scale() > 3 {
+            return Err(AppError::InvalidInput(format!(
+                "quantity of {} has more than 3 decimals",
+                line.barcode
+            )));
+        }
+        let left = record.sold(&line.barcode) - record.returned(&line.barcode);
+        if line.quantity > left {
+            return Err(AppError::InvalidInput(format!(
+                "only {left} of {} are left to return",
+                line.barcode
+            )));
+        }
+    }
+    match request.refund_method {
+        PaymentMethod::Points => Err(AppError::InvalidInput(
+            "refunds cannot be paid in points".to_string(),
+        )),
+        PaymentMethod::Credit if record.receipt.customer_id.is_none() => Err(
+            AppError::InvalidInput("credit refund needs the customer of the sale".to_string()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Records the return and puts the restocked goods back in front of the tills.
+async fn insert(
+    db: &Database,
+    record: &SaleRecord,
+    mut sale_return: SaleReturn,
+    user: &User,
+) -> Result<(StatusCode, Json<SaleReturn>), AppError> {
+    let inserted = db
+        .insert_return(
+            &sale_return,
+            record.receipt.customer_id,
+            record.returns.len(),
+            user.id,
+        )
+        .await?;
+    let Some(id) = inserted else {
+        return Err(AppError::Conflict(format!(
+            "receipt {} changed while returning, try again",
+            record.id
+        )));
+    };
+    sale_return.id = id;
+    for line in &sale_return.lines {
+        if line.disposition == Disposition::Restock {
+            events::publish_item(db, &line.barcode).await;
+        }
+    }
+    Ok((StatusCode::CREATED, Json(sale_return)))
+}
+
+/// The receipt with what was returned of it so far, for the till to pick a return from.
+pub async fn get_sale(
+    State(db): State<Database>,
+    Path(id): Path<u32>,
+) -> Result<Json<SaleRecord>, AppError> {
+    db.select_sale(id)
+        .await?
+        .map(Json)
+        .ok_or(AppError::NotFound)
+}
+
+/// Takes back some of what was sold on a receipt and refunds the share of what was paid for it
+/// with `refund_method`, and with the points spent on the sale once the money paid is refunded.
+/// The customer of the sale loses the points the money refunded had earned them, down to what the
+/// sale has left. The receipt itself stays as it was.
+pub async fn post_return(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(id): Path<u32>,
+    Json(request): Json<ReturnRequest>,
+) -> Result<(StatusCode, Json<SaleReturn>), AppError> {
+    let db = state.db;
+    let record = db.select_sale(id).await?.ok_or(AppError::NotFound)?;
+    validate(&record, &request)?;
+
+    let lines: Vec<ReturnLine> = request
+        .lines
+        .iter()
+        .map(|line| {
+            let (refund, tax) = record.refund(&line.barcode, line.quantity);
+            ReturnLine {
+                refund,
+                tax,
+                ..line.clone()
+            }
+        })
+        .collect();
+    let (money, redeemed) = record.refund_tender(lines.iter().map(|line| line.refund).sum());
+    let points = match record.receipt.customer_id {
+        Some(_) => -points_earned(money, state.baht_per_point).min(record.points_left()),
+        None => 0,
+    };
+    let refunds = [
+        (request.refund_method, money),
+        (PaymentMethod::Points, Decimal::from(redeemed)),
+    ];
+    let sale_return = SaleReturn {
+        id: 0,
+        receipt_id: id,
+        created_at: Local::now().naive_local(),
+        void: false,
+        reason: request.reason.trim().to_string(),
+        lines,
+        refunds: refunds
+            .into_iter()
+            .filter(|(_, amount)| *amount > Decimal::ZERO)
+            .map(|(method, amount)| Payment {
+                method,
+                amount,
+                reference: None,
+            })
+            .collect(),
+        points: points + redeemed,
+    };
+    insert(&db, &record, sale_return, &user).await
+}
+
+/// Undoes a whole receipt: every line goes back on the shelf, every payment is given back the
+/// way it was paid and the points are returned as they were before the sale. Only the owner may
+/// void, and only receipts nothing was returned from yet.
+pub async fn post_void(
+    State(db): State<Database>,
+    Owner(user): Owner,
+    Path(id): Path<u32>,
+    Json(request): Json<VoidRequest>,
+) -> Result<(StatusCode, Json<SaleReturn>), AppError> {
+    let reason = request.reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::InvalidInput("a void needs a reason".to_string()));
+    }
+    let record = db.select_sale(id).await?.ok_or(AppError::NotFound)?;
+    if record.voided() {
+        return Err(AppError::Conflict(format!("receipt {id} was voided")));
+    }
+    if !record.returns.is_empty() {
+        return Err(AppError::Conflict(format!(
+            "receipt {id} has returns, return the rest of it instead"
+        )));
+    }
+
+    let mut lines: Vec<ReturnLine> = Vec::new();
+    for item in &record.receipt.items {
+        if lines.iter().any(|line| line.barcode == item.barcode) {
+            continue;
+        }
+        let quantity = record.sold(&item.barcode);
+        let (refund, tax) = record.refund(&item.barcode, quantity);
+        lines.push(ReturnLine {
+            barcode: item.barcode.clone(),
+            quantity,
+            disposition: Disposition::Restock,
+            refund,
+            tax,
+        });
+    }
+    let receipt = &record.receipt;
+    let sale_return = SaleReturn {
+        id: 0,
+        receipt_id: id,
+        created_at: Local::now().naive_local(),
+        void: true,
+        reason: reason.to_string(),
+        lines,
+        refunds: receipt
+            .payments
+            .iter()
+            .map(|payment| Payment {
+                reference: None,
+                ..payment.clone()
+            })
+            .collect(),
+        points: receipt.redeemed_points() - receipt.points,
+    };
+    insert(&db, &record, sale_return, &user).await
+}
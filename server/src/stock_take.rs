@@ -0,0 +1,77 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+use rust_decimal::Decimal;
+use shared::{ItemEvent, StockCount, StockTake, StockTakeReport};
+
+use crate::audit::{Actor, Source};
+use crate::{AppError, Database, Owner, events};
+
+async fn report(db: &Database, id: u32) -> Result<StockTakeReport, AppError> {
+    let stock_take = db.select_stock_take(id).await?.ok_or(AppError::NotFound)?;
+    let lines = db.select_stock_take_lines(id).await?;
+    Ok(StockTakeReport { stock_take, lines })
+}
+
+pub async fn post_stock_take(State(db): State<Database>) -> Result<Json<StockTake>, AppError> {
+    let id = db.insert_stock_take().await?;
+    let stock_take = db.select_stock_take(id).await?.ok_or(AppError::NotFound)?;
+    Ok(Json(stock_take))
+}
+
+pub async fn get_stock_takes(State(db): State<Database>) -> Result<Json<Vec<StockTake>>, AppError> {
+    Ok(Json(db.select_stock_takes().await?))
+}
+
+pub async fn get_stock_take(
+    State(db): State<Database>,
+    Path(id): Path<u32>,
+) -> Result<Json<StockTakeReport>, AppError> {
+    Ok(Json(report(&db, id).await?))
+}
+
+/// Stores counted quantities for an open stock-take. Counts are absolute, so a terminal that was
+/// offline can resend everything it counted and the latest value wins.
+pub async fn put_stock_counts(
+    State(db): State<Database>,
+    Path(id): Path<u32>,
+    Json(counts): Json<Vec<StockCount>>,
+) -> Result<Json<StockTakeReport>, AppError> {
+    let stock_take = db.select_stock_take(id).await?.ok_or(AppError::NotFound)?;
+    if stock_take.committed_at.is_some() {
+        return Err(AppError::Conflict(
+            "stock-take is already committed".to_string(),
+        ));
+    }
+    if let Some(count) = counts.iter().find(|count| count.counted < Decimal::ZERO) {
+        return Err(AppError::InvalidInput(format!(
+            "counted quantity of {} is negative",
+            count.barcode
+        )));
+    }
+
+    db.upsert_stock_counts(id, &counts).await?;
+
+    Ok(Json(report(&db, id).await?))
+}
+
+pub async fn commit_stock_take(
+    State(db): State<Database>,
+    Owner(user): Owner,
+    Path(id): Path<u32>,
+) -> Result<Json<StockTakeReport>, AppError> {
+    if !db
+        .commit_stock_take(id, &Actor::user(&user, Source::StockTake))
+        .await?
+    {
+        return match db.select_stock_take(id).await? {
+            None => Err(AppError::NotFound),
+            Some(_) => Err(AppError::Conflict(
+                "stock-take is already committed".to_string(),
+            )),
+        };
+    }
+    events::publish(ItemEvent::Reload);
+    Ok(Json(report(&db, id).await?))
+}
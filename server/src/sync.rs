@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use shared::{SyncConflict, SyncReport};
+use sqlx::{MySqlPool, SqlitePool};
+
+use crate::AppError;
+use crate::audit::Actor;
+use crate::config::SyncMode;
+use crate::database::{CatalogueEntry, Database, Item, LegacyItem, Refusal};
+
+/// Columns of `ab01f`, the catalogue table of the old program: barcode, name, cost, price,
+/// quantity and expiry date.
+const SELECT_ITEMS: &str = "SELECT b01100, b01110, b01140, b01150, b01160, b01211 FROM ab01f";
+const ADD_QUANTITY: &str = "UPDATE ab01f SET b01160 = b01160 + ? WHERE b01100 = ?";
+const INSERT_ITEM: &str = "
+    INSERT INTO ab01f (b01100, b01110, b01140, b01150, b01160, b01211)
+    VALUES (?, ?, ?, ?, ?, ?)
+";
+
+type Row = (String, String, f32, f32, i32, String);
+
+/// The database of the old program. Besides the machine in the shop it can be a SQLite
+/// stand-in with the schema of `server/tests/fixtures/legacy`.
+enum OldDatabase {
+    MySql(MySqlPool),
+    Sqlite(SqlitePool),
+}
+
+impl OldDatabase {
+    async fn connect(url: &str) -> sqlx::Result<Self> {
+        if url.starts_with("sqlite:") {
+            Ok(OldDatabase::Sqlite(SqlitePool::connect(url).await?))
+        } else {
+            Ok(OldDatabase::MySql(MySqlPool::connect(url).await?))
+        }
+    }
+
+    async fn select_items(&self) -> sqlx::Result<Vec<(LegacyItem, Option<NaiveDate>)>> {
+        let rows: Vec<Row> = match self {
+            OldDatabase::MySql(pool) => sqlx::query_as(SELECT_ITEMS).fetch_all(pool).await?,
+            OldDatabase::Sqlite(pool) => sqlx::query_as(SELECT_ITEMS).fetch_all(pool).await?,
+        };
+        Ok(rows
+            .into_iter()
+            .map(|(barcode, name, cost, price, quantity, expire_date)| {
+                let item = LegacyItem {
+                    barcode,
+                    name,
+                    cost: decimal(cost),
+                    price: decimal(price),
+                    quantity,
+                };
+                (item, parse_date(&expire_date))
+            })
+            .collect())
+    }
+
+    /// Adds `change` to the stock of an item, leaving what the old program sold meanwhile.
+    async fn add_quantity(&self, barcode: &str, change: i32) -> sqlx::Result<()> {
+        match self {
+            OldDatabase::MySql(pool) => {
+                sqlx::query(ADD_QUANTITY)
+                    .bind(change)
+                    .bind(barcode)
+                    .execute(pool)
+                    .await?;
+            }
+            OldDatabase::Sqlite(pool) => {
+                sqlx::query(ADD_QUANTITY)
+                    .bind(change)
+                    .bind(barcode)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn insert_item(
+        &self,
+        item: &LegacyItem,
+        expire_date: Option<NaiveDate>,
+    ) -> sqlx::Result<()> {
+        let expire_date = expire_date
+            .map(|date| date.format("%d-%m-%Y").to_string())
+            .unwrap_or_default();
+        match self {
+            OldDatabase::MySql(pool) => {
+                sqlx::query(INSERT_ITEM)
+                    .bind(&item.barcode)
+                    .bind(&item.name)
+                    .bind(float(item.cost))
+                    .bind(float(item.price))
+                    .bind(item.quantity)
+                    .bind(expire_date)
+                    .execute(pool)
+                    .await?;
+            }
+            OldDatabase::Sqlite(pool) => {
+                sqlx::query(INSERT_ITEM)
+                    .bind(&item.barcode)
+                    .bind(&item.name)
+                    .bind(float(item.cost))
+                    .bind(float(item.price))
+                    .bind(item.quantity)
+                    .bind(expire_date)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The old program keeps money as floating point.
+fn decimal(value: f32) -> Decimal {
+    Decimal::from_f32(value).unwrap_or_default().round_dp(2)
+}
+
+fn float(value: Decimal) -> f32 {
+    value.to_f32().unwrap_or_default()
+}
+
+/// The old program counts whole units, so weighed stock goes there rounded.
+fn whole(quantity: Decimal) -> i32 {
+    quantity.round().to_i32().unwrap_or_default()
+}
+
+/// The old program keeps expiry dates as `dd-mm-yyyy` text. Anything else, including the
+/// empty text it writes for items without one, is no date.
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    match text.trim().split('-').collect::<Vec<_>>()[..] {
+        [day, month, year] if day.len() == 2 && month.len() == 2 && year.len() == 4 => {
+            NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+        }
+        _ => None,
+    }
+}
+
+/// Barcodes up to 1000 are the old program's codes for goods sold without a barcode, and stay
+/// there.
+fn is_old_code(barcode: &str) -> bool {
+    barcode.parse::<i32>().is_ok_and(|barcode| barcode <= 1000)
+}
+
+/// Writes a row of the old program to the catalogue with `quantity` in stock, keeping the unit,
+/// image, category and tax class.
+async fn write(
+    db: &Database,
+    item: &LegacyItem,
+    quantity: Decimal,
+    expire_date: Option<NaiveDate>,
+    actor: &Actor,
+) -> Result<(), AppError> {
+    let entry = CatalogueEntry::Item(
+        Item {
+            barcode: item.barcode.clone(),
+            name: item.name.clone(),
+            cost: item.cost,
+            price: item.price,
+            quantity,
+            unit: None,
+            image: None,
+            category_id: None,
+            tax_class: None,
+        },
+        expire_date.into_iter().collect(),
+    );
+    if let Some(Err(refusal)) = db.write_catalogue(&[entry], actor, true).await?.pop() {
+        return Err(match refusal {
+            Refusal::Conflict(message) => {
+                AppError::Conflict(format!("{}: {message}", item.barcode))
+            }
+            Refusal::Invalid(message) => {
+                AppError::InvalidInput(format!("{}: {message}", item.barcode))
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Syncs the catalogue with the old program, see [`SyncMode`]. Each item is committed on its
+/// own, so a sync that fails half way keeps what it did.
+pub(crate) async fn sync(
+    db: &Database,
+    old_database_url: &str,
+    mode: SyncMode,
+    actor: &Actor,
+) -> Result<SyncReport, AppError> {
+    let old = OldDatabase::connect(old_database_url).await?;
+    println!("start syncing....");
+    let report = match mode {
+        SyncMode::Pull => pull(db, &old, actor).await?,
+        SyncMode::TwoWay => two_way(db, &old, actor).await?,
+    };
+    println!("end syncing");
+    Ok(report)
+}
+
+/// Lists the conflicts of a sync in the log of the server.
+pub(crate) fn print_conflicts(report: &SyncReport) {
+    for conflict in &report.conflicts {
+        eprintln!(
+            "{} {}: {} is {} here and {} in the old program, kept {}",
+            conflict.barcode,
+            conflict.name,
+            conflict.field,
+            conflict.ours,
+            conflict.theirs,
+            conflict.ours
+        );
+    }
+}
+
+async fn pull(db: &Database, old: &OldDatabase, actor: &Actor) -> Result<SyncReport, AppError> {
+    let mut report = SyncReport::default();
+    for (item, expire_date) in old.select_items().await? {
+        if is_old_code(&item.barcode) {
+            continue;
+        }
+        write(db, &item, item.quantity.into(), expire_date, actor).await?;
+        report.pulled += 1;
+    }
+    Ok(report)
+}
+
+/// Merges one field of an item changed in the old program since the last sync.
+struct Merge<'a> {
+    item: &'a Item,
+    changed_here: bool,
+    conflicts: Vec<SyncConflict>,
+}
+
+impl Merge<'_> {
+    /// The value of the old program when only it changed the field, ours otherwise. A field
+    /// changed to different values on both sides is a conflict and keeps ours.
+    fn field<T: PartialEq + Clone + ToString>(
+        &mut self,
+        field: &str,
+        ours: &T,
+        theirs: &T,
+        last: &T,
+    ) -> T {
+        if theirs == last || ours == theirs {
+            return ours.clone();
+        }
+        if self.changed_here && ours != last {
+            self.conflicts.push(SyncConflict {
+                barcode: self.item.barcode.clone(),
+                name: self.item.name.clone(),
+                field: field.to_string(),
+                ours: ours.to_string(),
+                theirs: theirs.to_string(),
+            });
+            return ours.clone();
+        }
+        theirs.clone()
+    }
+}
+
+/// Compares both sides with the rows of the old program kept after the last sync. Names,
+/// costs and prices changed there are copied here unless the item was also changed here since
+/// the sync, by its `updated_at`. Quantities change on both sides as both sell, so the
+/// difference of each side is added to the other, never set, as either may sell during the sync.
+/// Items added here are added there too.
+async fn two_way(db: &Database, old: &OldDatabase, actor: &Actor) -> Result<SyncReport, AppError> {
+    let mut report = SyncReport::default();
+    let mut ours: HashMap<String, Item> = db
+        .select_items()
+        .await?
+        .into_iter()
+        .map(|item| (item.barcode.clone(), item))
+        .collect();
+    let updated_at: HashMap<String, NaiveDateTime> =
+        db.select_item_updated_at().await?.into_iter().collect();
+    let last_synced: HashMap<String, (LegacyItem, NaiveDateTime)> = db
+        .select_legacy_items()
+        .await?
+        .into_iter()
+        .map(|(item, synced_at)| (item.barcode.clone(), (item, synced_at)))
+        .collect();
+
+    for (theirs, expire_date) in old.select_items().await? {
+        if is_old_code(&theirs.barcode) {
+            continue;
+        }
+        let (Some(item), Some((last, synced_at))) = (
+            ours.remove(&theirs.barcode),
+            last_synced.get(&theirs.barcode),
+        ) else {
+            // Not synced both ways before, or deleted here: copy it like a pull
+            write(db, &theirs, theirs.quantity.into(), expire_date, actor).await?;
+            db.upsert_legacy_item(&theirs).await?;
+            report.pulled += 1;
+            continue;
+        };
+
+        let mut merge = Merge {
+            item: &item,
+            changed_here: updated_at
+                .get(&item.barcode)
+                .is_some_and(|updated_at| updated_at > synced_at),
+            conflicts: Vec::new(),
+        };
+        let their_change = theirs.quantity - last.quantity;
+        let our_change = whole(item.quantity) - last.quantity;
+        let merged = LegacyItem {
+            barcode: item.barcode.clone(),
+            name: merge.field("name", &item.name, &theirs.name, &last.name),
+            cost: merge.field("cost", &item.cost, &theirs.cost, &last.cost),
+            price: merge.field("price", &item.price, &theirs.price, &last.price),
+            quantity: theirs.quantity + our_change,
+        };
+        report.conflicts.append(&mut merge.conflicts);
+
+        if merged.name != item.name
+            || merged.cost != item.cost
+            || merged.price != item.price
+            || their_change != 0
+        {
+            report.pulled += 1;
+        }
+        let push: BoxFuture<'_, sqlx::Result<()>> = match our_change {
+            0 => Box::pin(async { Ok(()) }),
+            change => {
+                report.pushed += 1;
+                Box::pin(old.add_quantity(&theirs.barcode, change))
+            }
+        };
+        let legacy = LegacyItem {
+            quantity: merged.quantity,
+            ..theirs.clone()
+        };
+        db.merge_legacy_item(
+            &merged,
+            Decimal::from(their_change),
+            expire_date,
+            &legacy,
+            actor,
+            push,
+        )
+        .await?;
+    }
+
+    // Items synced before and missing now were deleted in the old program and are left alone
+    for item in ours.into_values() {
+        if is_old_code(&item.barcode) || last_synced.contains_key(&item.barcode) {
+            continue;
+        }
+        let new = LegacyItem {
+            barcode: item.barcode,
+            name: item.name,
+            cost: item.cost,
+            price: item.price,
+            quantity: whole(item.quantity),
+        };
+        let expire_date = db
+            .select_expire_dates(&new.barcode)
+            .await?
+            .into_iter()
+            .min();
+        old.insert_item(&new, expire_date).await?;
+        db.upsert_legacy_item(&new).await?;
+        report.added += 1;
+    }
+    Ok(report)
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::{TestServer, item, ok};
+use reqwest::StatusCode;
+use shared::barcode;
+
+async fn allocate(server: &TestServer, kind: &str) -> String {
+    ok(server
+        .post(&format!("/barcodes/{kind}"), &())
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn barcodes_are_handed_out_once() {
+    let server = TestServer::start().await;
+
+    let first = allocate(&server, "internal").await;
+    assert_eq!(first, "2000000000015");
+    assert!(barcode::is_ean13(&first));
+    // Someone typed the next one in by hand, so it is passed over
+    let taken = barcode::internal(2).unwrap();
+    ok(server
+        .post("/items", &item(&taken, "ส้มเขียวหวาน", 2000, 3500, 10))
+        .send()
+        .await
+        .unwrap());
+    assert_eq!(
+        allocate(&server, "internal").await,
+        barcode::internal(3).unwrap()
+    );
+
+    // Weighed goods count on their own
+    let weighed = allocate(&server, "embedded").await;
+    assert_eq!(weighed, barcode::embedded(1).unwrap());
+    ok(server
+        .post("/items", &item(&weighed, "หมูสามชั้น", 12000, 18000, 0))
+        .send()
+        .await
+        .unwrap());
+
+    assert_eq!(
+        allocate(&server, "weight").await,
+        barcode::weight(1).unwrap()
+    );
+
+    let cashier = server.cashier("cashier").await;
+    let response = server
+        .as_user(&cashier)
+        .post("/barcodes/internal", &())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = server.post("/barcodes/secret", &()).send().await.unwrap();
+    assert!(response.status().is_client_error());
+}
+
+#[tokio::test]
+async fn new_items_need_a_valid_barcode() {
+    let server = TestServer::start().await;
+    let scanned = barcode::with_check_digit("210000112550").unwrap();
+    for invalid in [
+        "8850999320015",
+        "AB 12",
+        "ส้ม",
+        // A weighed lot rather than the item
+        scanned.as_str(),
+    ] {
+        let response = server
+            .post("/items", &item(invalid, "ของใหม่", 100, 200, 1))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{invalid}");
+    }
+    // Codes of the shop's own that are not EAN-13 are fine
+    for valid in ["7", "AB-12", "8850999320014"] {
+        ok(server
+            .post("/items", &item(valid, "ของใหม่", 100, 200, 1))
+            .send()
+            .await
+            .unwrap());
+    }
+    assert_eq!(server.items().await.len(), 3);
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::{TestServer, ok};
+use shared::ImportReport;
+
+const HEADER: &str =
+    "barcode,ref_barcode,name,cost,price,quantity,unit,category_id,tax_class,expire_dates\n";
+
+async fn import(server: &TestServer, rows: &str, dry_run: bool) -> ImportReport {
+    let response = server
+        .client
+        .post(format!("{}/import/items?dry_run={dry_run}", server.url))
+        .bearer_auth(&server.token)
+        .body(format!("{HEADER}{rows}"))
+        .send()
+        .await
+        .unwrap();
+    ok(response).json().await.unwrap()
+}
+
+fn rows_of(errors: &[shared::ImportRowError]) -> Vec<usize> {
+    errors.iter().map(|error| error.row).collect()
+}
+
+#[tokio::test]
+async fn import_commits_good_rows() {
+    let server = TestServer::start().await;
+    let rows = "\
+8850001,,โค้ก,10,15,24,piece,,standard,2026-12-31
+8850099,8850001,โค้ก แพ็ค 6,,85,6,,,,
+";
+
+    let report = import(&server, rows, true).await;
+    assert_eq!((report.inserted, report.committed), (2, false));
+    assert!(server.items().await.is_empty());
+
+    let report = import(&server, rows, false).await;
+    assert!(
+        report.errors.is_empty() && report.conflicts.is_empty(),
+        "{report:?}"
+    );
+    assert_eq!((report.inserted, report.committed), (2, true));
+    let items = server.items().await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].name, "โค้ก");
+    assert_eq!(items[0].bulk_item.len(), 1);
+
+    // The same file again updates the rows
+    let report = import(&server, rows, false).await;
+    assert_eq!((report.inserted, report.updated), (0, 2));
+}
+
+#[tokio::test]
+async fn import_reports_duplicate_and_bad_rows_and_commits_nothing() {
+    let server = TestServer::start().await;
+    import(
+        &server,
+        "8850001,,โค้ก,10,15,24,,,,\n8850099,8850001,โค้ก แพ็ค 6,,85,6,,,,\n",
+        false,
+    )
+    .await;
+
+    let report = import(
+        &server,
+        "\
+8850002,,น้ำดื่ม,5,7,12,,,,
+8850002,,น้ำดื่ม อีกครั้ง,5,7,12,,,,
+8850099,8850002,น้ำดื่ม แพ็ค 6,,40,6,,,,
+8850003,,ไม่มีหมวด,5,7,12,,999,,
+8850004,,ราคาผิด,5,abc,12,,,,
+",
+        false,
+    )
+    .await;
+    assert!(!report.committed);
+    assert_eq!(report.inserted, 1);
+    // In the file twice, and a bulk item barcode another bulk item has
+    assert_eq!(rows_of(&report.conflicts), [3, 4]);
+    // A price that isn't a number, and a category the database doesn't have
+    let mut errors = rows_of(&report.errors);
+    errors.sort();
+    assert_eq!(errors, [5, 6]);
+
+    let items = server.items().await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].barcode, "8850001");
+}
@@ -0,0 +1,267 @@
+mod common;
+
+use chrono::Local;
+use common::{COKE, TestServer, ok, payment, receipt};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use shared::{Customer, CustomerPayment, Payment, PaymentMethod, Receipt, Statement};
+
+fn customer(name: &str, phone: &str) -> Customer {
+    Customer {
+        name: name.to_string(),
+        phone: phone.to_string(),
+        credit_limit: Decimal::new(500, 0),
+        ..Default::default()
+    }
+}
+
+/// A sale of Coke to a customer.
+fn sale(key: &str, customer_id: u32, quantity: i32, payments: Vec<Payment>) -> Receipt {
+    Receipt {
+        payments,
+        customer_id: Some(customer_id),
+        ..receipt(key, &[(COKE, 1500, quantity)])
+    }
+}
+
+async fn add_customer(server: &TestServer, customer: &Customer) -> Customer {
+    let response = server.post("/customers", customer).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    response.json().await.unwrap()
+}
+
+async fn get_customer(server: &TestServer, id: u32) -> Customer {
+    ok(server
+        .get(&format!("/customers/{id}"))
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn customers_are_found_by_name_or_phone() {
+    let server = TestServer::start().await;
+    let somchai = add_customer(&server, &customer("สมชาย", "081-234-5678")).await;
+    let somsri = add_customer(&server, &customer("สมศรี", "0899999999")).await;
+
+    let search = |keyword: &str| {
+        let request = server.get(&format!("/customers?keyword={keyword}"));
+        async move {
+            ok(request.send().await.unwrap())
+                .json::<Vec<Customer>>()
+                .await
+                .unwrap()
+        }
+    };
+    assert_eq!(search("สม").await, vec![somchai.clone(), somsri.clone()]);
+    assert_eq!(search("5678").await, vec![somchai.clone()]);
+    assert!(search("มานี").await.is_empty());
+
+    let mut changed = somsri.clone();
+    changed.address = "12 ซอย 3".to_string();
+    changed.credit_limit = Decimal::new(1000, 0);
+    let response = server
+        .put(&format!("/customers/{}", somsri.id), &changed)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(ok(response).json::<Customer>().await.unwrap(), changed);
+
+    // Cashiers sign customers up, but only the owner gives them credit
+    let cashier = server.as_user(&server.cashier("cashier").await);
+    let added = add_customer(&cashier, &customer("มานี", "")).await;
+    assert_eq!(added.credit_limit, Decimal::ZERO);
+    let response = cashier
+        .put(&format!("/customers/{}", added.id), &customer("มานี", ""))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    for invalid in [customer(" ", ""), customer("x", "08x"), {
+        let mut negative = customer("x", "");
+        negative.credit_limit = Decimal::new(-1, 0);
+        negative
+    }] {
+        let response = server.post("/customers", &invalid).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    let response = server.get("/customers/999").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn credit_sales_and_payments_make_the_statement() {
+    let server = TestServer::start().await;
+    server.seed().await;
+    let id = add_customer(&server, &customer("สมชาย", "0812345678"))
+        .await
+        .id;
+
+    // 150 baht on credit earns 6 points at 25 baht a point
+    let response = server
+        .post(
+            "/receipts",
+            &sale(
+                "till-1-0001",
+                id,
+                10,
+                vec![payment(PaymentMethod::Credit, 150)],
+            ),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let receipt_id: u32 = response.json().await.unwrap();
+    let owing = get_customer(&server, id).await;
+    assert_eq!(owing.balance, Decimal::new(150, 0));
+    assert_eq!(owing.points, 6);
+
+    // 5 points pay for 5 baht, the other 25 baht in cash earns another point
+    ok(server
+        .post(
+            "/receipts",
+            &sale(
+                "till-1-0002",
+                id,
+                2,
+                vec![
+                    payment(PaymentMethod::Points, 5),
+                    payment(PaymentMethod::Cash, 100),
+                ],
+            ),
+        )
+        .send()
+        .await
+        .unwrap());
+    assert_eq!(get_customer(&server, id).await.points, 2);
+
+    let paid = CustomerPayment {
+        method: PaymentMethod::Cash,
+        amount: Decimal::new(100, 0),
+        note: "งวดแรก".to_string(),
+    };
+    let response = server
+        .post(&format!("/customers/{id}/payments"), &paid)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(
+        response.json::<Customer>().await.unwrap().balance,
+        Decimal::new(50, 0)
+    );
+
+    let today = Local::now().date_naive();
+    let statement: Statement = ok(server
+        .get(&format!(
+            "/customers/{id}/statement?from={today}&to={today}"
+        ))
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(statement.opening_balance, Decimal::ZERO);
+    assert_eq!(statement.closing_balance(), Decimal::new(50, 0));
+    let entries: Vec<_> = statement
+        .entries
+        .iter()
+        .map(|entry| (entry.receipt_id, entry.method, entry.amount, entry.points))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            (Some(receipt_id), None, Decimal::new(150, 0), 6),
+            (Some(receipt_id + 1), None, Decimal::ZERO, -4),
+            (None, Some(PaymentMethod::Cash), Decimal::new(-100, 0), 0),
+        ]
+    );
+    assert_eq!(statement.entries[2].note, "งวดแรก");
+
+    let tomorrow = today.succ_opt().unwrap();
+    let statement: Statement = ok(server
+        .get(&format!(
+            "/customers/{id}/statement?from={tomorrow}&to={tomorrow}"
+        ))
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(statement.opening_balance, Decimal::new(50, 0));
+    assert!(statement.entries.is_empty());
+}
+
+#[tokio::test]
+async fn invalid_customer_payments_are_refused() {
+    let server = TestServer::start().await;
+    server.seed().await;
+    let id = add_customer(&server, &customer("สมชาย", "")).await.id;
+
+    let mut no_customer = sale(
+        "till-1-0001",
+        id,
+        1,
+        vec![payment(PaymentMethod::Points, 15)],
+    );
+    no_customer.customer_id = None;
+    let mut part_of_a_point = sale("till-1-0002", id, 1, Vec::new());
+    part_of_a_point.payments = vec![
+        Payment {
+            method: PaymentMethod::Points,
+            amount: Decimal::new(55, 1),
+            reference: None,
+        },
+        payment(PaymentMethod::Cash, 10),
+    ];
+    let unknown = sale(
+        "till-1-0003",
+        999,
+        1,
+        vec![payment(PaymentMethod::Credit, 15)],
+    );
+    for receipt in [no_customer, part_of_a_point, unknown] {
+        let response = server.post("/receipts", &receipt).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    for (method, amount) in [(PaymentMethod::Cash, 0), (PaymentMethod::Credit, 100)] {
+        let response = server
+            .post(
+                &format!("/customers/{id}/payments"),
+                &CustomerPayment {
+                    method,
+                    amount: Decimal::new(amount, 0),
+                    note: String::new(),
+                },
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    let response = server
+        .post(
+            "/customers/999/payments",
+            &CustomerPayment {
+                method: PaymentMethod::Cash,
+                amount: Decimal::new(100, 0),
+                note: String::new(),
+            },
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let customer = get_customer(&server, id).await;
+    assert_eq!(customer.balance, Decimal::ZERO);
+    assert_eq!(customer.points, 0);
+}
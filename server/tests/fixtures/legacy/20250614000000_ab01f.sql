@@ -0,0 +1,20 @@
+-- The part of the old program's database that the server reads: its catalogue table `ab01f`.
+-- Written so it runs on MySQL/MariaDB and SQLite alike, to set up a stand-in for the machine
+-- in the shop:
+--
+--   export DATABASE_URL=sqlite://legacy.db
+--   sqlx database create
+--   sqlx migrate run --source server/tests/fixtures/legacy
+--
+-- b01100 barcode, b01110 name, b01140 cost, b01150 price, b01160 quantity,
+-- b01211 expiry date as dd-mm-yyyy text, empty when there is none.
+
+CREATE TABLE ab01f
+(
+    b01100 VARCHAR(20)  NOT NULL,
+    b01110 VARCHAR(100) NOT NULL,
+    b01140 FLOAT        NOT NULL,
+    b01150 FLOAT        NOT NULL,
+    b01160 INT          NOT NULL,
+    b01211 VARCHAR(10)  NOT NULL DEFAULT ''
+);
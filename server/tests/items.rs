@@ -0,0 +1,236 @@
+mod common;
+
+use common::{TestServer, item, ok};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use shared::{Category, Header, Health, Item};
+
+#[tokio::test]
+async fn health_reports_the_schema() {
+    let server = TestServer::start().await;
+    let health: Health = ok(server.get("/health").send().await.unwrap())
+        .json()
+        .await
+        .unwrap();
+    assert!(health.database);
+    assert!(health.schema_version.is_some());
+}
+
+#[tokio::test]
+async fn requests_need_a_session() {
+    let server = TestServer::start().await;
+    let response = server
+        .client
+        .get(format!("{}/items", server.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = server
+        .client
+        .get(format!("{}/items", server.url))
+        .bearer_auth("not a token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn items_round_trip() {
+    let server = TestServer::start().await;
+    let seeded = server.seed().await;
+
+    let mut items = server.items().await;
+    items.sort_by(|a, b| a.barcode.cmp(&b.barcode));
+    let mut expected = seeded.clone();
+    expected.sort_by(|a, b| a.barcode.cmp(&b.barcode));
+    assert_eq!(items, expected);
+
+    let mut coke = seeded[0].clone();
+    coke.price = Decimal::new(1600, 2);
+    coke.quantity = Decimal::from(20);
+    ok(server
+        .put(&format!("/items/{}", coke.barcode), &coke)
+        .send()
+        .await
+        .unwrap());
+    let items = server.items().await;
+    let stored = items.iter().find(|i| i.barcode == coke.barcode).unwrap();
+    assert_eq!(stored, &coke);
+
+    ok(server
+        .delete(&format!("/items/{}", coke.barcode))
+        .send()
+        .await
+        .unwrap());
+    assert_eq!(server.items().await.len(), seeded.len() - 1);
+    let response = server
+        .delete(&format!("/items/{}", coke.barcode))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn adding_an_item_twice_conflicts() {
+    let server = TestServer::start().await;
+    let seeded = server.seed().await;
+    let response = server.post("/items", &seeded[0]).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn invalid_items_are_refused() {
+    let server = TestServer::start().await;
+    for invalid in [
+        item("", "ไม่มีบาร์โค้ด", 100, 200, 1),
+        item("1234567", "", 100, 200, 1),
+        item("1234567", "ราคาติดลบ", 100, -200, 1),
+        item("1234567", "ราคาเกิน", 100, 10_000_000_000, 1),
+        item("1234567", "จำนวนเกิน", 100, 200, 10_000_000),
+        Item {
+            cost: Decimal::new(1005, 3),
+            ..item("1234567", "ต่ำกว่าสตางค์", 100, 200, 1)
+        },
+    ] {
+        let response = server.post("/items", &invalid).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    let response = server
+        .post(
+            "/items",
+            &item("1234567", "ราคาเกิน", 100, 10_000_000_000, 1),
+        )
+        .send()
+        .await
+        .unwrap();
+    let message = response.text().await.unwrap();
+    assert!(message.contains("is more than 99999999.99"), "{message}");
+    assert!(server.items().await.is_empty());
+
+    // The most there is room for
+    ok(server
+        .post("/items", &item("1234567", "แพง", 100, 9_999_999_999, 1))
+        .send()
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn cashiers_cannot_change_the_cost() {
+    let server = TestServer::start().await;
+    let seeded = server.seed().await;
+    let cashier = server.cashier("cashier").await;
+
+    let mut water = seeded[1].clone();
+    water.cost = Decimal::new(100, 2);
+    let response = server
+        .as_user(&cashier)
+        .put(&format!("/items/{}", water.barcode), &water)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let mut water = seeded[1].clone();
+    water.price = Decimal::new(800, 2);
+    ok(server
+        .as_user(&cashier)
+        .put(&format!("/items/{}", water.barcode), &water)
+        .send()
+        .await
+        .unwrap());
+
+    let response = server
+        .as_user(&cashier)
+        .post("/items", &item("1234567", "ของใหม่", 100, 200, 1))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn search_matches_names_and_barcodes() {
+    let server = TestServer::start().await;
+    server.seed().await;
+
+    let search = |keyword: &str| {
+        server
+            .get("/items/search")
+            .query(&[("keyword", keyword)])
+            .send()
+    };
+    let headers: Vec<Header> = ok(search("สิงห์").await.unwrap()).json().await.unwrap();
+    assert_eq!(
+        headers,
+        vec![Header {
+            barcode: "8851959132012".to_string(),
+            name: "น้ำดื่มสิงห์ 600 มล.".to_string(),
+        }]
+    );
+
+    let headers: Vec<Header> = ok(search("8850").await.unwrap()).json().await.unwrap();
+    assert_eq!(headers.len(), 2);
+
+    let headers: Vec<Header> = ok(search("ไม่มี").await.unwrap()).json().await.unwrap();
+    assert!(headers.is_empty());
+}
+
+#[tokio::test]
+async fn items_filter_by_category_and_subcategories() {
+    let server = TestServer::start().await;
+    let drinks: Category = ok(server
+        .post(
+            "/categories",
+            &Category {
+                name: "เครื่องดื่ม".to_string(),
+                ..Default::default()
+            },
+        )
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    let soda: Category = ok(server
+        .post(
+            "/categories",
+            &Category {
+                parent_id: Some(drinks.id),
+                name: "น้ำอัดลม".to_string(),
+                ..Default::default()
+            },
+        )
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+
+    let mut coke = item("8850999320014", "โค้ก 325 มล.", 1150, 1500, 24);
+    coke.category_id = Some(soda.id);
+    let mut water = item("8851959132012", "น้ำดื่มสิงห์ 600 มล.", 550, 700, 48);
+    water.category_id = Some(drinks.id);
+    let toothpaste = item("8850006321102", "ยาสีฟันคอลเกต", 3200, 4500, 6);
+    for item in [&coke, &water, &toothpaste] {
+        ok(server.post("/items", item).send().await.unwrap());
+    }
+
+    let in_category = |id: u32| server.get("/items").query(&[("category", id)]).send();
+    let items: Vec<shared::Item> = ok(in_category(drinks.id).await.unwrap())
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 2);
+    let items: Vec<shared::Item> = ok(in_category(soda.id).await.unwrap())
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(items, vec![coke]);
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use std::time::Duration;
+
+use chrono::{Days, Local};
+use common::{Legacy, TestServer, ok};
+use reqwest::StatusCode;
+use server::config::SyncMode;
+use shared::{Job, JobStatus};
+
+async fn status(server: &TestServer, job: Job) -> JobStatus {
+    let jobs: Vec<JobStatus> = ok(server.get("/jobs").send().await.unwrap())
+        .json()
+        .await
+        .unwrap();
+    jobs.into_iter().find(|status| status.job == job).unwrap()
+}
+
+/// Starts the job and waits for it to finish.
+async fn run(server: &TestServer, job: Job) -> JobStatus {
+    let response = server
+        .post(&format!("/jobs/{}", path(job)), &())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    for _ in 0..100 {
+        let status = status(server, job).await;
+        if !status.running {
+            return status;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("{job} did not finish");
+}
+
+fn path(job: Job) -> String {
+    serde_json::to_value(job)
+        .unwrap()
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn jobs_are_listed_for_the_owner() {
+    let server = TestServer::start().await;
+    let jobs: Vec<JobStatus> = ok(server.get("/jobs").send().await.unwrap())
+        .json()
+        .await
+        .unwrap();
+    let jobs: Vec<Job> = jobs.into_iter().map(|status| status.job).collect();
+    assert_eq!(jobs, Job::ALL.to_vec());
+
+    let cashier = server.cashier("cashier").await;
+    let response = server.as_user(&cashier).get("/jobs").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = server
+        .as_user(&cashier)
+        .post("/jobs/sync", &())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn expiry_check_lists_what_expires_within_a_week() {
+    let soon = Local::now().date_naive() + Days::new(3);
+    let later = Local::now().date_naive() + Days::new(30);
+    let legacy = Legacy::new().await;
+    legacy
+        .insert(
+            "8850999320014",
+            "โค้ก",
+            11.5,
+            15.0,
+            24,
+            &soon.format("%d-%m-%Y").to_string(),
+        )
+        .await;
+    legacy
+        .insert(
+            "8851959132012",
+            "น้ำดื่ม",
+            5.5,
+            7.0,
+            48,
+            &later.format("%d-%m-%Y").to_string(),
+        )
+        .await;
+    let server = TestServer::start_with(Some(legacy.url.clone()), SyncMode::Pull).await;
+
+    let status = run(&server, Job::Sync).await;
+    assert!(!status.failed);
+    assert_eq!(
+        status.message.as_deref(),
+        Some("2 pulled, 0 pushed, 0 added, 0 conflicts")
+    );
+
+    let status = run(&server, Job::ExpiryCheck).await;
+    assert!(!status.failed);
+    assert!(status.finished_at.is_some());
+    let message = status.message.unwrap();
+    assert!(message.starts_with("1 expire by"), "{message}");
+    assert!(message.contains(&format!("โค้ก ({soon})")), "{message}");
+    assert!(!message.contains("น้ำดื่ม"), "{message}");
+}
+
+#[tokio::test]
+async fn failed_runs_are_reported() {
+    let server = TestServer::start().await;
+    let status = run(&server, Job::Sync).await;
+    assert!(status.failed);
+    assert_eq!(
+        status.message.as_deref(),
+        Some("configuration: old_database_url is not set")
+    );
+}
+
+#[tokio::test]
+async fn nightly_report_sums_up_the_day() {
+    let server = TestServer::start().await;
+    let status = run(&server, Job::NightlyReport).await;
+    assert!(!status.failed);
+    let today = Local::now().date_naive();
+    assert_eq!(
+        status.message.unwrap(),
+        format!("{today}: 0 sold for 0, 0 profit")
+    );
+}
+
+#[tokio::test]
+async fn unknown_jobs_are_not_found() {
+    let server = TestServer::start().await;
+    let response = server.post("/jobs/backup", &()).send().await.unwrap();
+    assert!(response.status().is_client_error());
+}
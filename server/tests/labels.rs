@@ -0,0 +1,134 @@
+mod common;
+
+use common::{TestServer, item, ok};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use shared::{LabelFormat, LabelQueueItem, LabelRequest};
+
+const COKE: &str = "8850999320014";
+const WATER: &str = "8851959132012";
+const TOOTHPASTE: &str = "8850006321102";
+
+fn request(barcodes: &[&str], format: LabelFormat) -> LabelRequest {
+    LabelRequest {
+        barcodes: barcodes.iter().map(|barcode| barcode.to_string()).collect(),
+        format,
+    }
+}
+
+async fn queue(server: &TestServer) -> Vec<LabelQueueItem> {
+    ok(server.get("/labels/queue").send().await.unwrap())
+        .json()
+        .await
+        .unwrap()
+}
+
+async fn labels(server: &TestServer, request: &LabelRequest) -> (String, Vec<u8>) {
+    let response = ok(server.post("/labels", request).send().await.unwrap());
+    let content_type = response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    (content_type, response.bytes().await.unwrap().to_vec())
+}
+
+#[tokio::test]
+async fn price_changes_queue_new_tags() {
+    let server = TestServer::start().await;
+    server.seed().await;
+
+    // New items have no tag yet, they come by name
+    let barcodes: Vec<String> = queue(&server)
+        .await
+        .into_iter()
+        .map(|item| item.barcode)
+        .collect();
+    assert_eq!(barcodes, vec![WATER, TOOTHPASTE, COKE]);
+
+    let (content_type, body) = labels(&server, &request(&[COKE, WATER], LabelFormat::Pdf)).await;
+    assert_eq!(content_type, "application/pdf");
+    assert!(body.starts_with(b"%PDF-"));
+    assert!(body.ends_with(b"%%EOF\n"));
+    let queued = queue(&server).await;
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].barcode, TOOTHPASTE);
+
+    // The same price written differently is not a change
+    let mut coke = item(COKE, "โค้ก 325 มล.", 1150, 1500, 24);
+    coke.price = Decimal::new(15, 0);
+    ok(server
+        .put(&format!("/items/{COKE}"), &coke)
+        .send()
+        .await
+        .unwrap());
+    assert_eq!(queue(&server).await.len(), 1);
+
+    coke.price = Decimal::new(1600, 2);
+    ok(server
+        .put(&format!("/items/{COKE}"), &coke)
+        .send()
+        .await
+        .unwrap());
+    let queued = queue(&server).await;
+    assert_eq!(queued.len(), 2);
+    assert_eq!(
+        queued[1],
+        LabelQueueItem {
+            barcode: COKE.to_string(),
+            name: "โค้ก 325 มล.".to_string(),
+            price: Decimal::new(16, 0),
+            printed_price: Some(Decimal::new(15, 0)),
+        }
+    );
+
+    // Printed on the label printer this time
+    let (_, body) = labels(&server, &request(&[COKE], LabelFormat::Tspl)).await;
+    assert!(body.starts_with(b"SIZE 50 mm,30 mm\r\n"));
+    assert_eq!(
+        body.windows(b"PRINT 1,1".len())
+            .filter(|window| window == b"PRINT 1,1")
+            .count(),
+        1
+    );
+    assert_eq!(queue(&server).await.len(), 1);
+
+    for invalid in [
+        request(&[], LabelFormat::Pdf),
+        request(&["0"], LabelFormat::Pdf),
+    ] {
+        let response = server.post("/labels", &invalid).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn sheets_hold_twenty_four_tags() {
+    let server = TestServer::start().await;
+    server.seed().await;
+    // Not an EAN-13, so drawn as Code 128
+    ok(server
+        .post(
+            "/items",
+            &item(
+                "SM-0001",
+                "ขนมปังกรอบรสเนยสดหอมหวานอร่อยมาก ถุงใหญ่พิเศษ",
+                1000,
+                2000,
+                5,
+            ),
+        )
+        .send()
+        .await
+        .unwrap());
+
+    let barcodes = [COKE, WATER, TOOTHPASTE, "SM-0001"].repeat(7);
+    let (content_type, body) = labels(&server, &request(&barcodes, LabelFormat::Png)).await;
+    assert_eq!(content_type, "image/png");
+
+    let decoder = png::Decoder::new(body.as_slice());
+    let reader = decoder.read_info().unwrap();
+    let info = reader.info();
+    assert_eq!(info.bit_depth, png::BitDepth::One);
+    // Two A4 pages at 12 dots a millimetre, one under the other
+    assert_eq!((info.width, info.height), (210 * 12, 2 * 297 * 12));
+}
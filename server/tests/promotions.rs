@@ -0,0 +1,192 @@
+mod common;
+
+use chrono::Local;
+use common::{TestServer, ok};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use shared::{
+    CategorySales, Discount, Payment, Promotion, PromotionRule, Receipt, ReceiptItem,
+    apply_promotions,
+};
+
+fn promotion(name: &str, rule: PromotionRule, barcodes: &[&str]) -> Promotion {
+    Promotion {
+        name: name.to_string(),
+        rule,
+        barcodes: barcodes.iter().map(|barcode| barcode.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+fn buy_2_get_1() -> Promotion {
+    promotion(
+        "ซื้อ 2 แถม 1",
+        PromotionRule::BuyGet { buy: 2, get: 1 },
+        &["8850999320014"],
+    )
+}
+
+async fn promotions(server: &TestServer) -> Vec<Promotion> {
+    ok(server.get("/promotions").send().await.unwrap())
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn owner_manages_promotions() {
+    let server = TestServer::start().await;
+    server.seed().await;
+
+    let response = server
+        .post("/promotions", &buy_2_get_1())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let mut created: Promotion = response.json().await.unwrap();
+    assert_eq!(promotions(&server).await, vec![created.clone()]);
+
+    created.rule = PromotionRule::Percent {
+        percent: Decimal::new(10, 0),
+    };
+    created.barcodes.push("8851959132012".to_string());
+    ok(server
+        .put(&format!("/promotions/{}", created.id), &created)
+        .send()
+        .await
+        .unwrap());
+    assert_eq!(promotions(&server).await, vec![created.clone()]);
+
+    let cashier = server.as_user(&server.cashier("cashier").await);
+    assert_eq!(promotions(&cashier).await.len(), 1);
+    let response = cashier
+        .post("/promotions", &buy_2_get_1())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let path = format!("/promotions/{}", created.id);
+    let response = server.delete(&path).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = server.delete(&path).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(promotions(&server).await.is_empty());
+}
+
+#[tokio::test]
+async fn invalid_promotions_are_refused() {
+    let server = TestServer::start().await;
+    server.seed().await;
+
+    let mut ends_first = buy_2_get_1();
+    ends_first.starts_at = Some(Local::now().naive_local());
+    ends_first.ends_at = ends_first.starts_at;
+    for invalid in [
+        promotion(
+            "",
+            PromotionRule::BuyGet { buy: 2, get: 1 },
+            &["8850999320014"],
+        ),
+        promotion("x", PromotionRule::BuyGet { buy: 2, get: 1 }, &[]),
+        promotion(
+            "x",
+            PromotionRule::BuyGet { buy: 2, get: 0 },
+            &["8850999320014"],
+        ),
+        promotion(
+            "x",
+            PromotionRule::Percent {
+                percent: Decimal::new(120, 0),
+            },
+            &["8850999320014"],
+        ),
+        promotion(
+            "x",
+            PromotionRule::BuyGet { buy: 2, get: 1 },
+            &["0000000000000"],
+        ),
+        ends_first,
+    ] {
+        let response = server.post("/promotions", &invalid).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    assert!(promotions(&server).await.is_empty());
+}
+
+#[tokio::test]
+async fn discounts_are_saved_with_the_receipt() {
+    let server = TestServer::start().await;
+    server.seed().await;
+    ok(server
+        .post("/promotions", &buy_2_get_1())
+        .send()
+        .await
+        .unwrap());
+
+    let now = Local::now().naive_local();
+    let items = vec![ReceiptItem {
+        barcode: "8850999320014".to_string(),
+        price: Decimal::new(1500, 2),
+        quantity: Decimal::from(3),
+        ..Default::default()
+    }];
+    let discounts = apply_promotions(&promotions(&server).await, &items, now);
+    assert_eq!(discounts.len(), 1);
+    assert_eq!(discounts[0].amount, Decimal::new(1500, 2));
+    let mut sale = Receipt {
+        key: "till-1-0001".to_string(),
+        created_at: now,
+        items,
+        discounts,
+        ..Default::default()
+    };
+
+    // Paying the price before the discount is change, not short
+    sale.payments = vec![Payment {
+        amount: Decimal::new(3000, 2),
+        ..Default::default()
+    }];
+    let response = server.post("/receipts", &sale).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let today = now.date();
+    let sales: Vec<CategorySales> = ok(server
+        .get(&format!("/reports/categories?from={today}&to={today}"))
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(sales.len(), 1);
+    assert_eq!(sales[0].quantity, Decimal::from(3));
+    assert_eq!(sales[0].revenue, Decimal::new(3000, 2));
+}
+
+#[tokio::test]
+async fn discounts_cannot_exceed_the_line() {
+    let server = TestServer::start().await;
+    server.seed().await;
+
+    let sale = Receipt {
+        key: "till-1-0002".to_string(),
+        created_at: Local::now().naive_local(),
+        items: vec![ReceiptItem {
+            barcode: "8850999320014".to_string(),
+            price: Decimal::new(1500, 2),
+            quantity: Decimal::ONE,
+            ..Default::default()
+        }],
+        discounts: vec![Discount {
+            promotion_id: 1,
+            name: "ลดทั้งร้าน".to_string(),
+            barcode: "8850999320014".to_string(),
+            amount: Decimal::new(2000, 2),
+        }],
+        ..Default::default()
+    };
+    let response = server.post("/receipts", &sale).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,235 @@
+mod common;
+
+use chrono::Local;
+use common::{COKE, TOOTHPASTE, TestServer, WATER, item, ok, quantity, receipt};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use shared::barcode;
+use shared::{CategorySales, Item, Payment, PaymentMethod, PaymentSales, ReceiptItem, Unit};
+
+fn paid(method: PaymentMethod, amount: i64, reference: Option<&str>) -> Payment {
+    Payment {
+        method,
+        amount: Decimal::new(amount, 2),
+        reference: reference.map(str::to_string),
+    }
+}
+
+async fn payment_sales(server: &TestServer) -> Vec<PaymentSales> {
+    let today = Local::now().date_naive();
+    server
+        .get(&format!("/reports/payments?from={today}&to={today}"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn sales_take_stock_off_the_shelf() {
+    let server = TestServer::start().await;
+    server.seed().await;
+    let cashier = server.cashier("cashier").await;
+
+    let sale = receipt("till-1-0001", &[(COKE, 1500, 3), (WATER, 700, 2)]);
+    let response = server
+        .as_user(&cashier)
+        .post("/receipts", &sale)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let items = server.items().await;
+    assert_eq!(quantity(&items, COKE), Decimal::from(21));
+    assert_eq!(quantity(&items, WATER), Decimal::from(46));
+    assert_eq!(quantity(&items, TOOTHPASTE), Decimal::from(6));
+}
+
+#[tokio::test]
+async fn resent_receipts_are_counted_once() {
+    let server = TestServer::start().await;
+    server.seed().await;
+
+    let sale = receipt("till-1-0002", &[(TOOTHPASTE, 4500, 1)]);
+    let first = server.post("/receipts", &sale).send().await.unwrap();
+    assert_eq!(first.status(), StatusCode::CREATED);
+    let first: u32 = first.json().await.unwrap();
+
+    let again = server.post("/receipts", &sale).send().await.unwrap();
+    assert_eq!(again.status(), StatusCode::OK);
+    let again: u32 = again.json().await.unwrap();
+    assert_eq!(first, again);
+
+    // Recorded before the checks of today, such as credit needing a customer
+    let mut changed = sale.clone();
+    changed.payments = vec![paid(PaymentMethod::Credit, 4500, Some("ป้าแดง"))];
+    let again = server.post("/receipts", &changed).send().await.unwrap();
+    assert_eq!(again.status(), StatusCode::OK);
+
+    assert_eq!(
+        quantity(&server.items().await, TOOTHPASTE),
+        Decimal::from(5)
+    );
+}
+
+#[tokio::test]
+async fn sales_of_deleted_items_are_recorded() {
+    let server = TestServer::start().await;
+    server.seed().await;
+    let sale = receipt("till-1-0003", &[(TOOTHPASTE, 4500, 1)]);
+    ok(server.post("/receipts", &sale).send().await.unwrap());
+
+    // Sold by a till that was offline while the toothpaste was taken out of the catalogue
+    ok(server
+        .delete(&format!("/items/{TOOTHPASTE}"))
+        .send()
+        .await
+        .unwrap());
+    let sale = receipt("till-1-0013", &[(COKE, 1500, 1), (TOOTHPASTE, 4500, 2)]);
+    let response = server.post("/receipts", &sale).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let id: u32 = response.json().await.unwrap();
+
+    assert_eq!(quantity(&server.items().await, COKE), Decimal::from(23));
+    let record: shared::SaleRecord =
+        ok(server.get(&format!("/receipts/{id}")).send().await.unwrap())
+            .json()
+            .await
+            .unwrap();
+    assert_eq!(record.receipt.total(), Decimal::new(10500, 2));
+}
+
+#[tokio::test]
+async fn invalid_receipts_are_refused() {
+    let server = TestServer::start().await;
+    server.seed().await;
+
+    for invalid in [
+        receipt("", &[(COKE, 1500, 1)]),
+        receipt("till-1-0004", &[]),
+        receipt("till-1-0005", &[(COKE, 1500, 0)]),
+        receipt("till-1-0006", &[(COKE, -1500, 1)]),
+    ] {
+        let response = server.post("/receipts", &invalid).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    assert_eq!(quantity(&server.items().await, COKE), Decimal::from(24));
+}
+
+#[tokio::test]
+async fn split_payments_are_reported_by_method() {
+    let server = TestServer::start().await;
+    server.seed().await;
+
+    // 44 baht: 20 by PromptPay and a 100 baht note, 76 back in change
+    let mut split = receipt("till-1-0007", &[(COKE, 1500, 2), (WATER, 700, 2)]);
+    split.payments = vec![
+        paid(PaymentMethod::PromptPay, 2000, None),
+        paid(PaymentMethod::Cash, 10000, None),
+    ];
+    let response = server.post("/receipts", &split).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // Receipts from clients that do not send payments were paid in cash
+    let cash = receipt("till-1-0008", &[(TOOTHPASTE, 4500, 1)]);
+    let response = server.post("/receipts", &cash).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    assert_eq!(
+        payment_sales(&server).await,
+        vec![
+            PaymentSales {
+                method: PaymentMethod::Cash,
+                receipts: 2,
+                amount: Decimal::new(6900, 2),
+            },
+            PaymentSales {
+                method: PaymentMethod::PromptPay,
+                receipts: 1,
+                amount: Decimal::new(2000, 2),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn payments_must_cover_the_receipt() {
+    let server = TestServer::start().await;
+    server.seed().await;
+
+    let payments = [
+        vec![paid(PaymentMethod::Cash, 1000, None)],
+        vec![paid(PaymentMethod::PromptPay, 2000, None)],
+        vec![paid(PaymentMethod::Credit, 1500, None)],
+        // Credit is kept in the ledger of a customer, a name alone won't do
+        vec![paid(PaymentMethod::Credit, 1500, Some("ป้าแดง"))],
+        vec![
+            paid(PaymentMethod::Cash, 1500, None),
+            paid(PaymentMethod::BankTransfer, 0, None),
+        ],
+    ];
+    for (i, payments) in payments.into_iter().enumerate() {
+        let mut invalid = receipt(&format!("till-1-001{i}"), &[(COKE, 1500, 1)]);
+        invalid.payments = payments;
+        let response = server.post("/receipts", &invalid).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    assert_eq!(quantity(&server.items().await, COKE), Decimal::from(24));
+    assert!(payment_sales(&server).await.is_empty());
+}
+
+#[tokio::test]
+async fn weighed_goods_sell_by_the_kilogram() {
+    let server = TestServer::start().await;
+    let pork = Item {
+        unit: Unit::Kilogram,
+        quantity: Decimal::new(5500, 3),
+        ..item(&barcode::weight(1).unwrap(), "หมูสามชั้น", 12000, 18000, 0)
+    };
+    ok(server.post("/items", &pork).send().await.unwrap());
+    assert_eq!(
+        quantity(&server.items().await, &pork.barcode),
+        Decimal::new(55, 1)
+    );
+    // Pieces come whole
+    let half = Item {
+        quantity: Decimal::new(15, 1),
+        ..item(COKE, "โค้ก 325 มล.", 1150, 1500, 0)
+    };
+    let response = server.post("/items", &half).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // 750 g off the scale
+    let mut sale = receipt("till-1-0020", &[]);
+    sale.items.push(ReceiptItem {
+        barcode: pork.barcode.clone(),
+        price: pork.price,
+        quantity: Decimal::new(750, 3),
+        ..Default::default()
+    });
+    let response = server.post("/receipts", &sale).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(
+        quantity(&server.items().await, &pork.barcode),
+        Decimal::new(475, 2)
+    );
+    let today = Local::now().date_naive();
+    let sales: Vec<CategorySales> = ok(server
+        .get(&format!("/reports/categories?from={today}&to={today}"))
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(sales[0].quantity, Decimal::new(75, 2));
+    assert_eq!(sales[0].revenue, Decimal::new(13500, 2));
+
+    sale.key = "till-1-0021".to_string();
+    sale.items[0].quantity = Decimal::new(5, 4);
+    let response = server.post("/receipts", &sale).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,415 @@
+mod common;
+
+use chrono::Local;
+use common::{COKE, TestServer, WATER, ok, payment, quantity, receipt, record};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use shared::{
+    CategorySales, Customer, Disposition, PaymentMethod, PaymentSales, Receipt, ReturnLine,
+    ReturnRequest, SaleRecord, SaleReturn, Statement, VoidRequest,
+};
+
+fn line(barcode: &str, quantity: i32, disposition: Disposition) -> ReturnLine {
+    ReturnLine {
+        barcode: barcode.to_string(),
+        quantity: quantity.into(),
+        disposition,
+        ..Default::default()
+    }
+}
+
+fn request(lines: Vec<ReturnLine>, refund_method: PaymentMethod) -> ReturnRequest {
+    ReturnRequest {
+        lines,
+        refund_method,
+        reason: "ของเสีย".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn returns_restock_and_come_off_the_reports() {
+    let server = TestServer::start().await;
+    server.seed().await;
+    let id = record(
+        &server,
+        &Receipt {
+            payments: vec![payment(PaymentMethod::Cash, 59)],
+            ..receipt("till-1-0001", &[(COKE, 1500, 3), (WATER, 700, 2)])
+        },
+    )
+    .await;
+
+    // Any cashier takes goods back
+    let cashier = server.as_user(&server.cashier("cashier").await);
+    let response = cashier
+        .post(
+            &format!("/receipts/{id}/returns"),
+            &request(
+                vec![
+                    line(COKE, 1, Disposition::Restock),
+                    line(WATER, 1, Disposition::Waste),
+                ],
+                PaymentMethod::Cash,
+            ),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let returned: SaleReturn = response.json().await.unwrap();
+    assert_eq!(returned.refund(), Decimal::new(22, 0));
+    assert_eq!(returned.lines[0].refund, Decimal::new(15, 0));
+    assert!(!returned.void);
+
+    // The wasted bottle does not go back on the shelf
+    let items = server.items().await;
+    assert_eq!(quantity(&items, COKE), Decimal::from(22));
+    assert_eq!(quantity(&items, WATER), Decimal::from(46));
+
+    let record: SaleRecord = ok(server.get(&format!("/receipts/{id}")).send().await.unwrap())
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(record.receipt.items.len(), 2);
+    assert_eq!(record.receipt.items[0].name, "โค้ก 325 มล.");
+    assert_eq!(record.returns, vec![returned]);
+    assert_eq!(record.returned(COKE), Decimal::ONE);
+
+    let today = Local::now().date_naive();
+    let payments: Vec<PaymentSales> = ok(server
+        .get(&format!("/reports/payments?from={today}&to={today}"))
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(
+        payments,
+        vec![PaymentSales {
+            method: PaymentMethod::Cash,
+            receipts: 1,
+            amount: Decimal::new(37, 0),
+        }]
+    );
+    let categories: Vec<CategorySales> = ok(server
+        .get(&format!("/reports/categories?from={today}&to={today}"))
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0].quantity, Decimal::from(3));
+    assert_eq!(categories[0].revenue, Decimal::new(37, 0));
+    // Only the restocked coke gives its cost back
+    assert_eq!(categories[0].cost, Decimal::new(3400, 2));
+
+    for (invalid, method) in [
+        (
+            vec![line(COKE, 3, Disposition::Restock)],
+            PaymentMethod::Cash,
+        ),
+        (
+            vec![line(COKE, 0, Disposition::Restock)],
+            PaymentMethod::Cash,
+        ),
+        (
+            vec![
+                line(COKE, 1, Disposition::Restock),
+                line(COKE, 1, Disposition::Waste),
+            ],
+            PaymentMethod::Cash,
+        ),
+        (
+            vec![line(COKE, 1, Disposition::Restock)],
+            PaymentMethod::Points,
+        ),
+        (
+            vec![line(COKE, 1, Disposition::Restock)],
+            PaymentMethod::Credit,
+        ),
+        (Vec::new(), PaymentMethod::Cash),
+    ] {
+        let response = server
+            .post(
+                &format!("/receipts/{id}/returns"),
+                &request(invalid, method),
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    let response = server
+        .post(
+            "/receipts/999/returns",
+            &request(
+                vec![line(COKE, 1, Disposition::Restock)],
+                PaymentMethod::Cash,
+            ),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // A receipt with returns is returned the rest of the way, not voided
+    let response = server
+        .post(
+            &format!("/receipts/{id}/void"),
+            &VoidRequest {
+                reason: "ผิดพลาด".to_string(),
+            },
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn only_the_owner_voids_receipts() {
+    let server = TestServer::start().await;
+    server.seed().await;
+    let customer: Customer = ok(server
+        .post(
+            "/customers",
+            &Customer {
+                name: "สมชาย".to_string(),
+                credit_limit: Decimal::new(500, 0),
+                ..Default::default()
+            },
+        )
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    let get_customer = || async {
+        ok(server
+            .get(&format!("/customers/{}", customer.id))
+            .send()
+            .await
+            .unwrap())
+        .json::<Customer>()
+        .await
+        .unwrap()
+    };
+
+    // 150 baht on credit earns 6 points, half of it brought back takes 3 of them
+    let on_credit = Receipt {
+        customer_id: Some(customer.id),
+        payments: vec![payment(PaymentMethod::Credit, 150)],
+        ..receipt("till-1-0001", &[(COKE, 1500, 10)])
+    };
+    let id = record(&server, &on_credit).await;
+    let response = server
+        .post(
+            &format!("/receipts/{id}/returns"),
+            &request(
+                vec![line(COKE, 5, Disposition::Restock)],
+                PaymentMethod::Credit,
+            ),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let owing = get_customer().await;
+    assert_eq!(owing.balance, Decimal::new(75, 0));
+    assert_eq!(owing.points, 3);
+
+    let with_points = Receipt {
+        customer_id: Some(customer.id),
+        payments: vec![
+            payment(PaymentMethod::Points, 3),
+            payment(PaymentMethod::Cash, 57),
+        ],
+        ..receipt("till-1-0002", &[(COKE, 1500, 4)])
+    };
+    let id = record(&server, &with_points).await;
+    assert_eq!(get_customer().await.points, 2);
+
+    let void = |reason: &str| VoidRequest {
+        reason: reason.to_string(),
+    };
+    let cashier = server.as_user(&server.cashier("cashier").await);
+    let response = cashier
+        .post(&format!("/receipts/{id}/void"), &void("ลูกค้ายกเลิก"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = server
+        .post(&format!("/receipts/{id}/void"), &void(" "))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = server
+        .post(&format!("/receipts/{id}/void"), &void("ลูกค้ายกเลิก"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let voided: SaleReturn = response.json().await.unwrap();
+    assert!(voided.void);
+    assert_eq!(voided.refunds, with_points.payments);
+    let record: SaleRecord = ok(server.get(&format!("/receipts/{id}")).send().await.unwrap())
+        .json()
+        .await
+        .unwrap();
+    assert!(record.voided());
+    assert_eq!(
+        voided.lines,
+        vec![ReturnLine {
+            barcode: COKE.to_string(),
+            quantity: Decimal::from(4),
+            disposition: Disposition::Restock,
+            refund: Decimal::new(60, 0),
+            tax: record.receipt.items[0].tax,
+        }]
+    );
+
+    // The points spent come back and the ones earned go
+    let customer = get_customer().await;
+    assert_eq!(customer.balance, Decimal::new(75, 0));
+    assert_eq!(customer.points, 3);
+    assert_eq!(
+        quantity(&server.items().await, COKE),
+        Decimal::from(24 - 10 + 5)
+    );
+
+    for path in ["void", "returns"] {
+        let response = server
+            .post(
+                &format!("/receipts/{id}/{path}"),
+                &request(
+                    vec![line(COKE, 1, Disposition::Restock)],
+                    PaymentMethod::Cash,
+                ),
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    let today = Local::now().date_naive();
+    let statement: Statement = ok(server
+        .get(&format!(
+            "/customers/{}/statement?from={today}&to={today}",
+            customer.id
+        ))
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    let entries: Vec<_> = statement
+        .entries
+        .iter()
+        .map(|entry| (entry.return_id.is_some(), entry.amount, entry.points))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            (false, Decimal::new(150, 0), 6),
+            (true, Decimal::new(-75, 0), -3),
+            (false, Decimal::ZERO, -1),
+            (true, Decimal::ZERO, 1),
+        ]
+    );
+    assert_eq!(statement.entries[3].note, "ลูกค้ายกเลิก");
+}
+
+#[tokio::test]
+async fn points_spent_come_back_after_the_money() {
+    let server = TestServer::start().await;
+    server.seed().await;
+    let customer: Customer = ok(server
+        .post(
+            "/customers",
+            &Customer {
+                name: "สมหญิง".to_string(),
+                ..Default::default()
+            },
+        )
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    let points = || async {
+        ok(server
+            .get(&format!("/customers/{}", customer.id))
+            .send()
+            .await
+            .unwrap())
+        .json::<Customer>()
+        .await
+        .unwrap()
+        .points
+    };
+    let sell = |key: &str, quantity: i32, payments| Receipt {
+        customer_id: Some(customer.id),
+        payments,
+        ..receipt(key, &[(COKE, 1500, quantity)])
+    };
+    record(
+        &server,
+        &sell("till-1-0001", 10, vec![payment(PaymentMethod::Cash, 150)]),
+    )
+    .await;
+    // 60 baht with 3 points spent, the 57 baht paid earns 2
+    let id = record(
+        &server,
+        &sell(
+            "till-1-0002",
+            4,
+            vec![
+                payment(PaymentMethod::Points, 3),
+                payment(PaymentMethod::Cash, 57),
+            ],
+        ),
+    )
+    .await;
+    assert_eq!(points().await, 6 - 3 + 2);
+
+    let give_back = || async {
+        let response = server
+            .post(
+                &format!("/receipts/{id}/returns"),
+                &request(
+                    vec![line(COKE, 2, Disposition::Restock)],
+                    PaymentMethod::Cash,
+                ),
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        response.json::<SaleReturn>().await.unwrap()
+    };
+    let first = give_back().await;
+    assert_eq!(first.refunds, vec![payment(PaymentMethod::Cash, 30)]);
+    assert_eq!(first.points, -1);
+
+    // Only 27 baht of the money is left, the points spent make up the rest
+    let last = give_back().await;
+    assert_eq!(
+        last.refunds,
+        vec![
+            payment(PaymentMethod::Cash, 27),
+            payment(PaymentMethod::Points, 3),
+        ]
+    );
+    assert_eq!(last.points, 3 - 1);
+    assert_eq!(points().await, 6);
+}
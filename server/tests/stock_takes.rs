@@ -0,0 +1,59 @@
+mod common;
+
+use common::{COKE, TestServer, WATER, ok, quantity, receipt, record};
+use rust_decimal::Decimal;
+use shared::{StockCount, StockTake, StockTakeReport};
+
+fn count(barcode: &str, counted: i64) -> StockCount {
+    StockCount {
+        barcode: barcode.to_string(),
+        counted: counted.into(),
+    }
+}
+
+#[tokio::test]
+async fn sales_during_a_count_still_come_off() {
+    let server = TestServer::start().await;
+    server.seed().await;
+    let stock_take: StockTake = ok(server.post("/stock-takes", &()).send().await.unwrap())
+        .json()
+        .await
+        .unwrap();
+    let counts = format!("/stock-takes/{}/counts", stock_take.id);
+
+    // 24 cokes and 48 bottles of water on the books, 2 cokes missing from the shelf
+    ok(server
+        .put(&counts, &[count(COKE, 22), count(WATER, 48)])
+        .send()
+        .await
+        .unwrap());
+    // Sold after they were counted
+    record(&server, &receipt("till-1-0001", &[(COKE, 1500, 3)])).await;
+    record(&server, &receipt("till-1-0002", &[(WATER, 700, 5)])).await;
+    // Sent again by a till that was offline, which keeps the quantity they were counted against
+    ok(server
+        .put(&counts, &[count(COKE, 22)])
+        .send()
+        .await
+        .unwrap());
+
+    let report: StockTakeReport = ok(server
+        .post(&format!("/stock-takes/{}/commit", stock_take.id), &())
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    let coke = report
+        .lines
+        .iter()
+        .find(|line| line.barcode == COKE)
+        .unwrap();
+    assert_eq!(coke.system_quantity, Decimal::from(24));
+    assert_eq!(coke.variance(), Decimal::from(-2));
+
+    let items = server.items().await;
+    assert_eq!(quantity(&items, COKE), Decimal::from(19));
+    assert_eq!(quantity(&items, WATER), Decimal::from(43));
+}
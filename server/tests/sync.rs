@@ -0,0 +1,296 @@
+mod common;
+
+use chrono::NaiveDate;
+use common::{Legacy, TestServer, item, ok};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use server::config::SyncMode;
+use shared::{Item, SyncConflict, SyncReport};
+
+async fn sync(server: &TestServer) -> SyncReport {
+    ok(server.post("/sync", &()).send().await.unwrap())
+        .json()
+        .await
+        .unwrap()
+}
+
+fn find<'a>(items: &'a [Item], barcode: &str) -> Option<&'a Item> {
+    items.iter().find(|item| item.barcode == barcode)
+}
+
+#[tokio::test]
+async fn sync_is_for_the_owner() {
+    let server = TestServer::start().await;
+    let cashier = server.cashier("cashier").await;
+    let response = server
+        .as_user(&cashier)
+        .post("/sync", &())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn sync_needs_the_old_database() {
+    let server = TestServer::start().await;
+    let response = server.post("/sync", &()).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"], "old_database_url is not set");
+}
+
+#[tokio::test]
+async fn sync_skips_barcodes_up_to_1000() {
+    let legacy = Legacy::new().await;
+    legacy.insert("7", "ผักบุ้ง", 5.0, 10.0, 0, "").await;
+    legacy.insert("1000", "ไข่ไก่", 3.0, 4.0, 0, "").await;
+    legacy.insert("1001", "ขนมปัง", 20.0, 25.0, 5, "").await;
+    legacy.insert("AB-12", "ถุงผ้า", 15.0, 29.0, 3, "").await;
+    let server = TestServer::start_with(Some(legacy.url.clone()), SyncMode::Pull).await;
+
+    assert_eq!(sync(&server).await.pulled, 2);
+
+    let mut barcodes: Vec<String> = server
+        .items()
+        .await
+        .into_iter()
+        .map(|item| item.barcode)
+        .collect();
+    barcodes.sort();
+    assert_eq!(barcodes, vec!["1001", "AB-12"]);
+}
+
+#[tokio::test]
+async fn sync_reads_dd_mm_yyyy_expiry_dates() {
+    let legacy = Legacy::new().await;
+    legacy
+        .insert("8850999320014", "โค้ก", 11.5, 15.0, 24, "31-12-2026")
+        .await;
+    legacy
+        .insert("8851959132012", "น้ำดื่ม", 5.5, 7.0, 48, "")
+        .await;
+    legacy
+        .insert("8850006321102", "ยาสีฟัน", 32.0, 45.0, 6, "2026-12-31")
+        .await;
+    legacy
+        .insert("8850123456787", "นม", 12.0, 14.0, 10, "31-02-2026")
+        .await;
+    let server = TestServer::start_with(Some(legacy.url.clone()), SyncMode::Pull).await;
+
+    sync(&server).await;
+
+    let items = server.items().await;
+    assert_eq!(items.len(), 4);
+    assert_eq!(
+        find(&items, "8850999320014").unwrap().expire_date,
+        vec![NaiveDate::from_ymd_opt(2026, 12, 31).unwrap()]
+    );
+    for barcode in ["8851959132012", "8850006321102", "8850123456787"] {
+        assert!(find(&items, barcode).unwrap().expire_date.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn sync_converts_floats_to_decimals() {
+    let legacy = Legacy::new().await;
+    legacy
+        .insert("8850999320014", "โค้ก", 11.5, 19.99, 24, "")
+        .await;
+    legacy
+        .insert("8851959132012", "น้ำดื่ม", 0.1, 7.0, 48, "")
+        .await;
+    legacy
+        .insert("8850006321102", "ยาสีฟัน", 32.333, 45.005, 6, "")
+        .await;
+    let server = TestServer::start_with(Some(legacy.url.clone()), SyncMode::Pull).await;
+
+    sync(&server).await;
+
+    let items = server.items().await;
+    let prices = |barcode: &str| {
+        let item = find(&items, barcode).unwrap();
+        (item.cost, item.price)
+    };
+    assert_eq!(
+        prices("8850999320014"),
+        (Decimal::new(1150, 2), Decimal::new(1999, 2))
+    );
+    assert_eq!(
+        prices("8851959132012"),
+        (Decimal::new(10, 2), Decimal::new(700, 2))
+    );
+    assert_eq!(
+        prices("8850006321102"),
+        (Decimal::new(3233, 2), Decimal::new(4500, 2))
+    );
+}
+
+#[tokio::test]
+async fn resync_updates_instead_of_duplicating() {
+    let legacy = Legacy::new().await;
+    legacy
+        .insert("8850999320014", "โค้ก", 11.5, 15.0, 24, "31-12-2026")
+        .await;
+    legacy
+        .insert("8851959132012", "น้ำดื่ม", 5.5, 7.0, 48, "")
+        .await;
+    let server = TestServer::start_with(Some(legacy.url.clone()), SyncMode::Pull).await;
+
+    sync(&server).await;
+    legacy
+        .execute("UPDATE ab01f SET b01150 = 16, b01160 = 20 WHERE b01100 = '8850999320014'")
+        .await;
+    legacy
+        .insert("8850006321102", "ยาสีฟัน", 32.0, 45.0, 6, "")
+        .await;
+    sync(&server).await;
+
+    let items = server.items().await;
+    assert_eq!(items.len(), 3);
+    let coke = find(&items, "8850999320014").unwrap();
+    assert_eq!(coke.price, Decimal::new(16, 0));
+    assert_eq!(coke.quantity, Decimal::from(20));
+    assert_eq!(
+        coke.expire_date,
+        vec![NaiveDate::from_ymd_opt(2026, 12, 31).unwrap()]
+    );
+}
+
+#[tokio::test]
+async fn two_way_sync_adds_up_sales_on_both_sides() {
+    let legacy = Legacy::new().await;
+    legacy
+        .insert("8850999320014", "โค้ก", 11.5, 15.0, 24, "")
+        .await;
+    legacy
+        .insert("8851959132012", "น้ำดื่ม", 5.5, 7.0, 48, "")
+        .await;
+    let server = TestServer::start_with(Some(legacy.url.clone()), SyncMode::TwoWay).await;
+    assert_eq!(sync(&server).await.pulled, 2);
+
+    // 3 cokes sold here, 2 cokes and 8 bottles of water in the old program
+    let mut coke = find(&server.items().await, "8850999320014")
+        .unwrap()
+        .clone();
+    coke.quantity -= Decimal::from(3);
+    ok(server
+        .put("/items/8850999320014", &coke)
+        .send()
+        .await
+        .unwrap());
+    legacy
+        .execute("UPDATE ab01f SET b01160 = 22 WHERE b01100 = '8850999320014'")
+        .await;
+    legacy
+        .execute("UPDATE ab01f SET b01160 = 40 WHERE b01100 = '8851959132012'")
+        .await;
+
+    let report = sync(&server).await;
+    assert_eq!(report.pulled, 2);
+    assert_eq!(report.pushed, 1);
+    assert!(report.conflicts.is_empty());
+
+    let items = server.items().await;
+    assert_eq!(
+        find(&items, "8850999320014").unwrap().quantity,
+        Decimal::from(19)
+    );
+    assert_eq!(
+        find(&items, "8851959132012").unwrap().quantity,
+        Decimal::from(40)
+    );
+    let rows = legacy.items().await;
+    assert_eq!(rows[0].3, 19);
+    assert_eq!(rows[1].3, 40);
+
+    // Nothing changed since, so nothing to do
+    assert_eq!(sync(&server).await, SyncReport::default());
+}
+
+#[tokio::test]
+async fn two_way_sync_adds_new_items_to_the_old_program() {
+    let legacy = Legacy::new().await;
+    legacy
+        .insert("8850999320014", "โค้ก", 11.5, 15.0, 24, "")
+        .await;
+    let server = TestServer::start_with(Some(legacy.url.clone()), SyncMode::TwoWay).await;
+    sync(&server).await;
+
+    let mut milk = item("8850123456787", "นมจืด", 1200, 1400, 10);
+    milk.expire_date = vec![NaiveDate::from_ymd_opt(2026, 11, 30).unwrap()];
+    ok(server.post("/items", &milk).send().await.unwrap());
+
+    let report = sync(&server).await;
+    assert_eq!(report.added, 1);
+    let rows = legacy.items().await;
+    assert_eq!(
+        rows[0],
+        ("8850123456787".to_string(), "นมจืด".to_string(), 14.0, 10)
+    );
+
+    // Synced back as it is, not as a second item
+    assert_eq!(sync(&server).await, SyncReport::default());
+    assert_eq!(server.items().await.len(), 2);
+}
+
+#[tokio::test]
+async fn two_way_sync_reports_changes_on_both_sides() {
+    let legacy = Legacy::new().await;
+    legacy
+        .insert("8850999320014", "โค้ก", 11.5, 15.0, 24, "")
+        .await;
+    legacy
+        .insert("8851959132012", "น้ำดื่ม", 5.5, 7.0, 48, "")
+        .await;
+    let server = TestServer::start_with(Some(legacy.url.clone()), SyncMode::TwoWay).await;
+    sync(&server).await;
+
+    // Both programs changed the price of coke, only the old one that of water
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    let mut coke = find(&server.items().await, "8850999320014")
+        .unwrap()
+        .clone();
+    coke.price = Decimal::new(16, 0);
+    ok(server
+        .put("/items/8850999320014", &coke)
+        .send()
+        .await
+        .unwrap());
+    legacy
+        .execute("UPDATE ab01f SET b01150 = 17 WHERE b01100 = '8850999320014'")
+        .await;
+    legacy
+        .execute("UPDATE ab01f SET b01150 = 8 WHERE b01100 = '8851959132012'")
+        .await;
+
+    let report = sync(&server).await;
+    assert_eq!(
+        report.conflicts,
+        vec![SyncConflict {
+            barcode: "8850999320014".to_string(),
+            name: "โค้ก".to_string(),
+            field: "price".to_string(),
+            ours: "16".to_string(),
+            theirs: "17".to_string(),
+        }]
+    );
+
+    let items = server.items().await;
+    assert_eq!(
+        find(&items, "8850999320014").unwrap().price,
+        Decimal::new(16, 0)
+    );
+    assert_eq!(
+        find(&items, "8851959132012").unwrap().price,
+        Decimal::new(8, 0)
+    );
+
+    // Reported once, the price here stays
+    assert!(sync(&server).await.conflicts.is_empty());
+    let items = server.items().await;
+    assert_eq!(
+        find(&items, "8850999320014").unwrap().price,
+        Decimal::new(16, 0)
+    );
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use chrono::{Datelike, Local};
+use common::{COKE, OWNER, PASSWORD, TOOTHPASTE, TestServer, WATER, ok, receipt, record};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use shared::{Buyer, Discount, TaxClass, TaxInvoice, VatSummary};
+
+fn buyer() -> Buyer {
+    Buyer {
+        name: "บริษัท ตัวอย่าง จำกัด".to_string(),
+        tax_id: "0105561234567".to_string(),
+        address: "1 ถนนสุขุมวิท กรุงเทพฯ".to_string(),
+        branch: String::new(),
+    }
+}
+
+#[tokio::test]
+async fn login_tells_the_till_the_vat_rate() {
+    let server = TestServer::start().await;
+    let session = server.login(OWNER, PASSWORD).await;
+    assert_eq!(session.vat_rate, Decimal::new(7, 0));
+}
+
+#[tokio::test]
+async fn sales_are_taxed_by_the_class_of_the_item() {
+    let server = TestServer::start().await;
+    let mut items = server.seed().await;
+    let water = items.iter_mut().find(|item| item.barcode == WATER).unwrap();
+    water.tax_class = TaxClass::Exempt;
+    ok(server
+        .put(&format!("/items/{WATER}"), water)
+        .send()
+        .await
+        .unwrap());
+
+    // The till does not get to pick the class
+    let mut sale = receipt(
+        "till-1-0001",
+        &[(COKE, 1500, 3), (WATER, 700, 2), (TOOTHPASTE, 4500, 1)],
+    );
+    sale.items[0].tax_class = TaxClass::Exempt;
+    sale.discounts.push(Discount {
+        promotion_id: 1,
+        name: "ลด 5 บาท".to_string(),
+        barcode: TOOTHPASTE.to_string(),
+        amount: Decimal::new(5, 0),
+    });
+    let id = record(&server, &sale).await;
+
+    let invoice: TaxInvoice = ok(server
+        .post(&format!("/receipts/{id}/tax-invoice"), &None::<Buyer>)
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(invoice.total, Decimal::new(9900, 2));
+    // 2.94 on the coke and 2.62 on the toothpaste after its discount
+    assert_eq!(invoice.vat, Decimal::new(556, 2));
+
+    let today = Local::now().date_naive();
+    let vat: Vec<VatSummary> = ok(server
+        .get(&format!("/reports/vat?from={today}&to={today}"))
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(
+        vat,
+        vec![VatSummary {
+            month: today.with_day(1).unwrap(),
+            standard: Decimal::new(8500, 2),
+            vat: Decimal::new(556, 2),
+            zero_rated: Decimal::ZERO,
+            exempt: Decimal::new(1400, 2),
+        }]
+    );
+    assert_eq!(vat[0].taxable(), Decimal::new(7944, 2));
+
+    let cashier = server.cashier("cashier").await;
+    let response = server
+        .as_user(&cashier)
+        .get(&format!("/reports/vat?from={today}&to={today}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn tax_invoices_are_numbered_by_kind() {
+    let server = TestServer::start().await;
+    server.seed().await;
+    let mut ids = Vec::new();
+    for i in 0..3 {
+        ids.push(
+            record(
+                &server,
+                &receipt(&format!("till-1-000{i}"), &[(COKE, 1500, 1)]),
+            )
+            .await,
+        );
+    }
+
+    let issue = |id: u32, buyer: Option<Buyer>| {
+        let server = server.clone();
+        async move {
+            server
+                .post(&format!("/receipts/{id}/tax-invoice"), &buyer)
+                .send()
+                .await
+                .unwrap()
+        }
+    };
+
+    let response = issue(ids[0], None).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let first: TaxInvoice = response.json().await.unwrap();
+    assert_eq!(first.number(), "ABB00000001");
+
+    let response = issue(ids[1], Some(buyer())).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let full: TaxInvoice = response.json().await.unwrap();
+    assert_eq!(full.number(), "INV00000001");
+    assert_eq!(full.buyer, Some(buyer()));
+
+    let response = issue(ids[2], None).await;
+    let second: TaxInvoice = response.json().await.unwrap();
+    assert_eq!(second.number(), "ABB00000002");
+
+    // Asking again reprints the same invoice
+    let response = issue(ids[0], None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<TaxInvoice>().await.unwrap(), first);
+    let reprint: TaxInvoice = ok(server
+        .get(&format!("/receipts/{}/tax-invoice", ids[1]))
+        .send()
+        .await
+        .unwrap())
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(reprint, full);
+
+    assert_eq!(
+        issue(ids[0], Some(buyer())).await.status(),
+        StatusCode::CONFLICT
+    );
+    assert_eq!(issue(999, None).await.status(), StatusCode::NOT_FOUND);
+    let invalid = Buyer {
+        tax_id: "12345".to_string(),
+        ..buyer()
+    };
+    let sale = record(&server, &receipt("till-1-0009", &[(COKE, 1500, 1)])).await;
+    assert_eq!(
+        issue(sale, Some(invalid)).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+    let response = server
+        .get(&format!("/receipts/{sale}/tax-invoice"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
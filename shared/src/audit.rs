@@ -0,0 +1,93 @@
+//! The audit log of changes to the catalogue.
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// One change to an item, its bulk items or expiry dates. `before` is empty for inserts and
+/// `after` for deletes.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct AuditEntry {
+    pub id: u32,
+    pub entity: String,
+    pub action: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub user: Option<String>,
+    pub source: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl AuditEntry {
+    /// The fields that differ as `(field, before, after)`, sorted by field.
+    pub fn changes(&self) -> Vec<(String, String, String)> {
+        fn show(value: Option<&serde_json::Value>) -> String {
+            match value {
+                None | Some(serde_json::Value::Null) => "-".to_string(),
+                Some(serde_json::Value::String(value)) => value.clone(),
+                Some(value) => value.to_string(),
+            }
+        }
+
+        let empty = serde_json::Map::new();
+        let before = self
+            .before
+            .as_ref()
+            .and_then(serde_json::Value::as_object)
+            .unwrap_or(&empty);
+        let after = self
+            .after
+            .as_ref()
+            .and_then(serde_json::Value::as_object)
+            .unwrap_or(&empty);
+
+        let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+        fields.sort();
+        fields.dedup();
+        fields
+            .into_iter()
+            .filter(|field| before.get(*field) != after.get(*field))
+            .map(|field| {
+                (
+                    field.clone(),
+                    show(before.get(field)),
+                    show(after.get(field)),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audit_changes() {
+        let entry = AuditEntry {
+            before: Some(serde_json::json!({ "name": "a", "price": "10.00", "quantity": 3 })),
+            after: Some(serde_json::json!({ "name": "a", "price": "12.00", "quantity": 3 })),
+            ..Default::default()
+        };
+        assert_eq!(
+            entry.changes(),
+            vec![(
+                "price".to_string(),
+                "10.00".to_string(),
+                "12.00".to_string()
+            )]
+        );
+
+        let insert = AuditEntry {
+            after: Some(serde_json::json!({ "expire_date": "2025-01-31" })),
+            ..Default::default()
+        };
+        assert_eq!(
+            insert.changes(),
+            vec![(
+                "expire_date".to_string(),
+                "-".to_string(),
+                "2025-01-31".to_string()
+            )]
+        );
+    }
+}
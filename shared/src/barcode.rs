@@ -0,0 +1,301 @@
+//! Checking barcodes, and making the shop's own for goods that come without one.
+//!
+//! EAN-13 codes starting 20 to 29 are never printed by manufacturers and are left for use
+//! inside a shop. Unlabelled goods are numbered from [`INTERNAL_PREFIX`]. Weighed goods get a
+//! number from [`EMBEDDED_PREFIX`] or [`WEIGHT_PREFIX`], depending on what the scale prints in
+//! place of the zeros: `21` + 5 digits of item number + 5 digits of price in satang + the check
+//! digit, or `22` + 5 digits of item number + 5 digits of weight in grams (or millilitres) +
+//! the check digit.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// The prefix of barcodes for goods that are sold by the piece without one.
+pub const INTERNAL_PREFIX: &str = "20";
+/// The prefix of barcodes that carry the price of a weighed lot.
+pub const EMBEDDED_PREFIX: &str = "21";
+/// The prefix of barcodes that carry the weight of a weighed lot.
+pub const WEIGHT_PREFIX: &str = "22";
+
+/// The longest barcode the catalogue can hold.
+pub const MAX_LENGTH: usize = 64;
+
+/// The kind of barcode the server hands out for goods without one.
+#[derive(Default, Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum BarcodeKind {
+    /// Sold by the piece
+    #[default]
+    Internal,
+    /// Weighed, with the price printed into the barcode by the scale
+    Embedded,
+    /// Weighed, with the weight printed into the barcode by the scale
+    Weight,
+}
+
+impl BarcodeKind {
+    pub const ALL: [BarcodeKind; 3] = [
+        BarcodeKind::Internal,
+        BarcodeKind::Embedded,
+        BarcodeKind::Weight,
+    ];
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            BarcodeKind::Internal => INTERNAL_PREFIX,
+            BarcodeKind::Embedded => EMBEDDED_PREFIX,
+            BarcodeKind::Weight => WEIGHT_PREFIX,
+        }
+    }
+
+    /// The barcode numbered `number` from the prefix, `None` when the number doesn't fit.
+    pub fn barcode(&self, number: u64) -> Option<String> {
+        match self {
+            BarcodeKind::Internal => internal(number),
+            BarcodeKind::Embedded => embedded(number),
+            BarcodeKind::Weight => weight(number),
+        }
+    }
+}
+
+impl std::fmt::Display for BarcodeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BarcodeKind::Internal => write!(f, "internal"),
+            BarcodeKind::Embedded => write!(f, "embedded"),
+            BarcodeKind::Weight => write!(f, "weight"),
+        }
+    }
+}
+
+/// Why a barcode can't go into the catalogue.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BarcodeError {
+    Empty,
+    TooLong,
+    /// Spaces, control characters or letters outside ASCII, which scanners don't send
+    Character,
+    /// An EAN-8, UPC-A or EAN-13 whose last digit doesn't check
+    CheckDigit,
+    /// A barcode the scale printed with a price or weight in it, rather than the zeros of the
+    /// item
+    Embedded,
+}
+
+impl std::fmt::Display for BarcodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BarcodeError::Empty => write!(f, "barcode is empty"),
+            BarcodeError::TooLong => write!(f, "barcode is longer than {MAX_LENGTH} characters"),
+            BarcodeError::Character => write!(f, "barcode has a character scanners can't send"),
+            BarcodeError::CheckDigit => write!(f, "barcode has the wrong check digit"),
+            BarcodeError::Embedded => {
+                write!(f, "a scale barcode must have zeros for the price or weight")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BarcodeError {}
+
+/// The GS1 check digit for `digits`, `None` when they are not all digits. The same sum serves
+/// EAN-8, UPC-A and EAN-13: weights 3 and 1 from the right.
+pub fn check_digit(digits: &str) -> Option<u32> {
+    if digits.is_empty() {
+        return None;
+    }
+    let mut sum = 0;
+    for (i, c) in digits.chars().rev().enumerate() {
+        let digit = c.to_digit(10)?;
+        sum += match i % 2 {
+            0 => digit * 3,
+            _ => digit,
+        };
+    }
+    Some((10 - sum % 10) % 10)
+}
+
+/// `digits` followed by their check digit.
+pub fn with_check_digit(digits: &str) -> Option<String> {
+    check_digit(digits).map(|digit| format!("{digits}{digit}"))
+}
+
+/// Whether `barcode` is 13 digits ending in the right check digit.
+pub fn is_ean13(barcode: &str) -> bool {
+    barcode.len() == 13 && checks(barcode)
+}
+
+fn checks(barcode: &str) -> bool {
+    if barcode.is_empty() || !barcode.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    let (digits, check) = barcode.split_at(barcode.len() - 1);
+    check_digit(digits).is_some_and(|digit| check.parse() == Ok(digit))
+}
+
+/// Checks a barcode before an item is created with it. Codes of the old program and codes the
+/// shop made up, like `AB-12`, are fine. Only all-digit codes of the length of an EAN-8, UPC-A
+/// or EAN-13 are taken for one and need their check digit.
+pub fn validate(barcode: &str) -> Result<(), BarcodeError> {
+    if barcode.is_empty() {
+        return Err(BarcodeError::Empty);
+    }
+    if barcode.len() > MAX_LENGTH {
+        return Err(BarcodeError::TooLong);
+    }
+    if !barcode.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(BarcodeError::Character);
+    }
+    let numeric = barcode.chars().all(|c| c.is_ascii_digit());
+    if numeric && matches!(barcode.len(), 8 | 12 | 13) && !checks(barcode) {
+        return Err(BarcodeError::CheckDigit);
+    }
+    if parse_embedded(barcode).is_some() || parse_weight(barcode).is_some() {
+        return Err(BarcodeError::Embedded);
+    }
+    Ok(())
+}
+
+/// The internal barcode numbered `number`, `None` past the last of the ten digits.
+pub fn internal(number: u64) -> Option<String> {
+    if number > 9_999_999_999 {
+        return None;
+    }
+    with_check_digit(&format!("{INTERNAL_PREFIX}{number:010}"))
+}
+
+/// The barcode of the weighed item numbered `number`, with zeros for the price. `None` past
+/// the last of the five digits.
+pub fn embedded(number: u64) -> Option<String> {
+    scale_item(EMBEDDED_PREFIX, number)
+}
+
+/// The barcode of the weighed item numbered `number`, with zeros for the weight. `None` past
+/// the last of the five digits.
+pub fn weight(number: u64) -> Option<String> {
+    scale_item(WEIGHT_PREFIX, number)
+}
+
+fn scale_item(prefix: &str, number: u64) -> Option<String> {
+    if number > 99_999 {
+        return None;
+    }
+    with_check_digit(&format!("{prefix}{number:05}00000"))
+}
+
+/// Splits a scanned price-embedded barcode into the barcode of the item and the price of the
+/// lot. `None` for any other barcode, and for the item's own barcode with zeros for the price.
+pub fn parse_embedded(barcode: &str) -> Option<(String, Decimal)> {
+    split(EMBEDDED_PREFIX, barcode).map(|(item, satang)| (item, Decimal::new(satang, 2)))
+}
+
+/// Splits a scanned weight-embedded barcode into the barcode of the item and the weight of the
+/// lot in kilograms (or litres). `None` for any other barcode, and for the item's own barcode
+/// with zeros for the weight.
+pub fn parse_weight(barcode: &str) -> Option<(String, Decimal)> {
+    split(WEIGHT_PREFIX, barcode).map(|(item, grams)| (item, Decimal::new(grams, 3)))
+}
+
+/// The barcode of the item and the 5 digits the scale printed in place of its zeros.
+fn split(prefix: &str, barcode: &str) -> Option<(String, i64)> {
+    if !barcode.starts_with(prefix) || !is_ean13(barcode) {
+        return None;
+    }
+    let value: i64 = barcode[7..12].parse().ok()?;
+    if value == 0 {
+        return None;
+    }
+    let item = with_check_digit(&format!("{}00000", &barcode[..7]))?;
+    Some((item, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_digits() {
+        assert_eq!(check_digit("885099932001"), Some(4));
+        assert_eq!(check_digit("200000000001"), Some(5));
+        // EAN-8 and UPC-A
+        assert_eq!(check_digit("9638507"), Some(4));
+        assert_eq!(check_digit("03600029145"), Some(2));
+        assert_eq!(check_digit(""), None);
+        assert_eq!(check_digit("88509993200x"), None);
+        assert!(is_ean13("8850999320014"));
+        assert!(!is_ean13("8850999320015"));
+        assert!(!is_ean13("885099932001"));
+        assert!(!is_ean13("8850999320014 "));
+        assert!(!is_ean13("88509993200ก"));
+        assert!(!is_ean13("8850999320ก"));
+    }
+
+    #[test]
+    fn validation() {
+        for valid in ["8850999320014", "96385074", "036000291452", "7", "AB-12"] {
+            assert_eq!(validate(valid), Ok(()), "{valid}");
+        }
+        assert_eq!(validate(""), Err(BarcodeError::Empty));
+        assert_eq!(validate(&"1".repeat(65)), Err(BarcodeError::TooLong));
+        assert_eq!(validate("AB 12"), Err(BarcodeError::Character));
+        assert_eq!(validate("ส้ม"), Err(BarcodeError::Character));
+        assert_eq!(validate("8850999320015"), Err(BarcodeError::CheckDigit));
+        assert_eq!(validate("96385075"), Err(BarcodeError::CheckDigit));
+        // Any other length of digits is a code of the shop's own
+        assert_eq!(validate("88509993200"), Ok(()));
+        assert_eq!(
+            validate(&with_check_digit("210000101250").unwrap()),
+            Err(BarcodeError::Embedded)
+        );
+        assert_eq!(
+            validate(&with_check_digit("220000101250").unwrap()),
+            Err(BarcodeError::Embedded)
+        );
+    }
+
+    #[test]
+    fn internal_barcodes() {
+        assert_eq!(internal(1).as_deref(), Some("2000000000015"));
+        assert_eq!(internal(9_999_999_999).as_deref(), Some("2099999999998"));
+        assert_eq!(internal(10_000_000_000), None);
+        assert_eq!(embedded(1).as_deref(), Some("2100001000004"));
+        assert_eq!(embedded(100_000), None);
+        assert_eq!(weight(1).as_deref(), Some("2200001000001"));
+        for number in [1, 42, 99_999] {
+            assert!(validate(&internal(number).unwrap()).is_ok());
+            assert!(validate(&embedded(number).unwrap()).is_ok());
+            assert!(validate(&weight(number).unwrap()).is_ok());
+        }
+    }
+
+    #[test]
+    fn embedded_prices() {
+        let item = embedded(1).unwrap();
+        // 125.50 baht of item 00001
+        let scanned = with_check_digit("210000112550").unwrap();
+        assert_eq!(
+            parse_embedded(&scanned),
+            Some((item.clone(), Decimal::new(12550, 2)))
+        );
+        assert_eq!(parse_embedded(&item), None);
+        assert_eq!(parse_embedded("8850999320014"), None);
+        assert_eq!(parse_embedded(&internal(1).unwrap()), None);
+        // A misread check digit
+        let check = check_digit("210000112550").unwrap();
+        let misread = format!("210000112550{}", (check + 1) % 10);
+        assert_eq!(parse_embedded(&misread), None);
+    }
+
+    #[test]
+    fn embedded_weights() {
+        let item = weight(1).unwrap();
+        // 1.25 kilos of item 00001
+        let scanned = with_check_digit("220000101250").unwrap();
+        assert_eq!(
+            parse_weight(&scanned),
+            Some((item.clone(), Decimal::new(1250, 3)))
+        );
+        assert_eq!(parse_weight(&item), None);
+        assert_eq!(parse_embedded(&scanned), None);
+    }
+}
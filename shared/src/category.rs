@@ -0,0 +1,77 @@
+//! Categories items are sorted into, nested under each other.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Category {
+    pub id: u32,
+    pub parent_id: Option<u32>,
+    pub name: String,
+}
+
+impl std::fmt::Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Returns `id` followed by the ids of every category nested under it.
+pub fn category_with_descendants(categories: &[Category], id: u32) -> Vec<u32> {
+    let mut ids = vec![id];
+    let mut i = 0;
+    while i < ids.len() {
+        let parent = ids[i];
+        for category in categories {
+            if category.parent_id == Some(parent) && !ids.contains(&category.id) {
+                ids.push(category.id);
+            }
+        }
+        i += 1;
+    }
+    ids
+}
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct CategorySales {
+    pub category_id: Option<u32>,
+    pub name: Option<String>,
+    pub quantity: Decimal,
+    pub cost: Decimal,
+    pub revenue: Decimal,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn category_descendants() {
+        let categories = vec![
+            Category {
+                id: 1,
+                parent_id: None,
+                name: "เครื่องดื่ม".to_string(),
+            },
+            Category {
+                id: 2,
+                parent_id: Some(1),
+                name: "น้ำอัดลม".to_string(),
+            },
+            Category {
+                id: 3,
+                parent_id: Some(2),
+                name: "โคล่า".to_string(),
+            },
+            Category {
+                id: 4,
+                parent_id: None,
+                name: "ขนม".to_string(),
+            },
+        ];
+
+        assert_eq!(category_with_descendants(&categories, 1), vec![1, 2, 3]);
+        assert_eq!(category_with_descendants(&categories, 2), vec![2, 3]);
+        assert_eq!(category_with_descendants(&categories, 4), vec![4]);
+    }
+}
@@ -0,0 +1,129 @@
+//! Customer accounts: credit, loyalty points and statements.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::PaymentMethod;
+
+/// A regular of the shop, who may buy on credit up to `credit_limit` and collects loyalty points.
+/// `balance` is what they owe and `points` what they have left to redeem, both worked out from
+/// their ledger by the server.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Customer {
+    pub id: u32,
+    pub name: String,
+    pub phone: String,
+    pub address: String,
+    pub credit_limit: Decimal,
+    #[serde(default)]
+    pub balance: Decimal,
+    #[serde(default)]
+    pub points: i64,
+}
+
+impl Customer {
+    /// How much more the customer can buy on credit.
+    pub fn available_credit(&self) -> Decimal {
+        (self.credit_limit - self.balance).max(Decimal::ZERO)
+    }
+}
+
+impl std::fmt::Display for Customer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.phone.is_empty() {
+            true => write!(f, "{}", self.name),
+            false => write!(f, "{} ({})", self.name, self.phone),
+        }
+    }
+}
+
+/// A change to what a customer owes or their points: a sale, with its receipt, a return from
+/// it, with the receipt and the return, or a payment against the balance, with how it was paid.
+/// `amount` is negative for payments and refunds and `points` for points redeemed or taken back.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct LedgerEntry {
+    pub id: u32,
+    pub created_at: NaiveDateTime,
+    pub receipt_id: Option<u32>,
+    #[serde(default)]
+    pub return_id: Option<u32>,
+    pub method: Option<PaymentMethod>,
+    pub amount: Decimal,
+    pub points: i64,
+    pub note: String,
+}
+
+/// Money a customer pays off their balance.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct CustomerPayment {
+    pub method: PaymentMethod,
+    pub amount: Decimal,
+    pub note: String,
+}
+
+/// What a customer owed at the start of the period, and what changed it during the period.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Statement {
+    pub customer: Customer,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub opening_balance: Decimal,
+    pub entries: Vec<LedgerEntry>,
+}
+
+impl Statement {
+    pub fn closing_balance(&self) -> Decimal {
+        self.opening_balance
+            + self
+                .entries
+                .iter()
+                .map(|entry| entry.amount)
+                .sum::<Decimal>()
+    }
+}
+
+/// Points earned by spending `amount`, one for every whole `baht_per_point`. Nothing is earned
+/// when `baht_per_point` is zero.
+pub fn points_earned(amount: Decimal, baht_per_point: Decimal) -> i64 {
+    if baht_per_point <= Decimal::ZERO || amount <= Decimal::ZERO {
+        return 0;
+    }
+    (amount / baht_per_point).floor().try_into().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn customer_statement() {
+        assert_eq!(points_earned(Decimal::new(9999, 2), Decimal::new(25, 0)), 3);
+        assert_eq!(points_earned(Decimal::new(100, 0), Decimal::ZERO), 0);
+        assert_eq!(points_earned(Decimal::new(-100, 0), Decimal::new(25, 0)), 0);
+
+        let customer = Customer {
+            credit_limit: Decimal::new(500, 0),
+            balance: Decimal::new(650, 0),
+            ..Default::default()
+        };
+        assert_eq!(customer.available_credit(), Decimal::ZERO);
+
+        let statement = Statement {
+            customer,
+            opening_balance: Decimal::new(300, 0),
+            entries: vec![
+                LedgerEntry {
+                    amount: Decimal::new(450, 0),
+                    ..Default::default()
+                },
+                LedgerEntry {
+                    amount: Decimal::new(-100, 0),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(statement.closing_balance(), Decimal::new(650, 0));
+    }
+}
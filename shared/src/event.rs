@@ -0,0 +1,83 @@
+//! Changes to the catalogue pushed to the tills.
+
+use serde::{Deserialize, Serialize};
+
+use crate::Item;
+
+/// Pushed to connected clients when the catalogue changes. `Reload` is sent after changes too
+/// big to describe one item at a time, like an import, and when a client fell behind.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ItemEvent {
+    Changed { item: Item },
+    Deleted { barcode: String },
+    Reload,
+}
+
+impl ItemEvent {
+    /// Applies the change to a loaded list of items. Returns `false` when the list has to be
+    /// fetched again instead.
+    pub fn apply(&self, items: &mut Vec<Item>) -> bool {
+        match self {
+            ItemEvent::Changed { item } => {
+                match items
+                    .iter_mut()
+                    .find(|current| current.barcode == item.barcode)
+                {
+                    Some(current) => *current = item.clone(),
+                    None => items.push(item.clone()),
+                }
+                true
+            }
+            ItemEvent::Deleted { barcode } => {
+                items.retain(|item| &item.barcode != barcode);
+                true
+            }
+            ItemEvent::Reload => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn item_event() {
+        let item = |barcode: &str, price: i64| Item {
+            barcode: barcode.to_string(),
+            price: Decimal::new(price, 0),
+            ..Default::default()
+        };
+        let mut items = vec![item("0", 10), item("1", 20)];
+
+        assert!(
+            ItemEvent::Changed {
+                item: item("1", 25)
+            }
+            .apply(&mut items)
+        );
+        assert_eq!(items, vec![item("0", 10), item("1", 25)]);
+
+        assert!(ItemEvent::Changed { item: item("2", 5) }.apply(&mut items));
+        assert_eq!(items.len(), 3);
+
+        assert!(
+            ItemEvent::Deleted {
+                barcode: "0".to_string()
+            }
+            .apply(&mut items)
+        );
+        assert_eq!(items, vec![item("1", 25), item("2", 5)]);
+
+        assert!(!ItemEvent::Reload.apply(&mut items));
+
+        let json = serde_json::to_string(&ItemEvent::Reload).unwrap();
+        assert_eq!(json, r#"{"type":"reload"}"#);
+        assert_eq!(
+            serde_json::from_str::<ItemEvent>(&json).unwrap(),
+            ItemEvent::Reload
+        );
+    }
+}
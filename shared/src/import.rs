@@ -0,0 +1,20 @@
+//! What an import of the catalogue from a spreadsheet did.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub committed: bool,
+    pub inserted: usize,
+    pub updated: usize,
+    pub errors: Vec<ImportRowError>,
+    pub conflicts: Vec<ImportRowError>,
+}
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ImportRowError {
+    pub row: usize,
+    pub barcode: String,
+    pub message: String,
+}
@@ -0,0 +1,43 @@
+//! Jobs the server runs in the background.
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Work the server does in the background on a schedule, or when the owner asks for it.
+#[derive(Default, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Job {
+    /// Sync the catalogue with the old program
+    #[default]
+    Sync,
+    /// Look for stock that expires within a week
+    ExpiryCheck,
+    /// Sum up the sales of the day
+    NightlyReport,
+}
+
+impl Job {
+    pub const ALL: [Job; 3] = [Job::Sync, Job::ExpiryCheck, Job::NightlyReport];
+}
+
+impl std::fmt::Display for Job {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Job::Sync => write!(f, "sync"),
+            Job::ExpiryCheck => write!(f, "expiry check"),
+            Job::NightlyReport => write!(f, "nightly report"),
+        }
+    }
+}
+
+/// How a [`Job`] is doing. `message` sums up the last run, or why it failed.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct JobStatus {
+    pub job: Job,
+    pub running: bool,
+    pub started_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+    pub failed: bool,
+    pub message: Option<String>,
+    pub next_run: Option<NaiveDateTime>,
+}
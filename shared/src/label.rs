@@ -0,0 +1,56 @@
+//! Shelf tags: the items whose tags are out of date and how a sheet of them is printed.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// How a sheet of shelf tags comes out: a page for A4 sticker sheets, or the commands of a
+/// thermal label printer that speaks TSPL, one label per tag.
+#[derive(Default, Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelFormat {
+    #[default]
+    Pdf,
+    Png,
+    Tspl,
+}
+
+impl LabelFormat {
+    pub const ALL: [LabelFormat; 3] = [LabelFormat::Pdf, LabelFormat::Png, LabelFormat::Tspl];
+
+    /// The extension of a file in the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LabelFormat::Pdf => "pdf",
+            LabelFormat::Png => "png",
+            LabelFormat::Tspl => "prn",
+        }
+    }
+}
+
+impl std::fmt::Display for LabelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LabelFormat::Pdf => write!(f, "PDF"),
+            LabelFormat::Png => write!(f, "PNG"),
+            LabelFormat::Tspl => write!(f, "เครื่องพิมพ์ฉลาก"),
+        }
+    }
+}
+
+/// An item whose shelf tag is out of date: its price changed since the tag was printed, or it
+/// never had one.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct LabelQueueItem {
+    pub barcode: String,
+    pub name: String,
+    pub price: Decimal,
+    /// The price on the tag now on the shelf
+    pub printed_price: Option<Decimal>,
+}
+
+/// The items to print a shelf tag for, in order. Printing takes them off the queue.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct LabelRequest {
+    pub barcodes: Vec<String>,
+    pub format: LabelFormat,
+}
@@ -1,15 +1,60 @@
 use chrono::NaiveDate;
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::{Deserialize, Serialize};
 
+mod audit;
+pub mod barcode;
+mod category;
+mod customer;
+mod event;
+mod import;
+mod job;
+mod label;
+pub mod number;
+mod promotion;
+mod promptpay;
+mod receipt;
+mod returns;
+mod stock_take;
+mod sync;
+mod tax;
+mod unit;
+mod user;
+
+pub use audit::AuditEntry;
+pub use category::{Category, CategorySales, category_with_descendants};
+pub use customer::{Customer, CustomerPayment, LedgerEntry, Statement, points_earned};
+pub use event::ItemEvent;
+pub use import::{ImportReport, ImportRowError};
+pub use job::{Job, JobStatus};
+pub use label::{LabelFormat, LabelQueueItem, LabelRequest};
+pub use promotion::{Discount, Promotion, PromotionRule, apply_promotions};
+pub use promptpay::promptpay_payload;
+pub use receipt::{Payment, PaymentMethod, PaymentSales, Receipt, ReceiptItem};
+pub use returns::{Disposition, ReturnLine, ReturnRequest, SaleRecord, SaleReturn, VoidRequest};
+pub use stock_take::{StockCount, StockTake, StockTakeLine, StockTakeReport};
+pub use sync::{SyncConflict, SyncReport};
+pub use tax::{Buyer, TaxClass, TaxInvoice, VatSummary, vat};
+pub use unit::Unit;
+pub use user::{Login, NewUser, Role, Session, User};
+
+/// Sarabun, the Thai font the tills show and the shelf tags are printed in.
+pub const FONT: &[u8] = include_bytes!("../asset/Sarabun-Regular.ttf");
+
 #[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct Item {
     pub barcode: String,
     pub name: String,
     pub cost: Decimal,
     pub price: Decimal,
-    pub quantity: i32,
+    /// In `unit`, whole pieces or up to grams and millilitres
+    pub quantity: Decimal,
+    #[serde(default)]
+    pub unit: Unit,
     pub image: Option<Vec<u8>>,
+    pub category_id: Option<u32>,
+    #[serde(default)]
+    pub tax_class: TaxClass,
     pub expire_date: Vec<NaiveDate>,
     pub bulk_item: Vec<BulkItem>,
 }
@@ -19,26 +64,30 @@ pub struct BulkItem {
     pub barcode: Option<String>,
     pub name: String,
     pub price: Decimal,
-    pub quantity: i32,
+    pub quantity: Decimal,
     pub image: Option<Vec<u8>>,
 }
 
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct Header {
-    barcode: String,
-    name: String,
+    pub barcode: String,
+    pub name: String,
 }
 
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
+/// Rounds money to satang, halves away from zero.
+pub fn round_money(amount: Decimal) -> Decimal {
+    amount.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Port the server listens on for LAN discovery broadcasts. It answers [`DISCOVERY_REQUEST`]
+/// with its HTTP port as text.
+pub const DISCOVERY_PORT: u16 = 3001;
+pub const DISCOVERY_REQUEST: &[u8] = b"sunminimart?";
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
+/// Answer of `GET /health`. `schema_version` is the latest migration applied to the database.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Health {
+    pub version: String,
+    pub database: bool,
+    pub schema_version: Option<i64>,
 }
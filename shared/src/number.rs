@@ -0,0 +1,150 @@
+//! Reading numbers as people type them, and the range of money and quantities the database
+//! keeps.
+//!
+//! Thai and English write numbers the same way, `1,234.50`, but Thai may also use its own
+//! digits, `๑,๒๓๔.๕๐`, and amounts come with `฿` or `บาท` around them.
+
+use rust_decimal::Decimal;
+
+/// The largest amount of money the database keeps, `DECIMAL(10, 2)`: 99,999,999.99 baht.
+pub const MAX_MONEY: Decimal = Decimal::from_parts(0x540B_E3FF, 2, 0, false, 2);
+/// The largest quantity the database keeps, `DECIMAL(10, 3)`: 9,999,999.999 of the unit.
+pub const MAX_QUANTITY: Decimal = Decimal::from_parts(0x540B_E3FF, 2, 0, false, 3);
+
+/// Why an amount can't be kept as money.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MoneyError {
+    Negative,
+    /// More than [`MAX_MONEY`]
+    TooLarge,
+    /// Finer than a satang
+    Satang,
+}
+
+impl std::fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoneyError::Negative => write!(f, "is negative"),
+            MoneyError::TooLarge => write!(f, "is more than {MAX_MONEY}"),
+            MoneyError::Satang => write!(f, "has more than 2 decimals"),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+/// Checks that `amount` fits the money columns as it is, rather than being cut to fit.
+pub fn validate_money(amount: Decimal) -> Result<(), MoneyError> {
+    if amount.is_sign_negative() && !amount.is_zero() {
+        return Err(MoneyError::Negative);
+    }
+    if amount > MAX_MONEY {
+        return Err(MoneyError::TooLarge);
+    }
+    if amount.normalize().scale() > 2 {
+        return Err(MoneyError::Satang);
+    }
+    Ok(())
+}
+
+/// Reads a number in Thai or English, with or without `,` between the thousands. `None` for
+/// anything else, like commas in the wrong place or a decimal comma.
+pub fn parse_decimal(text: &str) -> Option<Decimal> {
+    let text = text.trim();
+    let text = text.strip_prefix('฿').unwrap_or(text);
+    let text = text
+        .strip_suffix("บาท")
+        .or_else(|| text.strip_suffix('฿'))
+        .unwrap_or(text)
+        .trim();
+    let text: String = text.chars().map(arabic).collect();
+
+    let (sign, unsigned) = match text.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", text.as_str()),
+    };
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let groups: Vec<&str> = whole.split(',').collect();
+    if groups.len() > 1
+        && (!(1..=3).contains(&groups[0].len()) || groups[1..].iter().any(|group| group.len() != 3))
+    {
+        return None;
+    }
+    let whole = groups.concat();
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    if !whole
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let whole = if whole.is_empty() { "0" } else { &whole };
+    let fraction = if fraction.is_empty() { "0" } else { fraction };
+    format!("{sign}{whole}.{fraction}").parse().ok()
+}
+
+/// The digit `c` in Arabic numerals when it is a Thai one.
+fn arabic(c: char) -> char {
+    match c {
+        '๐'..='๙' => char::from_digit(u32::from(c) - u32::from('๐'), 10).unwrap_or(c),
+        c => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits() {
+        assert_eq!(MAX_MONEY, Decimal::new(9_999_999_999, 2));
+        assert_eq!(MAX_QUANTITY, Decimal::new(9_999_999_999, 3));
+    }
+
+    #[test]
+    fn numbers_in_thai_and_english() {
+        for (text, number) in [
+            ("16.5", Decimal::new(165, 1)),
+            (" 35. ", Decimal::new(35, 0)),
+            (".5", Decimal::new(5, 1)),
+            ("-2", Decimal::new(-2, 0)),
+            ("1,234.50", Decimal::new(123450, 2)),
+            ("12,345,678", Decimal::new(12_345_678, 0)),
+            ("๑,๒๓๔.๕๐", Decimal::new(123450, 2)),
+            ("฿1,500", Decimal::new(1500, 0)),
+            ("45 บาท", Decimal::new(45, 0)),
+            ("๔๕บาท", Decimal::new(45, 0)),
+        ] {
+            assert_eq!(parse_decimal(text), Some(number), "{text}");
+        }
+        for text in [
+            "", ".", "-", "abc", "1,23", "1234,567", ",123", "12,5", "1.2.3", "1 000", "1e3", "๑๒x",
+        ] {
+            assert_eq!(parse_decimal(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn money_ranges() {
+        assert_eq!(validate_money(Decimal::ZERO), Ok(()));
+        assert_eq!(validate_money(Decimal::new(1050, 2)), Ok(()));
+        assert_eq!(validate_money(Decimal::new(10500, 3)), Ok(()));
+        assert_eq!(validate_money(MAX_MONEY), Ok(()));
+        assert_eq!(
+            validate_money(Decimal::new(-1, 2)),
+            Err(MoneyError::Negative)
+        );
+        assert_eq!(
+            validate_money(MAX_MONEY + Decimal::new(1, 2)),
+            Err(MoneyError::TooLarge)
+        );
+        assert_eq!(
+            validate_money(Decimal::new(1005, 3)),
+            Err(MoneyError::Satang)
+        );
+        assert_eq!(MoneyError::TooLarge.to_string(), "is more than 99999999.99");
+    }
+}
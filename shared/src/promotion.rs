@@ -0,0 +1,342 @@
+//! Promotions the till applies to the cart while they run, and the discounts they give.
+
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{ReceiptItem, round_money};
+
+/// A price rule applied to the cart automatically while it runs. Units of all its items count
+/// together, so a bundle can mix them.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Promotion {
+    pub id: u32,
+    pub name: String,
+    pub rule: PromotionRule,
+    pub barcodes: Vec<String>,
+    pub starts_at: Option<NaiveDateTime>,
+    pub ends_at: Option<NaiveDateTime>,
+}
+
+impl Promotion {
+    pub fn is_active(&self, at: NaiveDateTime) -> bool {
+        self.starts_at.is_none_or(|starts_at| starts_at <= at)
+            && self.ends_at.is_none_or(|ends_at| at < ends_at)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PromotionRule {
+    /// `percent` off every unit
+    Percent { percent: Decimal },
+    /// `amount` off every unit
+    Amount { amount: Decimal },
+    /// Of every `buy + get` units the cheapest `get` are free
+    BuyGet { buy: u32, get: u32 },
+    /// Any `quantity` units together for `price`
+    Bundle { quantity: u32, price: Decimal },
+}
+
+impl Default for PromotionRule {
+    fn default() -> Self {
+        PromotionRule::Percent {
+            percent: Decimal::ZERO,
+        }
+    }
+}
+
+/// Money taken off a line of a receipt by a promotion.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Discount {
+    pub promotion_id: u32,
+    pub name: String,
+    pub barcode: String,
+    pub amount: Decimal,
+}
+
+/// The discounts the promotions running `at` give on `items`. Promotions are applied by id and
+/// a unit gets at most one of them, so the first promotion that can use a unit keeps it. Units
+/// of multi-buys and bundles are grouped from the most expensive down, and units left over
+/// from an incomplete group are free for later promotions. Only whole units go into groups, a
+/// part of a kilo can still get a percent or amount off.
+pub fn apply_promotions(
+    promotions: &[Promotion],
+    items: &[ReceiptItem],
+    at: NaiveDateTime,
+) -> Vec<Discount> {
+    let mut left: Vec<Decimal> = items
+        .iter()
+        .map(|item| item.quantity.max(Decimal::ZERO))
+        .collect();
+    let mut promotions: Vec<&Promotion> = promotions
+        .iter()
+        .filter(|promotion| promotion.is_active(at))
+        .collect();
+    promotions.sort_by_key(|promotion| promotion.id);
+
+    let mut discounts = Vec::new();
+    for promotion in promotions {
+        let lines: Vec<usize> = (0..items.len())
+            .filter(|&i| left[i] > Decimal::ZERO && promotion.barcodes.contains(&items[i].barcode))
+            .collect();
+        let mut off = vec![Decimal::ZERO; items.len()];
+        match promotion.rule {
+            PromotionRule::Percent { percent } => {
+                for i in lines {
+                    let total = items[i].price * left[i];
+                    off[i] = round_money(total * percent / Decimal::ONE_HUNDRED);
+                    left[i] = Decimal::ZERO;
+                }
+            }
+            PromotionRule::Amount { amount } => {
+                for i in lines {
+                    off[i] = round_money(amount.min(items[i].price) * left[i]);
+                    left[i] = Decimal::ZERO;
+                }
+            }
+            PromotionRule::BuyGet { buy, get } => {
+                for group in groups(items, &left, &lines, buy + get) {
+                    for &i in &group {
+                        left[i] -= Decimal::ONE;
+                    }
+                    for &i in &group[buy as usize..] {
+                        off[i] += items[i].price;
+                    }
+                }
+            }
+            PromotionRule::Bundle { quantity, price } => {
+                for group in groups(items, &left, &lines, quantity) {
+                    let total: Decimal = group.iter().map(|&i| items[i].price).sum();
+                    if total <= price {
+                        continue;
+                    }
+                    // Shared by the units by their price, the last one takes what rounding left
+                    let mut rest = total - price;
+                    for (n, &i) in group.iter().enumerate() {
+                        let share = match n + 1 == group.len() {
+                            true => rest,
+                            false => round_money(items[i].price * (total - price) / total),
+                        };
+                        off[i] += share;
+                        rest -= share;
+                        left[i] -= Decimal::ONE;
+                    }
+                }
+            }
+        }
+        for (i, amount) in off.into_iter().enumerate() {
+            if amount > Decimal::ZERO {
+                discounts.push(Discount {
+                    promotion_id: promotion.id,
+                    name: promotion.name.clone(),
+                    barcode: items[i].barcode.clone(),
+                    amount,
+                });
+            }
+        }
+    }
+    discounts
+}
+
+/// The units left on `lines` as line indices, most expensive first, in full groups of `size`.
+fn groups(items: &[ReceiptItem], left: &[Decimal], lines: &[usize], size: u32) -> Vec<Vec<usize>> {
+    if size == 0 {
+        return Vec::new();
+    }
+    let mut units: Vec<usize> = lines
+        .iter()
+        .flat_map(|&i| std::iter::repeat_n(i, left[i].trunc().try_into().unwrap_or(0)))
+        .collect();
+    units.sort_by(|a, b| items[*b].price.cmp(&items[*a].price));
+    units
+        .chunks_exact(size as usize)
+        .map(<[usize]>::to_vec)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Receipt;
+    use chrono::NaiveDate;
+
+    fn line(barcode: &str, price: i64, quantity: i32) -> ReceiptItem {
+        ReceiptItem {
+            barcode: barcode.to_string(),
+            price: Decimal::new(price, 2),
+            quantity: Decimal::from(quantity),
+            ..Default::default()
+        }
+    }
+
+    fn promotion(id: u32, rule: PromotionRule, barcodes: &[&str]) -> Promotion {
+        Promotion {
+            id,
+            name: format!("promotion {id}"),
+            rule,
+            barcodes: barcodes.iter().map(|barcode| barcode.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn amounts(discounts: &[Discount]) -> Vec<(u32, &str, Decimal)> {
+        discounts
+            .iter()
+            .map(|discount| {
+                (
+                    discount.promotion_id,
+                    discount.barcode.as_str(),
+                    discount.amount,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn percent_and_amount_off() {
+        let now = NaiveDateTime::default();
+        let items = [line("a", 1999, 3), line("b", 500, 2), line("c", 1000, 1)];
+        let promotions = [
+            promotion(
+                1,
+                PromotionRule::Percent {
+                    percent: Decimal::new(10, 0),
+                },
+                &["a"],
+            ),
+            promotion(
+                2,
+                PromotionRule::Amount {
+                    amount: Decimal::new(800, 2),
+                },
+                &["a", "b"],
+            ),
+        ];
+        // 10% of 59.97 is 5.997, and b is not taken below zero
+        assert_eq!(
+            amounts(&apply_promotions(&promotions, &items, now)),
+            vec![
+                (1, "a", Decimal::new(600, 2)),
+                (2, "b", Decimal::new(1000, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn buy_get() {
+        let now = NaiveDateTime::default();
+        let buy_2_get_1 = [promotion(
+            1,
+            PromotionRule::BuyGet { buy: 2, get: 1 },
+            &["a", "b"],
+        )];
+        // Units 20, 20, 20, 15, 15, 15, 15: two groups, the cheapest of each is free
+        let items = [line("a", 2000, 3), line("b", 1500, 4)];
+        assert_eq!(
+            amounts(&apply_promotions(&buy_2_get_1, &items, now)),
+            vec![
+                (1, "a", Decimal::new(2000, 2)),
+                (1, "b", Decimal::new(1500, 2))
+            ]
+        );
+        assert!(apply_promotions(&buy_2_get_1, &[line("a", 2000, 2)], now).is_empty());
+    }
+
+    #[test]
+    fn weighed_lines() {
+        let now = NaiveDateTime::default();
+        // Half a kilo at 99.99 a kilo
+        let pork = ReceiptItem {
+            barcode: "a".to_string(),
+            price: Decimal::new(9999, 2),
+            quantity: Decimal::new(5, 1),
+            ..Default::default()
+        };
+        assert_eq!(pork.total(), Decimal::new(5000, 2));
+
+        let percent = promotion(
+            1,
+            PromotionRule::Percent {
+                percent: Decimal::new(10, 0),
+            },
+            &["a"],
+        );
+        assert_eq!(
+            apply_promotions(&[percent], std::slice::from_ref(&pork), now)[0].amount,
+            Decimal::new(500, 2)
+        );
+        // Only whole kilos make up a multi-buy
+        let buy_1_get_1 = promotion(1, PromotionRule::BuyGet { buy: 1, get: 1 }, &["a"]);
+        let rice = ReceiptItem {
+            quantity: Decimal::new(2500, 3),
+            ..pork
+        };
+        assert_eq!(
+            apply_promotions(&[buy_1_get_1], &[rice], now)[0].amount,
+            Decimal::new(9999, 2)
+        );
+    }
+
+    #[test]
+    fn bundle() {
+        let now = NaiveDateTime::default();
+        let promotions = [
+            promotion(
+                1,
+                PromotionRule::Bundle {
+                    quantity: 3,
+                    price: Decimal::new(5000, 2),
+                },
+                &["a", "b"],
+            ),
+            promotion(
+                2,
+                PromotionRule::Percent {
+                    percent: Decimal::new(50, 0),
+                },
+                &["b"],
+            ),
+        ];
+        // One bundle of 25 + 25 + 10 for 50, the b left over is half price
+        let items = [line("a", 2500, 2), line("b", 1000, 2)];
+        let discounts = apply_promotions(&promotions, &items, now);
+        assert_eq!(
+            amounts(&discounts),
+            vec![
+                (1, "a", Decimal::new(834, 2)),
+                (1, "b", Decimal::new(166, 2)),
+                (2, "b", Decimal::new(500, 2)),
+            ]
+        );
+        let receipt = Receipt {
+            items: items.to_vec(),
+            discounts,
+            ..Default::default()
+        };
+        assert_eq!(receipt.total(), Decimal::new(5500, 2));
+    }
+
+    #[test]
+    fn promotion_period() {
+        let at = |day: u32| {
+            NaiveDate::from_ymd_opt(2026, 10, day)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+        };
+        let mut sale = promotion(
+            1,
+            PromotionRule::Percent {
+                percent: Decimal::new(20, 0),
+            },
+            &["a"],
+        );
+        sale.starts_at = Some(at(10));
+        sale.ends_at = Some(at(20));
+        assert!(!sale.is_active(at(9)));
+        assert!(sale.is_active(at(10)));
+        assert!(!sale.is_active(at(20)));
+        assert!(apply_promotions(&[sale], &[line("a", 1000, 1)], at(25)).is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+//! Thai QR codes that pay the shop by PromptPay.
+
+use rust_decimal::Decimal;
+
+/// The text of a Thai QR payment code (EMVCo merchant presented mode) that pays `amount` to the
+/// PromptPay account `id`: a mobile number, a 13 digit citizen or tax id, or a 15 digit
+/// e-wallet id. Without an amount the payer types it in.
+pub fn promptpay_payload(id: &str, amount: Option<Decimal>) -> Result<String, String> {
+    fn field(id: &str, value: &str) -> String {
+        format!("{id}{:02}{value}", value.len())
+    }
+
+    let digits: String = id.chars().filter(|c| !matches!(c, ' ' | '-')).collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("invalid PromptPay id: {id}"));
+    }
+    let account = match digits.len() {
+        10 if digits.starts_with('0') => field("01", &format!("0066{}", &digits[1..])),
+        13 => field("02", &digits),
+        15 => field("03", &digits),
+        _ => return Err(format!("invalid PromptPay id: {id}")),
+    };
+
+    let mut payload = field("00", "01");
+    payload += &field("01", if amount.is_some() { "12" } else { "11" });
+    payload += &field("29", &(field("00", "A000000677010111") + &account));
+    payload += &field("58", "TH");
+    payload += &field("53", "764");
+    if let Some(amount) = amount {
+        payload += &field("54", &format!("{:.2}", amount.round_dp(2)));
+    }
+    payload += "6304";
+    let crc = crc16(payload.as_bytes());
+    Ok(format!("{payload}{crc:04X}"))
+}
+
+/// CRC-16/CCITT-FALSE, the checksum EMVCo codes end with.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = match crc & 0x8000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x1021,
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn promptpay() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(
+            promptpay_payload("081-234-5678", None).unwrap(),
+            "00020101021129370016A000000677010111011300668123456785802TH530376463045D82"
+        );
+        assert_eq!(
+            promptpay_payload("0812345678", Some(Decimal::new(422, 2))).unwrap(),
+            "00020101021229370016A000000677010111011300668123456785802TH530376454044.2263045D49"
+        );
+        assert!(
+            promptpay_payload("1234567890123", None)
+                .unwrap()
+                .contains("02131234567890123")
+        );
+        assert!(promptpay_payload("12345", None).is_err());
+        assert!(promptpay_payload("08l2345678", None).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! Receipts of sales and how they were paid.
+
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{Discount, TaxClass, round_money, vat};
+
+/// A sale as rung up at the till. `key` is generated by the client when the sale is made, so the
+/// server can tell a receipt that is sent again after a dropped connection from a new one.
+/// Receipts without payments were queued by clients that did not ask how they were paid, and
+/// count as paid in cash.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Receipt {
+    pub key: String,
+    pub created_at: NaiveDateTime,
+    pub items: Vec<ReceiptItem>,
+    #[serde(default)]
+    pub discounts: Vec<Discount>,
+    #[serde(default)]
+    pub payments: Vec<Payment>,
+    /// The customer who bought, needed to pay on credit or with points
+    #[serde(default)]
+    pub customer_id: Option<u32>,
+    /// Loyalty points the customer earned, filled in by the server
+    #[serde(default)]
+    pub points: i64,
+}
+
+impl Receipt {
+    /// Works out the VAT of every line at `rate` percent, on what the line costs after its
+    /// discounts. Discounts of a barcode on more than one line are taken off them in order.
+    pub fn apply_tax(&mut self, rate: Decimal) {
+        let mut discounts: Vec<(String, Decimal)> = Vec::new();
+        for discount in &self.discounts {
+            match discounts
+                .iter_mut()
+                .find(|(barcode, _)| *barcode == discount.barcode)
+            {
+                Some((_, amount)) => *amount += discount.amount,
+                None => discounts.push((discount.barcode.clone(), discount.amount)),
+            }
+        }
+        for item in &mut self.items {
+            let total = item.total();
+            let discount = match discounts
+                .iter_mut()
+                .find(|(barcode, _)| *barcode == item.barcode)
+            {
+                Some((_, left)) => {
+                    let discount = (*left).min(total);
+                    *left -= discount;
+                    discount
+                }
+                None => Decimal::ZERO,
+            };
+            item.tax = vat(total - discount, item.tax_class, rate);
+        }
+    }
+
+    /// The VAT included in the total, see [`Receipt::apply_tax`].
+    pub fn tax(&self) -> Decimal {
+        self.items.iter().map(|item| item.tax).sum()
+    }
+
+    /// The items at their prices, before discounts.
+    pub fn subtotal(&self) -> Decimal {
+        self.items.iter().map(ReceiptItem::total).sum()
+    }
+
+    pub fn discount(&self) -> Decimal {
+        self.discounts.iter().map(|discount| discount.amount).sum()
+    }
+
+    pub fn total(&self) -> Decimal {
+        self.subtotal() - self.discount()
+    }
+
+    pub fn paid(&self) -> Decimal {
+        self.payments.iter().map(|payment| payment.amount).sum()
+    }
+
+    /// What is still to be paid, zero once the payments cover the total.
+    pub fn remaining(&self) -> Decimal {
+        (self.total() - self.paid()).max(Decimal::ZERO)
+    }
+
+    /// Cash handed back when more was paid than the total.
+    pub fn change(&self) -> Decimal {
+        (self.paid() - self.total()).max(Decimal::ZERO)
+    }
+
+    /// What was paid with `method`.
+    pub fn paid_with(&self, method: PaymentMethod) -> Decimal {
+        self.payments
+            .iter()
+            .filter(|payment| payment.method == method)
+            .map(|payment| payment.amount)
+            .sum()
+    }
+
+    /// Points spent on the receipt, a point for each baht paid with points.
+    pub fn redeemed_points(&self) -> i64 {
+        self.paid_with(PaymentMethod::Points)
+            .trunc()
+            .try_into()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ReceiptItem {
+    pub barcode: String,
+    pub name: String,
+    pub price: Decimal,
+    /// In the unit of the item, so a weighed line is priced by the kilo
+    pub quantity: Decimal,
+    #[serde(default)]
+    pub tax_class: TaxClass,
+    /// VAT included in the line after discounts, filled in by the server
+    #[serde(default)]
+    pub tax: Decimal,
+}
+
+impl ReceiptItem {
+    /// The price of the line, rounded to satang for weighed goods.
+    pub fn total(&self) -> Decimal {
+        round_money(self.price * self.quantity)
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentMethod {
+    #[default]
+    Cash,
+    PromptPay,
+    BankTransfer,
+    /// Owed by the customer of the receipt
+    Credit,
+    /// Loyalty points of the customer of the receipt, a baht each
+    Points,
+}
+
+impl PaymentMethod {
+    pub const ALL: [PaymentMethod; 5] = [
+        PaymentMethod::Cash,
+        PaymentMethod::PromptPay,
+        PaymentMethod::BankTransfer,
+        PaymentMethod::Credit,
+        PaymentMethod::Points,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentMethod::Cash => "cash",
+            PaymentMethod::PromptPay => "prompt_pay",
+            PaymentMethod::BankTransfer => "bank_transfer",
+            PaymentMethod::Credit => "credit",
+            PaymentMethod::Points => "points",
+        }
+    }
+}
+
+impl std::str::FromStr for PaymentMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PaymentMethod::ALL
+            .into_iter()
+            .find(|method| method.as_str() == s)
+            .ok_or_else(|| format!("unknown payment method: {s}"))
+    }
+}
+
+impl std::fmt::Display for PaymentMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaymentMethod::Cash => write!(f, "เงินสด"),
+            PaymentMethod::PromptPay => write!(f, "พร้อมเพย์"),
+            PaymentMethod::BankTransfer => write!(f, "โอนเงิน"),
+            PaymentMethod::Credit => write!(f, "เงินเชื่อ"),
+            PaymentMethod::Points => write!(f, "แต้มสะสม"),
+        }
+    }
+}
+
+/// Part of a receipt paid one way. A receipt paid with cash and PromptPay has two. `reference`
+/// is the slip number of transfers.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Payment {
+    pub method: PaymentMethod,
+    pub amount: Decimal,
+    pub reference: Option<String>,
+}
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct PaymentSales {
+    pub method: PaymentMethod,
+    pub receipts: i64,
+    pub amount: Decimal,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn receipt_total() {
+        let receipt = Receipt {
+            items: vec![
+                ReceiptItem {
+                    price: Decimal::new(1250, 2),
+                    quantity: Decimal::new(2, 0),
+                    ..Default::default()
+                },
+                ReceiptItem {
+                    price: Decimal::new(7, 0),
+                    quantity: Decimal::new(3, 0),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(receipt.total(), Decimal::new(46, 0));
+    }
+
+    #[test]
+    fn receipt_payments() {
+        let mut receipt = Receipt {
+            items: vec![ReceiptItem {
+                price: Decimal::new(45, 0),
+                quantity: Decimal::new(2, 0),
+                ..Default::default()
+            }],
+            payments: vec![Payment {
+                method: PaymentMethod::PromptPay,
+                amount: Decimal::new(50, 0),
+                reference: None,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(receipt.remaining(), Decimal::new(40, 0));
+        assert_eq!(receipt.change(), Decimal::ZERO);
+
+        receipt.payments.push(Payment {
+            amount: Decimal::new(100, 0),
+            ..Default::default()
+        });
+        assert_eq!(receipt.paid(), Decimal::new(150, 0));
+        assert_eq!(receipt.remaining(), Decimal::ZERO);
+        assert_eq!(receipt.change(), Decimal::new(60, 0));
+
+        for method in PaymentMethod::ALL {
+            assert_eq!(method.as_str().parse(), Ok(method));
+            assert_eq!(
+                serde_json::to_value(method).unwrap(),
+                serde_json::json!(method.as_str())
+            );
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Goods brought back and receipts voided, and the refunds given for them.
+
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{Payment, PaymentMethod, Receipt, ReceiptItem, round_money};
+
+/// What happens to goods brought back.
+#[derive(Default, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Disposition {
+    /// Back on the shelf to be sold again
+    #[default]
+    Restock,
+    /// Thrown away, like spoiled or broken goods
+    Waste,
+}
+
+impl Disposition {
+    pub const ALL: [Disposition; 2] = [Disposition::Restock, Disposition::Waste];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Disposition::Restock => "restock",
+            Disposition::Waste => "waste",
+        }
+    }
+}
+
+impl std::str::FromStr for Disposition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Disposition::ALL
+            .into_iter()
+            .find(|disposition| disposition.as_str() == s)
+            .ok_or_else(|| format!("unknown disposition: {s}"))
+    }
+}
+
+impl std::fmt::Display for Disposition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Disposition::Restock => write!(f, "คืนเข้าสต็อก"),
+            Disposition::Waste => write!(f, "ของเสีย"),
+        }
+    }
+}
+
+/// Units of an item brought back. `refund` and `tax` are their share of the line they were sold
+/// on, filled in by the server.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ReturnLine {
+    pub barcode: String,
+    pub quantity: Decimal,
+    #[serde(default)]
+    pub disposition: Disposition,
+    #[serde(default)]
+    pub refund: Decimal,
+    #[serde(default)]
+    pub tax: Decimal,
+}
+
+/// Goods brought back from a receipt and the money given back for them, or the whole receipt
+/// undone when `void`. `points` is what it changed in the points of the customer.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SaleReturn {
+    pub id: u32,
+    pub receipt_id: u32,
+    pub created_at: NaiveDateTime,
+    pub void: bool,
+    pub reason: String,
+    pub lines: Vec<ReturnLine>,
+    pub refunds: Vec<Payment>,
+    pub points: i64,
+}
+
+impl SaleReturn {
+    pub fn refund(&self) -> Decimal {
+        self.refunds.iter().map(|refund| refund.amount).sum()
+    }
+
+    /// Points spent on the sale that the return gave back.
+    pub fn redeemed_points(&self) -> i64 {
+        self.refunds
+            .iter()
+            .filter(|refund| refund.method == PaymentMethod::Points)
+            .map(|refund| refund.amount)
+            .sum::<Decimal>()
+            .trunc()
+            .try_into()
+            .unwrap_or(0)
+    }
+}
+
+/// What a till asks to return from a receipt. The server works out the refund, paid back with
+/// `refund_method`.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ReturnRequest {
+    pub lines: Vec<ReturnLine>,
+    pub refund_method: PaymentMethod,
+    pub reason: String,
+}
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct VoidRequest {
+    pub reason: String,
+}
+
+/// A recorded sale with what was returned of it since. `receipt.points` is what it earned.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SaleRecord {
+    pub id: u32,
+    pub receipt: Receipt,
+    pub returns: Vec<SaleReturn>,
+}
+
+impl SaleRecord {
+    pub fn sold(&self, barcode: &str) -> Decimal {
+        self.receipt
+            .items
+            .iter()
+            .filter(|item| item.barcode == barcode)
+            .map(|item| item.quantity)
+            .sum()
+    }
+
+    fn returned_lines<'a>(&'a self, barcode: &'a str) -> impl Iterator<Item = &'a ReturnLine> {
+        self.returns
+            .iter()
+            .flat_map(|sale_return| &sale_return.lines)
+            .filter(move |line| line.barcode == barcode)
+    }
+
+    /// Units of `barcode` returned so far.
+    pub fn returned(&self, barcode: &str) -> Decimal {
+        self.returned_lines(barcode).map(|line| line.quantity).sum()
+    }
+
+    pub fn voided(&self) -> bool {
+        self.returns.iter().any(|sale_return| sale_return.void)
+    }
+
+    /// Points the sale earned that returns have not taken back yet.
+    pub fn points_left(&self) -> i64 {
+        let taken: i64 = self
+            .returns
+            .iter()
+            .map(|sale_return| sale_return.points - sale_return.redeemed_points())
+            .sum();
+        (self.receipt.points + taken).max(0)
+    }
+
+    /// Splits `refund` more of the sale into money and the points spent on it that go back. What
+    /// was paid with money is refunded first, the points once that is used up.
+    pub fn refund_tender(&self, refund: Decimal) -> (Decimal, i64) {
+        let paid = (self.receipt.total() - self.receipt.paid_with(PaymentMethod::Points))
+            .max(Decimal::ZERO);
+        let refunded: Decimal = self
+            .returns
+            .iter()
+            .flat_map(|sale_return| &sale_return.lines)
+            .map(|line| line.refund)
+            .sum();
+        // Points are whole, a part of one is given back with a later return
+        let points = |refunded: Decimal| -> i64 {
+            (refunded - paid)
+                .max(Decimal::ZERO)
+                .trunc()
+                .try_into()
+                .unwrap_or(0)
+        };
+        (
+            refund.min((paid - refunded).max(Decimal::ZERO)),
+            points(refunded + refund) - points(refunded),
+        )
+    }
+
+    /// What `quantity` more units of `barcode` are refunded and the VAT in that: their share of
+    /// what the lines sold for after discounts. The last units get what is left, so that the
+    /// refunds of a barcode add up to what it sold for.
+    pub fn refund(&self, barcode: &str, quantity: Decimal) -> (Decimal, Decimal) {
+        let sold = self.sold(barcode);
+        if sold <= Decimal::ZERO {
+            return (Decimal::ZERO, Decimal::ZERO);
+        }
+        let lines = || {
+            self.receipt
+                .items
+                .iter()
+                .filter(|item| item.barcode == barcode)
+        };
+        let discount: Decimal = self
+            .receipt
+            .discounts
+            .iter()
+            .filter(|discount| discount.barcode == barcode)
+            .map(|discount| discount.amount)
+            .sum();
+        let net = lines().map(ReceiptItem::total).sum::<Decimal>() - discount;
+        let tax: Decimal = lines().map(|item| item.tax).sum();
+
+        if self.returned(barcode) + quantity >= sold {
+            let refunded: Decimal = self.returned_lines(barcode).map(|line| line.refund).sum();
+            let refunded_tax: Decimal = self.returned_lines(barcode).map(|line| line.tax).sum();
+            return (net - refunded, tax - refunded_tax);
+        }
+        let share = quantity / sold;
+        (round_money(net * share), round_money(tax * share))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Discount;
+
+    #[test]
+    fn sale_refund() {
+        let mut record = SaleRecord {
+            id: 1,
+            receipt: Receipt {
+                items: vec![ReceiptItem {
+                    barcode: "0".to_string(),
+                    price: Decimal::new(10, 0),
+                    quantity: Decimal::new(3, 0),
+                    tax: Decimal::new(163, 2),
+                    ..Default::default()
+                }],
+                discounts: vec![Discount {
+                    barcode: "0".to_string(),
+                    amount: Decimal::new(5, 0),
+                    ..Default::default()
+                }],
+                points: 2,
+                ..Default::default()
+            },
+            returns: Vec::new(),
+        };
+        // A third of the 25 baht paid and its VAT
+        assert_eq!(
+            record.refund("0", Decimal::ONE),
+            (Decimal::new(833, 2), Decimal::new(54, 2))
+        );
+        assert_eq!(
+            record.refund("1", Decimal::ONE),
+            (Decimal::ZERO, Decimal::ZERO)
+        );
+
+        record.returns.push(SaleReturn {
+            lines: vec![ReturnLine {
+                barcode: "0".to_string(),
+                quantity: Decimal::new(1, 0),
+                refund: Decimal::new(833, 2),
+                tax: Decimal::new(54, 2),
+                ..Default::default()
+            }],
+            points: -1,
+            ..Default::default()
+        });
+        assert_eq!(record.returned("0"), Decimal::ONE);
+        assert_eq!(record.points_left(), 1);
+        assert!(!record.voided());
+        // The rest of the line gets the rest of the money
+        assert_eq!(
+            record.refund("0", Decimal::new(2, 0)),
+            (Decimal::new(1667, 2), Decimal::new(109, 2))
+        );
+    }
+
+    #[test]
+    fn sale_refund_tender() {
+        // 60 baht paid with 3 points and 57 baht in cash
+        let mut record = SaleRecord {
+            receipt: Receipt {
+                items: vec![ReceiptItem {
+                    barcode: "0".to_string(),
+                    price: Decimal::new(15, 0),
+                    quantity: Decimal::new(4, 0),
+                    ..Default::default()
+                }],
+                payments: vec![
+                    Payment {
+                        method: PaymentMethod::Points,
+                        amount: Decimal::new(3, 0),
+                        reference: None,
+                    },
+                    Payment {
+                        method: PaymentMethod::Cash,
+                        amount: Decimal::new(57, 0),
+                        reference: None,
+                    },
+                ],
+                points: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            record.refund_tender(Decimal::new(45, 0)),
+            (Decimal::new(45, 0), 0)
+        );
+
+        record.returns.push(SaleReturn {
+            lines: vec![ReturnLine {
+                barcode: "0".to_string(),
+                quantity: Decimal::new(3, 0),
+                refund: Decimal::new(45, 0),
+                ..Default::default()
+            }],
+            refunds: vec![Payment {
+                method: PaymentMethod::Cash,
+                amount: Decimal::new(45, 0),
+                reference: None,
+            }],
+            points: -1,
+            ..Default::default()
+        });
+        assert_eq!(record.points_left(), 1);
+        assert_eq!(
+            record.refund_tender(Decimal::new(15, 0)),
+            (Decimal::new(12, 0), 3)
+        );
+        assert_eq!(
+            record.refund_tender(Decimal::new(135, 1)),
+            (Decimal::new(12, 0), 1)
+        );
+    }
+}
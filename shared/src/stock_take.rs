@@ -0,0 +1,83 @@
+//! Stock-takes: counts of the shelves against the quantities on the books.
+
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::round_money;
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct StockTake {
+    pub id: u32,
+    pub started_at: NaiveDateTime,
+    pub committed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct StockCount {
+    pub barcode: String,
+    pub counted: Decimal,
+}
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct StockTakeLine {
+    pub barcode: String,
+    pub name: String,
+    pub system_quantity: Decimal,
+    pub counted_quantity: Decimal,
+    pub cost: Decimal,
+}
+
+impl StockTakeLine {
+    /// Counted minus system quantity, negative when stock is missing from the shelf.
+    pub fn variance(&self) -> Decimal {
+        self.counted_quantity - self.system_quantity
+    }
+
+    pub fn variance_value(&self) -> Decimal {
+        round_money(self.cost * self.variance())
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct StockTakeReport {
+    pub stock_take: StockTake,
+    pub lines: Vec<StockTakeLine>,
+}
+
+impl StockTakeReport {
+    pub fn variance_value(&self) -> Decimal {
+        self.lines.iter().map(StockTakeLine::variance_value).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stock_take_variance() {
+        let report = StockTakeReport {
+            lines: vec![
+                StockTakeLine {
+                    system_quantity: Decimal::new(10, 0),
+                    counted_quantity: Decimal::new(7, 0),
+                    cost: Decimal::new(125, 1),
+                    ..Default::default()
+                },
+                StockTakeLine {
+                    system_quantity: Decimal::new(2, 0),
+                    counted_quantity: Decimal::new(4, 0),
+                    cost: Decimal::new(5, 0),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(report.lines[0].variance(), Decimal::new(-3, 0));
+        assert_eq!(report.lines[0].variance_value(), Decimal::new(-375, 1));
+        assert_eq!(report.lines[1].variance(), Decimal::new(2, 0));
+        assert_eq!(report.variance_value(), Decimal::new(-275, 1));
+    }
+}
@@ -0,0 +1,37 @@
+//! What a sync with the old program did.
+
+use serde::{Deserialize, Serialize};
+
+/// What a sync with the old program did. `pushed` counts the quantities written back to it and
+/// `added` the items created in it, both only done by a two-way sync.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SyncReport {
+    pub pulled: usize,
+    pub pushed: usize,
+    pub added: usize,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+impl std::fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} pulled, {} pushed, {} added, {} conflicts",
+            self.pulled,
+            self.pushed,
+            self.added,
+            self.conflicts.len()
+        )
+    }
+}
+
+/// A field changed both here and in the old program since the last sync. The value here is
+/// kept, so the owner has to fix the old program by hand if it is the right one.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SyncConflict {
+    pub barcode: String,
+    pub name: String,
+    pub field: String,
+    pub ours: String,
+    pub theirs: String,
+}
@@ -0,0 +1,194 @@
+//! VAT: how it applies to items, the tax invoices issued for receipts and the monthly summary
+//! for the VAT return.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::round_money;
+
+/// How VAT applies to an item. Prices include the VAT, so it is worked out of them.
+#[derive(Default, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxClass {
+    /// VAT at the rate of the shop
+    #[default]
+    Standard,
+    /// VAT at 0%, like exports
+    Zero,
+    /// No VAT at all, like fresh produce and newspapers
+    Exempt,
+}
+
+impl TaxClass {
+    pub const ALL: [TaxClass; 3] = [TaxClass::Standard, TaxClass::Zero, TaxClass::Exempt];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaxClass::Standard => "standard",
+            TaxClass::Zero => "zero",
+            TaxClass::Exempt => "exempt",
+        }
+    }
+}
+
+impl std::str::FromStr for TaxClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TaxClass::ALL
+            .into_iter()
+            .find(|class| class.as_str() == s)
+            .ok_or_else(|| format!("unknown tax class: {s}"))
+    }
+}
+
+impl std::fmt::Display for TaxClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaxClass::Standard => write!(f, "ภาษีมูลค่าเพิ่ม"),
+            TaxClass::Zero => write!(f, "อัตราศูนย์"),
+            TaxClass::Exempt => write!(f, "ยกเว้นภาษี"),
+        }
+    }
+}
+
+/// The VAT included in `amount` of `class` when the rate is `rate` percent, rounded to satang.
+pub fn vat(amount: Decimal, class: TaxClass, rate: Decimal) -> Decimal {
+    match class {
+        TaxClass::Standard => round_money(amount * rate / (Decimal::ONE_HUNDRED + rate)),
+        TaxClass::Zero | TaxClass::Exempt => Decimal::ZERO,
+    }
+}
+
+/// Who a full tax invoice is made out to. An empty branch is the head office.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Buyer {
+    pub name: String,
+    pub tax_id: String,
+    pub address: String,
+    pub branch: String,
+}
+
+/// A tax invoice issued for a receipt: a full one when it names the buyer, otherwise an
+/// abbreviated one. Each kind is numbered on its own, without gaps.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct TaxInvoice {
+    pub receipt_id: u32,
+    pub sequence: u32,
+    pub buyer: Option<Buyer>,
+    pub issued_at: NaiveDateTime,
+    pub total: Decimal,
+    pub vat: Decimal,
+}
+
+impl TaxInvoice {
+    /// The number printed on the invoice, `INV` for full invoices and `ABB` for abbreviated ones
+    /// followed by the sequence.
+    pub fn number(&self) -> String {
+        let prefix = match self.buyer {
+            Some(_) => "INV",
+            None => "ABB",
+        };
+        format!("{prefix}{:08}", self.sequence)
+    }
+
+    /// The total without its VAT.
+    pub fn net(&self) -> Decimal {
+        self.total - self.vat
+    }
+}
+
+/// Output VAT of the sales of one month, for the monthly VAT return. Amounts are net of
+/// discounts and `standard` includes the VAT.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct VatSummary {
+    /// The first day of the month
+    pub month: NaiveDate,
+    pub standard: Decimal,
+    pub vat: Decimal,
+    pub zero_rated: Decimal,
+    pub exempt: Decimal,
+}
+
+impl VatSummary {
+    /// What the standard rated sales come to without their VAT.
+    pub fn taxable(&self) -> Decimal {
+        self.standard - self.vat
+    }
+
+    pub fn sales(&self) -> Decimal {
+        self.standard + self.zero_rated + self.exempt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Discount, Receipt, ReceiptItem};
+
+    #[test]
+    fn receipt_tax() {
+        let rate = Decimal::new(7, 0);
+        assert_eq!(
+            vat(Decimal::new(107, 0), TaxClass::Standard, rate),
+            Decimal::new(7, 0)
+        );
+        // 0.105 exactly, halves round up rather than to even
+        assert_eq!(
+            vat(Decimal::new(1605, 3), TaxClass::Standard, rate),
+            Decimal::new(11, 2)
+        );
+        assert_eq!(
+            vat(Decimal::new(107, 0), TaxClass::Zero, rate),
+            Decimal::ZERO
+        );
+
+        let line = |barcode: &str, price: i64, quantity: i32, tax_class: TaxClass| ReceiptItem {
+            barcode: barcode.to_string(),
+            price: Decimal::new(price, 0),
+            quantity: Decimal::from(quantity),
+            tax_class,
+            ..Default::default()
+        };
+        let mut receipt = Receipt {
+            items: vec![
+                line("a", 107, 1, TaxClass::Standard),
+                line("b", 10, 3, TaxClass::Standard),
+                line("c", 20, 1, TaxClass::Exempt),
+                line("b", 10, 1, TaxClass::Standard),
+            ],
+            discounts: vec![Discount {
+                barcode: "b".to_string(),
+                amount: Decimal::new(35, 0),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        receipt.apply_tax(rate);
+        let taxes: Vec<Decimal> = receipt.items.iter().map(|item| item.tax).collect();
+        // The second b line is left with 5 of the discount
+        assert_eq!(
+            taxes,
+            vec![
+                Decimal::new(7, 0),
+                Decimal::ZERO,
+                Decimal::ZERO,
+                Decimal::new(33, 2)
+            ]
+        );
+        assert_eq!(receipt.tax(), Decimal::new(733, 2));
+
+        for class in TaxClass::ALL {
+            assert_eq!(class.as_str().parse(), Ok(class));
+        }
+        let invoice = TaxInvoice {
+            sequence: 42,
+            total: Decimal::new(107, 0),
+            vat: Decimal::new(7, 0),
+            ..Default::default()
+        };
+        assert_eq!(invoice.number(), "ABB00000042");
+        assert_eq!(invoice.net(), Decimal::new(100, 0));
+    }
+}
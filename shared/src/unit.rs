@@ -0,0 +1,76 @@
+//! The units items are counted and sold in.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// What the quantity of an item counts. Goods sold by weight or volume are counted to the gram
+/// or millilitre, three decimals of the unit.
+#[derive(Default, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    #[default]
+    Piece,
+    Kilogram,
+    Litre,
+}
+
+impl Unit {
+    pub const ALL: [Unit; 3] = [Unit::Piece, Unit::Kilogram, Unit::Litre];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Unit::Piece => "piece",
+            Unit::Kilogram => "kilogram",
+            Unit::Litre => "litre",
+        }
+    }
+
+    /// The decimals a quantity in the unit can have.
+    pub fn scale(&self) -> u32 {
+        match self {
+            Unit::Piece => 0,
+            Unit::Kilogram | Unit::Litre => 3,
+        }
+    }
+
+    /// Whether `quantity` can be counted in the unit: whole pieces, or up to grams and
+    /// millilitres.
+    pub fn allows(&self, quantity: Decimal) -> bool {
+        quantity.normalize().scale() <= self.scale()
+    }
+}
+
+impl std::str::FromStr for Unit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Unit::ALL
+            .into_iter()
+            .find(|unit| unit.as_str() == s)
+            .ok_or_else(|| format!("unknown unit: {s}"))
+    }
+}
+
+impl std::fmt::Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unit::Piece => write!(f, "ชิ้น"),
+            Unit::Kilogram => write!(f, "กิโลกรัม"),
+            Unit::Litre => write!(f, "ลิตร"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_allows() {
+        assert!(Unit::Kilogram.allows(Decimal::new(1250, 3)));
+        assert!(!Unit::Kilogram.allows(Decimal::new(12505, 4)));
+        assert!(Unit::Piece.allows(Decimal::new(2000, 3)));
+        assert!(!Unit::Piece.allows(Decimal::new(25, 1)));
+        assert_eq!("litre".parse(), Ok(Unit::Litre));
+    }
+}
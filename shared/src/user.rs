@@ -0,0 +1,62 @@
+//! User accounts, what they may do and their sessions.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Owner,
+    #[default]
+    Cashier,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Owner => "owner",
+            Role::Cashier => "cashier",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "owner" => Ok(Role::Owner),
+            "cashier" => Ok(Role::Cashier),
+            _ => Err(format!("unknown role: {s}")),
+        }
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct User {
+    pub id: u32,
+    pub name: String,
+    pub role: Role,
+}
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct NewUser {
+    pub name: String,
+    pub password: String,
+    pub role: Role,
+}
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Login {
+    pub name: String,
+    pub password: String,
+}
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Session {
+    pub token: String,
+    pub user: User,
+    /// VAT in percent the server works out the tax of receipts at, for the till to show.
+    #[serde(default)]
+    pub vat_rate: Decimal,
+}